@@ -0,0 +1,12 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use openapi_deref::OpenApiDereferencer;
+
+fn parse(c: &mut Criterion) {
+    let spec = std::fs::read("oai_examples/api.github.com.json").unwrap();
+    c.bench_function("from_bytes api.github.com", |b| {
+        b.iter(|| OpenApiDereferencer::from_bytes(&spec).unwrap())
+    });
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::{pointer, OpenApiError};
+
+///Index of the places in the document a reference can name without a JSON pointer: `$id` URIs,
+///`$anchor` names and `$dynamicAnchor` names.
+#[derive(Debug, Default)]
+pub(crate) struct AnchorIndex {
+    ///`$id` -> pointer of the schema declaring it
+    ids: HashMap<String, String>,
+    ///(pointer of the enclosing `$id`, anchor name) -> pointer of the schema declaring it
+    anchors: HashMap<(String, String), String>,
+    ///anchor name -> pointers of every schema declaring it
+    dynamic_anchors: HashMap<String, Vec<String>>,
+}
+
+impl AnchorIndex {
+    pub(crate) fn build(json: &Value) -> Self {
+        let mut index = AnchorIndex::default();
+        index.scan(json, "", "");
+        index
+    }
+
+    fn scan(&mut self, value: &Value, location: &str, resource: &str) {
+        match value {
+            Value::Object(object) => {
+                let mut resource = resource;
+                if let Some(Value::String(id)) = object.get("$id") {
+                    self.ids.insert(id.clone(), location.into());
+                    resource = location;
+                }
+                if let Some(Value::String(anchor)) = object.get("$anchor") {
+                    self.anchors
+                        .insert((resource.into(), anchor.clone()), location.into());
+                }
+                if let Some(Value::String(anchor)) = object.get("$dynamicAnchor") {
+                    self.anchors
+                        .insert((resource.into(), anchor.clone()), location.into());
+                    self.dynamic_anchors
+                        .entry(anchor.clone())
+                        .or_default()
+                        .push(location.into());
+                }
+                for (key, value) in object {
+                    self.scan(value, &pointer::push(location, key), resource);
+                }
+            }
+            Value::Array(values) => {
+                for (i, value) in values.iter().enumerate() {
+                    self.scan(value, &format!("{location}/{i}"), resource);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    ///Find the JSON pointer for a reference that uses an `$id` and/or an anchor name, like
+    ///`#pet`, `https://example.com/pet` or `https://example.com/pet#name`.
+    pub(crate) fn locate(&self, reference: &str) -> Result<String, OpenApiError> {
+        let (base, fragment) = reference.split_once('#').unwrap_or((reference, ""));
+        let resource = if base.is_empty() {
+            ""
+        } else {
+            self.ids.get(base).map(String::as_str).ok_or_else(|| {
+                OpenApiError::UnsupportedRefFormat {
+                    reference: reference.into(),
+                }
+            })?
+        };
+        if fragment.is_empty() || fragment.starts_with('/') {
+            return Ok(format!("{resource}{fragment}"));
+        }
+        if let Some(location) = self
+            .anchors
+            .get(&(resource.to_string(), fragment.to_string()))
+        {
+            return Ok(location.clone());
+        }
+        //We don't track which resource a `#name` ref was written in, so fall back to the anchor
+        //if its name is unique in the document.
+        let mut matches = self
+            .anchors
+            .iter()
+            .filter(|((_, name), _)| name == fragment)
+            .map(|(_, location)| location);
+        match (matches.next(), matches.next()) {
+            (Some(location), None) if base.is_empty() => Ok(location.clone()),
+            _ => Err(OpenApiError::AnchorNotFound {
+                reference: reference.into(),
+            }),
+        }
+    }
+
+    ///Find the JSON pointer for a `$dynamicRef`. We don't track the dynamic scope, so this only
+    ///works when the document declares the anchor once. Without a matching `$dynamicAnchor` it
+    ///behaves like a plain `$ref`.
+    pub(crate) fn locate_dynamic(&self, reference: &str) -> Result<String, OpenApiError> {
+        let name = reference.strip_prefix('#').unwrap_or_default();
+        match self.dynamic_anchors.get(name).map(Vec::as_slice) {
+            Some([location]) => Ok(location.clone()),
+            Some(_) => Err(OpenApiError::UnsupportedDynamicRef {
+                reference: reference.into(),
+            }),
+            None => self.locate(reference),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    pub fn test_locate_anchors() -> Result<()> {
+        let json = json!({
+            "components": {
+                "schemas": {
+                    "Pet": {"$anchor": "pet", "type": "object"},
+                    "Owner": {
+                        "$id": "https://example.com/owner",
+                        "$defs": {"Name": {"$anchor": "name", "type": "string"}}
+                    },
+                    "Tree": {"$dynamicAnchor": "node", "type": "object"}
+                }
+            }
+        });
+        let index = AnchorIndex::build(&json);
+        assert_eq!("/components/schemas/Pet", index.locate("#pet")?);
+        assert_eq!(
+            "/components/schemas/Owner",
+            index.locate("https://example.com/owner")?
+        );
+        assert_eq!(
+            "/components/schemas/Owner/$defs/Name",
+            index.locate("https://example.com/owner#name")?
+        );
+        assert_eq!(
+            "/components/schemas/Owner/$defs/Name",
+            index.locate("https://example.com/owner#/$defs/Name")?
+        );
+        assert_eq!("/components/schemas/Tree", index.locate_dynamic("#node")?);
+        assert!(matches!(
+            index.locate("#missing"),
+            Err(OpenApiError::AnchorNotFound { .. })
+        ));
+        assert!(matches!(
+            index.locate("other.json#/components/schemas/Pet"),
+            Err(OpenApiError::UnsupportedRefFormat { .. })
+        ));
+        Ok(())
+    }
+}
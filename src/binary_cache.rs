@@ -0,0 +1,236 @@
+use openapiv3::v3_1::OpenApi;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use sha2::{Digest, Sha256};
+
+use crate::builder::DerefOptions;
+use crate::{
+    DereferencedOpenApi, OpenApiDereferencer, OpenApiDereferencerBuilder, OpenApiError, State,
+};
+
+const MAGIC: &[u8; 4] = b"OADC";
+///Bump when the layout or the openapiv3 types change, so old caches are rejected.
+const VERSION: u16 = 2;
+const HEADER_LEN: usize = 4 + 2 + 32;
+
+///A JSON value bincode can read back. `Value` and the untagged openapiv3 enums need a format
+///that describes itself, which bincode isn't.
+#[derive(Serialize, Deserialize)]
+enum CachedValue {
+    Null,
+    Bool(bool),
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<CachedValue>),
+    Object(Vec<(String, CachedValue)>),
+}
+
+impl From<&Value> for CachedValue {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => CachedValue::Null,
+            Value::Bool(b) => CachedValue::Bool(*b),
+            Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+                (Some(n), _) => CachedValue::Unsigned(n),
+                (_, Some(n)) => CachedValue::Signed(n),
+                _ => CachedValue::Float(n.as_f64().unwrap_or_default()),
+            },
+            Value::String(s) => CachedValue::String(s.clone()),
+            Value::Array(values) => CachedValue::Array(values.iter().map(Into::into).collect()),
+            Value::Object(object) => CachedValue::Object(
+                object
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<CachedValue> for Value {
+    fn from(value: CachedValue) -> Self {
+        match value {
+            CachedValue::Null => Value::Null,
+            CachedValue::Bool(b) => Value::Bool(b),
+            CachedValue::Unsigned(n) => Value::Number(n.into()),
+            CachedValue::Signed(n) => Value::Number(n.into()),
+            CachedValue::Float(n) => Number::from_f64(n).map_or(Value::Null, Value::Number),
+            CachedValue::String(s) => Value::String(s),
+            CachedValue::Array(values) => {
+                Value::Array(values.into_iter().map(Into::into).collect())
+            }
+            CachedValue::Object(entries) => Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, value.into()))
+                    .collect::<Map<String, Value>>(),
+            ),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedSpec {
+    partial: bool,
+    ///The input document, so the loaded spec can do everything a freshly dereferenced one can
+    input: CachedValue,
+    openapi: CachedValue,
+}
+
+///What a cache is good for: the bytes the spec was read from and the options that change what
+///dereferencing turns them into.
+fn cache_key(source: &[u8], options: &DerefOptions) -> [u8; 32] {
+    let options = DerefOptions {
+        release_input: false,
+        ..options.clone()
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(source);
+    hasher.update(format!("{options:?}").as_bytes());
+    hasher.finalize().into()
+}
+
+fn cache_error(e: bincode::Error) -> OpenApiError {
+    OpenApiError::ParsingError {
+        msg: format!("Error in the spec cache {e}"),
+        location: None,
+    }
+}
+
+impl DereferencedOpenApi {
+    ///The dereferenced spec as bytes for persisting and loading with `from_cache_bytes` instead
+    ///of dereferencing at every start. `source` is what the spec was read from, the cache is
+    ///keyed on it and the options it was dereferenced with. Documents pulled in by a loader
+    ///aren't part of the key. The input document is saved too, so it needs to still be around.
+    pub fn to_cache_bytes(&self, source: &[u8]) -> Result<Vec<u8>, OpenApiError> {
+        if !self.has_input() {
+            return Err(OpenApiError::InputReleased);
+        }
+        let openapi =
+            serde_json::to_value(&self.openapi).map_err(|e| OpenApiError::ParsingError {
+                msg: format!("Error serializing the spec {e}"),
+                location: None,
+            })?;
+        let spec = CachedSpec {
+            partial: self.state() == State::PartiallyDereferenced,
+            input: (&self.json).into(),
+            openapi: (&openapi).into(),
+        };
+        let mut bytes = Vec::with_capacity(HEADER_LEN);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&cache_key(source, &self.options));
+        bincode::serialize_into(&mut bytes, &spec).map_err(cache_error)?;
+        Ok(bytes)
+    }
+}
+
+impl OpenApiDereferencerBuilder {
+    ///Load a dereferenced spec saved by `to_cache_bytes` without reading or dereferencing
+    ///`source` again. If `source` has changed since, the cache was made with different options
+    ///or by another version of the crate, this fails with `StaleCache` and the spec needs
+    ///dereferencing again. The cached input is already bundled, so the document loader isn't
+    ///used, and changes to the documents it loaded don't make the cache stale.
+    pub fn build_from_cache(
+        self,
+        bytes: &[u8],
+        source: &[u8],
+    ) -> Result<DereferencedOpenApi, OpenApiError> {
+        let stale = |msg: &str| OpenApiError::StaleCache { msg: msg.into() };
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            return Err(stale("not a cache"));
+        }
+        if bytes[4..6] != VERSION.to_le_bytes() {
+            return Err(stale("made by another version"));
+        }
+        if bytes[6..HEADER_LEN] != cache_key(source, self.options()) {
+            return Err(stale("the source document or the options changed"));
+        }
+        let spec: CachedSpec = bincode::deserialize(&bytes[HEADER_LEN..]).map_err(cache_error)?;
+        let openapi: OpenApi = serde_json::from_value(spec.openapi.into()).map_err(|e| {
+            OpenApiError::ParsingError {
+                msg: format!("Error reading the cache {e}"),
+                location: None,
+            }
+        })?;
+        let mut dereferencer =
+            self.configure(OpenApiDereferencer::from_parts(spec.input.into(), openapi));
+        dereferencer.state = if spec.partial {
+            State::PartiallyDereferenced
+        } else {
+            State::Dereferenced
+        };
+        dereferencer.index_operation_ids();
+        Ok(DereferencedOpenApi(dereferencer))
+    }
+}
+
+impl OpenApiDereferencer {
+    ///Load a spec cached with the default options, see
+    ///`OpenApiDereferencerBuilder::build_from_cache`.
+    pub fn from_cache_bytes(
+        bytes: &[u8],
+        source: &[u8],
+    ) -> Result<DereferencedOpenApi, OpenApiError> {
+        OpenApiDereferencer::builder().build_from_cache(bytes, source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExternalRefPolicy, RefOutput};
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_cache_bytes() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Cache", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "operationId": "listPets",
+                        "responses": {"200": {"$ref": "#/components/responses/Pets"}}
+                    }
+                }
+            },
+            "components": {"responses": {"Pets": {"description": "Pets", "x-size": 1.5}}}
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let bytes = dereferenced.to_cache_bytes(spec.as_bytes())?;
+
+        let loaded = OpenApiDereferencer::from_cache_bytes(&bytes, spec.as_bytes())?;
+        assert_eq!(State::Dereferenced, loaded.state());
+        assert_eq!(
+            serde_json::to_value(&dereferenced.openapi)?,
+            serde_json::to_value(&loaded.openapi)?
+        );
+        assert_eq!(dereferenced.json, loaded.json);
+        assert_eq!("/pets", loaded.get_operation_by_id("listPets")?.0);
+
+        let changed = spec.replace("1.0.0", "1.0.1");
+        assert!(matches!(
+            OpenApiDereferencer::from_cache_bytes(&bytes, changed.as_bytes()),
+            Err(OpenApiError::StaleCache { .. })
+        ));
+        assert!(matches!(
+            OpenApiDereferencer::builder()
+                .output(RefOutput::Inline)
+                .build_from_cache(&bytes, spec.as_bytes()),
+            Err(OpenApiError::StaleCache { .. })
+        ));
+
+        let builder = || OpenApiDereferencer::builder().external_refs(ExternalRefPolicy::Keep);
+        let bytes = builder()
+            .build_from_str(spec)?
+            .dereference()?
+            .to_cache_bytes(spec.as_bytes())?;
+        assert!(builder().build_from_cache(&bytes, spec.as_bytes()).is_ok());
+        assert!(OpenApiDereferencer::from_cache_bytes(&bytes, spec.as_bytes()).is_err());
+        Ok(())
+    }
+}
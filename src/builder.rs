@@ -0,0 +1,459 @@
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::loader::{bundle_external, ResolvedDocuments};
+use crate::{
+    DocumentLoader, ExampleFetcher, OpenApiDereferencer, OpenApiError, RefCache, ResourceLimits,
+};
+
+///What to do with refs to other documents, e.g. `pets.yaml#/Pet`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExternalRefPolicy {
+    ///Fail with `UnsupportedRefFormat`
+    #[default]
+    Error,
+    ///Leave the ref as it is
+    Keep,
+}
+
+///What to do when a schema refers back to itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CyclePolicy {
+    ///Stop at the first recursion and leave the `$ref` there
+    #[default]
+    KeepRef,
+    ///Fail with `CircularReference`
+    Error,
+}
+
+///The extension `RefOutput::Annotated` adds to record where resolved content came from.
+pub const ORIGINAL_REF_EXTENSION: &str = "x-original-ref";
+
+///How resolved refs show up in the dereferenced spec. Schemas are always inlined, so they only
+///change with `Annotated`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RefOutput {
+    ///Keep the ref next to the resolved item, as a `DereferencedReference`
+    #[default]
+    KeepRef,
+    ///Replace the ref with the resolved item
+    Inline,
+    ///Replace the ref with the resolved item and add an `x-original-ref` extension to it with
+    ///the ref, so the serialized spec still says where everything came from. Types without
+    ///extensions, like callbacks, are just inlined.
+    Annotated,
+}
+
+///Which parts of the spec get dereferenced. Everything is in scope by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DerefScope {
+    pub components: bool,
+    pub paths: bool,
+    pub webhooks: bool,
+    ///Only dereference paths starting with one of these. Empty means every path.
+    pub path_prefixes: Vec<String>,
+}
+
+impl Default for DerefScope {
+    fn default() -> Self {
+        DerefScope {
+            components: true,
+            paths: true,
+            webhooks: true,
+            path_prefixes: vec![],
+        }
+    }
+}
+
+impl DerefScope {
+    pub(crate) fn includes_path(&self, path: &str) -> bool {
+        self.paths
+            && (self.path_prefixes.is_empty()
+                || self
+                    .path_prefixes
+                    .iter()
+                    .any(|prefix| path.starts_with(prefix.as_str())))
+    }
+}
+
+///How many refs deep dereferencing goes by default.
+pub const DEFAULT_MAX_REF_DEPTH: usize = 100;
+
+#[derive(Debug, Clone)]
+pub(crate) struct DerefOptions {
+    pub(crate) max_ref_depth: usize,
+    pub(crate) external_refs: ExternalRefPolicy,
+    pub(crate) cycles: CyclePolicy,
+    pub(crate) output: RefOutput,
+    pub(crate) scope: DerefScope,
+    pub(crate) lenient: bool,
+    pub(crate) limits: ResourceLimits,
+    pub(crate) release_input: bool,
+    pub(crate) inline_max_references: Option<usize>,
+    pub(crate) inline_max_size: Option<usize>,
+    pub(crate) strict: bool,
+}
+
+impl Default for DerefOptions {
+    fn default() -> Self {
+        DerefOptions {
+            max_ref_depth: DEFAULT_MAX_REF_DEPTH,
+            external_refs: ExternalRefPolicy::default(),
+            cycles: CyclePolicy::default(),
+            output: RefOutput::default(),
+            scope: DerefScope::default(),
+            lenient: false,
+            limits: ResourceLimits::default(),
+            release_input: false,
+            inline_max_references: None,
+            inline_max_size: None,
+            strict: false,
+        }
+    }
+}
+
+///Configures how an `OpenApiDereferencer` dereferences. Get one from
+///`OpenApiDereferencer::builder()`.
+#[derive(Default)]
+pub struct OpenApiDereferencerBuilder {
+    options: DerefOptions,
+    example_fetcher: Option<Arc<dyn ExampleFetcher>>,
+    should_cancel: Option<Arc<dyn Fn() -> bool + Send + Sync>>,
+    cache: Option<Box<dyn RefCache>>,
+    document_loader: Option<Box<dyn DocumentLoader>>,
+    resolved_documents: Option<Arc<ResolvedDocuments>>,
+}
+
+impl OpenApiDereferencerBuilder {
+    ///How many schema refs deep to follow before failing with `MaxRefDepthExceeded`. Defaults to
+    ///`DEFAULT_MAX_REF_DEPTH`.
+    pub fn max_ref_depth(mut self, max_ref_depth: usize) -> Self {
+        self.options.max_ref_depth = max_ref_depth;
+        self
+    }
+
+    pub fn external_refs(mut self, policy: ExternalRefPolicy) -> Self {
+        self.options.external_refs = policy;
+        self
+    }
+
+    pub fn cycles(mut self, policy: CyclePolicy) -> Self {
+        self.options.cycles = policy;
+        self
+    }
+
+    pub fn output(mut self, output: RefOutput) -> Self {
+        self.options.output = output;
+        self
+    }
+
+    pub fn scope(mut self, scope: DerefScope) -> Self {
+        self.options.scope = scope;
+        self
+    }
+
+    ///Shorthand for a scope covering the components and the paths starting with `prefixes`.
+    pub fn only_paths<S: Into<String>>(mut self, prefixes: impl IntoIterator<Item = S>) -> Self {
+        self.options.scope = DerefScope {
+            webhooks: false,
+            path_prefixes: prefixes.into_iter().map(Into::into).collect(),
+            ..DerefScope::default()
+        };
+        self
+    }
+
+    ///Fail with `LimitExceeded` once dereferencing does more work than `limits` allow.
+    pub fn limits(mut self, limits: ResourceLimits) -> Self {
+        self.options.limits = limits;
+        self
+    }
+
+    ///Leave refs to components that are referenced more than `max_references` times as refs
+    ///instead of inlining them, to keep the size of the dereferenced spec down. See
+    ///`OpenApiDereferencer::component_usage`. Path items are always inlined.
+    pub fn inline_max_references(mut self, max_references: usize) -> Self {
+        self.options.inline_max_references = Some(max_references);
+        self
+    }
+
+    ///Leave refs to components bigger than `max_size` bytes of JSON as refs instead of inlining
+    ///them. Path items are always inlined.
+    pub fn inline_max_size(mut self, max_size: usize) -> Self {
+        self.options.inline_max_size = Some(max_size);
+        self
+    }
+
+    ///Fail with `UnknownFields` if the spec has fields the 3.1 spec doesn't allow, see
+    ///`OpenApiDereferencer::unknown_fields`.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.options.strict = strict;
+        self
+    }
+
+    ///Drop the input document and the cache once dereferencing is done, see
+    ///`OpenApiDereferencer::release_input`.
+    pub fn release_input(mut self, release_input: bool) -> Self {
+        self.options.release_input = release_input;
+        self
+    }
+
+    ///Fail with `Cancelled` as soon as `should_cancel` returns true. It's checked for every ref
+    ///and schema, so a service can give up on a spec that's taking too long, e.g. by flipping an
+    ///`AtomicBool` after a timeout.
+    pub fn cancel_when(mut self, should_cancel: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        self.should_cancel = Some(Arc::new(should_cancel));
+        self
+    }
+
+    ///See `OpenApiDereferencer::with_example_fetcher`.
+    pub fn example_fetcher(mut self, fetcher: impl ExampleFetcher + 'static) -> Self {
+        self.example_fetcher = Some(Arc::new(fetcher));
+        self
+    }
+
+    ///Keep resolved values in `cache` instead of a fresh in memory map. Pass an `Arc` to hold on
+    ///to it after dereferencing.
+    pub fn cache(mut self, cache: impl RefCache + 'static) -> Self {
+        self.cache = Some(Box::new(cache));
+        self
+    }
+
+    ///Pull the documents refs to other files point at into the spec with `loader` before
+    ///dereferencing. Relative refs are resolved against the spec's own directory.
+    pub fn document_loader(mut self, loader: impl DocumentLoader + 'static) -> Self {
+        self.document_loader = Some(Box::new(loader));
+        self
+    }
+
+    ///Reuse the external documents other bundles already resolved, see `DerefSession`.
+    pub(crate) fn resolved_documents(mut self, resolved: Arc<ResolvedDocuments>) -> Self {
+        self.resolved_documents = Some(resolved);
+        self
+    }
+
+    pub fn build_from_value(self, json: Value) -> Result<OpenApiDereferencer, OpenApiError> {
+        let dereferencer = OpenApiDereferencer::from_value(json)?;
+        self.apply(dereferencer)
+    }
+
+    pub fn build_from_str(self, the_str: &str) -> Result<OpenApiDereferencer, OpenApiError> {
+        let dereferencer: OpenApiDereferencer = the_str.parse()?;
+        self.apply(dereferencer)
+    }
+
+    pub fn build_from_bytes(self, bytes: &[u8]) -> Result<OpenApiDereferencer, OpenApiError> {
+        let dereferencer = OpenApiDereferencer::from_bytes(bytes)?;
+        self.apply(dereferencer)
+    }
+
+    ///A builder with the same options, example fetcher and cancellation, for dereferencing
+    ///again. The cache and document loader aren't carried over.
+    pub(crate) fn for_reload(&self) -> Self {
+        OpenApiDereferencerBuilder {
+            options: self.options.clone(),
+            example_fetcher: self.example_fetcher.clone(),
+            should_cancel: self.should_cancel.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn options(&self) -> &DerefOptions {
+        &self.options
+    }
+
+    fn apply(
+        self,
+        mut dereferencer: OpenApiDereferencer,
+    ) -> Result<OpenApiDereferencer, OpenApiError> {
+        if let Some(loader) = self.document_loader.as_deref() {
+            let json = bundle_external(
+                std::mem::take(&mut dereferencer.json),
+                "",
+                loader,
+                self.resolved_documents.as_deref(),
+            )?;
+            dereferencer = OpenApiDereferencer::from_value(json)?;
+        }
+        if self.options.strict {
+            let fields = dereferencer.unknown_fields()?;
+            if !fields.is_empty() {
+                return Err(OpenApiError::UnknownFields { fields });
+            }
+        }
+        Ok(self.configure(dereferencer))
+    }
+
+    ///Hand the options, fetcher, cancellation and cache over to `dereferencer`.
+    pub(crate) fn configure(self, mut dereferencer: OpenApiDereferencer) -> OpenApiDereferencer {
+        dereferencer.options = self.options;
+        dereferencer.example_fetcher = self.example_fetcher;
+        dereferencer.should_cancel = self.should_cancel;
+        if let Some(cache) = self.cache {
+            dereferencer.serde_values = cache;
+        }
+        dereferencer
+    }
+}
+
+impl OpenApiDereferencer {
+    pub fn builder() -> OpenApiDereferencerBuilder {
+        OpenApiDereferencerBuilder::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::as_item;
+    use anyhow::Result;
+    use openapiv3::v3_1::ReferenceOr;
+    use std::str::FromStr;
+
+    const SPEC: &str = r##"{
+        "openapi": "3.1.0",
+        "info": {"title": "Builder", "version": "1.0.0"},
+        "paths": {
+            "/pets": {
+                "get": {
+                    "responses": {
+                        "200": {"$ref": "#/components/responses/Pets"},
+                        "404": {"$ref": "errors.json#/NotFound"}
+                    }
+                }
+            },
+            "/stores": {
+                "get": {"responses": {"200": {"$ref": "#/components/responses/Pets"}}}
+            }
+        },
+        "components": {
+            "responses": {
+                "Pets": {"description": "pets"}
+            },
+            "schemas": {
+                "Node": {"type": "object", "properties": {"next": {"$ref": "#/components/schemas/Node"}}}
+            }
+        }
+    }"##;
+
+    #[test]
+    pub fn test_builder_options() -> Result<()> {
+        assert!(matches!(
+            OpenApiDereferencer::from_str(SPEC)?.dereference(),
+            Err(OpenApiError::Resolution { source, .. })
+                if matches!(*source, OpenApiError::UnsupportedRefFormat { .. })
+        ));
+
+        let dereferenced = OpenApiDereferencer::builder()
+            .external_refs(ExternalRefPolicy::Keep)
+            .output(RefOutput::Inline)
+            .only_paths(["/pets"])
+            .build_from_str(SPEC)?
+            .dereference()?;
+        let paths = &dereferenced.openapi.paths.as_ref().unwrap().paths;
+        let pets = as_item(&paths["/pets"]).unwrap();
+        let responses = pets.get.as_ref().unwrap().responses.as_ref().unwrap();
+        assert!(matches!(
+            responses.responses.get_index(0).unwrap().1,
+            ReferenceOr::Item(response) if response.description == "pets"
+        ));
+        assert!(matches!(
+            responses.responses.get_index(1).unwrap().1,
+            ReferenceOr::Reference { reference, .. } if reference == "errors.json#/NotFound"
+        ));
+        let stores = as_item(&paths["/stores"]).unwrap();
+        assert!(matches!(
+            stores
+                .get
+                .as_ref()
+                .unwrap()
+                .responses
+                .as_ref()
+                .unwrap()
+                .responses
+                .get_index(0)
+                .unwrap()
+                .1,
+            ReferenceOr::Reference { .. }
+        ));
+
+        let spec = SPEC.replace("errors.json#/NotFound", "#/components/responses/Pets");
+        assert!(matches!(
+            OpenApiDereferencer::builder()
+                .cycles(CyclePolicy::Error)
+                .build_from_str(&spec)?
+                .dereference(),
+            Err(OpenApiError::Resolution { source, .. })
+                if matches!(*source, OpenApiError::CircularReference { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_annotated_output() -> Result<()> {
+        let dereferenced = OpenApiDereferencer::builder()
+            .output(RefOutput::Annotated)
+            .external_refs(ExternalRefPolicy::Keep)
+            .build_from_str(SPEC)?
+            .dereference()?;
+        let json = serde_json::to_value(&dereferenced.openapi)?;
+        let response = &json["paths"]["/pets"]["get"]["responses"]["200"];
+        assert_eq!("pets", response["description"]);
+        assert_eq!(
+            "#/components/responses/Pets",
+            response[ORIGINAL_REF_EXTENSION]
+        );
+        assert!(response.get("$ref").is_none());
+        let next = &json["components"]["schemas"]["Node"]["properties"]["next"];
+        assert_eq!("#/components/schemas/Node", next["$ref"]);
+
+        let spec = SPEC.replace(
+            r##""#/components/schemas/Node"}}}"##,
+            r##""#/components/schemas/Leaf"}}}, "Leaf": {"type": "string"}"##,
+        );
+        let dereferenced = OpenApiDereferencer::builder()
+            .output(RefOutput::Annotated)
+            .external_refs(ExternalRefPolicy::Keep)
+            .build_from_str(&spec)?
+            .dereference()?;
+        let json = serde_json::to_value(&dereferenced.openapi)?;
+        let next = &json["components"]["schemas"]["Node"]["properties"]["next"];
+        assert_eq!("string", next["type"]);
+        assert_eq!("#/components/schemas/Leaf", next[ORIGINAL_REF_EXTENSION]);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_max_ref_depth() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Depth", "version": "1.0.0"},
+            "components": {
+                "schemas": {
+                    "A": {"$ref": "#/components/schemas/B"},
+                    "B": {"$ref": "#/components/schemas/C"},
+                    "C": {"$ref": "#/components/schemas/D"},
+                    "D": {"type": "string"}
+                }
+            }
+        }"##;
+        assert!(OpenApiDereferencer::from_str(spec)?.dereference().is_ok());
+        match OpenApiDereferencer::builder()
+            .max_ref_depth(2)
+            .build_from_str(spec)?
+            .dereference_lenient()
+        {
+            Err(OpenApiError::MaxRefDepthExceeded {
+                reference, chain, ..
+            }) => {
+                assert_eq!("#/components/schemas/C", reference);
+                assert_eq!(
+                    vec!["#/components/schemas/A", "#/components/schemas/B"],
+                    chain
+                );
+            }
+            _ => panic!("expected the ref depth to be exceeded"),
+        }
+        Ok(())
+    }
+}
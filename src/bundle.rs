@@ -0,0 +1,250 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json::{Map, Value};
+
+use crate::canonical::canonical;
+use crate::convert::for_each_schema;
+use crate::{pointer, DereferencedOpenApi, OpenApiError};
+
+///Segments of a schema's location that say nothing about what the schema is.
+const UNNAMED_SEGMENTS: [&str; 9] = [
+    "schema",
+    "schemas",
+    "items",
+    "properties",
+    "content",
+    "components",
+    "allOf",
+    "anyOf",
+    "oneOf",
+];
+
+impl DereferencedOpenApi {
+    ///The inverse of dereferencing. Schemas that show up identically in more than one place are
+    ///moved into `components/schemas` and replaced with refs, which can shrink a fully inlined
+    ///spec a lot. Identical component schemas are reused, otherwise the new components are named
+    ///after the schema's `title` or where it was first used. Only schemas with properties, an
+    ///enum or subschemas are worth a component, so `{"type": "string"}` is left alone.
+    pub fn rebundle(&self) -> Result<Value, OpenApiError> {
+        let mut document =
+            serde_json::to_value(&self.openapi).map_err(|e| OpenApiError::ParsingError {
+                msg: format!("Error serializing the dereferenced spec {e}"),
+                location: None,
+            })?;
+
+        //canonical schema -> how many times it's used, and the component already holding it
+        let mut uses: HashMap<String, usize> = HashMap::new();
+        let mut components: HashMap<String, String> = HashMap::new();
+        for_each_schema(&mut document, "", &mut |schema, location| {
+            if !worth_a_component(schema) {
+                return;
+            }
+            let key = canonical(&Value::Object(schema.clone()));
+            match component_name(location) {
+                Some(name) => {
+                    components.entry(key).or_insert(name);
+                }
+                None => *uses.entry(key).or_default() += 1,
+            }
+        });
+        let mut taken: HashSet<String> = document
+            .pointer("/components/schemas")
+            .and_then(Value::as_object)
+            .map(|schemas| schemas.keys().cloned().collect())
+            .unwrap_or_default();
+
+        let mut hoisted: Vec<(String, Map<String, Value>)> = vec![];
+        for_each_schema(&mut document, "", &mut |schema, location| {
+            if component_name(location).is_some() || !worth_a_component(schema) {
+                return;
+            }
+            let key = canonical(&Value::Object(schema.clone()));
+            let name = match components.get(&key) {
+                Some(name) => name.clone(),
+                None if uses.get(&key).is_some_and(|uses| *uses > 1) => {
+                    let name = unique_name(suggested_name(schema, location), &mut taken);
+                    hoisted.push((name.clone(), schema.clone()));
+                    components.insert(key, name.clone());
+                    name
+                }
+                None => return,
+            };
+            schema.clear();
+            schema.insert(
+                "$ref".into(),
+                pointer::push("#/components/schemas", &name).into(),
+            );
+        });
+
+        if !hoisted.is_empty() {
+            let Value::Object(root) = &mut document else {
+                return Ok(document);
+            };
+            let schemas = root
+                .entry("components")
+                .or_insert_with(|| Value::Object(Map::new()))
+                .as_object_mut()
+                .map(|components| {
+                    components
+                        .entry("schemas")
+                        .or_insert_with(|| Value::Object(Map::new()))
+                });
+            if let Some(Value::Object(schemas)) = schemas {
+                for (name, schema) in hoisted {
+                    schemas.insert(name, Value::Object(schema));
+                }
+            }
+        }
+        Ok(document)
+    }
+}
+
+pub(crate) fn worth_a_component(schema: &Map<String, Value>) -> bool {
+    ["properties", "enum", "allOf", "anyOf", "oneOf"]
+        .iter()
+        .any(|keyword| schema.contains_key(*keyword))
+}
+
+///The name of the component schema at `location`, if that's where it is.
+fn component_name(location: &str) -> Option<String> {
+    let name = location.strip_prefix("/components/schemas/")?;
+    (!name.contains('/')).then(|| pointer::unescape(name))
+}
+
+fn suggested_name(schema: &Map<String, Value>, location: &str) -> String {
+    let from_title = schema.get("title").and_then(Value::as_str).map(pascal_case);
+    let from_location = location
+        .rsplit('/')
+        .map(pointer::unescape)
+        .find(|segment| {
+            !UNNAMED_SEGMENTS.contains(&segment.as_str())
+                && !segment.contains('/')
+                && segment.parse::<u16>().is_err()
+                && !segment.ends_with("XX")
+        })
+        .map(|segment| pascal_case(&segment));
+    from_title
+        .or(from_location)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "Schema".into())
+}
+
+fn pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+fn unique_name(name: String, taken: &mut HashSet<String>) -> String {
+    let mut unique = name.clone();
+    let mut n = 2;
+    while taken.contains(&unique) {
+        unique = format!("{name}{n}");
+        n += 1;
+    }
+    taken.insert(unique.clone());
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_rebundle() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Bundle", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "ok",
+                                "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+                            }
+                        }
+                    },
+                    "post": {
+                        "requestBody": {
+                            "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+                        },
+                        "responses": {"201": {"description": "created"}}
+                    }
+                },
+                "/owners": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "ok",
+                                "content": {
+                                    "application/json": {
+                                        "schema": {
+                                            "type": "object",
+                                            "properties": {
+                                                "address": {"type": "object", "properties": {"street": {"type": "string"}, "floor": {"type": "integer", "minimum": 1}}}
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "put": {
+                        "requestBody": {
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "properties": {
+                                            "home": {"type": "object", "properties": {"street": {"type": "string"}, "floor": {"type": "integer", "minimum": 1.0}}}
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "responses": {"204": {"description": "updated"}}
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Pet": {"type": "object", "properties": {"name": {"type": "string"}}}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let bundled = dereferenced.rebundle()?;
+        let pets = &bundled["paths"]["/pets"];
+        assert_eq!(
+            "#/components/schemas/Pet",
+            pets["get"]["responses"]["200"]["content"]["application/json"]["schema"]["$ref"]
+        );
+        assert_eq!(
+            "#/components/schemas/Pet",
+            pets["post"]["requestBody"]["content"]["application/json"]["schema"]["$ref"]
+        );
+        let owners = &bundled["paths"]["/owners"];
+        let address = &owners["get"]["responses"]["200"]["content"]["application/json"]["schema"]
+            ["properties"]["address"]["$ref"];
+        let home = &owners["put"]["requestBody"]["content"]["application/json"]["schema"]
+            ["properties"]["home"]["$ref"];
+        assert_eq!(address, home);
+        let name = address.as_str().unwrap().rsplit('/').next().unwrap();
+        assert!(name == "Address" || name == "Home");
+        let schemas = bundled["components"]["schemas"].as_object().unwrap();
+        assert_eq!(2, schemas.len());
+        assert_eq!("string", schemas[name]["properties"]["street"]["type"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use serde_json::Value;
+
+use crate::memory::value_size;
+
+///Where the raw values refs resolve to are kept while dereferencing, keyed by JSON pointer.
+///Pointers are only unique within a document, so a cache shared between dereferencers should
+///only be shared between copies of the same spec or keep its own per document namespace.
+pub trait RefCache: Send + Sync {
+    fn get(&self, pointer: &str) -> Option<Value>;
+    fn put(&self, pointer: &str, value: Value);
+    ///Drop the entries at `pointer`, inside it and containing it, after the document changed
+    ///there, see `DereferencedOpenApi::apply_patch`. Returns false if the cache can't, and
+    ///`apply_patch` swaps it for a fresh in memory one instead.
+    fn invalidate(&self, _pointer: &str) -> bool {
+        false
+    }
+
+    ///Roughly how many bytes the cache is holding, for `memory_usage_estimate`.
+    fn size_estimate(&self) -> usize {
+        0
+    }
+}
+
+///The default cache, an unbounded map that lives as long as the dereferencer.
+#[derive(Debug, Default)]
+pub struct InMemoryRefCache {
+    values: RwLock<HashMap<String, Value>>,
+}
+
+impl RefCache for InMemoryRefCache {
+    fn get(&self, pointer: &str) -> Option<Value> {
+        self.values.read().ok()?.get(pointer).cloned()
+    }
+
+    fn put(&self, pointer: &str, value: Value) {
+        if let Ok(mut values) = self.values.write() {
+            values.insert(pointer.into(), value);
+        }
+    }
+
+    fn invalidate(&self, pointer: &str) -> bool {
+        let Ok(mut values) = self.values.write() else {
+            return false;
+        };
+        values.retain(|cached, _| !overlaps(cached, pointer));
+        true
+    }
+
+    fn size_estimate(&self) -> usize {
+        self.values.read().map_or(0, |values| entries_size(&values))
+    }
+}
+
+///A map behind a mutex, for sharing between threads in an `Arc`.
+#[derive(Debug, Default)]
+pub struct SharedRefCache {
+    values: Mutex<HashMap<String, Value>>,
+}
+
+impl RefCache for SharedRefCache {
+    fn get(&self, pointer: &str) -> Option<Value> {
+        self.values.lock().ok()?.get(pointer).cloned()
+    }
+
+    fn put(&self, pointer: &str, value: Value) {
+        if let Ok(mut values) = self.values.lock() {
+            values.insert(pointer.into(), value);
+        }
+    }
+
+    fn invalidate(&self, pointer: &str) -> bool {
+        let Ok(mut values) = self.values.lock() else {
+            return false;
+        };
+        values.retain(|cached, _| !overlaps(cached, pointer));
+        true
+    }
+
+    fn size_estimate(&self) -> usize {
+        self.values.lock().map_or(0, |values| entries_size(&values))
+    }
+}
+
+///Whether one JSON pointer is the same as, inside or containing the other.
+pub(crate) fn overlaps(a: &str, b: &str) -> bool {
+    let within = |inner: &str, outer: &str| {
+        inner
+            .strip_prefix(outer)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    };
+    within(a, b) || within(b, a)
+}
+
+fn entries_size(values: &HashMap<String, Value>) -> usize {
+    values
+        .iter()
+        .map(|(pointer, value)| pointer.len() + value_size(value))
+        .sum()
+}
+
+impl<C: RefCache + ?Sized> RefCache for Arc<C> {
+    fn get(&self, pointer: &str) -> Option<Value> {
+        (**self).get(pointer)
+    }
+
+    fn put(&self, pointer: &str, value: Value) {
+        (**self).put(pointer, value)
+    }
+
+    fn invalidate(&self, pointer: &str) -> bool {
+        (**self).invalidate(pointer)
+    }
+
+    fn size_estimate(&self) -> usize {
+        (**self).size_estimate()
+    }
+}
+
+impl Default for Box<dyn RefCache> {
+    fn default() -> Self {
+        Box::new(InMemoryRefCache::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+
+    #[test]
+    pub fn test_cache_is_shared() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Cache", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {"responses": {"200": {"$ref": "#/components/responses/Pets"}}}
+                }
+            },
+            "components": {
+                "responses": {"Pets": {"description": "pets"}}
+            }
+        }"##;
+        let cache = Arc::new(InMemoryRefCache::default());
+        OpenApiDereferencer::builder()
+            .cache(cache.clone())
+            .build_from_str(spec)?
+            .dereference()?;
+        assert_eq!(
+            Some(serde_json::json!({"description": "pets"})),
+            cache.get("/components/responses/Pets")
+        );
+        Ok(())
+    }
+}
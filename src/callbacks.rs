@@ -0,0 +1,109 @@
+use std::str::FromStr;
+
+use openapiv3::v3_1::{Operation, PathItem};
+
+use crate::runtime_expression::{Exchange, ExpressionTemplate};
+use crate::{as_item, OpenApiError};
+
+///A callback an operation will make, with its URL worked out, see [`callback_requests`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallbackRequest<'a> {
+    ///Name of the callback on the operation
+    pub name: &'a str,
+    ///The callback's key, e.g. `{$request.body#/callbackUrl}`
+    pub expression: &'a str,
+    pub url: String,
+    pub path_item: &'a PathItem,
+}
+
+///The callbacks `operation` will make for the request and response captured in `exchange`, with
+///their URL expressions filled in and percent encoded unless they're the whole URL. The operation has to come from a dereferenced spec so the
+///callbacks aren't refs.
+pub fn callback_requests<'a>(
+    operation: &'a Operation,
+    exchange: &Exchange,
+) -> Result<Vec<CallbackRequest<'a>>, OpenApiError> {
+    let mut requests = vec![];
+    for (name, callback) in &operation.callbacks {
+        let callback = as_item(callback).ok_or(OpenApiError::NotDereferenced)?;
+        for (expression, path_item) in callback {
+            requests.push(CallbackRequest {
+                name,
+                expression,
+                url: ExpressionTemplate::from_str(expression)?.substitute_url(exchange)?,
+                path_item,
+            });
+        }
+    }
+    Ok(requests)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Method, OpenApiDereferencer};
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    pub fn test_callback_requests() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Callbacks", "version": "1.0.0"},
+            "paths": {
+                "/subscriptions": {
+                    "post": {
+                        "operationId": "subscribe",
+                        "callbacks": {
+                            "onEvent": {"$ref": "#/components/callbacks/Event"},
+                            "onCancel": {
+                                "https://example.com/cancelled?id={$response.body#/id}&reason={$response.body#/reason}": {
+                                    "post": {"responses": {"200": {"description": "ok"}}}
+                                }
+                            }
+                        },
+                        "responses": {"201": {"description": "Subscribed"}}
+                    }
+                }
+            },
+            "components": {
+                "callbacks": {
+                    "Event": {
+                        "{$request.body#/callbackUrl}": {
+                            "post": {"responses": {"200": {"description": "ok"}}}
+                        }
+                    }
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let (_, _, operation) = dereferenced.get_operation_by_id("subscribe")?;
+        let mut exchange = Exchange::new(Method::Post, "https://api.example.com/subscriptions");
+        exchange.request.body = Some(json!({"callbackUrl": "https://client.example.com/events"}));
+        exchange.response.body = Some(json!({"id": 42, "reason": "too many & too often"}));
+
+        let requests = callback_requests(operation, &exchange)?;
+        let urls: Vec<(&str, &str)> = requests
+            .iter()
+            .map(|request| (request.name, request.url.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("onEvent", "https://client.example.com/events"),
+                (
+                    "onCancel",
+                    "https://example.com/cancelled?id=42&reason=too%20many%20%26%20too%20often"
+                ),
+            ],
+            urls
+        );
+        assert!(requests[0].path_item.post.is_some());
+
+        exchange.request.body = None;
+        assert!(matches!(
+            callback_requests(operation, &exchange),
+            Err(OpenApiError::RuntimeValueMissing { .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+use serde_json::{Number, Value};
+use sha2::{Digest, Sha256};
+
+use crate::{OpenApiDereferencer, OpenApiError};
+
+impl OpenApiDereferencer {
+    ///The spec as JSON with every object's keys sorted, no whitespace, and whole numbers written
+    ///without a fraction, so the same spec always serializes to the same bytes.
+    pub fn to_canonical_json(&self) -> Result<String, OpenApiError> {
+        let value =
+            serde_json::to_value(&self.openapi).map_err(|e| OpenApiError::ParsingError {
+                msg: format!("Error serializing the spec {e}"),
+                location: None,
+            })?;
+        let mut canonical = String::new();
+        write_canonical(&value, &mut canonical);
+        Ok(canonical)
+    }
+
+    ///A SHA-256 of the canonical JSON as hex, for checking whether a spec changed between
+    ///builds.
+    pub fn content_hash(&self) -> Result<String, OpenApiError> {
+        let digest = Sha256::digest(self.to_canonical_json()?.as_bytes());
+        Ok(digest.iter().map(|byte| format!("{byte:02x}")).collect())
+    }
+}
+
+///Any JSON value written canonically, so values that only differ in key order or in how whole
+///numbers are written compare equal.
+pub(crate) fn canonical(value: &Value) -> String {
+    let mut canonical = String::new();
+    write_canonical(value, &mut canonical);
+    canonical
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(object) => {
+            let mut entries: Vec<(&String, &Value)> = object.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(value, out);
+            }
+            out.push('}');
+        }
+        Value::Array(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(value, out);
+            }
+            out.push(']');
+        }
+        Value::Number(number) => out.push_str(&canonical_number(number)),
+        value => out.push_str(&value.to_string()),
+    }
+}
+
+///`1.0` and `1` are the same number, so write them the same way.
+fn canonical_number(number: &Number) -> String {
+    match number.as_f64() {
+        Some(float) if number.is_f64() && float.fract() == 0.0 && float.abs() < i64::MAX as f64 => {
+            (float as i64).to_string()
+        }
+        _ => number.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_content_hash() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Hash", "version": "1.0.0"},
+            "paths": {},
+            "components": {
+                "schemas": {
+                    "Pet": {"type": "object", "properties": {"age": {"type": "integer", "maximum": 30}}}
+                }
+            }
+        }"##;
+        let reordered = r##"{
+            "components": {
+                "schemas": {
+                    "Pet": {"properties": {"age": {"maximum": 30.0, "type": "integer"}}, "type": "object"}
+                }
+            },
+            "paths": {},
+            "info": {"version": "1.0.0", "title": "Hash"},
+            "openapi": "3.1.0"
+        }"##;
+        let hash = OpenApiDereferencer::from_str(spec)?
+            .dereference()?
+            .content_hash()?;
+        assert_eq!(64, hash.len());
+        assert_eq!(
+            hash,
+            OpenApiDereferencer::from_str(reordered)?
+                .dereference()?
+                .content_hash()?
+        );
+        let changed = spec.replace("30", "31");
+        assert_ne!(
+            hash,
+            OpenApiDereferencer::from_str(&changed)?
+                .dereference()?
+                .content_hash()?
+        );
+        Ok(())
+    }
+}
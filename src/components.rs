@@ -0,0 +1,312 @@
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::fmt;
+
+use indexmap::IndexMap;
+use openapiv3::v3_1::{Parameter, ReferenceOr, RequestBody, Response, SchemaObject};
+use serde_json::Value;
+
+use crate::merge::{renamed_ref, rewrite_refs};
+use crate::visit_mut::for_each_reference_mut;
+use crate::{as_item, pointer, DereferencedOpenApi, OpenApiDereferencer, OpenApiError};
+
+///The sections of `components`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ComponentKind {
+    Schemas,
+    Responses,
+    Parameters,
+    Examples,
+    RequestBodies,
+    Headers,
+    SecuritySchemes,
+    Links,
+    Callbacks,
+    PathItems,
+}
+
+impl ComponentKind {
+    pub const ALL: [ComponentKind; 10] = [
+        ComponentKind::Schemas,
+        ComponentKind::Responses,
+        ComponentKind::Parameters,
+        ComponentKind::Examples,
+        ComponentKind::RequestBodies,
+        ComponentKind::Headers,
+        ComponentKind::SecuritySchemes,
+        ComponentKind::Links,
+        ComponentKind::Callbacks,
+        ComponentKind::PathItems,
+    ];
+
+    ///The key of the section in `components`, e.g. `requestBodies`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ComponentKind::Schemas => "schemas",
+            ComponentKind::Responses => "responses",
+            ComponentKind::Parameters => "parameters",
+            ComponentKind::Examples => "examples",
+            ComponentKind::RequestBodies => "requestBodies",
+            ComponentKind::Headers => "headers",
+            ComponentKind::SecuritySchemes => "securitySchemes",
+            ComponentKind::Links => "links",
+            ComponentKind::Callbacks => "callbacks",
+            ComponentKind::PathItems => "pathItems",
+        }
+    }
+
+    ///The ref to a component of this kind, e.g. `#/components/schemas/Pet`.
+    pub fn reference(&self, name: &str) -> String {
+        pointer::push(&format!("#/components/{}", self.as_str()), name)
+    }
+}
+
+impl fmt::Display for ComponentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl OpenApiDereferencer {
+    ///Rename a component and point every ref to it, or into it, at the new name. Both the input
+    ///document and the typed spec are updated, so this works before or after dereferencing.
+    pub fn rename_component(
+        &mut self,
+        kind: ComponentKind,
+        old_name: &str,
+        new_name: &str,
+    ) -> Result<(), OpenApiError> {
+        self.input()?;
+        let section = self
+            .json
+            .get_mut("components")
+            .and_then(|components| components.get_mut(kind.as_str()))
+            .and_then(Value::as_object_mut);
+        let Some(section) = section.filter(|section| section.contains_key(old_name)) else {
+            return Err(OpenApiError::ComponentNotFound {
+                kind,
+                name: old_name.into(),
+            });
+        };
+        if section.contains_key(new_name) {
+            return Err(OpenApiError::ComponentExists {
+                kind,
+                name: new_name.into(),
+            });
+        }
+        *section = std::mem::take(section)
+            .into_iter()
+            .map(|(name, value)| {
+                if name == old_name {
+                    (new_name.to_string(), value)
+                } else {
+                    (name, value)
+                }
+            })
+            .collect();
+
+        let renames = HashMap::from([(kind.reference(old_name), kind.reference(new_name))]);
+        rewrite_refs(&mut self.json, &renames);
+        if let Some(components) = self.openapi.components.as_mut() {
+            match kind {
+                ComponentKind::Schemas => rename_key(&mut components.schemas, old_name, new_name),
+                ComponentKind::Responses => {
+                    rename_key(&mut components.responses, old_name, new_name)
+                }
+                ComponentKind::Parameters => {
+                    rename_key(&mut components.parameters, old_name, new_name)
+                }
+                ComponentKind::Examples => rename_key(&mut components.examples, old_name, new_name),
+                ComponentKind::RequestBodies => {
+                    rename_key(&mut components.request_bodies, old_name, new_name)
+                }
+                ComponentKind::Headers => rename_key(&mut components.headers, old_name, new_name),
+                ComponentKind::SecuritySchemes => {
+                    rename_key(&mut components.security_schemes, old_name, new_name)
+                }
+                ComponentKind::Links => rename_key(&mut components.links, old_name, new_name),
+                ComponentKind::Callbacks => {
+                    rename_key(&mut components.callbacks, old_name, new_name)
+                }
+                ComponentKind::PathItems => {
+                    rename_key(&mut components.path_items, old_name, new_name)
+                }
+            }
+        }
+        for_each_reference_mut(&mut self.openapi, &mut |reference| {
+            if let Some(renamed) = renamed_ref(reference, &renames) {
+                *reference = renamed;
+            }
+        });
+        //Anything indexed by location is out of date now
+        self.anchors = OnceCell::new();
+        #[cfg(feature = "validation")]
+        self.validators.borrow_mut().clear();
+        Ok(())
+    }
+}
+
+impl DereferencedOpenApi {
+    ///See `OpenApiDereferencer::rename_component`.
+    pub fn rename_component(
+        &mut self,
+        kind: ComponentKind,
+        old_name: &str,
+        new_name: &str,
+    ) -> Result<(), OpenApiError> {
+        self.0.rename_component(kind, old_name, new_name)?;
+        self.invalidate_caches();
+        Ok(())
+    }
+
+    ///Get a dereferenced schema from `components`.
+    pub fn schema(&self, name: &str) -> Result<&SchemaObject, OpenApiError> {
+        let schemas = self.openapi.components.as_ref().map(|c| &c.schemas);
+        schemas
+            .and_then(|schemas| schemas.get(name))
+            .ok_or_else(|| component_not_found(ComponentKind::Schemas, name))
+    }
+
+    ///Get a dereferenced parameter from `components`.
+    pub fn parameter(&self, name: &str) -> Result<&Parameter, OpenApiError> {
+        let parameters = self.openapi.components.as_ref().map(|c| &c.parameters);
+        component(parameters, ComponentKind::Parameters, name)
+    }
+
+    ///Get a dereferenced response from `components`.
+    pub fn response(&self, name: &str) -> Result<&Response, OpenApiError> {
+        let responses = self.openapi.components.as_ref().map(|c| &c.responses);
+        component(responses, ComponentKind::Responses, name)
+    }
+
+    ///Get a dereferenced request body from `components`.
+    pub fn request_body(&self, name: &str) -> Result<&RequestBody, OpenApiError> {
+        let request_bodies = self.openapi.components.as_ref().map(|c| &c.request_bodies);
+        component(request_bodies, ComponentKind::RequestBodies, name)
+    }
+}
+
+///Components that are refs which weren't resolved, e.g. when dereferencing skipped them, are
+///treated as missing.
+fn component<'a, T>(
+    section: Option<&'a IndexMap<String, ReferenceOr<T>>>,
+    kind: ComponentKind,
+    name: &str,
+) -> Result<&'a T, OpenApiError> {
+    section
+        .and_then(|section| section.get(name))
+        .and_then(as_item)
+        .ok_or_else(|| component_not_found(kind, name))
+}
+
+pub(crate) fn component_not_found(kind: ComponentKind, name: &str) -> OpenApiError {
+    OpenApiError::ComponentNotFound {
+        kind,
+        name: name.into(),
+    }
+}
+
+fn rename_key<V>(map: &mut IndexMap<String, V>, old_name: &str, new_name: &str) {
+    *map = std::mem::take(map)
+        .into_iter()
+        .map(|(name, value)| {
+            if name == old_name {
+                (new_name.to_string(), value)
+            } else {
+                (name, value)
+            }
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    const SPEC: &str = r##"{
+        "openapi": "3.1.0",
+        "info": {"title": "Rename", "version": "1.0.0"},
+        "paths": {
+            "/pets": {
+                "get": {
+                    "responses": {
+                        "200": {
+                            "description": "ok",
+                            "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+                        }
+                    }
+                }
+            }
+        },
+        "components": {
+            "schemas": {
+                "Pet": {"type": "object", "properties": {"name": {"$ref": "#/components/schemas/Pet/$defs/Name"}}, "$defs": {"Name": {"type": "string"}}},
+                "Owner": {"type": "object"},
+                "Node": {"type": "object", "properties": {"next": {"$ref": "#/components/schemas/Node"}}, "$defs": {"Next": {"$ref": "#/components/schemas/Node"}}}
+            }
+        }
+    }"##;
+
+    #[test]
+    pub fn test_rename_component() -> Result<()> {
+        let mut dereferencer = OpenApiDereferencer::from_str(SPEC)?;
+        assert!(matches!(
+            dereferencer.rename_component(ComponentKind::Schemas, "Pet", "Owner"),
+            Err(OpenApiError::ComponentExists { .. })
+        ));
+        assert!(matches!(
+            dereferencer.rename_component(ComponentKind::Responses, "Pet", "Animal"),
+            Err(OpenApiError::ComponentNotFound { .. })
+        ));
+        dereferencer.rename_component(ComponentKind::Schemas, "Pet", "Animal")?;
+        let json = &dereferencer.json;
+        assert!(json["components"]["schemas"].get("Pet").is_none());
+        assert_eq!(
+            "#/components/schemas/Animal/$defs/Name",
+            json["components"]["schemas"]["Animal"]["properties"]["name"]["$ref"]
+        );
+        assert_eq!(
+            "#/components/schemas/Animal",
+            json["paths"]["/pets"]["get"]["responses"]["200"]["content"]["application/json"]
+                ["schema"]["$ref"]
+        );
+        let dereferenced = dereferencer.dereference()?;
+        let components = dereferenced.openapi.components.as_ref().unwrap();
+        assert!(components.schemas.contains_key("Animal"));
+
+        let mut dereferenced = OpenApiDereferencer::from_str(SPEC)?.dereference()?;
+        dereferenced.rename_component(ComponentKind::Schemas, "Node", "Link")?;
+        let components = dereferenced.openapi.components.as_ref().unwrap();
+        let node = serde_json::to_value(&components.schemas["Link"])?;
+        assert_eq!(
+            "#/components/schemas/Link",
+            node["properties"]["next"]["$ref"]
+        );
+        assert_eq!("#/components/schemas/Link", node["$defs"]["Next"]["$ref"]);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_component_accessors() -> Result<()> {
+        let dereferenced = OpenApiDereferencer::from_str(SPEC)?.dereference()?;
+        let pet = serde_json::to_value(dereferenced.schema("Pet")?)?;
+        assert_eq!("string", pet["properties"]["name"]["type"]);
+        assert!(matches!(
+            dereferenced.schema("Cat"),
+            Err(OpenApiError::ComponentNotFound {
+                kind: ComponentKind::Schemas,
+                ..
+            })
+        ));
+        assert!(matches!(
+            dereferenced.response("Pet"),
+            Err(OpenApiError::ComponentNotFound {
+                kind: ComponentKind::Responses,
+                ..
+            })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,267 @@
+use openapiv3::v3_1::{MediaType, Operation, Response, StatusCode};
+
+use crate::as_item;
+
+///Lookups on dereferenced operations.
+pub trait OperationExt {
+    ///The response for success: the lowest `2xx` status, then `2XX`. `default` isn't used, it's
+    ///as likely to be the error response. Refs that haven't been dereferenced are skipped.
+    fn success_response(&self) -> Option<&Response>;
+}
+
+impl OperationExt for Operation {
+    fn success_response(&self) -> Option<&Response> {
+        let responses = self.responses.as_ref()?;
+        let mut codes: Vec<(&u16, &_)> = responses
+            .responses
+            .iter()
+            .filter_map(|(status, response)| match status {
+                StatusCode::Code(code) if (200..300).contains(code) => Some((code, response)),
+                _ => None,
+            })
+            .collect();
+        codes.sort_by_key(|(code, _)| **code);
+        codes
+            .into_iter()
+            .find_map(|(_, response)| as_item(response))
+            .or_else(|| {
+                responses
+                    .responses
+                    .get(&StatusCode::Range(2))
+                    .and_then(as_item)
+            })
+    }
+}
+
+///Lookups on the content of dereferenced responses.
+pub trait ResponseExt {
+    ///The JSON content, `application/json` or a `+json` type like `application/problem+json`.
+    fn json_content(&self) -> Option<&MediaType>;
+    ///The content that best fits an `Accept` header, along with its media type.
+    fn content_for(&self, accept: &str) -> Option<(&str, &MediaType)>;
+}
+
+impl ResponseExt for Response {
+    fn json_content(&self) -> Option<&MediaType> {
+        let json = |key: &str, exact: bool| {
+            let essence = essence(key);
+            if exact {
+                essence == "application/json"
+            } else {
+                essence.ends_with("+json")
+            }
+        };
+        let find = |exact| {
+            self.content
+                .iter()
+                .find(|(key, _)| json(key, exact))
+                .map(|(_, media_type)| media_type)
+        };
+        find(true).or_else(|| find(false))
+    }
+
+    fn content_for(&self, accept: &str) -> Option<(&str, &MediaType)> {
+        let media_type = negotiate_media_type(accept, self.content.keys().map(String::as_str))?;
+        self.content
+            .get_key_value(media_type)
+            .map(|(key, value)| (key.as_str(), value))
+    }
+}
+
+///Pick the media type from `available` that best fits an `Accept` header. Ranges are ranked by
+///their `q` and then by how specific they are: `application/json`, then a structured syntax
+///suffix like `application/json` for `application/problem+json`, then `application/*`, then
+///`*/*`. The media types can be ranges too, like `image/*`, and rank below the exact types they
+///cover. Ranges with `q=0` rule a media type out.
+pub fn negotiate_media_type<'a>(
+    accept: &str,
+    available: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let ranges: Vec<(String, f32)> = accept
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let media_range = essence(parts.next()?);
+            if media_range.is_empty() {
+                return None;
+            }
+            let q = parts
+                .filter_map(|parameter| parameter.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((media_range, q))
+        })
+        .collect();
+    available
+        .into_iter()
+        .filter_map(|media_type| {
+            let essence = essence(media_type);
+            //The most specific matching range decides the media type's quality
+            let (specificity, q) = ranges
+                .iter()
+                .filter_map(|(range, q)| Some((specificity(range, &essence)?, *q)))
+                .max_by_key(|(specificity, _)| *specificity)?;
+            (q > 0.0).then_some((media_type, q, specificity))
+        })
+        .fold(
+            None,
+            |best: Option<(&str, f32, u8)>, candidate| match best {
+                Some(best) if (best.1, best.2) >= (candidate.1, candidate.2) => Some(best),
+                _ => Some(candidate),
+            },
+        )
+        .map(|(media_type, _, _)| media_type)
+}
+
+///How specifically `range` matches `media_type`, or `None` if it doesn't. Either of them can be
+///a range, the less specific one decides.
+fn specificity(range: &str, media_type: &str) -> Option<u8> {
+    if range == media_type {
+        return Some(4);
+    }
+    let (kind, subtype) = media_type.split_once('/')?;
+    let (range_kind, range_subtype) = range.split_once('/')?;
+    if (kind, subtype) == ("*", "*") {
+        return Some(1);
+    }
+    if subtype == "*" {
+        return (range_kind == "*" || range_kind == kind).then_some(2.min(specificity_of(range)));
+    }
+    match (range_kind, range_subtype) {
+        ("*", "*") => Some(1),
+        (range_kind, "*") if range_kind == kind => Some(2),
+        (range_kind, suffix) if range_kind == kind => {
+            let (_, subtype_suffix) = subtype.rsplit_once('+')?;
+            (subtype_suffix == suffix).then_some(3)
+        }
+        _ => None,
+    }
+}
+
+///How specific a media range is on its own.
+fn specificity_of(range: &str) -> u8 {
+    match range.split_once('/') {
+        Some(("*", _)) => 1,
+        Some((_, "*")) => 2,
+        _ => 4,
+    }
+}
+
+///Whether a media type is JSON, either `application/json` or one with a `+json` suffix.
+pub(crate) fn is_json(media_type: &str) -> bool {
+    let essence = essence(media_type);
+    essence == "application/json" || essence.ends_with("+json")
+}
+
+///The media type without parameters, lowercased.
+fn essence(media_type: &str) -> String {
+    media_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Method, OpenApiDereferencer};
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_success_response_and_content() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Content", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "default": {"description": "Error"}
+                        }
+                    },
+                    "post": {
+                        "responses": {
+                            "default": {"description": "Error"},
+                            "202": {"description": "Accepted"},
+                            "201": {"$ref": "#/components/responses/Created"}
+                        }
+                    }
+                }
+            },
+            "components": {
+                "responses": {
+                    "Created": {
+                        "description": "Created",
+                        "content": {
+                            "text/plain": {},
+                            "application/problem+json": {},
+                            "application/json; charset=utf-8": {}
+                        }
+                    }
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let (_, _, operation) = dereferenced
+            .iter_operations()
+            .find(|(_, method, _)| *method == Method::Post)
+            .unwrap();
+        let response = operation.success_response().unwrap();
+        let (_, _, only_default) = dereferenced
+            .iter_operations()
+            .find(|(_, method, _)| *method == Method::Get)
+            .unwrap();
+        assert!(only_default.success_response().is_none());
+        assert_eq!("Created", response.description);
+        assert!(response.json_content().is_some());
+        assert_eq!(
+            Some("application/json; charset=utf-8"),
+            response.content_for("application/json").map(|(key, _)| key)
+        );
+        assert_eq!(
+            Some("text/plain"),
+            response
+                .content_for("text/*, application/json;q=0.5")
+                .map(|(key, _)| key)
+        );
+
+        let available = ["application/json", "application/problem+json", "text/html"];
+        assert_eq!(
+            Some("application/problem+json"),
+            negotiate_media_type("application/*;q=0.5, application/problem+json", available)
+        );
+        assert_eq!(
+            Some("application/problem+json"),
+            negotiate_media_type(
+                "application/json",
+                ["text/html", "application/problem+json"]
+            )
+        );
+        assert_eq!(
+            Some("text/html"),
+            negotiate_media_type("application/json;q=0, */*", available)
+        );
+        assert_eq!(
+            Some("application/json"),
+            negotiate_media_type("*/*", available)
+        );
+        assert_eq!(None, negotiate_media_type("image/png", available));
+
+        let ranges = ["application/json", "image/*", "*/*"];
+        assert_eq!(Some("image/*"), negotiate_media_type("image/png", ranges));
+        assert_eq!(Some("*/*"), negotiate_media_type("text/html", ranges));
+        assert_eq!(
+            Some("image/png"),
+            negotiate_media_type("image/png", ["image/*", "image/png"])
+        );
+        assert_eq!(
+            Some("image/*"),
+            negotiate_media_type("image/*, */*;q=0.1", ["*/*", "image/*"])
+        );
+        assert_eq!(None, negotiate_media_type("text/*", ["image/*"]));
+        Ok(())
+    }
+}
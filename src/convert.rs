@@ -0,0 +1,558 @@
+use std::fmt;
+
+use serde_json::{json, Map, Value};
+
+use crate::{pointer, DereferencedOpenApi, OpenApiDereferencer, OpenApiError};
+
+///Something that changed converting between OpenAPI versions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionNote {
+    ///JSON pointer to the converted part of the document
+    pub location: String,
+    pub message: String,
+    ///Whether information was dropped because the target version can't express it
+    pub lossy: bool,
+}
+
+impl fmt::Display for ConversionNote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lossy = if self.lossy { " (lossy)" } else { "" };
+        write!(f, "{}: {}{lossy}", self.location, self.message)
+    }
+}
+
+struct Notes(Vec<ConversionNote>);
+
+impl Notes {
+    fn changed(&mut self, location: &str, message: impl Into<String>) {
+        self.0.push(ConversionNote {
+            location: location.into(),
+            message: message.into(),
+            lossy: false,
+        });
+    }
+
+    fn lost(&mut self, location: &str, message: impl Into<String>) {
+        self.0.push(ConversionNote {
+            location: location.into(),
+            message: message.into(),
+            lossy: true,
+        });
+    }
+}
+
+///Keywords holding a single subschema.
+pub(crate) const SCHEMA_KEYWORDS: [&str; 11] = [
+    "items",
+    "not",
+    "additionalProperties",
+    "additionalItems",
+    "if",
+    "then",
+    "else",
+    "contains",
+    "propertyNames",
+    "unevaluatedItems",
+    "unevaluatedProperties",
+];
+///Keywords holding a list of subschemas.
+pub(crate) const SCHEMA_LIST_KEYWORDS: [&str; 4] = ["allOf", "anyOf", "oneOf", "prefixItems"];
+///Keywords holding a map of subschemas.
+pub(crate) const SCHEMA_MAP_KEYWORDS: [&str; 5] = [
+    "properties",
+    "patternProperties",
+    "$defs",
+    "definitions",
+    "dependentSchemas",
+];
+
+///Call `f` on every schema in an OpenAPI document, parents before their subschemas.
+pub(crate) fn for_each_schema(
+    document: &mut Value,
+    location: &str,
+    f: &mut dyn FnMut(&mut Map<String, Value>, &str),
+) {
+    let Value::Object(object) = document else {
+        if let Value::Array(values) = document {
+            for (i, value) in values.iter_mut().enumerate() {
+                for_each_schema(value, &format!("{location}/{i}"), f);
+            }
+        }
+        return;
+    };
+    for (key, value) in object.iter_mut() {
+        let child = pointer::push(location, key);
+        if key.starts_with("x-") || key == "example" || key == "examples" {
+            continue;
+        }
+        if key == "schema" {
+            for_each_subschema(value, &child, f);
+        } else if key == "schemas" && location == "/components" {
+            if let Value::Object(schemas) = value {
+                for (name, value) in schemas.iter_mut() {
+                    for_each_subschema(value, &pointer::push(&child, name), f);
+                }
+            }
+        } else {
+            for_each_schema(value, &child, f);
+        }
+    }
+}
+
+///Call `f` on `value` and every subschema inside it, parents first.
+pub(crate) fn for_each_subschema(
+    value: &mut Value,
+    location: &str,
+    f: &mut dyn FnMut(&mut Map<String, Value>, &str),
+) {
+    let Value::Object(object) = value else {
+        return;
+    };
+    f(object, location);
+    for (key, value) in object.iter_mut() {
+        let child = pointer::push(location, key);
+        if SCHEMA_KEYWORDS.contains(&key.as_str()) {
+            for_each_subschema(value, &child, f);
+        } else if SCHEMA_LIST_KEYWORDS.contains(&key.as_str()) {
+            if let Value::Array(values) = value {
+                for (i, value) in values.iter_mut().enumerate() {
+                    for_each_subschema(value, &format!("{child}/{i}"), f);
+                }
+            }
+        } else if SCHEMA_MAP_KEYWORDS.contains(&key.as_str()) {
+            if let Value::Object(values) = value {
+                for (name, value) in values.iter_mut() {
+                    for_each_subschema(value, &pointer::push(&child, name), f);
+                }
+            }
+        }
+    }
+}
+
+///3.1 schema keywords 3.0 has no equivalent for.
+const UNSUPPORTED_IN_3_0: [&str; 22] = [
+    "$id",
+    "$anchor",
+    "$dynamicAnchor",
+    "$dynamicRef",
+    "$schema",
+    "$comment",
+    "$defs",
+    "prefixItems",
+    "if",
+    "then",
+    "else",
+    "contains",
+    "minContains",
+    "maxContains",
+    "propertyNames",
+    "patternProperties",
+    "dependentRequired",
+    "dependentSchemas",
+    "unevaluatedProperties",
+    "unevaluatedItems",
+    "contentMediaType",
+    "contentEncoding",
+];
+
+fn downconvert_schema(schema: &mut Map<String, Value>, location: &str, notes: &mut Notes) {
+    for keyword in UNSUPPORTED_IN_3_0 {
+        if schema.remove(keyword).is_some() {
+            notes.lost(
+                location,
+                format!("Removed {keyword}, which 3.0 doesn't support"),
+            );
+        }
+    }
+    let types = match schema.remove("type") {
+        Some(Value::Array(types)) => Some(types),
+        Some(Value::String(kind)) if kind == "null" => Some(vec![Value::String(kind)]),
+        Some(kind) => {
+            schema.insert("type".into(), kind);
+            None
+        }
+        None => None,
+    };
+    if let Some(types) = types {
+        let nullable = types.iter().any(|t| t == "null");
+        let types: Vec<Value> = types.into_iter().filter(|t| t != "null").collect();
+        match types.as_slice() {
+            [] => {
+                //3.0 only has nullable, which needs a type to apply to
+                schema.insert("type".into(), "string".into());
+                schema.insert("enum".into(), Value::Array(vec![Value::Null]));
+                notes.changed(location, "Converted type null to a null only enum");
+            }
+            [single] => {
+                schema.insert("type".into(), single.clone());
+            }
+            _ => {
+                let any_of: Vec<Value> = types
+                    .iter()
+                    .map(|t| match nullable {
+                        true => json!({ "type": t, "nullable": true }),
+                        false => json!({ "type": t }),
+                    })
+                    .collect();
+                //Keep an existing anyOf by requiring both
+                match schema.remove("anyOf") {
+                    Some(existing) => {
+                        let all_of = schema
+                            .entry("allOf")
+                            .or_insert_with(|| Value::Array(vec![]));
+                        if let Value::Array(all_of) = all_of {
+                            all_of.push(json!({ "anyOf": existing }));
+                            all_of.push(json!({ "anyOf": any_of }));
+                        }
+                    }
+                    None => {
+                        schema.insert("anyOf".into(), Value::Array(any_of));
+                    }
+                }
+                notes.changed(location, "Converted a type array to anyOf");
+            }
+        }
+        if nullable {
+            schema.insert("nullable".into(), Value::Bool(true));
+            notes.changed(location, "Converted a null type to nullable");
+        }
+    }
+    if let Some(value) = schema.remove("const") {
+        schema.insert("enum".into(), Value::Array(vec![value]));
+        notes.changed(location, "Converted const to a single value enum");
+    }
+    for (exclusive, bound) in [
+        ("exclusiveMinimum", "minimum"),
+        ("exclusiveMaximum", "maximum"),
+    ] {
+        let Some(Value::Number(value)) = schema.get(exclusive).cloned() else {
+            continue;
+        };
+        //With both set the stricter bound applies
+        let bound_is_stricter = schema
+            .get(bound)
+            .and_then(Value::as_f64)
+            .zip(value.as_f64())
+            .is_some_and(|(bound_value, exclusive_value)| match exclusive {
+                "exclusiveMinimum" => bound_value > exclusive_value,
+                _ => bound_value < exclusive_value,
+            });
+        if bound_is_stricter {
+            schema.remove(exclusive);
+            notes.changed(
+                location,
+                format!("Removed {exclusive}, which {bound} already implies"),
+            );
+        } else {
+            schema.insert(bound.into(), Value::Number(value));
+            schema.insert(exclusive.into(), Value::Bool(true));
+            notes.changed(
+                location,
+                format!("Converted numeric {exclusive} to a boolean"),
+            );
+        }
+    }
+    if let Some(Value::Array(mut examples)) = schema.remove("examples") {
+        if examples.len() > 1 {
+            notes.lost(location, "Kept only the first of the schema's examples");
+        }
+        if !examples.is_empty() {
+            schema.insert("example".into(), examples.swap_remove(0));
+        }
+    }
+}
+
+fn upconvert_schema(schema: &mut Map<String, Value>, location: &str, notes: &mut Notes) {
+    if let Some(nullable) = schema.remove("nullable") {
+        if nullable == Value::Bool(true) {
+            match schema.get_mut("type") {
+                Some(Value::String(kind)) => {
+                    let kind = std::mem::take(kind);
+                    schema.insert("type".into(), json!([kind, "null"]));
+                }
+                Some(Value::Array(kinds)) => {
+                    if !kinds.iter().any(|kind| kind == "null") {
+                        kinds.push("null".into());
+                    }
+                }
+                Some(_) => {}
+                //An enum gets null added below
+                None if schema.contains_key("enum") => {}
+                None => {
+                    //Like `allOf` with a `$ref`, which there's no type to add null to
+                    let inner = std::mem::take(schema);
+                    schema.insert(
+                        "anyOf".into(),
+                        json!([Value::Object(inner), {"type": "null"}]),
+                    );
+                }
+            }
+            if let Some(Value::Array(values)) = schema.get_mut("enum") {
+                if !values.contains(&Value::Null) {
+                    values.push(Value::Null);
+                }
+            }
+            notes.changed(location, "Converted nullable to a null type");
+        }
+    }
+    for (exclusive, bound) in [
+        ("exclusiveMinimum", "minimum"),
+        ("exclusiveMaximum", "maximum"),
+    ] {
+        match schema.get(exclusive) {
+            Some(Value::Bool(true)) => match schema.remove(bound) {
+                Some(value) => {
+                    schema.insert(exclusive.into(), value);
+                    notes.changed(
+                        location,
+                        format!("Converted boolean {exclusive} to a number"),
+                    );
+                }
+                None => {
+                    schema.remove(exclusive);
+                    notes.lost(location, format!("Removed {exclusive} without a {bound}"));
+                }
+            },
+            Some(Value::Bool(false)) => {
+                schema.remove(exclusive);
+            }
+            _ => {}
+        }
+    }
+    if let Some(example) = schema.remove("example") {
+        if !schema.contains_key("examples") {
+            schema.insert("examples".into(), Value::Array(vec![example]));
+            notes.changed(location, "Converted example to examples");
+        }
+    }
+    if schema.get("type").is_some_and(|t| t == "string") {
+        match schema.get("format").and_then(Value::as_str) {
+            Some("binary") => {
+                schema.remove("format");
+                schema.insert("contentMediaType".into(), "application/octet-stream".into());
+                notes.changed(location, "Converted format binary to contentMediaType");
+            }
+            Some("byte") => {
+                schema.remove("format");
+                schema.insert("contentEncoding".into(), "base64".into());
+                notes.changed(location, "Converted format byte to contentEncoding");
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_version(document: &Value, prefix: &str) -> bool {
+    document
+        .get("openapi")
+        .and_then(Value::as_str)
+        .is_some_and(|version| version.starts_with(prefix))
+}
+
+impl DereferencedOpenApi {
+    ///Convert the dereferenced spec to an OpenAPI 3.0 document, for tools that don't support
+    ///3.1 yet. Anything 3.0 can't express is dropped and reported in the notes.
+    pub fn to_v3_0(&self) -> Result<(Value, Vec<ConversionNote>), OpenApiError> {
+        let mut document =
+            serde_json::to_value(&self.openapi).map_err(|e| OpenApiError::ParsingError {
+                msg: format!("Error serializing the dereferenced spec {e}"),
+                location: None,
+            })?;
+        let mut notes = Notes(vec![]);
+        for (location, key) in [
+            ("", "webhooks"),
+            ("", "jsonSchemaDialect"),
+            ("/info", "summary"),
+            ("/info/license", "identifier"),
+            ("/components", "pathItems"),
+        ] {
+            let removed = document
+                .pointer_mut(location)
+                .and_then(Value::as_object_mut)
+                .and_then(|parent| parent.remove(key));
+            //Empty maps are serialized even when the input didn't have them
+            if removed.is_some_and(|removed| removed != json!({})) {
+                notes.lost(
+                    location,
+                    format!("Removed {key}, which 3.0 doesn't support"),
+                );
+            }
+        }
+        if let Value::Object(object) = &mut document {
+            object.insert("openapi".into(), "3.0.3".into());
+            object
+                .entry("paths")
+                .or_insert_with(|| Value::Object(Map::new()));
+        }
+        for_each_schema(&mut document, "", &mut |schema, location| {
+            downconvert_schema(schema, location, &mut notes)
+        });
+        Ok((document, notes.0))
+    }
+}
+
+impl OpenApiDereferencer {
+    ///Load an OpenAPI 3.0 document by converting its 3.0-isms, like `nullable` and boolean
+    ///`exclusiveMinimum`, to their 3.1 form first. 3.1 documents load as is.
+    pub fn from_v3_0_value(mut json: Value) -> Result<(Self, Vec<ConversionNote>), OpenApiError> {
+        let mut notes = Notes(vec![]);
+        if is_version(&json, "3.0") {
+            if let Value::Object(object) = &mut json {
+                object.insert("openapi".into(), "3.1.0".into());
+            }
+            for_each_schema(&mut json, "", &mut |schema, location| {
+                upconvert_schema(schema, location, &mut notes)
+            });
+        }
+        Ok((Self::from_value(json)?, notes.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_to_v3_0() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Convert", "version": "1.0.0", "license": {"name": "MIT", "identifier": "MIT"}},
+            "webhooks": {
+                "newPet": {"post": {"responses": {"200": {"description": "ok"}}}}
+            },
+            "components": {
+                "schemas": {
+                    "Pet": {
+                        "type": "object",
+                        "properties": {
+                            "name": {"type": ["string", "null"]},
+                            "age": {"type": "integer", "exclusiveMinimum": 0},
+                            "kind": {"const": "dog"},
+                            "tags": {"type": "array", "prefixItems": [{"type": "string"}]}
+                        }
+                    }
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let (converted, notes) = dereferenced.to_v3_0()?;
+        assert_eq!("3.0.3", converted["openapi"]);
+        assert!(converted.get("webhooks").is_none());
+        assert!(converted["info"]["license"].get("identifier").is_none());
+        assert_eq!(json!({}), converted["paths"]);
+        let properties = &converted["components"]["schemas"]["Pet"]["properties"];
+        assert_eq!(
+            json!({"type": "string", "nullable": true}),
+            properties["name"]
+        );
+        assert_eq!(
+            json!({"type": "integer", "minimum": 0.0, "exclusiveMinimum": true}),
+            properties["age"]
+        );
+        assert_eq!(json!({"enum": ["dog"]}), properties["kind"]);
+        assert_eq!(json!({"type": "array"}), properties["tags"]);
+        assert_eq!(3, notes.iter().filter(|note| note.lossy).count());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_to_v3_0_keeps_constraints() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Convert", "version": "1.0.0"},
+            "components": {
+                "schemas": {
+                    "Id": {
+                        "type": ["string", "integer", "null"],
+                        "anyOf": [{"minLength": 1}, {"minimum": 1}]
+                    },
+                    "Age": {"type": "integer", "minimum": 5, "exclusiveMinimum": 0},
+                    "Score": {"type": "number", "maximum": 10, "exclusiveMaximum": 5},
+                    "Nothing": {"type": "null"}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let (converted, _) = dereferenced.to_v3_0()?;
+        let schemas = &converted["components"]["schemas"];
+        assert_eq!(
+            json!({
+                "nullable": true,
+                "allOf": [
+                    {"anyOf": [{"minLength": 1}, {"minimum": 1.0}]},
+                    {"anyOf": [
+                        {"type": "string", "nullable": true},
+                        {"type": "integer", "nullable": true}
+                    ]}
+                ]
+            }),
+            schemas["Id"]
+        );
+        assert_eq!(json!({"type": "integer", "minimum": 5.0}), schemas["Age"]);
+        assert_eq!(
+            json!({"type": "number", "maximum": 5.0, "exclusiveMaximum": true}),
+            schemas["Score"]
+        );
+        assert_eq!(
+            json!({"type": "string", "nullable": true, "enum": [null]}),
+            schemas["Nothing"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_from_v3_0_value() -> Result<()> {
+        let spec = json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Convert", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "parameters": [
+                            {"name": "min", "in": "query", "schema": {"type": "integer", "minimum": 1, "exclusiveMinimum": true}}
+                        ],
+                        "responses": {"200": {"description": "ok"}}
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Pet": {
+                        "type": "object",
+                        "properties": {
+                            "name": {"type": "string", "nullable": true, "example": "Rex"},
+                            "owner": {"nullable": true, "allOf": [{"$ref": "#/components/schemas/Owner"}]},
+                            "photo": {"type": "string", "format": "binary"}
+                        }
+                    },
+                    "Owner": {"type": "object"}
+                }
+            }
+        });
+        let (dereferencer, notes) = OpenApiDereferencer::from_v3_0_value(spec)?;
+        let json = &dereferencer.json;
+        assert_eq!("3.1.0", json["openapi"]);
+        assert_eq!(
+            json!({"type": "integer", "exclusiveMinimum": 1}),
+            json["paths"]["/pets"]["get"]["parameters"][0]["schema"]
+        );
+        let properties = &json["components"]["schemas"]["Pet"]["properties"];
+        assert_eq!(
+            json!({"type": ["string", "null"], "examples": ["Rex"]}),
+            properties["name"]
+        );
+        assert_eq!(
+            json!({"anyOf": [{"allOf": [{"$ref": "#/components/schemas/Owner"}]}, {"type": "null"}]}),
+            properties["owner"]
+        );
+        assert_eq!(
+            json!({"type": "string", "contentMediaType": "application/octet-stream"}),
+            properties["photo"]
+        );
+        assert_eq!(5, notes.len());
+        assert!(dereferencer.dereference().is_ok());
+        Ok(())
+    }
+}
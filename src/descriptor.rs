@@ -0,0 +1,699 @@
+use openapiv3::schemars::schema::{
+    InstanceType, Schema as SchemarsSchema, SchemaObject, SingleOrVec, SubschemaValidation,
+};
+use openapiv3::v3_1::{MediaType, Operation, Parameter, ParameterSchemaOrContent};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::flatten::flatten_schema_all_of;
+use crate::nullable::{is_null, is_nullable, non_null_types};
+use crate::parameters::parameter_data;
+use crate::{as_item, DereferencedOpenApi, Discriminator, Method, OpenApiError};
+
+///Recursive schemas are described as [`TypeDescriptor::Any`] past this many levels.
+const MAX_DEPTH: usize = 16;
+
+///An operation boiled down for code generators, with everything resolved and `allOf` merged so
+///there's no need to walk the openapiv3 and schemars types. See
+///[`DereferencedOpenApi::operation_descriptors`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct OperationDescriptor {
+    pub operation_id: Option<String>,
+    pub method: Method,
+    pub path: String,
+    pub path_segments: Vec<PathSegment>,
+    ///Path level parameters that apply followed by the operation's own
+    pub parameters: Vec<ParameterDescriptor>,
+    pub request_body: Option<RequestBodyDescriptor>,
+    ///In the order they're in the spec, with `default` last
+    pub responses: Vec<ResponseDescriptor>,
+    pub summary: Option<String>,
+    pub tags: Vec<String>,
+    pub deprecated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum PathSegment {
+    Literal(String),
+    ///A `{name}` template parameter
+    Parameter(String),
+    ///A segment mixing text and parameters, like `{name}.{ext}`
+    Template(Vec<PathSegment>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum ParameterLocation {
+    Path,
+    Query,
+    Header,
+    Cookie,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct ParameterDescriptor {
+    pub name: String,
+    pub location: ParameterLocation,
+    pub required: bool,
+    pub deprecated: bool,
+    pub schema: TypeDescriptor,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct RequestBodyDescriptor {
+    pub required: bool,
+    pub content: Vec<ContentDescriptor>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct ResponseDescriptor {
+    ///`200`, `4XX` or `default`
+    pub status: String,
+    pub description: String,
+    pub content: Vec<ContentDescriptor>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct ContentDescriptor {
+    pub media_type: String,
+    pub schema: TypeDescriptor,
+}
+
+///The shape of a schema as a code generator sees it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum TypeDescriptor {
+    ///Anything goes, e.g. `{}` or a schema without a type
+    Any,
+    Null,
+    Boolean,
+    Integer {
+        format: Option<String>,
+    },
+    Number {
+        format: Option<String>,
+    },
+    String {
+        format: Option<String>,
+    },
+    Array {
+        items: Box<TypeDescriptor>,
+    },
+    Object {
+        properties: Vec<PropertyDescriptor>,
+        ///The type of properties that aren't listed, `None` if they're not allowed
+        #[serde(rename = "additionalProperties")]
+        additional_properties: Option<Box<TypeDescriptor>>,
+    },
+    ///One of a fixed set of values, from `enum` or `const`
+    Enum {
+        values: Vec<Value>,
+        ///The type the schema gives the values, if it does
+        base: Option<Box<TypeDescriptor>>,
+    },
+    ///Any of several types, from `oneOf`, `anyOf` or a list of types
+    Union {
+        variants: Vec<TypeDescriptor>,
+        ///Picks the variant for `oneOf` and `anyOf`s that have one
+        discriminator: Option<Discriminator>,
+    },
+    Nullable {
+        inner: Box<TypeDescriptor>,
+    },
+    ///A recursive `$ref` that was left in place when dereferencing
+    Reference {
+        reference: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct PropertyDescriptor {
+    pub name: String,
+    pub required: bool,
+    pub schema: TypeDescriptor,
+}
+
+///Describe a dereferenced schema, merging any `allOf` first.
+pub fn describe_schema(schema: &SchemarsSchema) -> TypeDescriptor {
+    let mut schema = schema.clone();
+    flatten_schema_all_of(&mut schema);
+    describe(&schema, 0)
+}
+
+fn describe(schema: &SchemarsSchema, depth: usize) -> TypeDescriptor {
+    let object = match schema {
+        SchemarsSchema::Bool(_) => return TypeDescriptor::Any,
+        SchemarsSchema::Object(object) => object,
+    };
+    if let Some(reference) = &object.reference {
+        return TypeDescriptor::Reference {
+            reference: reference.clone(),
+        };
+    }
+    if depth > MAX_DEPTH {
+        return TypeDescriptor::Any;
+    }
+    let inner = describe_non_null(schema, depth);
+    let covers_null = matches!(
+        inner,
+        TypeDescriptor::Any | TypeDescriptor::Null | TypeDescriptor::Nullable { .. }
+    );
+    if is_nullable(schema) && !covers_null {
+        TypeDescriptor::Nullable {
+            inner: Box::new(inner),
+        }
+    } else {
+        inner
+    }
+}
+
+fn describe_non_null(schema: &SchemarsSchema, depth: usize) -> TypeDescriptor {
+    let SchemarsSchema::Object(object) = schema else {
+        return TypeDescriptor::Any;
+    };
+    let values: Option<Vec<Value>> = object
+        .enum_values
+        .clone()
+        .or_else(|| object.const_value.clone().map(|value| vec![value]));
+    if let Some(values) = values {
+        if values.iter().all(Value::is_null) {
+            return TypeDescriptor::Null;
+        }
+        let base = (!non_null_types(schema).is_empty())
+            .then(|| Box::new(describe_types(schema, object, depth)));
+        return TypeDescriptor::Enum {
+            values: values
+                .into_iter()
+                .filter(|value| !value.is_null())
+                .collect(),
+            base,
+        };
+    }
+    let branches: Vec<&SchemarsSchema> = object
+        .subschemas
+        .iter()
+        .flat_map(|subschemas| {
+            [&subschemas.one_of, &subschemas.any_of]
+                .into_iter()
+                .flatten()
+                .flatten()
+        })
+        .filter(|branch| !is_null(branch))
+        .collect();
+    if !branches.is_empty() {
+        //Properties next to the `oneOf` are shared by every branch
+        let base = object
+            .object
+            .as_ref()
+            .is_some_and(|validation| !validation.properties.is_empty())
+            .then(|| base_schema(object));
+        let describe_branch = |branch: &&SchemarsSchema| match &base {
+            Some(base) => describe(&with_base(base, branch), depth + 1),
+            None => describe(branch, depth + 1),
+        };
+        return match branches.as_slice() {
+            [only] => describe_branch(only),
+            branches => TypeDescriptor::Union {
+                variants: branches.iter().map(describe_branch).collect(),
+                discriminator: Discriminator::of(schema),
+            },
+        };
+    }
+    describe_types(schema, object, depth)
+}
+
+///Describe a schema by its `type`s.
+fn describe_types(schema: &SchemarsSchema, object: &SchemaObject, depth: usize) -> TypeDescriptor {
+    let types = non_null_types(schema);
+    match types.as_slice() {
+        [] if object.object.is_some() => describe_type(schema, InstanceType::Object, depth),
+        [] if object.array.is_some() => describe_type(schema, InstanceType::Array, depth),
+        [] if object.instance_type.is_some() => TypeDescriptor::Null,
+        [] => TypeDescriptor::Any,
+        [instance_type] => describe_type(schema, *instance_type, depth),
+        types => TypeDescriptor::Union {
+            variants: types
+                .iter()
+                .map(|instance_type| describe_type(schema, *instance_type, depth))
+                .collect(),
+            discriminator: None,
+        },
+    }
+}
+
+///A schema without its `oneOf`, `anyOf` and discriminator, to merge into each branch.
+fn base_schema(object: &SchemaObject) -> SchemaObject {
+    let mut base = object.clone();
+    if let Some(subschemas) = base.subschemas.as_mut() {
+        subschemas.one_of = None;
+        subschemas.any_of = None;
+    }
+    base.extensions.remove("discriminator");
+    base
+}
+
+///`branch` with `base` merged into it, as if they were an `allOf`.
+fn with_base(base: &SchemaObject, branch: &SchemarsSchema) -> SchemarsSchema {
+    let mut merged = base.clone();
+    let subschemas = merged
+        .subschemas
+        .get_or_insert_with(|| Box::new(SubschemaValidation::default()));
+    subschemas
+        .all_of
+        .get_or_insert_with(Vec::new)
+        .push(branch.clone());
+    let mut merged = SchemarsSchema::Object(merged);
+    flatten_schema_all_of(&mut merged);
+    merged
+}
+
+fn describe_type(
+    schema: &SchemarsSchema,
+    instance_type: InstanceType,
+    depth: usize,
+) -> TypeDescriptor {
+    let SchemarsSchema::Object(object) = schema else {
+        return TypeDescriptor::Any;
+    };
+    let format = object.format.clone();
+    match instance_type {
+        InstanceType::Null => TypeDescriptor::Null,
+        InstanceType::Boolean => TypeDescriptor::Boolean,
+        InstanceType::Integer => TypeDescriptor::Integer { format },
+        InstanceType::Number => TypeDescriptor::Number { format },
+        InstanceType::String => TypeDescriptor::String { format },
+        InstanceType::Array => {
+            let items = match object.array.as_ref().and_then(|array| array.items.as_ref()) {
+                Some(SingleOrVec::Single(items)) => describe(items, depth + 1),
+                Some(SingleOrVec::Vec(items)) => items
+                    .first()
+                    .map_or(TypeDescriptor::Any, |items| describe(items, depth + 1)),
+                None => TypeDescriptor::Any,
+            };
+            TypeDescriptor::Array {
+                items: Box::new(items),
+            }
+        }
+        InstanceType::Object => {
+            let validation = object.object.as_deref();
+            let properties = validation
+                .into_iter()
+                .flat_map(|validation| &validation.properties)
+                .map(|(name, property)| PropertyDescriptor {
+                    name: name.clone(),
+                    required: validation.is_some_and(|v| v.required.contains(name)),
+                    schema: describe(property, depth + 1),
+                })
+                .collect();
+            let additional_properties = match validation
+                .and_then(|validation| validation.additional_properties.as_deref())
+            {
+                Some(SchemarsSchema::Bool(false)) => None,
+                Some(additional) => Some(Box::new(describe(additional, depth + 1))),
+                None => Some(Box::new(TypeDescriptor::Any)),
+            };
+            TypeDescriptor::Object {
+                properties,
+                additional_properties,
+            }
+        }
+    }
+}
+
+fn describe_content<'a>(
+    content: impl IntoIterator<Item = (&'a String, &'a MediaType)>,
+) -> Vec<ContentDescriptor> {
+    content
+        .into_iter()
+        .map(|(media_type, content)| ContentDescriptor {
+            media_type: media_type.clone(),
+            schema: content
+                .schema
+                .as_ref()
+                .map_or(TypeDescriptor::Any, |schema| {
+                    describe_schema(&schema.json_schema)
+                }),
+        })
+        .collect()
+}
+
+fn describe_parameter(parameter: &Parameter) -> ParameterDescriptor {
+    let data = parameter_data(parameter);
+    let location = match parameter {
+        Parameter::Path { .. } => ParameterLocation::Path,
+        Parameter::Query { .. } => ParameterLocation::Query,
+        Parameter::Header { .. } => ParameterLocation::Header,
+        Parameter::Cookie { .. } => ParameterLocation::Cookie,
+    };
+    let schema = match &data.format {
+        ParameterSchemaOrContent::Schema(schema) => describe_schema(&schema.json_schema),
+        ParameterSchemaOrContent::Content(content) => describe_content(content)
+            .into_iter()
+            .next()
+            .map_or(TypeDescriptor::Any, |content| content.schema),
+    };
+    ParameterDescriptor {
+        name: data.name.clone(),
+        location,
+        required: data.required,
+        deprecated: data.deprecated.unwrap_or(false),
+        schema,
+    }
+}
+
+fn path_segments(path: &str) -> Vec<PathSegment> {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| {
+            let mut parts = segment_parts(segment);
+            match parts.len() {
+                1 => parts.remove(0),
+                _ => PathSegment::Template(parts),
+            }
+        })
+        .collect()
+}
+
+///The text and `{name}` parameters in a path segment.
+fn segment_parts(mut segment: &str) -> Vec<PathSegment> {
+    let mut parts = vec![];
+    while let Some((start, end)) = segment
+        .find('{')
+        .and_then(|start| Some((start, start + segment[start..].find('}')?)))
+    {
+        if start > 0 {
+            parts.push(PathSegment::Literal(segment[..start].into()));
+        }
+        parts.push(PathSegment::Parameter(segment[start + 1..end].into()));
+        segment = &segment[end + 1..];
+    }
+    if !segment.is_empty() {
+        parts.push(PathSegment::Literal(segment.into()));
+    }
+    parts
+}
+
+impl DereferencedOpenApi {
+    ///Describe every operation in the spec, see [`OperationDescriptor`].
+    pub fn operation_descriptors(&self) -> Result<Vec<OperationDescriptor>, OpenApiError> {
+        self.iter_operations()
+            .map(|(path, method, operation)| self.describe_operation(path, method, operation))
+            .collect()
+    }
+
+    ///Describe a single operation.
+    pub fn operation_descriptor(
+        &self,
+        path: &str,
+        method: Method,
+    ) -> Result<OperationDescriptor, OpenApiError> {
+        let operation = method.operation(self.path_item(path)?).ok_or_else(|| {
+            OpenApiError::OperationNotFound {
+                path: path.into(),
+                method,
+            }
+        })?;
+        self.describe_operation(path, method, operation)
+    }
+
+    fn describe_operation(
+        &self,
+        path: &str,
+        method: Method,
+        operation: &Operation,
+    ) -> Result<OperationDescriptor, OpenApiError> {
+        let parameters = self
+            .effective_parameters(path, method)?
+            .into_iter()
+            .map(describe_parameter)
+            .collect();
+        let request_body = operation
+            .request_body
+            .as_ref()
+            .and_then(as_item)
+            .map(|request_body| RequestBodyDescriptor {
+                required: request_body.required,
+                content: describe_content(&request_body.content),
+            });
+        let mut responses = vec![];
+        if let Some(operation_responses) = &operation.responses {
+            let statuses = operation_responses
+                .responses
+                .iter()
+                .map(|(status, response)| (status.to_string(), response))
+                .chain(
+                    operation_responses
+                        .default
+                        .iter()
+                        .map(|response| ("default".to_string(), response)),
+                );
+            for (status, response) in statuses {
+                if let Some(response) = as_item(response) {
+                    responses.push(ResponseDescriptor {
+                        status,
+                        description: response.description.clone(),
+                        content: describe_content(&response.content),
+                    });
+                }
+            }
+        }
+        Ok(OperationDescriptor {
+            operation_id: operation.operation_id.clone(),
+            method,
+            path: path.into(),
+            path_segments: path_segments(path),
+            parameters,
+            request_body,
+            responses,
+            summary: operation.summary.clone(),
+            tags: operation.tags.clone(),
+            deprecated: operation.deprecated,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_operation_descriptors() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Descriptors", "version": "1.0.0"},
+            "paths": {
+                "/pets/{petId}": {
+                    "parameters": [{"name": "petId", "in": "path", "required": true, "schema": {"type": "integer", "format": "int64"}}],
+                    "patch": {
+                        "operationId": "updatePet",
+                        "parameters": [{"name": "fields", "in": "query", "schema": {"type": "array", "items": {"type": "string"}}}],
+                        "requestBody": {
+                            "required": true,
+                            "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+                        },
+                        "responses": {
+                            "200": {
+                                "description": "The pet",
+                                "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+                            },
+                            "default": {"description": "Error"}
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Pet": {
+                        "allOf": [
+                            {"type": "object", "required": ["id"], "properties": {"id": {"type": "integer"}}},
+                            {
+                                "type": "object",
+                                "properties": {
+                                    "kind": {"enum": ["dog", "cat"]},
+                                    "owner": {"type": ["string", "null"]}
+                                },
+                                "additionalProperties": false
+                            }
+                        ]
+                    }
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let descriptors = dereferenced.operation_descriptors()?;
+        assert_eq!(1, descriptors.len());
+        let descriptor = &descriptors[0];
+        assert_eq!(
+            descriptor,
+            &dereferenced.operation_descriptor("/pets/{petId}", Method::Patch)?
+        );
+        assert_eq!(Some("updatePet"), descriptor.operation_id.as_deref());
+        assert_eq!(
+            vec![
+                PathSegment::Literal("pets".into()),
+                PathSegment::Parameter("petId".into())
+            ],
+            descriptor.path_segments
+        );
+        let parameters: Vec<(&str, ParameterLocation, bool, &TypeDescriptor)> = descriptor
+            .parameters
+            .iter()
+            .map(|p| (p.name.as_str(), p.location, p.required, &p.schema))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "petId",
+                    ParameterLocation::Path,
+                    true,
+                    &TypeDescriptor::Integer {
+                        format: Some("int64".into())
+                    }
+                ),
+                (
+                    "fields",
+                    ParameterLocation::Query,
+                    false,
+                    &TypeDescriptor::Array {
+                        items: Box::new(TypeDescriptor::String { format: None })
+                    }
+                ),
+            ],
+            parameters
+        );
+
+        let pet = TypeDescriptor::Object {
+            properties: vec![
+                PropertyDescriptor {
+                    name: "id".into(),
+                    required: true,
+                    schema: TypeDescriptor::Integer { format: None },
+                },
+                PropertyDescriptor {
+                    name: "kind".into(),
+                    required: false,
+                    schema: TypeDescriptor::Enum {
+                        values: vec!["dog".into(), "cat".into()],
+                        base: None,
+                    },
+                },
+                PropertyDescriptor {
+                    name: "owner".into(),
+                    required: false,
+                    schema: TypeDescriptor::Nullable {
+                        inner: Box::new(TypeDescriptor::String { format: None }),
+                    },
+                },
+            ],
+            additional_properties: None,
+        };
+        let request_body = descriptor.request_body.as_ref().unwrap();
+        assert!(request_body.required);
+        assert_eq!("application/json", request_body.content[0].media_type);
+        assert_eq!(pet, request_body.content[0].schema);
+        let statuses: Vec<&str> = descriptor
+            .responses
+            .iter()
+            .map(|response| response.status.as_str())
+            .collect();
+        assert_eq!(vec!["200", "default"], statuses);
+        assert_eq!(pet, descriptor.responses[0].content[0].schema);
+
+        let json = serde_json::to_value(descriptor)?;
+        assert_eq!("integer", json["parameters"][0]["schema"]["kind"]);
+        assert_eq!("parameter", json["pathSegments"][1]["kind"]);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_describe_schema_shapes() -> Result<()> {
+        let describe = |value: serde_json::Value| -> Result<TypeDescriptor> {
+            Ok(describe_schema(&serde_json::from_value(value)?))
+        };
+        let string = || TypeDescriptor::String { format: None };
+        assert_eq!(
+            TypeDescriptor::Nullable {
+                inner: Box::new(TypeDescriptor::Enum {
+                    values: vec!["a".into()],
+                    base: Some(Box::new(string())),
+                })
+            },
+            describe(serde_json::json!({"type": "string", "enum": ["a", null]}))?
+        );
+        assert_eq!(
+            TypeDescriptor::Nullable {
+                inner: Box::new(string())
+            },
+            describe(
+                serde_json::json!({"anyOf": [{"type": ["string", "null"]}, {"type": "null"}]})
+            )?
+        );
+
+        let property = |name: &str, schema| PropertyDescriptor {
+            name: name.into(),
+            required: false,
+            schema,
+        };
+        let pet = |extra: PropertyDescriptor| TypeDescriptor::Object {
+            properties: vec![property("name", string()), extra],
+            additional_properties: Some(Box::new(TypeDescriptor::Any)),
+        };
+        assert_eq!(
+            TypeDescriptor::Union {
+                variants: vec![
+                    pet(property("bark", TypeDescriptor::Boolean)),
+                    pet(property("lives", TypeDescriptor::Integer { format: None })),
+                ],
+                discriminator: Some(Discriminator {
+                    property_name: "name".into(),
+                    mapping: Default::default(),
+                }),
+            },
+            describe(serde_json::json!({
+                "type": "object",
+                "properties": {"name": {"type": "string"}},
+                "discriminator": {"propertyName": "name"},
+                "oneOf": [
+                    {"type": "object", "properties": {"bark": {"type": "boolean"}}},
+                    {"type": "object", "properties": {"lives": {"type": "integer"}}}
+                ]
+            }))?
+        );
+
+        assert_eq!(
+            vec![
+                PathSegment::Literal("files".into()),
+                PathSegment::Template(vec![
+                    PathSegment::Parameter("name".into()),
+                    PathSegment::Literal(".".into()),
+                    PathSegment::Parameter("ext".into()),
+                ]),
+                PathSegment::Template(vec![
+                    PathSegment::Literal("v".into()),
+                    PathSegment::Parameter("version".into()),
+                ]),
+            ],
+            path_segments("/files/{name}.{ext}/v{version}")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,717 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use indexmap::IndexMap;
+use openapiv3::v3_1::{
+    MediaType, Operation, Parameter, ParameterSchemaOrContent, PathItem, ReferenceOr,
+};
+use serde_json::Value;
+
+use crate::operations::path_item_operations;
+use crate::parameters::{operation_parameters, parameter_data, parameter_location};
+use crate::{as_item, pointer, DereferencedOpenApi, Method};
+
+///Schemas are compared this many levels deep, which also stops recursive schemas.
+const MAX_DEPTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    OperationAdded,
+    OperationRemoved,
+    ParameterAdded,
+    ParameterRemoved,
+    ///A parameter or request body became required or optional
+    RequiredChanged,
+    RequestBodyAdded,
+    RequestBodyRemoved,
+    ResponseAdded,
+    ResponseRemoved,
+    MediaTypeAdded,
+    MediaTypeRemoved,
+    PropertyAdded,
+    PropertyRemoved,
+    TypeChanged,
+    EnumValueAdded,
+    EnumValueRemoved,
+    ///A bound like `maxLength` or `minimum` changed
+    ConstraintChanged,
+}
+
+///A single difference between two specs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecChange {
+    pub kind: ChangeKind,
+    ///JSON pointer to the changed part of the spec. Parameters are addressed by `in` and name
+    ///rather than their index, e.g. `/paths/~1pets/get/parameters/query/limit`.
+    pub location: String,
+    ///Whether clients written against the old spec can break against the new one
+    pub breaking: bool,
+    pub message: String,
+}
+
+impl fmt::Display for SpecChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let breaking = if self.breaking {
+            "breaking"
+        } else {
+            "non-breaking"
+        };
+        write!(f, "[{breaking}] {}: {}", self.location, self.message)
+    }
+}
+
+///Every difference found between two specs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpecDiff {
+    pub changes: Vec<SpecChange>,
+}
+
+impl SpecDiff {
+    pub fn is_breaking(&self) -> bool {
+        self.changes.iter().any(|change| change.breaking)
+    }
+
+    pub fn breaking_changes(&self) -> impl Iterator<Item = &SpecChange> {
+        self.changes.iter().filter(|change| change.breaking)
+    }
+
+    fn push(&mut self, kind: ChangeKind, location: &str, breaking: bool, message: String) {
+        self.changes.push(SpecChange {
+            kind,
+            location: location.into(),
+            breaking,
+            message,
+        });
+    }
+}
+
+///Which way data flows through a schema. Narrowing what the server accepts breaks clients, as
+///does widening what it sends back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Request,
+    Response,
+}
+
+///Compare two dereferenced specs, classifying each change as breaking or not for clients of
+///the old spec. Operations under both `paths` and `webhooks` are compared, and changes are
+///sorted by location.
+pub fn diff(old: &DereferencedOpenApi, new: &DereferencedOpenApi) -> SpecDiff {
+    let mut diff = SpecDiff::default();
+    let no_paths = IndexMap::new();
+    let (old_paths, new_paths) = (
+        old.openapi
+            .paths
+            .as_ref()
+            .map_or(&no_paths, |paths| &paths.paths),
+        new.openapi
+            .paths
+            .as_ref()
+            .map_or(&no_paths, |paths| &paths.paths),
+    );
+    diff_path_items(old_paths, new_paths, "/paths", &mut diff);
+    diff_path_items(
+        &old.openapi.webhooks,
+        &new.openapi.webhooks,
+        "/webhooks",
+        &mut diff,
+    );
+    diff.changes.sort_by(|a, b| a.location.cmp(&b.location));
+    diff
+}
+
+fn operations(
+    path_items: &IndexMap<String, ReferenceOr<PathItem>>,
+) -> IndexMap<(&str, Method), (&PathItem, &Operation)> {
+    path_items
+        .iter()
+        .filter_map(|(path, path_item)| as_item(path_item).map(|item| (path.as_str(), item)))
+        .flat_map(|(path, path_item)| {
+            path_item_operations(path_item)
+                .map(move |(method, operation)| ((path, method), (path_item, operation)))
+        })
+        .collect()
+}
+
+fn diff_path_items(
+    old: &IndexMap<String, ReferenceOr<PathItem>>,
+    new: &IndexMap<String, ReferenceOr<PathItem>>,
+    root: &str,
+    diff: &mut SpecDiff,
+) {
+    let (old_operations, new_operations) = (operations(old), operations(new));
+    for ((path, method), (old_path_item, old_operation)) in &old_operations {
+        let location = format!("{}/{method}", pointer::push(root, path));
+        match new_operations.get(&(*path, *method)) {
+            Some((new_path_item, new_operation)) => {
+                diff_parameters(
+                    &operation_parameters(old_path_item, old_operation),
+                    &operation_parameters(new_path_item, new_operation),
+                    &location,
+                    diff,
+                );
+                diff_operation(old_operation, new_operation, &location, diff);
+            }
+            None => diff.push(
+                ChangeKind::OperationRemoved,
+                &location,
+                true,
+                format!("Removed {method} {path}"),
+            ),
+        }
+    }
+    for (path, method) in new_operations.keys() {
+        if !old_operations.contains_key(&(*path, *method)) {
+            diff.push(
+                ChangeKind::OperationAdded,
+                &format!("{}/{method}", pointer::push(root, path)),
+                false,
+                format!("Added {method} {path}"),
+            );
+        }
+    }
+}
+
+fn diff_parameters(old: &[&Parameter], new: &[&Parameter], location: &str, diff: &mut SpecDiff) {
+    let key = |parameter: &&Parameter| {
+        (
+            parameter_location(parameter),
+            parameter_data(parameter).name.clone(),
+        )
+    };
+    let old_parameters: IndexMap<_, _> = old
+        .iter()
+        .map(|parameter| (key(parameter), *parameter))
+        .collect();
+    let new_parameters: IndexMap<_, _> = new
+        .iter()
+        .map(|parameter| (key(parameter), *parameter))
+        .collect();
+    for ((kind, name), old_parameter) in &old_parameters {
+        let location = format!("{location}/parameters/{kind}/{}", pointer::escape(name));
+        let Some(new_parameter) = new_parameters.get(&(*kind, name.clone())) else {
+            diff.push(
+                ChangeKind::ParameterRemoved,
+                &location,
+                false,
+                format!("Removed {kind} parameter {name}"),
+            );
+            continue;
+        };
+        let (old_data, new_data) = (parameter_data(old_parameter), parameter_data(new_parameter));
+        if old_data.required != new_data.required {
+            diff.push(
+                ChangeKind::RequiredChanged,
+                &location,
+                new_data.required,
+                format!(
+                    "{kind} parameter {name} is now {}",
+                    if new_data.required {
+                        "required"
+                    } else {
+                        "optional"
+                    }
+                ),
+            );
+        }
+        if let (Some(old_schema), Some(new_schema)) = (
+            format_schema(&old_data.format),
+            format_schema(&new_data.format),
+        ) {
+            diff_schema(
+                &old_schema,
+                &new_schema,
+                &format!("{location}/schema"),
+                Direction::Request,
+                0,
+                diff,
+            );
+        }
+    }
+    for ((kind, name), new_parameter) in &new_parameters {
+        if !old_parameters.contains_key(&(*kind, name.clone())) {
+            let required = parameter_data(new_parameter).required;
+            diff.push(
+                ChangeKind::ParameterAdded,
+                &format!("{location}/parameters/{kind}/{}", pointer::escape(name)),
+                required,
+                format!(
+                    "Added {} {kind} parameter {name}",
+                    if required { "required" } else { "optional" }
+                ),
+            );
+        }
+    }
+}
+
+fn format_schema(format: &ParameterSchemaOrContent) -> Option<Value> {
+    match format {
+        ParameterSchemaOrContent::Schema(schema) => serde_json::to_value(&schema.json_schema).ok(),
+        ParameterSchemaOrContent::Content(content) => content
+            .values()
+            .next()
+            .and_then(|media_type| media_type.schema.as_ref())
+            .and_then(|schema| serde_json::to_value(&schema.json_schema).ok()),
+    }
+}
+
+fn diff_operation(old: &Operation, new: &Operation, location: &str, diff: &mut SpecDiff) {
+    let body_location = format!("{location}/requestBody");
+    match (
+        old.request_body.as_ref().and_then(as_item),
+        new.request_body.as_ref().and_then(as_item),
+    ) {
+        (Some(old_body), Some(new_body)) => {
+            if old_body.required != new_body.required {
+                diff.push(
+                    ChangeKind::RequiredChanged,
+                    &body_location,
+                    new_body.required,
+                    format!(
+                        "Request body is now {}",
+                        if new_body.required {
+                            "required"
+                        } else {
+                            "optional"
+                        }
+                    ),
+                );
+            }
+            diff_content(
+                &old_body.content,
+                &new_body.content,
+                &format!("{body_location}/content"),
+                Direction::Request,
+                diff,
+            );
+        }
+        (Some(_), None) => diff.push(
+            ChangeKind::RequestBodyRemoved,
+            &body_location,
+            true,
+            "Removed request body".into(),
+        ),
+        (None, Some(new_body)) => diff.push(
+            ChangeKind::RequestBodyAdded,
+            &body_location,
+            new_body.required,
+            "Added request body".into(),
+        ),
+        (None, None) => {}
+    }
+    let responses = |operation: &Operation| {
+        let mut responses: IndexMap<String, _> = IndexMap::new();
+        if let Some(operation_responses) = operation.responses.as_ref() {
+            for (status, response) in &operation_responses.responses {
+                if let Some(response) = as_item(response) {
+                    responses.insert(status.to_string(), &response.content);
+                }
+            }
+            if let Some(response) = operation_responses.default.as_ref().and_then(as_item) {
+                responses.insert("default".into(), &response.content);
+            }
+        }
+        responses
+    };
+    let (old_responses, new_responses) = (responses(old), responses(new));
+    for (status, old_content) in &old_responses {
+        let location = format!("{location}/responses/{status}");
+        match new_responses.get(status) {
+            Some(new_content) => diff_content(
+                old_content,
+                new_content,
+                &format!("{location}/content"),
+                Direction::Response,
+                diff,
+            ),
+            None => diff.push(
+                ChangeKind::ResponseRemoved,
+                &location,
+                true,
+                format!("Removed {status} response"),
+            ),
+        }
+    }
+    for status in new_responses.keys() {
+        if !old_responses.contains_key(status) {
+            diff.push(
+                ChangeKind::ResponseAdded,
+                &format!("{location}/responses/{status}"),
+                false,
+                format!("Added {status} response"),
+            );
+        }
+    }
+}
+
+fn diff_content(
+    old: &IndexMap<String, MediaType>,
+    new: &IndexMap<String, MediaType>,
+    location: &str,
+    direction: Direction,
+    diff: &mut SpecDiff,
+) {
+    for (content_type, old_media_type) in old {
+        let location = pointer::push(location, content_type);
+        let Some(new_media_type) = new.get(content_type) else {
+            diff.push(
+                ChangeKind::MediaTypeRemoved,
+                &location,
+                direction == Direction::Request,
+                format!("Removed media type {content_type}"),
+            );
+            continue;
+        };
+        let schema = |media_type: &MediaType| {
+            media_type
+                .schema
+                .as_ref()
+                .and_then(|schema| serde_json::to_value(&schema.json_schema).ok())
+        };
+        if let (Some(old_schema), Some(new_schema)) =
+            (schema(old_media_type), schema(new_media_type))
+        {
+            diff_schema(
+                &old_schema,
+                &new_schema,
+                &format!("{location}/schema"),
+                direction,
+                0,
+                diff,
+            );
+        }
+    }
+    for content_type in new.keys() {
+        if !old.contains_key(content_type) {
+            diff.push(
+                ChangeKind::MediaTypeAdded,
+                &pointer::push(location, content_type),
+                direction == Direction::Response,
+                format!("Added media type {content_type}"),
+            );
+        }
+    }
+}
+
+fn types(schema: &Value) -> Option<BTreeSet<&str>> {
+    match schema.get("type")? {
+        Value::String(kind) => Some(BTreeSet::from([kind.as_str()])),
+        Value::Array(kinds) => Some(kinds.iter().filter_map(Value::as_str).collect()),
+        _ => None,
+    }
+}
+
+fn values<'a>(schema: &'a Value, keyword: &str) -> &'a [Value] {
+    schema
+        .get(keyword)
+        .and_then(Value::as_array)
+        .map_or(&[], Vec::as_slice)
+}
+
+fn required(schema: &Value) -> Vec<&str> {
+    values(schema, "required")
+        .iter()
+        .filter_map(Value::as_str)
+        .collect()
+}
+
+fn properties(schema: &Value) -> Option<&serde_json::Map<String, Value>> {
+    schema.get("properties").and_then(Value::as_object)
+}
+
+fn diff_schema(
+    old: &Value,
+    new: &Value,
+    location: &str,
+    direction: Direction,
+    depth: usize,
+    diff: &mut SpecDiff,
+) {
+    if depth > MAX_DEPTH || old == new {
+        return;
+    }
+    let request = direction == Direction::Request;
+    match (types(old), types(new)) {
+        (Some(old_types), Some(new_types)) if old_types != new_types => {
+            let narrowed = !old_types.is_subset(&new_types);
+            let widened = !new_types.is_subset(&old_types);
+            diff.push(
+                ChangeKind::TypeChanged,
+                location,
+                if request { narrowed } else { widened },
+                format!("Type changed from {old_types:?} to {new_types:?}"),
+            );
+        }
+        (None, Some(_)) => diff.push(
+            ChangeKind::TypeChanged,
+            location,
+            request,
+            "Type restricted".into(),
+        ),
+        (Some(_), None) => diff.push(
+            ChangeKind::TypeChanged,
+            location,
+            !request,
+            "Type restriction removed".into(),
+        ),
+        _ => {}
+    }
+
+    if old.get("enum").is_some() || new.get("enum").is_some() {
+        let (old_enum, new_enum) = (values(old, "enum"), values(new, "enum"));
+        //No enum at all allows anything
+        if !new_enum.is_empty() {
+            for value in old_enum.iter().filter(|value| !new_enum.contains(*value)) {
+                diff.push(
+                    ChangeKind::EnumValueRemoved,
+                    location,
+                    request,
+                    format!("Removed enum value {value}"),
+                );
+            }
+        }
+        if !old_enum.is_empty() {
+            for value in new_enum.iter().filter(|value| !old_enum.contains(*value)) {
+                diff.push(
+                    ChangeKind::EnumValueAdded,
+                    location,
+                    !request,
+                    format!("Added enum value {value}"),
+                );
+            }
+        }
+    }
+
+    let (old_required, new_required) = (required(old), required(new));
+    for name in new_required
+        .iter()
+        .filter(|name| !old_required.contains(*name))
+    {
+        diff.push(
+            ChangeKind::RequiredChanged,
+            &pointer::push(&format!("{location}/properties"), name),
+            request,
+            format!("Property {name} is now required"),
+        );
+    }
+    for name in old_required
+        .iter()
+        .filter(|name| !new_required.contains(*name))
+    {
+        diff.push(
+            ChangeKind::RequiredChanged,
+            &pointer::push(&format!("{location}/properties"), name),
+            !request,
+            format!("Property {name} is now optional"),
+        );
+    }
+
+    //(keyword, whether a bigger value narrows the schema)
+    for (keyword, bigger_narrows) in [
+        ("minimum", true),
+        ("exclusiveMinimum", true),
+        ("minLength", true),
+        ("minItems", true),
+        ("minProperties", true),
+        ("maximum", false),
+        ("exclusiveMaximum", false),
+        ("maxLength", false),
+        ("maxItems", false),
+        ("maxProperties", false),
+    ] {
+        let (old_bound, new_bound) = (
+            old.get(keyword).and_then(Value::as_f64),
+            new.get(keyword).and_then(Value::as_f64),
+        );
+        let narrowed = match (old_bound, new_bound) {
+            (Some(old_bound), Some(new_bound)) if old_bound != new_bound => {
+                (new_bound > old_bound) == bigger_narrows
+            }
+            (None, Some(_)) => true,
+            (Some(_), None) => false,
+            _ => continue,
+        };
+        diff.push(
+            ChangeKind::ConstraintChanged,
+            location,
+            if request { narrowed } else { !narrowed },
+            format!("{keyword} changed from {old_bound:?} to {new_bound:?}"),
+        );
+    }
+
+    let no_properties = serde_json::Map::new();
+    let old_properties = properties(old).unwrap_or(&no_properties);
+    let new_properties = properties(new).unwrap_or(&no_properties);
+    for (name, old_property) in old_properties {
+        let property_location = pointer::push(&format!("{location}/properties"), name);
+        match new_properties.get(name) {
+            Some(new_property) => diff_schema(
+                old_property,
+                new_property,
+                &property_location,
+                direction,
+                depth + 1,
+                diff,
+            ),
+            None => diff.push(
+                ChangeKind::PropertyRemoved,
+                &property_location,
+                !request,
+                format!("Removed property {name}"),
+            ),
+        }
+    }
+    for name in new_properties.keys() {
+        if !old_properties.contains_key(name) {
+            diff.push(
+                ChangeKind::PropertyAdded,
+                &pointer::push(&format!("{location}/properties"), name),
+                false,
+                format!("Added property {name}"),
+            );
+        }
+    }
+    if let (Some(old_items), Some(new_items)) = (old.get("items"), new.get("items")) {
+        diff_schema(
+            old_items,
+            new_items,
+            &format!("{location}/items"),
+            direction,
+            depth + 1,
+            diff,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    const OLD: &str = r##"{
+        "openapi": "3.1.0",
+        "info": {"title": "Diff", "version": "1.0.0"},
+        "paths": {
+            "/pets": {
+                "get": {
+                    "parameters": [{"name": "limit", "in": "query", "schema": {"type": "integer", "maximum": 100}}],
+                    "responses": {
+                        "200": {
+                            "description": "ok",
+                            "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+                        }
+                    }
+                },
+                "delete": {"responses": {"204": {"description": "deleted"}}}
+            }
+        },
+        "components": {
+            "schemas": {
+                "Pet": {
+                    "type": "object",
+                    "properties": {
+                        "name": {"type": "string"},
+                        "kind": {"enum": ["dog", "cat"]},
+                        "size": {"enum": [1, 2]},
+                        "age": {"type": "integer"}
+                    }
+                }
+            }
+        },
+        "webhooks": {
+            "petAdopted": {"post": {"responses": {"200": {"description": "ok"}}}}
+        }
+    }"##;
+
+    const NEW: &str = r##"{
+        "openapi": "3.1.0",
+        "info": {"title": "Diff", "version": "2.0.0"},
+        "paths": {
+            "/pets": {
+                "get": {
+                    "parameters": [
+                        {"name": "limit", "in": "query", "schema": {"type": "integer", "maximum": 50}},
+                        {"name": "owner", "in": "query", "required": true, "schema": {"type": "string"}}
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "ok",
+                            "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+                        }
+                    }
+                },
+                "post": {"responses": {"201": {"description": "created"}}}
+            }
+        },
+        "components": {
+            "schemas": {
+                "Pet": {
+                    "type": "object",
+                    "properties": {
+                        "name": {"type": "string"},
+                        "kind": {"enum": ["dog", "cat", "bird"]},
+                        "size": {"enum": ["1", 2]},
+                        "color": {"type": "string"}
+                    }
+                }
+            }
+        }
+    }"##;
+
+    #[test]
+    pub fn test_diff() -> Result<()> {
+        let old = OpenApiDereferencer::from_str(OLD)?.dereference()?;
+        let new = OpenApiDereferencer::from_str(NEW)?.dereference()?;
+        let diff = diff(&old, &new);
+        let changes: Vec<(ChangeKind, &str, bool)> = diff
+            .changes
+            .iter()
+            .map(|change| (change.kind, change.location.as_str(), change.breaking))
+            .collect();
+        let properties =
+            "/paths/~1pets/get/responses/200/content/application~1json/schema/properties";
+        let (kind, size, age, color) = (
+            format!("{properties}/kind"),
+            format!("{properties}/size"),
+            format!("{properties}/age"),
+            format!("{properties}/color"),
+        );
+        for expected in [
+            (
+                ChangeKind::ConstraintChanged,
+                "/paths/~1pets/get/parameters/query/limit/schema",
+                true,
+            ),
+            (
+                ChangeKind::ParameterAdded,
+                "/paths/~1pets/get/parameters/query/owner",
+                true,
+            ),
+            (ChangeKind::EnumValueAdded, kind.as_str(), true),
+            (ChangeKind::EnumValueRemoved, size.as_str(), false),
+            (ChangeKind::EnumValueAdded, size.as_str(), true),
+            (ChangeKind::PropertyRemoved, age.as_str(), true),
+            (ChangeKind::PropertyAdded, color.as_str(), false),
+            (ChangeKind::OperationRemoved, "/paths/~1pets/delete", true),
+            (ChangeKind::OperationAdded, "/paths/~1pets/post", false),
+            (
+                ChangeKind::OperationRemoved,
+                "/webhooks/petAdopted/post",
+                true,
+            ),
+        ] {
+            assert!(changes.contains(&expected), "{expected:?} {changes:?}");
+        }
+        assert_eq!(10, changes.len());
+        assert!(diff
+            .changes
+            .windows(2)
+            .all(|pair| pair[0].location <= pair[1].location));
+        assert!(diff.is_breaking());
+
+        assert!(!super::diff(&old, &old).is_breaking());
+        Ok(())
+    }
+}
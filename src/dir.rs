@@ -0,0 +1,70 @@
+use std::path::Path;
+
+use crate::loader::{bundle_external, DocumentLoader, FileDocumentLoader};
+use crate::{OpenApiDereferencer, OpenApiError};
+
+///What the root document of a split spec is called, in the order they're looked for.
+const ENTRY_FILES: [&str; 3] = ["openapi.yaml", "openapi.yml", "openapi.json"];
+
+impl OpenApiDereferencer {
+    ///Load a spec split across files, e.g. `openapi.yaml` with `paths/` and `components/`
+    ///folders next to it, and refs like `./components/schemas/Pet.yaml` between them. Files
+    ///under `components/<kind>/` become components named after the file, so recursive schemas
+    ///keep working. Everything else is inlined where it's referenced. Refs to URLs are left for
+    ///the external ref policy. YAML needs the `yaml` feature.
+    pub fn from_dir(root: impl AsRef<Path>) -> Result<Self, OpenApiError> {
+        let root = root.as_ref();
+        let entry = ENTRY_FILES
+            .into_iter()
+            .find(|name| root.join(name).is_file())
+            .ok_or_else(|| OpenApiError::FileError {
+                path: root.into(),
+                msg: format!("None of {} found", ENTRY_FILES.join(", ")),
+            })?;
+        let loader = FileDocumentLoader { base: root.into() };
+        let document = bundle_external(loader.load(entry)?, entry, &loader, None)?;
+        OpenApiDereferencer::from_value(document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::as_item;
+    use anyhow::Result;
+
+    #[test]
+    pub fn test_from_dir() -> Result<()> {
+        let dereferenced = OpenApiDereferencer::from_dir("oai_examples/split")?.dereference()?;
+        let json = &dereferenced.json;
+        assert_eq!(
+            "#/components/schemas/Pet",
+            json["paths"]["/pets"]["get"]["responses"]["200"]["content"]["application/json"]
+                ["schema"]["items"]["$ref"]
+        );
+        assert_eq!(
+            "#/components/schemas/Pet",
+            json["components"]["schemas"]["Pet"]["properties"]["parent"]["$ref"]
+        );
+        assert_eq!(
+            "#/components/schemas/Error",
+            json["paths"]["/pets"]["get"]["responses"]["default"]["content"]["application/json"]
+                ["schema"]["$ref"]
+        );
+        assert_eq!(
+            "Pet name",
+            json["components"]["schemas"]["Pet"]["properties"]["name"]["description"]
+        );
+        let paths = &dereferenced.openapi.paths.as_ref().unwrap().paths;
+        assert!(as_item(&paths["/pets"]).is_some());
+        let schemas = &dereferenced.openapi.components.as_ref().unwrap().schemas;
+        assert_eq!(2, schemas.len());
+        assert!(schemas.contains_key("Pet") && schemas.contains_key("Error"));
+
+        assert!(matches!(
+            OpenApiDereferencer::from_dir("oai_examples/missing"),
+            Err(OpenApiError::FileError { .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use indexmap::IndexMap;
+use openapiv3::schemars::schema::Schema as SchemarsSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{pointer, OpenApiDereferencer, OpenApiError, Warning};
+
+///The discriminator object of a schema.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Discriminator {
+    pub property_name: String,
+    #[serde(default)]
+    pub mapping: IndexMap<String, String>,
+}
+
+impl Discriminator {
+    ///Get the discriminator off of a schema, if it has one.
+    pub fn of(schema: &SchemarsSchema) -> Option<Discriminator> {
+        match schema {
+            SchemarsSchema::Object(object) => object
+                .extensions
+                .get("discriminator")
+                .and_then(|discriminator| serde_json::from_value(discriminator.clone()).ok()),
+            SchemarsSchema::Bool(_) => None,
+        }
+    }
+
+    ///The reference a discriminator value maps to. Values without an explicit mapping
+    ///implicitly name a component schema.
+    pub fn reference_for(&self, value: &str) -> String {
+        match self.mapping.get(value) {
+            Some(target) => mapping_reference(target),
+            None => format!("#/components/schemas/{}", pointer::escape(value)),
+        }
+    }
+}
+
+///Mapping values are either references or bare component schema names.
+fn mapping_reference(target: &str) -> String {
+    if target.contains('#') || target.contains('/') {
+        target.into()
+    } else {
+        format!("#/components/schemas/{}", pointer::escape(target))
+    }
+}
+
+impl OpenApiDereferencer {
+    ///Get the concrete, dereferenced schema a discriminator value selects on a schema with a
+    ///discriminator.
+    pub fn discriminated_schema(
+        &self,
+        schema: &SchemarsSchema,
+        value: &str,
+    ) -> Result<SchemarsSchema, OpenApiError> {
+        let discriminator = Discriminator::of(schema).ok_or(OpenApiError::NoDiscriminator)?;
+        let reference = discriminator.reference_for(value);
+        let target: SchemarsSchema = self.dereference_type(&reference).map_err(|_| {
+            OpenApiError::DiscriminatorValueNotFound {
+                value: value.into(),
+            }
+        })?;
+        self.ref_stack.borrow_mut().push(reference);
+        let target = self.dereference_schemars_schema(target);
+        self.ref_stack.borrow_mut().pop();
+        target
+    }
+
+    ///Check every discriminator mapping in the source document resolves to one of the
+    ///`oneOf`/`anyOf` schemas next to it.
+    pub(crate) fn validate_discriminators(&mut self) {
+        let mut warnings = vec![];
+        find_discriminators(&self.json, "", &mut |location, schema, discriminator| {
+            let members: Vec<&str> = ["oneOf", "anyOf"]
+                .iter()
+                .filter_map(|keyword| schema.get(*keyword).and_then(Value::as_array))
+                .flatten()
+                .filter_map(|member| member.get("$ref").and_then(Value::as_str))
+                .collect();
+            for (value, target) in &discriminator.mapping {
+                let reference = mapping_reference(target);
+                if self.locate_reference(&reference).is_err() {
+                    warnings.push(Warning::UnresolvedDiscriminatorMapping {
+                        location: location.into(),
+                        value: value.clone(),
+                        reference,
+                    });
+                } else if !members.is_empty() && !members.contains(&reference.as_str()) {
+                    warnings.push(Warning::DiscriminatorMappingNotAMember {
+                        location: location.into(),
+                        value: value.clone(),
+                        reference,
+                    });
+                }
+            }
+        });
+        self.warnings.append(&mut warnings);
+    }
+}
+
+fn find_discriminators(
+    value: &Value,
+    location: &str,
+    found: &mut dyn FnMut(&str, &serde_json::Map<String, Value>, Discriminator),
+) {
+    match value {
+        Value::Object(object) => {
+            //A property called discriminator won't have a propertyName, so it's skipped here.
+            if let Some(discriminator) = object
+                .get("discriminator")
+                .and_then(|d| serde_json::from_value::<Discriminator>(d.clone()).ok())
+            {
+                found(location, object, discriminator);
+            }
+            for (key, value) in object {
+                find_discriminators(value, &pointer::push(location, key), found);
+            }
+        }
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                find_discriminators(value, &format!("{location}/{i}"), found);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    const SPEC: &str = r##"{
+        "openapi": "3.1.0",
+        "info": {"title": "Discriminator", "version": "1.0.0"},
+        "components": {
+            "schemas": {
+                "Pet": {
+                    "oneOf": [
+                        {"$ref": "#/components/schemas/Cat"},
+                        {"$ref": "#/components/schemas/Dog"}
+                    ],
+                    "discriminator": {
+                        "propertyName": "petType",
+                        "mapping": {
+                            "dog": "#/components/schemas/Dog",
+                            "lizard": "Lizard",
+                            "fish": "#/components/schemas/Fish"
+                        }
+                    }
+                },
+                "Cat": {"type": "object", "properties": {"meows": {"type": "boolean"}}},
+                "Dog": {"type": "object", "properties": {"barks": {"type": "boolean"}}},
+                "Lizard": {"type": "object"}
+            }
+        }
+    }"##;
+
+    #[test]
+    pub fn test_discriminated_schema() -> Result<()> {
+        let dereferenced = OpenApiDereferencer::from_str(SPEC)?.dereference()?;
+        let components = dereferenced.openapi.components.as_ref().unwrap();
+        let pet = &components.schemas["Pet"].json_schema;
+        let dog = serde_json::to_value(dereferenced.discriminated_schema(pet, "dog")?)?;
+        assert!(dog["properties"].get("barks").is_some());
+        let cat = serde_json::to_value(dereferenced.discriminated_schema(pet, "Cat")?)?;
+        assert!(cat["properties"].get("meows").is_some());
+        assert!(dereferenced.discriminated_schema(pet, "Bird").is_err());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_discriminator_mapping_warnings() -> Result<()> {
+        let dereferenced = OpenApiDereferencer::from_str(SPEC)?.dereference()?;
+        assert_eq!(
+            &[
+                Warning::DiscriminatorMappingNotAMember {
+                    location: "/components/schemas/Pet".into(),
+                    value: "lizard".into(),
+                    reference: "#/components/schemas/Lizard".into(),
+                },
+                Warning::UnresolvedDiscriminatorMapping {
+                    location: "/components/schemas/Pet".into(),
+                    value: "fish".into(),
+                    reference: "#/components/schemas/Fish".into(),
+                },
+            ],
+            dereferenced.warnings()
+        );
+        Ok(())
+    }
+}
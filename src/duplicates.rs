@@ -0,0 +1,168 @@
+use std::collections::HashSet;
+
+use indexmap::IndexMap;
+use serde_json::{Map, Value};
+
+use crate::bundle::worth_a_component;
+use crate::cache::overlaps;
+use crate::canonical::canonical;
+use crate::convert::{for_each_schema, for_each_subschema};
+use crate::{OpenApiDereferencer, OpenApiError};
+
+///Keywords that describe a schema without changing what it accepts.
+const ANNOTATIONS: [&str; 7] = [
+    "title",
+    "description",
+    "example",
+    "examples",
+    "$comment",
+    "deprecated",
+    "externalDocs",
+];
+
+///Schemas that look like the same thing, see [`OpenApiDereferencer::duplicate_schemas`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateSchemas {
+    ///JSON pointers to the schemas in the input document
+    pub locations: Vec<String>,
+    ///Whether the schemas are identical, rather than only matching once titles, descriptions
+    ///and examples are ignored
+    pub exact: bool,
+}
+
+impl OpenApiDereferencer {
+    ///Schemas in the input document, components or inline, that are the same apart from key
+    ///order, or apart from titles, descriptions and examples. They're candidates for
+    ///consolidating into one component. Only schemas with properties, an enum or subschemas are
+    ///compared, and duplicates nested in duplicates aren't reported again.
+    pub fn duplicate_schemas(&self) -> Result<Vec<DuplicateSchemas>, OpenApiError> {
+        //canonical schema -> locations, for identical and annotation free schemas
+        let mut identical: IndexMap<String, Vec<String>> = IndexMap::new();
+        let mut similar: IndexMap<String, Vec<String>> = IndexMap::new();
+        let mut document = self.input()?.clone();
+        for_each_schema(&mut document, "", &mut |schema, location| {
+            if !worth_a_component(schema) {
+                return;
+            }
+            identical
+                .entry(canonical(&Value::Object(schema.clone())))
+                .or_default()
+                .push(location.into());
+            similar
+                .entry(canonical(&without_annotations(schema)))
+                .or_default()
+                .push(location.into());
+        });
+        let exact_groups: HashSet<Vec<String>> = identical
+            .values()
+            .filter(|locations| locations.len() > 1)
+            .map(|locations| {
+                let mut locations = locations.clone();
+                locations.sort();
+                locations
+            })
+            .collect();
+        let mut duplicates: Vec<DuplicateSchemas> = vec![];
+        for mut locations in identical.into_values().chain(similar.into_values()) {
+            if locations.len() < 2 {
+                continue;
+            }
+            locations.sort();
+            //Identical schemas also come up again ignoring annotations
+            if duplicates
+                .iter()
+                .any(|duplicate| duplicate.locations == locations)
+            {
+                continue;
+            }
+            let exact = exact_groups.contains(&locations);
+            duplicates.push(DuplicateSchemas { locations, exact });
+        }
+        let all = duplicates.clone();
+        duplicates.retain(|duplicate| {
+            !all.iter().any(|other| {
+                other.locations != duplicate.locations
+                    && duplicate.locations.iter().all(|location| {
+                        other
+                            .locations
+                            .iter()
+                            .any(|outer| outer != location && overlaps(location, outer))
+                    })
+            })
+        });
+        duplicates.sort_by(|a, b| a.locations.cmp(&b.locations));
+        Ok(duplicates)
+    }
+}
+
+fn without_annotations(schema: &Map<String, Value>) -> Value {
+    let mut schema = Value::Object(schema.clone());
+    for_each_subschema(&mut schema, "", &mut |schema, _| {
+        schema.retain(|key, _| !ANNOTATIONS.contains(&key.as_str()));
+    });
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_duplicate_schemas() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Duplicates", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "Pets",
+                                "content": {
+                                    "application/json": {
+                                        "schema": {"properties": {"name": {"type": "string"}}, "type": "object"}
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Pet": {"type": "object", "properties": {"name": {"type": "string"}}},
+                    "Animal": {
+                        "type": "object",
+                        "description": "Any animal",
+                        "properties": {"name": {"type": "string", "title": "Name"}}
+                    },
+                    "Owner": {"type": "object", "properties": {"age": {"type": "integer"}}}
+                }
+            }
+        }"##;
+        let duplicates = OpenApiDereferencer::from_str(spec)?.duplicate_schemas()?;
+        assert_eq!(
+            vec![
+                DuplicateSchemas {
+                    locations: vec![
+                        "/components/schemas/Animal".into(),
+                        "/components/schemas/Pet".into(),
+                        "/paths/~1pets/get/responses/200/content/application~1json/schema".into(),
+                    ],
+                    exact: false,
+                },
+                DuplicateSchemas {
+                    locations: vec![
+                        "/components/schemas/Pet".into(),
+                        "/paths/~1pets/get/responses/200/content/application~1json/schema".into(),
+                    ],
+                    exact: true,
+                },
+            ],
+            duplicates
+        );
+        Ok(())
+    }
+}
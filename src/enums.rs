@@ -0,0 +1,110 @@
+use openapiv3::schemars::schema::Schema as SchemarsSchema;
+use serde_json::Value;
+use std::borrow::Cow;
+
+use crate::DereferencedOpenApi;
+
+///The values a schema is limited to, from `enum`, `const`, or a `oneOf`/`anyOf` where every
+///branch is a `const` or `enum` (the usual way to document each value). `None` if the schema
+///isn't an enum.
+pub fn enum_values(schema: &SchemarsSchema) -> Option<Vec<Value>> {
+    let SchemarsSchema::Object(object) = schema else {
+        return None;
+    };
+    if let Some(values) = &object.enum_values {
+        return Some(values.clone());
+    }
+    if let Some(value) = &object.const_value {
+        return Some(vec![value.clone()]);
+    }
+    let subschemas = object.subschemas.as_ref()?;
+    let branches = subschemas.one_of.as_ref().or(subschemas.any_of.as_ref())?;
+    let mut values: Vec<Value> = vec![];
+    for branch in branches {
+        for value in enum_values(branch)? {
+            if !values.contains(&value) {
+                values.push(value);
+            }
+        }
+    }
+    Some(values)
+}
+
+impl DereferencedOpenApi {
+    ///Every schema in the dereferenced spec that's an enum, with its JSON pointer and values.
+    ///A lone `const` isn't counted, so the branches of a `oneOf` of consts don't show up on
+    ///their own.
+    pub fn iter_enums(
+        &self,
+    ) -> impl Iterator<Item = (String, Cow<'_, SchemarsSchema>, Vec<Value>)> + '_ {
+        self.iter_schemas().filter_map(|(location, schema)| {
+            if let SchemarsSchema::Object(object) = schema.as_ref() {
+                if object.enum_values.is_none() && object.const_value.is_some() {
+                    return None;
+                }
+            }
+            let values = enum_values(&schema)?;
+            Some((location, schema, values))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_iter_enums() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Enums", "version": "1.0.0"},
+            "paths": {},
+            "components": {
+                "schemas": {
+                    "Pet": {
+                        "type": "object",
+                        "properties": {
+                            "status": {"$ref": "#/components/schemas/Status"},
+                            "size": {
+                                "oneOf": [
+                                    {"const": "small", "description": "Fits in a bag"},
+                                    {"const": "large"}
+                                ]
+                            },
+                            "name": {"type": "string"}
+                        }
+                    },
+                    "Status": {"type": "string", "enum": ["available", "sold"]}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let mut enums: Vec<(String, Vec<Value>)> = dereferenced
+            .iter_enums()
+            .map(|(location, _, values)| (location, values))
+            .collect();
+        enums.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            vec![
+                (
+                    "/components/schemas/Pet/properties/size".to_string(),
+                    vec![json!("small"), json!("large")]
+                ),
+                (
+                    "/components/schemas/Pet/properties/status".to_string(),
+                    vec![json!("available"), json!("sold")]
+                ),
+                (
+                    "/components/schemas/Status".to_string(),
+                    vec![json!("available"), json!("sold")]
+                ),
+            ],
+            enums
+        );
+        Ok(())
+    }
+}
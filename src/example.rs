@@ -0,0 +1,388 @@
+use indexmap::IndexMap;
+use openapiv3::schemars::schema::{
+    InstanceType, Schema as SchemarsSchema, SchemaObject as SchemarsObject, SingleOrVec,
+};
+use openapiv3::v3_1::{MediaType, Parameter, ParameterSchemaOrContent, Server};
+use serde_json::{json, Map, Value};
+
+use crate::parameters::parameter_data;
+use crate::{as_item, DereferencedOpenApi, Method, OpenApiError};
+
+///Recursive schemas stop generating after this many levels.
+const MAX_DEPTH: usize = 8;
+
+///Example request and response bodies for an operation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationExample {
+    pub request_body: Option<Value>,
+    ///Status code (`200`, `4XX`, `default`) -> example body
+    pub responses: IndexMap<String, Value>,
+}
+
+///Make up a plausible value for a dereferenced schema. `examples`, `example`, `const`,
+///`default` and `enum` are used when present, otherwise a value is built from the type and
+///format.
+pub fn generate_example(schema: &SchemarsSchema) -> Value {
+    generate(schema, 0)
+}
+
+fn generate(schema: &SchemarsSchema, depth: usize) -> Value {
+    let object = match schema {
+        SchemarsSchema::Bool(_) => return Value::Null,
+        SchemarsSchema::Object(object) => object,
+    };
+    if depth > MAX_DEPTH || object.is_ref() {
+        return Value::Null;
+    }
+    if let Some(metadata) = object.metadata.as_ref() {
+        if let Some(example) = metadata.examples.first() {
+            return example.clone();
+        }
+    }
+    if let Some(example) = object.extensions.get("example") {
+        return example.clone();
+    }
+    if let Some(value) = object.const_value.as_ref() {
+        return value.clone();
+    }
+    if let Some(default) = object.metadata.as_ref().and_then(|m| m.default.as_ref()) {
+        return default.clone();
+    }
+    if let Some(value) = object
+        .enum_values
+        .as_ref()
+        .and_then(|values| values.first())
+    {
+        return value.clone();
+    }
+    if let Some(subschemas) = object.subschemas.as_ref() {
+        if let Some(all_of) = subschemas.all_of.as_ref() {
+            let mut merged = Map::new();
+            for schema in all_of {
+                match generate(schema, depth + 1) {
+                    Value::Object(values) => merged.extend(values),
+                    Value::Null => {}
+                    other => return other,
+                }
+            }
+            if let Value::Object(values) = generate_typed(object, depth) {
+                merged.extend(values);
+            }
+            return Value::Object(merged);
+        }
+        if let Some(first) = subschemas
+            .one_of
+            .as_ref()
+            .or(subschemas.any_of.as_ref())
+            .and_then(|schemas| schemas.first())
+        {
+            return generate(first, depth + 1);
+        }
+    }
+    generate_typed(object, depth)
+}
+
+fn generate_typed(object: &SchemarsObject, depth: usize) -> Value {
+    let instance_type = match object.instance_type.as_ref() {
+        Some(SingleOrVec::Single(instance_type)) => Some(**instance_type),
+        Some(SingleOrVec::Vec(types)) => types
+            .iter()
+            .find(|t| **t != InstanceType::Null)
+            .or(types.first())
+            .copied(),
+        None if object.object.is_some() => Some(InstanceType::Object),
+        None if object.array.is_some() => Some(InstanceType::Array),
+        None => None,
+    };
+    match instance_type {
+        Some(InstanceType::Object) => {
+            let mut values = Map::new();
+            if let Some(validation) = object.object.as_ref() {
+                for (name, schema) in &validation.properties {
+                    values.insert(name.clone(), generate(schema, depth + 1));
+                }
+            }
+            Value::Object(values)
+        }
+        Some(InstanceType::Array) => {
+            let item = object.array.as_ref().and_then(|array| match &array.items {
+                Some(SingleOrVec::Single(schema)) => Some(generate(schema, depth + 1)),
+                Some(SingleOrVec::Vec(schemas)) => schemas.first().map(|s| generate(s, depth + 1)),
+                None => None,
+            });
+            let array = object.array.as_ref();
+            let min_items = array.and_then(|array| array.min_items).unwrap_or(1).max(1);
+            let items = array
+                .and_then(|array| array.max_items)
+                .map_or(min_items, |max_items| min_items.min(max_items));
+            match item {
+                Some(Value::Null) | None => json!([]),
+                Some(item) => Value::Array(vec![item; items as usize]),
+            }
+        }
+        Some(InstanceType::String) => generate_string(object),
+        Some(InstanceType::Integer) => {
+            let number = object.number.clone().unwrap_or_default();
+            let minimum = number
+                .minimum
+                .map(f64::ceil)
+                .or(number.exclusive_minimum.map(|m| m.floor() + 1.0));
+            let maximum = number
+                .maximum
+                .map(f64::floor)
+                .or(number.exclusive_maximum.map(|m| m.ceil() - 1.0));
+            let value = match (minimum, maximum) {
+                (Some(minimum), _) => minimum,
+                (None, Some(maximum)) if maximum < 0.0 => maximum,
+                (None, _) => 0.0,
+            };
+            json!(value as i64)
+        }
+        Some(InstanceType::Number) => {
+            let number = object.number.clone().unwrap_or_default();
+            let value = match (number.minimum, number.exclusive_minimum) {
+                (Some(minimum), _) => minimum,
+                (None, Some(minimum)) => match number.maximum.or(number.exclusive_maximum) {
+                    Some(maximum) if maximum - minimum <= 1.0 => (minimum + maximum) / 2.0,
+                    _ => minimum + 1.0,
+                },
+                (None, None) => match (number.maximum, number.exclusive_maximum) {
+                    (Some(maximum), _) if maximum < 0.0 => maximum,
+                    (None, Some(maximum)) if maximum <= 0.0 => maximum - 1.0,
+                    _ => 0.0,
+                },
+            };
+            json!(value)
+        }
+        Some(InstanceType::Boolean) => json!(true),
+        Some(InstanceType::Null) | None => Value::Null,
+    }
+}
+
+fn generate_string(object: &SchemarsObject) -> Value {
+    let value = match object.format.as_deref() {
+        Some("date-time") => "2024-01-01T00:00:00Z",
+        Some("date") => "2024-01-01",
+        Some("time") => "00:00:00Z",
+        Some("email") => "user@example.com",
+        Some("uuid") => "00000000-0000-0000-0000-000000000000",
+        Some("uri") | Some("url") => "https://example.com",
+        Some("hostname") => "example.com",
+        Some("ipv4") => "192.0.2.1",
+        Some("ipv6") => "2001:db8::1",
+        Some("byte") => "ZXhhbXBsZQ==",
+        _ => "string",
+    };
+    let string = object.string.clone().unwrap_or_default();
+    let min_length = string.min_length.unwrap_or(0) as usize;
+    let max_length = string.max_length.map_or(usize::MAX, |max| max as usize);
+    let value = if value.len() < min_length {
+        value.repeat(min_length / value.len() + 1)
+    } else {
+        value.to_string()
+    };
+    //Formats are ASCII so there's no worry about cutting a character in half
+    Value::String(value[..value.len().min(max_length).max(min_length)].into())
+}
+
+///An example for a media type, preferring the examples written in the spec.
+pub(crate) fn media_type_example(media_type: &MediaType) -> Option<Value> {
+    if let Some(example) = media_type.example.as_ref() {
+        return Some(example.clone());
+    }
+    if let Some(value) = media_type
+        .examples
+        .values()
+        .filter_map(as_item)
+        .find_map(|example| example.value.clone())
+    {
+        return Some(value);
+    }
+    media_type
+        .schema
+        .as_ref()
+        .map(|schema| generate_example(&schema.json_schema))
+}
+
+///An example value for a parameter, from its `example` or `examples` if it has them, otherwise
+///generated from its schema.
+pub(crate) fn parameter_example(parameter: &Parameter) -> Value {
+    let data = parameter_data(parameter);
+    if let Some(example) = data.example.as_ref() {
+        return example.clone();
+    }
+    if let Some(value) = data
+        .examples
+        .values()
+        .filter_map(as_item)
+        .find_map(|example| example.value.clone())
+    {
+        return value;
+    }
+    match &data.format {
+        ParameterSchemaOrContent::Schema(schema) => generate_example(&schema.json_schema),
+        ParameterSchemaOrContent::Content(content) => content
+            .values()
+            .next()
+            .and_then(media_type_example)
+            .unwrap_or(Value::Null),
+    }
+}
+
+///An example value as it'd be written in a URL or header.
+pub(crate) fn example_text(example: &Value) -> String {
+    match example {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+///The variables in a server's URL along with their defaults.
+pub(crate) fn server_variables(server: &Server) -> Vec<(String, String)> {
+    let server = serde_json::to_value(server).unwrap_or_default();
+    server
+        .get("variables")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .map(|(name, variable)| {
+            let default = variable.get("default").and_then(Value::as_str);
+            (name.clone(), default.unwrap_or_default().to_string())
+        })
+        .collect()
+}
+
+impl DereferencedOpenApi {
+    ///Example request and response bodies for an operation, using the first media type of each.
+    pub fn example_for_operation(
+        &self,
+        path: &str,
+        method: Method,
+    ) -> Result<OperationExample, OpenApiError> {
+        let operation = method.operation(self.path_item(path)?).ok_or_else(|| {
+            OpenApiError::OperationNotFound {
+                path: path.into(),
+                method,
+            }
+        })?;
+        let request_body = operation
+            .request_body
+            .as_ref()
+            .and_then(as_item)
+            .and_then(|request_body| request_body.content.values().next())
+            .and_then(media_type_example);
+        let mut responses = IndexMap::new();
+        if let Some(operation_responses) = operation.responses.as_ref() {
+            for (status, response) in &operation_responses.responses {
+                if let Some(example) = as_item(response)
+                    .and_then(|response| response.content.values().next())
+                    .and_then(media_type_example)
+                {
+                    responses.insert(status.to_string(), example);
+                }
+            }
+            if let Some(example) = operation_responses
+                .default
+                .as_ref()
+                .and_then(as_item)
+                .and_then(|response| response.content.values().next())
+                .and_then(media_type_example)
+            {
+                responses.insert("default".into(), example);
+            }
+        }
+        Ok(OperationExample {
+            request_body,
+            responses,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_generate_example() -> Result<()> {
+        let schema: SchemarsSchema = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "id": {"type": "string", "format": "uuid"},
+                "name": {"type": "string", "examples": ["Rex"]},
+                "kind": {"enum": ["dog", "cat"]},
+                "age": {"type": "integer", "minimum": 1},
+                "good": {"type": "boolean", "default": false},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "code": {"type": "string", "maxLength": 3},
+                "score": {"type": "integer", "maximum": -5},
+                "ratio": {"type": "number", "exclusiveMaximum": 0},
+                "none": {"type": "array", "items": {"type": "string"}, "maxItems": 0},
+                "owner": {
+                    "allOf": [
+                        {"type": "object", "properties": {"email": {"type": "string", "format": "email"}}},
+                        {"type": "object", "properties": {"since": {"type": "string", "format": "date"}}}
+                    ]
+                }
+            }
+        }))?;
+        assert_eq!(
+            json!({
+                "id": "00000000-0000-0000-0000-000000000000",
+                "name": "Rex",
+                "kind": "dog",
+                "age": 1,
+                "good": false,
+                "tags": ["string"],
+                "code": "str",
+                "score": -5,
+                "ratio": -1.0,
+                "none": [],
+                "owner": {"email": "user@example.com", "since": "2024-01-01"}
+            }),
+            generate_example(&schema)
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_example_for_operation() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Examples", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "post": {
+                        "requestBody": {
+                            "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+                        },
+                        "responses": {
+                            "201": {
+                                "description": "created",
+                                "content": {"application/json": {"example": {"name": "Written"}}}
+                            },
+                            "default": {
+                                "description": "error",
+                                "content": {"application/json": {"schema": {"type": "object", "properties": {"code": {"type": "integer"}}}}}
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Pet": {"type": "object", "properties": {"name": {"type": "string"}}}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let example = dereferenced.example_for_operation("/pets", Method::Post)?;
+        assert_eq!(Some(json!({"name": "string"})), example.request_body);
+        assert_eq!(json!({"name": "Written"}), example.responses["201"]);
+        assert_eq!(json!({"code": 0}), example.responses["default"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+use crate::convert::for_each_schema;
+use crate::strip::for_each_spec_object;
+use crate::{pointer, DereferencedOpenApi, OpenApiError};
+
+///An example in the spec that its schema doesn't accept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExampleViolation {
+    ///JSON pointer to the example value in the input document, where it was written rather
+    ///than every place it was inlined to
+    pub location: String,
+    ///JSON pointer to the offending part of the example, empty for the example itself
+    pub instance_path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ExampleViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}: {}",
+            self.location, self.instance_path, self.message
+        )
+    }
+}
+
+impl DereferencedOpenApi {
+    ///Check every example in the dereferenced spec against its schema: `example` and
+    ///`examples` on media types, parameters and headers, and the `examples` of schemas
+    ///themselves. `externalValue` examples aren't fetched. A schema that can't be compiled is
+    ///reported against each of its examples rather than failing the whole check.
+    pub fn validate_examples(&self) -> Result<Vec<ExampleViolation>, OpenApiError> {
+        let mut document = self.to_output_value()?;
+        //(JSON pointer to the example, example, schema)
+        let mut examples: Vec<(String, Value, Value)> = vec![];
+        for_each_spec_object(&mut document, "", &mut |object, location| {
+            let Some(schema @ (Value::Object(_) | Value::Bool(_))) = object.get("schema") else {
+                return;
+            };
+            for (example_location, example) in documented_examples(object, location) {
+                examples.push((example_location, example, schema.clone()));
+            }
+        });
+        for_each_schema(&mut document, "", &mut |schema, location| {
+            let mut bare = schema.clone();
+            bare.remove("example");
+            let Some(Value::Array(schema_examples)) = bare.remove("examples") else {
+                return;
+            };
+            for (i, example) in schema_examples.into_iter().enumerate() {
+                let example_location = format!("{location}/examples/{i}");
+                examples.push((example_location, example, Value::Object(bare.clone())));
+            }
+        });
+        let mut violations = vec![];
+        let mut checked = HashSet::new();
+        for (location, example, schema) in examples {
+            let location = self.source_of(&location);
+            if !checked.insert(location.clone()) {
+                continue;
+            }
+            let compiled = match self.compiled(&self.with_components(schema)) {
+                Ok(compiled) => compiled,
+                Err(e) => {
+                    violations.push(ExampleViolation {
+                        location,
+                        instance_path: String::new(),
+                        message: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            if let Err(errors) = compiled.validate(&example) {
+                violations.extend(errors.map(|e| ExampleViolation {
+                    location: location.clone(),
+                    instance_path: e.instance_path.to_string(),
+                    message: e.to_string(),
+                }));
+            }
+        }
+        Ok(violations)
+    }
+
+    ///Where the value at `location` in the dereferenced spec was written in the input,
+    ///following the refs that were inlined on the way to it. Just `location` once the input
+    ///has been released.
+    fn source_of(&self, location: &str) -> String {
+        let mut source = String::new();
+        let mut node = Some(&self.json);
+        for segment in location.split('/').skip(1) {
+            let mut followed = HashSet::new();
+            while let Some(reference) = node.and_then(|n| n.get("$ref")).and_then(Value::as_str) {
+                let Ok(pointer) = self.locate_reference(reference) else {
+                    break;
+                };
+                if !followed.insert(pointer.clone()) {
+                    break;
+                }
+                node = self.json.pointer(&pointer);
+                source = pointer;
+            }
+            node = node.and_then(|n| match n {
+                Value::Object(object) => object.get(&pointer::unescape(segment)),
+                Value::Array(values) => segment.parse().ok().and_then(|i: usize| values.get(i)),
+                _ => None,
+            });
+            source = format!("{source}/{segment}");
+        }
+        source
+    }
+}
+
+///The `example` and the `value`s under `examples` of a media type, parameter or header.
+fn documented_examples(object: &Map<String, Value>, location: &str) -> Vec<(String, Value)> {
+    let mut examples = vec![];
+    if let Some(example) = object.get("example") {
+        examples.push((pointer::push(location, "example"), example.clone()));
+    }
+    if let Some(Value::Object(named)) = object.get("examples") {
+        for (name, example) in named {
+            if let Some(value) = example.get("value") {
+                let example_location = pointer::push(&pointer::push(location, "examples"), name);
+                examples.push((pointer::push(&example_location, "value"), value.clone()));
+            }
+        }
+    }
+    examples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_validate_examples() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Examples", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "parameters": [
+                            {"name": "limit", "in": "query", "schema": {"type": "integer"}, "example": "ten"},
+                            {"name": "name", "in": "query", "schema": {"type": "string", "pattern": "("}, "example": "Rex"}
+                        ],
+                        "responses": {
+                            "200": {
+                                "description": "Pets",
+                                "content": {
+                                    "application/json": {
+                                        "schema": {"$ref": "#/components/schemas/Pet"},
+                                        "examples": {
+                                            "good": {"value": {"name": "Rex"}},
+                                            "bad": {"value": {"name": 7}}
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Pet": {
+                        "type": "object",
+                        "required": ["name"],
+                        "properties": {"name": {"type": "string"}},
+                        "examples": [{"name": "Fido"}, {}]
+                    }
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let mut locations: Vec<String> = dereferenced
+            .validate_examples()?
+            .into_iter()
+            .map(|violation| format!("{}{}", violation.location, violation.instance_path))
+            .collect();
+        locations.sort();
+        let media_type = "/paths/~1pets/get/responses/200/content/application~1json";
+        assert_eq!(
+            vec![
+                "/components/schemas/Pet/examples/1".to_string(),
+                "/paths/~1pets/get/parameters/0/example".to_string(),
+                "/paths/~1pets/get/parameters/1/example".to_string(),
+                format!("{media_type}/examples/bad/value/name"),
+            ],
+            locations
+        );
+        Ok(())
+    }
+}
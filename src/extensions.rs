@@ -0,0 +1,172 @@
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::visit_mut::unwrap_dereferenced_path_item;
+use crate::{pointer, OpenApiDereferencer, OpenApiError};
+
+impl OpenApiDereferencer {
+    ///The `x-*` extensions on the object at `json_pointer`, e.g. `/paths/~1pets/get`. After
+    ///dereferencing the pointer is into the dereferenced spec, so extensions on a referenced
+    ///component show up everywhere it's used.
+    pub fn extensions_at(
+        &self,
+        json_pointer: &str,
+    ) -> Result<IndexMap<String, Value>, OpenApiError> {
+        let not_found = || OpenApiError::PointerNotFound {
+            pointer: json_pointer.into(),
+        };
+        if !self.is_dereferenced() {
+            return self
+                .json
+                .pointer(json_pointer)
+                .and_then(extensions)
+                .ok_or_else(not_found);
+        }
+        let (part, rest) = self.output_part(json_pointer)?;
+        part.pointer(&rest)
+            .and_then(extensions)
+            .ok_or_else(not_found)
+    }
+
+    ///The path item or component schema `json_pointer` is in, serialized on its own, and where
+    ///in it the pointer goes. Anything else needs the whole spec.
+    fn output_part(&self, json_pointer: &str) -> Result<(Value, String), OpenApiError> {
+        let segments: Vec<&str> = json_pointer.split('/').collect();
+        let rest = |from: usize| {
+            segments
+                .get(from..)
+                .map(|rest| {
+                    rest.iter()
+                        .map(|segment| format!("/{segment}"))
+                        .collect::<String>()
+                })
+                .unwrap_or_default()
+        };
+        let path_items = match segments.get(1) {
+            Some(&"paths") => self.openapi.paths.as_ref().map(|paths| &paths.paths),
+            Some(&"webhooks") => Some(&self.openapi.webhooks),
+            _ => None,
+        };
+        let path_item = path_items
+            .zip(segments.get(2))
+            .and_then(|(path_items, name)| path_items.get(&pointer::unescape(name)));
+        if let Some(path_item) = path_item {
+            let mut path_item = path_item.clone();
+            unwrap_dereferenced_path_item(&mut path_item);
+            return Ok((to_json(&path_item)?, rest(3)));
+        }
+        let schema = match segments.get(1..4) {
+            Some(["components", "schemas", name]) => self
+                .openapi
+                .components
+                .as_ref()
+                .and_then(|components| components.schemas.get(&pointer::unescape(name))),
+            _ => None,
+        };
+        match schema {
+            Some(schema) => Ok((to_json(schema)?, rest(4))),
+            None => Ok((self.to_output_value()?, json_pointer.into())),
+        }
+    }
+}
+
+fn extensions(object: &Value) -> Option<IndexMap<String, Value>> {
+    Some(
+        object
+            .as_object()?
+            .iter()
+            .filter(|(key, _)| key.starts_with("x-"))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect(),
+    )
+}
+
+fn to_json<T: Serialize>(item: &T) -> Result<Value, OpenApiError> {
+    serde_json::to_value(item).map_err(|e| OpenApiError::ParsingError {
+        msg: format!("Error serializing the spec {e}"),
+        location: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_extensions_survive_dereferencing() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "x-root": 1,
+            "info": {"title": "Extensions", "version": "1.0.0", "x-info": 2},
+            "paths": {
+                "/pets": {
+                    "x-path-item": 3,
+                    "get": {
+                        "x-operation": 4,
+                        "parameters": [{"$ref": "#/components/parameters/Limit"}],
+                        "responses": {"200": {"$ref": "#/components/responses/Pets"}}
+                    }
+                }
+            },
+            "components": {
+                "x-components": 5,
+                "parameters": {
+                    "Limit": {"name": "limit", "in": "query", "schema": {"type": "integer"}, "x-parameter": 6}
+                },
+                "responses": {
+                    "Pets": {
+                        "description": "Pets",
+                        "x-response": 7,
+                        "content": {
+                            "application/json": {
+                                "x-media-type": 8,
+                                "schema": {"$ref": "#/components/schemas/Pet"}
+                            }
+                        }
+                    }
+                },
+                "schemas": {
+                    "Pet": {"type": "object", "x-schema": {"nested": [9]}}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let response = "/paths/~1pets/get/responses/200";
+        let media_type = format!("{response}/content/application~1json");
+        for (pointer, key, value) in [
+            ("", "x-root", json!(1)),
+            ("/info", "x-info", json!(2)),
+            ("/paths/~1pets", "x-path-item", json!(3)),
+            ("/paths/~1pets/get", "x-operation", json!(4)),
+            ("/components", "x-components", json!(5)),
+            (
+                "/components/schemas/Pet",
+                "x-schema",
+                json!({"nested": [9]}),
+            ),
+            ("/paths/~1pets/get/parameters/0", "x-parameter", json!(6)),
+            (response, "x-response", json!(7)),
+            (&media_type, "x-media-type", json!(8)),
+            (
+                &format!("{media_type}/schema"),
+                "x-schema",
+                json!({"nested": [9]}),
+            ),
+        ] {
+            assert_eq!(
+                Some(&value),
+                dereferenced.extensions_at(pointer)?.get(key),
+                "{key} at {pointer}"
+            );
+        }
+        assert!(matches!(
+            dereferenced.extensions_at("/paths/~1cats"),
+            Err(OpenApiError::PointerNotFound { .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use openapiv3::v3_1::{Example, ReferenceOr};
+use serde_json::Value;
+
+use crate::visit_mut::as_item_mut;
+use crate::{OpenApiDereferencer, OpenApiError};
+
+///Loads the content an example's `externalValue` points at.
+pub trait ExampleFetcher: Send + Sync {
+    fn fetch(&self, url: &str) -> Result<Value, OpenApiError>;
+}
+
+impl<F> ExampleFetcher for F
+where
+    F: Fn(&str) -> Result<Value, OpenApiError> + Send + Sync,
+{
+    fn fetch(&self, url: &str) -> Result<Value, OpenApiError> {
+        self(url)
+    }
+}
+
+///Loads external examples from disk, relative to `base`. JSON files are parsed, anything else
+///is inlined as a string.
+#[cfg(feature = "fs")]
+pub struct FileExampleFetcher {
+    pub base: PathBuf,
+}
+
+#[cfg(feature = "fs")]
+impl ExampleFetcher for FileExampleFetcher {
+    fn fetch(&self, url: &str) -> Result<Value, OpenApiError> {
+        let path = self.base.join(url.strip_prefix("file://").unwrap_or(url));
+        let contents =
+            std::fs::read_to_string(&path).map_err(|e| OpenApiError::ExampleFetchError {
+                url: url.into(),
+                msg: e.to_string(),
+            })?;
+        Ok(serde_json::from_str(&contents).unwrap_or(Value::String(contents)))
+    }
+}
+
+impl OpenApiDereferencer {
+    ///Inline `externalValue` examples as `value` while dereferencing, using `fetcher` to load
+    ///them. Without a fetcher external examples are left alone.
+    pub fn with_example_fetcher(mut self, fetcher: impl ExampleFetcher + 'static) -> Self {
+        self.example_fetcher = Some(Arc::new(fetcher));
+        self
+    }
+
+    pub(crate) fn dereference_example(
+        &self,
+        example: ReferenceOr<Example>,
+    ) -> Result<ReferenceOr<Example>, OpenApiError> {
+        let mut example = self.dereference_reference(example)?;
+        if let (Some(fetcher), Some(item)) =
+            (self.example_fetcher.as_ref(), as_item_mut(&mut example))
+        {
+            if item.value.is_none() {
+                //value and externalValue are mutually exclusive, so the url goes once it's inlined
+                if let Some(url) = item.external_value.take() {
+                    item.value = Some(fetcher.fetch(&url)?);
+                }
+            }
+        }
+        Ok(example)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::as_item;
+    use anyhow::Result;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_external_examples_are_fetched() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Examples", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "Pets",
+                                "content": {
+                                    "application/json": {
+                                        "examples": {
+                                            "external": {"externalValue": "https://example.com/pets.json"},
+                                            "referenced": {"$ref": "#/components/examples/Pet"}
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "examples": {
+                    "Pet": {"externalValue": "https://example.com/pet.json"}
+                }
+            }
+        }"##;
+        let fetcher = |url: &str| -> Result<Value, OpenApiError> { Ok(json!({ "fetched": url })) };
+        let dereferenced = OpenApiDereferencer::from_str(spec)?
+            .with_example_fetcher(fetcher)
+            .dereference()?;
+        let (_, _, operation) = dereferenced.iter_operations().next().unwrap();
+        let responses = operation.responses.as_ref().unwrap();
+        let response = as_item(responses.responses.values().next().unwrap()).unwrap();
+        let examples = &response.content["application/json"].examples;
+        let external = as_item(&examples["external"]).unwrap();
+        assert_eq!(
+            Some(json!({"fetched": "https://example.com/pets.json"})),
+            external.value
+        );
+        assert_eq!(None, external.external_value);
+        assert_eq!(
+            Some(json!({"fetched": "https://example.com/pet.json"})),
+            as_item(&examples["referenced"]).unwrap().value
+        );
+
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let components = dereferenced.openapi.components.as_ref().unwrap();
+        let pet = as_item(&components.examples["Pet"]).unwrap();
+        assert_eq!(None, pet.value);
+        Ok(())
+    }
+}
@@ -0,0 +1,681 @@
+use openapiv3::schemars::schema::{
+    InstanceType, Schema as SchemarsSchema, SchemaObject as SchemarsObject, SingleOrVec,
+};
+use serde_json::{json, Map, Number, Value};
+
+///Recursive schemas stop generating after this many levels.
+const MAX_DEPTH: usize = 8;
+///How far past the lower bound lengths, counts and numbers go when there's no upper bound, and
+///how far lengths and counts go at most.
+const SPREAD: u64 = 8;
+///How many strings matching a pattern are tried to get one of the right length.
+const PATTERN_ATTEMPTS: usize = 32;
+const ALPHANUMERIC: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+const WORDS: [&str; 8] = [
+    "alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel",
+];
+
+///Makes up random values that are valid for a dereferenced schema, for fixtures and load
+///testing. Enums, consts, `allOf`/`oneOf`/`anyOf`, required properties, string lengths, patterns,
+///common formats, numeric bounds, `multipleOf` and array sizes are respected. The same seed
+///always produces the same values.
+#[derive(Debug, Clone)]
+pub struct DataGenerator {
+    state: u64,
+}
+
+impl DataGenerator {
+    pub fn new(seed: u64) -> Self {
+        DataGenerator { state: seed }
+    }
+
+    pub fn generate(&mut self, schema: &SchemarsSchema) -> Value {
+        self.schema(schema, 0)
+    }
+
+    ///splitmix64, small and stable so a seed means the same thing across versions.
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    ///A number in `0..n`, `n` must not be 0.
+    fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    ///A number in `min..=max`, or `min` if the bounds are the wrong way round.
+    fn between(&mut self, min: u64, max: u64) -> u64 {
+        if max <= min {
+            return min;
+        }
+        match (max - min).checked_add(1) {
+            Some(n) => min + self.below(n),
+            None => self.next_u64(),
+        }
+    }
+
+    ///A length or count in `min..=max`, kept to a few past `min` so huge maximums don't make
+    ///huge values.
+    fn length(&mut self, min: u64, max: u64) -> u64 {
+        self.between(min, max.min(min.saturating_add(SPREAD)))
+    }
+
+    fn pick<'a, T>(&mut self, values: &'a [T]) -> &'a T {
+        &values[self.below(values.len() as u64) as usize]
+    }
+
+    fn chance(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+
+    fn schema(&mut self, schema: &SchemarsSchema, depth: usize) -> Value {
+        let object = match schema {
+            SchemarsSchema::Bool(_) => return Value::Null,
+            SchemarsSchema::Object(object) => object,
+        };
+        if depth > MAX_DEPTH || object.is_ref() {
+            return Value::Null;
+        }
+        if let Some(value) = object.const_value.as_ref() {
+            return value.clone();
+        }
+        if let Some(values) = object.enum_values.as_ref().filter(|v| !v.is_empty()) {
+            return self.pick(values).clone();
+        }
+        if let Some(subschemas) = object.subschemas.as_ref() {
+            if let Some(all_of) = subschemas.all_of.as_ref() {
+                let mut merged = Map::new();
+                for schema in all_of {
+                    match self.schema(schema, depth + 1) {
+                        Value::Object(values) => merged.extend(values),
+                        Value::Null => {}
+                        other => return other,
+                    }
+                }
+                if let Value::Object(values) = self.typed(object, depth) {
+                    merged.extend(values);
+                }
+                return Value::Object(merged);
+            }
+            if let Some(schemas) = subschemas
+                .one_of
+                .as_ref()
+                .or(subschemas.any_of.as_ref())
+                .filter(|schemas| !schemas.is_empty())
+            {
+                let schema = self.pick(schemas);
+                return self.schema(schema, depth + 1);
+            }
+        }
+        self.typed(object, depth)
+    }
+
+    fn typed(&mut self, object: &SchemarsObject, depth: usize) -> Value {
+        let instance_type = match object.instance_type.as_ref() {
+            Some(SingleOrVec::Single(instance_type)) => Some(**instance_type),
+            Some(SingleOrVec::Vec(types)) if !types.is_empty() => Some(*self.pick(types)),
+            _ if object.object.is_some() => Some(InstanceType::Object),
+            _ if object.array.is_some() => Some(InstanceType::Array),
+            _ if object.string.is_some() => Some(InstanceType::String),
+            _ if object.number.is_some() => Some(InstanceType::Number),
+            _ => None,
+        };
+        match instance_type {
+            Some(InstanceType::Object) => self.object(object, depth),
+            Some(InstanceType::Array) => self.array(object, depth),
+            Some(InstanceType::String) => Value::String(self.string(object)),
+            Some(InstanceType::Integer) => self.integer(object),
+            Some(InstanceType::Number) => self.number(object),
+            Some(InstanceType::Boolean) => Value::Bool(self.chance()),
+            Some(InstanceType::Null) | None => Value::Null,
+        }
+    }
+
+    ///Required properties are always there, the rest are a coin flip.
+    fn object(&mut self, object: &SchemarsObject, depth: usize) -> Value {
+        let mut values = Map::new();
+        if let Some(validation) = object.object.as_ref() {
+            for (name, schema) in &validation.properties {
+                if validation.required.contains(name) || self.chance() {
+                    values.insert(name.clone(), self.schema(schema, depth + 1));
+                }
+            }
+        }
+        Value::Object(values)
+    }
+
+    fn array(&mut self, object: &SchemarsObject, depth: usize) -> Value {
+        let Some(array) = object.array.as_ref() else {
+            return json!([]);
+        };
+        let min = array.min_items.unwrap_or(0) as u64;
+        let max = array.max_items.map_or(min + SPREAD / 2, u64::from);
+        let len = self.length(min, max);
+        let mut values: Vec<Value> = vec![];
+        match &array.items {
+            Some(SingleOrVec::Vec(schemas)) => {
+                for schema in schemas.iter().take(len as usize) {
+                    values.push(self.schema(schema, depth + 1));
+                }
+            }
+            Some(SingleOrVec::Single(schema)) => {
+                //Give up on unique items after a few collisions rather than looping forever
+                let mut attempts = 0;
+                while (values.len() as u64) < len && attempts < len * 4 {
+                    attempts += 1;
+                    let value = self.schema(schema, depth + 1);
+                    if array.unique_items != Some(true) || !values.contains(&value) {
+                        values.push(value);
+                    }
+                }
+            }
+            None => {}
+        }
+        Value::Array(values)
+    }
+
+    fn string(&mut self, object: &SchemarsObject) -> String {
+        let validation = object.string.as_ref();
+        if let Some(pattern) = validation.and_then(|string| string.pattern.as_deref()) {
+            let min = validation.and_then(|s| s.min_length).map_or(0, u64::from);
+            let max = validation
+                .and_then(|s| s.max_length)
+                .map_or(u64::MAX, u64::from);
+            if let Some(value) = self.pattern(pattern, min, max) {
+                return value;
+            }
+        }
+        if let Some(value) = object.format.as_deref().and_then(|f| self.format(f)) {
+            return value;
+        }
+        let min = validation.and_then(|s| s.min_length).unwrap_or(1) as u64;
+        let max = validation
+            .and_then(|s| s.max_length)
+            .map_or(min + SPREAD, u64::from);
+        let len = self.length(min, max);
+        (0..len).map(|_| *self.pick(ALPHANUMERIC) as char).collect()
+    }
+
+    fn format(&mut self, format: &str) -> Option<String> {
+        let value = match format {
+            "date-time" => format!("{}T{}", self.date(), self.time()),
+            "date" => self.date(),
+            "time" => self.time(),
+            "email" => format!("{}{}@example.com", self.pick(&WORDS), self.below(1000)),
+            "uuid" => {
+                let hex = format!("{:016x}{:016x}", self.next_u64(), self.next_u64());
+                format!(
+                    "{}-{}-4{}-a{}-{}",
+                    &hex[0..8],
+                    &hex[8..12],
+                    &hex[13..16],
+                    &hex[17..20],
+                    &hex[20..32]
+                )
+            }
+            "uri" | "url" => format!("https://example.com/{}", self.pick(&WORDS)),
+            "hostname" => format!("{}.example.com", self.pick(&WORDS)),
+            "ipv4" => format!(
+                "{}.{}.{}.{}",
+                self.between(1, 254),
+                self.below(256),
+                self.below(256),
+                self.between(1, 254)
+            ),
+            "ipv6" => format!("2001:db8::{:x}", self.below(0x10000)),
+            "byte" => {
+                const BASE64: &[u8] =
+                    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+                (0..self.between(1, 4) * 4)
+                    .map(|_| *self.pick(BASE64) as char)
+                    .collect()
+            }
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    fn date(&mut self) -> String {
+        format!(
+            "{}-{:02}-{:02}",
+            self.between(2000, 2030),
+            self.between(1, 12),
+            self.between(1, 28)
+        )
+    }
+
+    fn time(&mut self) -> String {
+        format!(
+            "{:02}:{:02}:{:02}Z",
+            self.below(24),
+            self.below(60),
+            self.below(60)
+        )
+    }
+
+    fn integer(&mut self, object: &SchemarsObject) -> Value {
+        let (min, max) = bounds(object);
+        let mut min = min.map(|m| m.ceil() as i64);
+        let mut max = max.map(|m| m.floor() as i64);
+        match object.format.as_deref() {
+            Some("int32") => {
+                min = Some(min.unwrap_or(i32::MIN as i64).max(i32::MIN as i64));
+                max = Some(max.unwrap_or(i32::MAX as i64).min(i32::MAX as i64));
+            }
+            Some("uint32") | Some("uint64") => min = Some(min.unwrap_or(0).max(0)),
+            _ => {}
+        }
+        let (min, max) = match (min, max) {
+            (Some(min), Some(max)) => (min, max),
+            (Some(min), None) => (min, min.saturating_add(SPREAD as i64 * 100)),
+            (None, Some(max)) => (max.saturating_sub(SPREAD as i64 * 100), max),
+            (None, None) => (0, SPREAD as i64 * 100),
+        };
+        let multiple = object
+            .number
+            .as_ref()
+            .and_then(|n| n.multiple_of)
+            .filter(|m| *m >= 1.0 && m.fract() == 0.0)
+            .map(|m| m as i64);
+        let first_and_last = multiple.map(|multiple| {
+            let first = min.div_euclid(multiple) + (min.rem_euclid(multiple) != 0) as i64;
+            (first, max.div_euclid(multiple), multiple)
+        });
+        let value = match first_and_last {
+            //With no multiple between the bounds there's no valid value, so stay in bounds
+            Some((first, last, multiple)) if first <= last => {
+                self.between_i64(first, last) * multiple
+            }
+            _ => self.between_i64(min, max),
+        };
+        json!(value)
+    }
+
+    fn between_i64(&mut self, min: i64, max: i64) -> i64 {
+        if max <= min {
+            return min;
+        }
+        let span = max.abs_diff(min);
+        match span.checked_add(1) {
+            Some(n) => min.wrapping_add(self.below(n) as i64),
+            None => self.next_u64() as i64,
+        }
+    }
+
+    fn number(&mut self, object: &SchemarsObject) -> Value {
+        let (min, max) = bounds(object);
+        let (min, max) = match (min, max) {
+            (Some(min), Some(max)) => (min, max),
+            (Some(min), None) => (min, min + SPREAD as f64 * 100.0),
+            (None, Some(max)) => (max - SPREAD as f64 * 100.0, max),
+            (None, None) => (0.0, SPREAD as f64 * 100.0),
+        };
+        let fraction = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        let in_bounds = |value: &f64| (min..=max).contains(value);
+        let multiple = object
+            .number
+            .as_ref()
+            .and_then(|n| n.multiple_of)
+            .filter(|multiple| *multiple > 0.0)
+            .and_then(|multiple| {
+                let first = (min / multiple).ceil();
+                let last = (max / multiple).floor();
+                let picked = first + ((last - first) * fraction).floor();
+                //Rounding can put a multiple just outside the bounds, so try its neighbours
+                [picked, picked + 1.0, picked - 1.0]
+                    .into_iter()
+                    .filter(|n| first <= *n && *n <= last)
+                    .map(|n| n * multiple)
+                    .find(in_bounds)
+            });
+        let value = multiple.unwrap_or_else(|| min + (max - min) * fraction);
+        Number::from_f64(value).map_or(Value::Null, Value::Number)
+    }
+
+    ///A string matching the common subset of regex syntax, or `None` for anything fancier. A
+    ///few are tried to find one with a length in `min..=max`, then the shortest the pattern
+    ///allows. If none fit the pattern wins.
+    fn pattern(&mut self, pattern: &str, min: u64, max: u64) -> Option<String> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut position = 0;
+        let alternatives = parse_alternatives(&chars, &mut position)?;
+        if position != chars.len() {
+            return None;
+        }
+        let fits = |value: &String| (min..=max).contains(&(value.chars().count() as u64));
+        let mut first = None;
+        for _ in 0..PATTERN_ATTEMPTS {
+            let mut value = String::new();
+            self.render_alternatives(&alternatives, &mut value, false);
+            if fits(&value) {
+                return Some(value);
+            }
+            first.get_or_insert(value);
+        }
+        let mut shortest = String::new();
+        self.render_alternatives(&alternatives, &mut shortest, true);
+        match fits(&shortest) {
+            true => Some(shortest),
+            false => first,
+        }
+    }
+
+    ///`shortest` repeats everything as few times as allowed.
+    fn render_alternatives(
+        &mut self,
+        alternatives: &[Vec<Node>],
+        value: &mut String,
+        shortest: bool,
+    ) {
+        if alternatives.is_empty() {
+            return;
+        }
+        for node in self.pick(alternatives) {
+            self.render(node, value, shortest);
+        }
+    }
+
+    fn render(&mut self, node: &Node, value: &mut String, shortest: bool) {
+        match node {
+            Node::Literal(c) => value.push(*c),
+            Node::Class {
+                ranges,
+                negated: true,
+            } => {
+                let candidates: Vec<char> = (' '..='~')
+                    .filter(|c| !ranges.iter().any(|(lo, hi)| lo <= c && c <= hi))
+                    .collect();
+                if !candidates.is_empty() {
+                    value.push(*self.pick(&candidates));
+                }
+            }
+            Node::Class { ranges, .. } => {
+                if !ranges.is_empty() {
+                    let (lo, hi) = *self.pick(ranges);
+                    value.extend(char::from_u32(self.between(lo as u64, hi as u64) as u32));
+                }
+            }
+            Node::Group(alternatives) => self.render_alternatives(alternatives, value, shortest),
+            Node::Repeat { node, min, max } => {
+                let count = match shortest {
+                    true => *min,
+                    false => self.length(*min, *max),
+                };
+                for _ in 0..count {
+                    self.render(node, value, shortest);
+                }
+            }
+        }
+    }
+}
+
+///Lower and upper bounds, with exclusive ones nudged inwards.
+fn bounds(object: &SchemarsObject) -> (Option<f64>, Option<f64>) {
+    let Some(number) = object.number.as_ref() else {
+        return (None, None);
+    };
+    let min = match (number.minimum, number.exclusive_minimum) {
+        (_, Some(exclusive)) => Some(exclusive + nudge(exclusive)),
+        (minimum, None) => minimum,
+    };
+    let max = match (number.maximum, number.exclusive_maximum) {
+        (_, Some(exclusive)) => Some(exclusive - nudge(exclusive)),
+        (maximum, None) => maximum,
+    };
+    (min, max)
+}
+
+fn nudge(bound: f64) -> f64 {
+    if bound.fract() == 0.0 {
+        1.0
+    } else {
+        bound.abs().max(1.0) * f64::EPSILON * 4.0
+    }
+}
+
+enum Node {
+    Literal(char),
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    Group(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: u64,
+        max: u64,
+    },
+}
+
+fn parse_alternatives(chars: &[char], position: &mut usize) -> Option<Vec<Vec<Node>>> {
+    let mut alternatives = vec![vec![]];
+    while let Some(&c) = chars.get(*position) {
+        *position += 1;
+        let node = match c {
+            '|' => {
+                alternatives.push(vec![]);
+                continue;
+            }
+            ')' => {
+                *position -= 1;
+                break;
+            }
+            '^' | '$' => continue,
+            '(' => {
+                if chars.get(*position) == Some(&'?') {
+                    //Only non-capturing groups, lookarounds can't be generated this simply
+                    if chars.get(*position + 1) != Some(&':') {
+                        return None;
+                    }
+                    *position += 2;
+                }
+                let group = parse_alternatives(chars, position)?;
+                if chars.get(*position) != Some(&')') {
+                    return None;
+                }
+                *position += 1;
+                Node::Group(group)
+            }
+            '[' => parse_class(chars, position)?,
+            '.' => Node::Class {
+                ranges: vec![('a', 'z'), ('A', 'Z'), ('0', '9')],
+                negated: false,
+            },
+            '\\' => {
+                let escaped = *chars.get(*position)?;
+                *position += 1;
+                escape_class(escaped).unwrap_or(Node::Literal(escaped))
+            }
+            '*' | '+' | '?' | '{' => return None,
+            c => Node::Literal(c),
+        };
+        let node = parse_quantifier(chars, position, node)?;
+        alternatives.last_mut()?.push(node);
+    }
+    Some(alternatives)
+}
+
+fn parse_quantifier(chars: &[char], position: &mut usize, node: Node) -> Option<Node> {
+    let (min, max) = match chars.get(*position) {
+        Some('*') => (0, SPREAD / 2),
+        Some('+') => (1, SPREAD / 2),
+        Some('?') => (0, 1),
+        Some('{') => {
+            let close = chars[*position..].iter().position(|c| *c == '}')? + *position;
+            let body: String = chars[*position + 1..close].iter().collect();
+            *position = close;
+            match body.split_once(',') {
+                None => {
+                    let n = body.trim().parse().ok()?;
+                    (n, n)
+                }
+                Some((min, "")) => {
+                    let min = min.trim().parse().ok()?;
+                    (min, min.saturating_add(SPREAD / 2))
+                }
+                Some((min, max)) => (min.trim().parse().ok()?, max.trim().parse().ok()?),
+            }
+        }
+        _ => return Some(node),
+    };
+    *position += 1;
+    //Lazy and possessive quantifiers match the same strings
+    if matches!(chars.get(*position), Some('?') | Some('+')) {
+        *position += 1;
+    }
+    Some(Node::Repeat {
+        node: Box::new(node),
+        min,
+        max,
+    })
+}
+
+fn parse_class(chars: &[char], position: &mut usize) -> Option<Node> {
+    let negated = chars.get(*position) == Some(&'^');
+    if negated {
+        *position += 1;
+    }
+    let mut ranges = vec![];
+    let mut first = true;
+    loop {
+        let c = *chars.get(*position)?;
+        *position += 1;
+        if c == ']' && !first {
+            break;
+        }
+        first = false;
+        let lo = if c == '\\' {
+            let escaped = *chars.get(*position)?;
+            *position += 1;
+            match escape_class(escaped) {
+                Some(Node::Class { ranges: class, .. }) => {
+                    ranges.extend(class);
+                    continue;
+                }
+                _ => escaped,
+            }
+        } else {
+            c
+        };
+        if chars.get(*position) == Some(&'-') && chars.get(*position + 1).is_some_and(|c| *c != ']')
+        {
+            let hi = chars[*position + 1];
+            *position += 2;
+            ranges.push((lo, hi));
+        } else {
+            ranges.push((lo, lo));
+        }
+    }
+    Some(Node::Class { ranges, negated })
+}
+
+///`\d`, `\w` and `\s` as classes. The negated ones pick something that can't match.
+fn escape_class(escaped: char) -> Option<Node> {
+    let ranges = match escaped {
+        'd' => vec![('0', '9')],
+        'w' => vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')],
+        's' => vec![(' ', ' ')],
+        'D' | 'S' => vec![('a', 'z')],
+        'W' => vec![('-', '-')],
+        _ => return None,
+    };
+    Some(Node::Class {
+        ranges,
+        negated: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    pub fn test_data_generator() -> Result<()> {
+        let schema: SchemarsSchema = serde_json::from_value(json!({
+            "type": "object",
+            "required": ["id", "kind", "sku", "age", "price", "tags", "created"],
+            "properties": {
+                "id": {"type": "string", "format": "uuid"},
+                "kind": {"enum": ["dog", "cat"]},
+                "sku": {"type": "string", "pattern": "^[A-Z]{3}-\\d{4}(-(new|used))?$"},
+                "age": {"type": "integer", "minimum": 1, "exclusiveMaximum": 20, "multipleOf": 2},
+                "price": {"type": "number", "minimum": 0.5, "maximum": 9.5},
+                "tags": {"type": "array", "minItems": 2, "maxItems": 4, "items": {"type": "string", "minLength": 3, "maxLength": 5}},
+                "created": {"type": "string", "format": "date-time"},
+                "nickname": {"type": "string"}
+            }
+        }))?;
+        let mut generator = DataGenerator::new(7);
+        let values: Vec<Value> = (0..50).map(|_| generator.generate(&schema)).collect();
+        for value in &values {
+            let id = value["id"].as_str().unwrap();
+            assert_eq!(36, id.len());
+            assert!(["dog", "cat"].contains(&value["kind"].as_str().unwrap()));
+
+            let sku = value["sku"].as_str().unwrap();
+            let (letters, rest) = sku.split_at(3);
+            assert!(letters.chars().all(|c| c.is_ascii_uppercase()), "{sku}");
+            assert!(rest[1..5].chars().all(|c| c.is_ascii_digit()), "{sku}");
+            assert!(["", "-new", "-used"].contains(&&rest[5..]), "{sku}");
+
+            let age = value["age"].as_i64().unwrap();
+            assert!((2..20).contains(&age) && age % 2 == 0, "{age}");
+            let price = value["price"].as_f64().unwrap();
+            assert!((0.5..=9.5).contains(&price));
+            let tags = value["tags"].as_array().unwrap();
+            assert!((2..=4).contains(&tags.len()));
+            assert!(tags
+                .iter()
+                .all(|tag| (3..=5).contains(&tag.as_str().unwrap().len())));
+            assert_eq!(
+                Some(b'T'),
+                value["created"].as_str().unwrap().bytes().nth(10)
+            );
+        }
+        assert!(values.iter().any(|value| value.get("nickname").is_some()));
+        assert!(values.iter().any(|value| value.get("nickname").is_none()));
+
+        let mut again = DataGenerator::new(7);
+        assert_eq!(values[0], again.generate(&schema));
+        assert_ne!(values[0], DataGenerator::new(8).generate(&schema));
+        Ok(())
+    }
+    #[test]
+    pub fn test_data_generator_bounds() -> Result<()> {
+        let schema: SchemarsSchema = serde_json::from_value(json!({
+            "type": "object",
+            "required": ["wide", "huge", "none", "step", "code", "inverted", "count"],
+            "properties": {
+                "wide": {"type": "integer", "minimum": i64::MIN, "maximum": i64::MAX, "multipleOf": 3},
+                "huge": {"type": "integer", "format": "uint64", "minimum": 0, "maximum": u64::MAX},
+                "none": {"type": "integer", "minimum": 1, "maximum": 3, "multipleOf": 5},
+                "step": {"type": "number", "minimum": 0.1, "maximum": 0.35, "multipleOf": 0.1},
+                "code": {"type": "string", "pattern": "^[a-z]+$", "minLength": 2, "maxLength": 3},
+                "inverted": {"type": "string", "minLength": 5, "maxLength": 2},
+                "count": {"type": "array", "maxItems": u32::MAX, "items": {"type": "string", "maxLength": u32::MAX}}
+            }
+        }))?;
+        let mut generator = DataGenerator::new(3);
+        for _ in 0..50 {
+            let value = generator.generate(&schema);
+            assert_eq!(0, value["wide"].as_i64().unwrap() % 3);
+            assert!(value["huge"].as_i64().unwrap() >= 0);
+            assert!((1..=3).contains(&value["none"].as_i64().unwrap()));
+            let step = value["step"].as_f64().unwrap();
+            assert!((0.1..=0.35).contains(&step), "{step}");
+            assert!((2..=3).contains(&value["code"].as_str().unwrap().len()));
+            assert!(value["inverted"].is_string());
+            let count = value["count"].as_array().unwrap();
+            assert!(count.len() as u64 <= SPREAD);
+            assert!(count
+                .iter()
+                .all(|item| item.as_str().unwrap().len() as u64 <= 1 + SPREAD));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,490 @@
+use std::collections::HashSet;
+
+use serde_json::{Map, Value};
+
+use crate::lenient::for_each_ref;
+use crate::redact::retain_operations;
+use crate::strip::for_each_spec_object;
+use crate::{pointer, DereferencedOpenApi, Method, OpenApiDereferencer, OpenApiError};
+
+impl OpenApiDereferencer {
+    ///A new dereferenced spec with only the operations tagged with at least one of `tags`.
+    ///Components the remaining operations don't need are left out, as are the other tags. Works
+    ///from the input document, so it needs to still be around.
+    pub fn filter_by_tags<S: AsRef<str>>(
+        &self,
+        tags: impl IntoIterator<Item = S>,
+    ) -> Result<DereferencedOpenApi, OpenApiError> {
+        let tags: HashSet<String> = tags.into_iter().map(|tag| tag.as_ref().into()).collect();
+        let mut filtered =
+            self.filtered(|method, object| method.is_none() || has_tag(object, &tags))?;
+        if let Some(Value::Array(declared)) = filtered.get_mut("tags") {
+            declared.retain(|tag| tag.get("name").is_some_and(|name| has_name(name, &tags)));
+        }
+        self.dereference_derived(filtered)
+    }
+
+    ///A new dereferenced spec with only the operations whose operationId is in
+    ///`operation_ids`, and the components and tags they use. Works from the input document, so
+    ///it needs to still be around.
+    pub fn filter_by_operation_ids<S: AsRef<str>>(
+        &self,
+        operation_ids: impl IntoIterator<Item = S>,
+    ) -> Result<DereferencedOpenApi, OpenApiError> {
+        let operation_ids: HashSet<String> = operation_ids
+            .into_iter()
+            .map(|operation_id| operation_id.as_ref().into())
+            .collect();
+        let mut filtered = self.filtered(|method, object| {
+            method.is_none()
+                || object
+                    .get("operationId")
+                    .is_some_and(|operation_id| has_name(operation_id, &operation_ids))
+        })?;
+        let mut used_tags = HashSet::new();
+        for (_, operation) in operations(&filtered) {
+            let tags = operation.get("tags").and_then(Value::as_array);
+            used_tags.extend(
+                tags.into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .map(String::from),
+            );
+        }
+        if let Some(Value::Array(declared)) = filtered.get_mut("tags") {
+            declared.retain(|tag| {
+                tag.get("name")
+                    .is_some_and(|name| has_name(name, &used_tags))
+            });
+        }
+        self.dereference_derived(filtered)
+    }
+
+    ///A new dereferenced spec without the operations marked `deprecated`. With `schemas` set,
+    ///properties whose schema is marked `deprecated` go as well. Components that were only used
+    ///by what was dropped are left out.
+    pub fn without_deprecated(&self, schemas: bool) -> Result<DereferencedOpenApi, OpenApiError> {
+        let mut document = self.filtered(|_, object| !is_deprecated(object))?;
+        if schemas {
+            for_each_spec_object(&mut document, "", &mut |object, _| {
+                self.remove_deprecated_properties(object);
+            });
+            //Components only the dropped properties used can go as well
+            self.prune_components(&mut document);
+        }
+        self.dereference_derived(document)
+    }
+
+    ///Drop the properties of a schema that are deprecated, looking through refs.
+    fn remove_deprecated_properties(&self, schema: &mut Map<String, Value>) {
+        let Some(Value::Object(properties)) = schema.get_mut("properties") else {
+            return;
+        };
+        let mut removed = vec![];
+        properties.retain(|name, property| {
+            let property = match property.get("$ref").and_then(Value::as_str) {
+                Some(reference) => self
+                    .locate_reference(reference)
+                    .ok()
+                    .and_then(|location| self.json.pointer(&location))
+                    .unwrap_or(property),
+                None => property,
+            };
+            let deprecated = property.as_object().is_some_and(is_deprecated);
+            if deprecated {
+                removed.push(name.clone());
+            }
+            !deprecated
+        });
+        if let Some(Value::Array(required)) = schema.get_mut("required") {
+            required.retain(|name| !removed.iter().any(|removed| name == removed));
+        }
+    }
+
+    ///Dereference a document made from this spec the same way this spec was, with the same
+    ///options, example fetcher and cancellation. The ref cache isn't shared, what it holds is
+    ///from this spec's document.
+    fn dereference_derived(&self, json: Value) -> Result<DereferencedOpenApi, OpenApiError> {
+        let mut derived = OpenApiDereferencer::from_value(json)?;
+        derived.options = self.options.clone();
+        derived.example_fetcher = self.example_fetcher.clone();
+        derived.should_cancel = self.should_cancel.clone();
+        derived.dereference()
+    }
+
+    ///The input document with only the operations `keep` accepts and the components they still
+    ///need.
+    pub(crate) fn filtered(
+        &self,
+        keep: impl FnMut(Option<Method>, &Map<String, Value>) -> bool,
+    ) -> Result<Value, OpenApiError> {
+        if self.json.is_null() {
+            return Err(OpenApiError::InputReleased);
+        }
+        let mut document = self.json.clone();
+        self.inline_path_item_refs(&mut document);
+        retain_operations(&mut document, keep);
+        self.prune_components(&mut document);
+        Ok(document)
+    }
+
+    ///Swap path items that are refs for what they point at, so their operations can be
+    ///filtered without touching `components/pathItems`.
+    fn inline_path_item_refs(&self, document: &mut Value) {
+        for section in ["paths", "webhooks"] {
+            let Some(Value::Object(path_items)) = document.get_mut(section) else {
+                continue;
+            };
+            for path_item in path_items.values_mut() {
+                let Some(Value::String(reference)) = path_item.get("$ref") else {
+                    continue;
+                };
+                let target = self
+                    .locate_reference(reference)
+                    .ok()
+                    .and_then(|location| self.json.pointer(&location));
+                if let Some(target) = target {
+                    *path_item = target.clone();
+                }
+            }
+        }
+    }
+
+    ///Drop the components nothing outside `components` leads to, following refs between
+    ///components as they are in `document`. Security schemes are kept while a security requirement names them.
+    fn prune_components(&self, document: &mut Value) {
+        let mut pending = vec![];
+        let mut security = HashSet::new();
+        if let Value::Object(object) = &*document {
+            for (key, value) in object.iter().filter(|(key, _)| *key != "components") {
+                for_each_ref(value, "", &mut |_, reference| {
+                    pending.push(reference.into())
+                });
+                if key == "security" {
+                    collect_security_names(value, &mut security);
+                } else {
+                    collect_security_names_in(value, &mut security);
+                }
+            }
+        }
+        let mut used: HashSet<(String, String)> = HashSet::new();
+        while let Some(reference) = pending.pop() {
+            let Ok(location) = self.locate_reference(&reference) else {
+                continue;
+            };
+            let Some(component) = component_at(&location) else {
+                continue;
+            };
+            if !used.insert(component.clone()) {
+                continue;
+            }
+            let component_location = format!(
+                "/components/{}/{}",
+                component.0,
+                pointer::escape(&component.1)
+            );
+            if let Some(value) = document.pointer(&component_location) {
+                for_each_ref(value, "", &mut |_, reference| {
+                    pending.push(reference.into())
+                });
+                collect_security_names_in(value, &mut security);
+            }
+        }
+        let Some(Value::Object(components)) = document.get_mut("components") else {
+            return;
+        };
+        for (section, named) in components.iter_mut() {
+            let Value::Object(named) = named else {
+                continue;
+            };
+            named.retain(|name, _| {
+                name.starts_with("x-")
+                    || used.contains(&(section.clone(), name.clone()))
+                    || (section == "securitySchemes" && security.contains(name))
+            });
+        }
+    }
+}
+
+///The operations under `paths` and `webhooks` of a document.
+fn operations(document: &Value) -> impl Iterator<Item = (Method, &Map<String, Value>)> {
+    ["paths", "webhooks"]
+        .into_iter()
+        .filter_map(|section| document.get(section).and_then(Value::as_object))
+        .flat_map(|path_items| path_items.values())
+        .flat_map(|path_item| {
+            Method::ALL.into_iter().filter_map(move |method| {
+                let operation = path_item.get(method.as_str())?.as_object()?;
+                Some((method, operation))
+            })
+        })
+}
+
+fn is_deprecated(object: &Map<String, Value>) -> bool {
+    object.get("deprecated") == Some(&Value::Bool(true))
+}
+
+fn has_tag(operation: &Map<String, Value>, tags: &HashSet<String>) -> bool {
+    let Some(Value::Array(operation_tags)) = operation.get("tags") else {
+        return false;
+    };
+    operation_tags.iter().any(|tag| has_name(tag, tags))
+}
+
+fn has_name(name: &Value, names: &HashSet<String>) -> bool {
+    name.as_str().is_some_and(|name| names.contains(name))
+}
+
+///The component section and name a JSON pointer is in, e.g. `/components/schemas/Pet/type` is
+///in `("schemas", "Pet")`.
+fn component_at(location: &str) -> Option<(String, String)> {
+    let mut segments = location.strip_prefix("/components/")?.split('/');
+    let section = segments.next()?;
+    let name = segments.next()?;
+    Some((section.into(), pointer::unescape(name)))
+}
+
+///Names of the security schemes in a list of security requirements.
+fn collect_security_names(requirements: &Value, names: &mut HashSet<String>) {
+    for requirement in requirements.as_array().into_iter().flatten() {
+        names.extend(
+            requirement
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(name, _)| name.clone()),
+        );
+    }
+}
+
+///Names of the security schemes in every `security` nested in `value`, e.g. on operations.
+fn collect_security_names_in(value: &Value, names: &mut HashSet<String>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                if key == "security" {
+                    collect_security_names(value, names);
+                } else {
+                    collect_security_names_in(value, names);
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                collect_security_names_in(value, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_filter_by_tags() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Filter", "version": "1.0.0"},
+            "tags": [{"name": "public"}, {"name": "billing"}, {"name": "admin"}],
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "tags": ["public"],
+                        "security": [{"key": []}],
+                        "responses": {
+                            "200": {
+                                "description": "Pets",
+                                "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pets"}}}
+                            }
+                        }
+                    },
+                    "delete": {
+                        "tags": ["admin"],
+                        "security": [{"oauth": []}],
+                        "responses": {"default": {"$ref": "#/components/responses/Error"}}
+                    }
+                },
+                "/invoices": {"$ref": "#/components/pathItems/Invoices"},
+                "/admin": {
+                    "get": {"tags": ["admin"], "responses": {"default": {"$ref": "#/components/responses/Error"}}}
+                }
+            },
+            "components": {
+                "pathItems": {
+                    "Invoices": {"get": {"tags": ["billing"], "responses": {"200": {"description": "Invoices"}}}}
+                },
+                "responses": {"Error": {"description": "Something went wrong"}},
+                "schemas": {
+                    "Pets": {"type": "array", "items": {"$ref": "#/components/schemas/Pet"}},
+                    "Pet": {"type": "object"},
+                    "Admin": {"type": "object"}
+                },
+                "securitySchemes": {
+                    "key": {"type": "apiKey", "name": "key", "in": "header"},
+                    "oauth": {"type": "http", "scheme": "bearer"}
+                }
+            }
+        }"##;
+        let dereferencer = OpenApiDereferencer::from_str(spec)?;
+        let filtered = dereferencer.filter_by_tags(["public", "billing"])?;
+        let operations: Vec<(&str, Method)> = filtered
+            .iter_operations()
+            .map(|(path, method, _)| (path, method))
+            .collect();
+        assert_eq!(
+            vec![("/pets", Method::Get), ("/invoices", Method::Get)],
+            operations
+        );
+        let components = filtered.openapi.components.as_ref().unwrap();
+        let mut schemas: Vec<&String> = components.schemas.keys().collect();
+        schemas.sort();
+        assert_eq!(vec!["Pet", "Pets"], schemas);
+        assert!(components.responses.is_empty());
+        assert!(components.path_items.is_empty());
+        assert_eq!(
+            vec!["key"],
+            components.security_schemes.keys().collect::<Vec<_>>()
+        );
+        let tags: Vec<&str> = filtered
+            .openapi
+            .tags
+            .iter()
+            .map(|tag| tag.name.as_str())
+            .collect();
+        assert_eq!(vec!["public", "billing"], tags);
+
+        let cancelled = OpenApiDereferencer::builder()
+            .cancel_when(|| true)
+            .build_from_str(spec)?
+            .filter_by_tags(["public"]);
+        assert!(matches!(cancelled, Err(OpenApiError::Cancelled)));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_without_deprecated() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Deprecated", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "Pets",
+                                "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+                            }
+                        }
+                    },
+                    "put": {
+                        "deprecated": true,
+                        "requestBody": {"$ref": "#/components/requestBodies/OldPet"},
+                        "responses": {"204": {"description": "Updated"}}
+                    }
+                },
+                "/old": {
+                    "get": {"deprecated": true, "responses": {"200": {"description": "Old"}}}
+                }
+            },
+            "components": {
+                "requestBodies": {
+                    "OldPet": {"content": {"application/json": {"schema": {"type": "object"}}}}
+                },
+                "schemas": {
+                    "Pet": {
+                        "type": "object",
+                        "required": ["name", "nickname"],
+                        "properties": {
+                            "name": {"type": "string"},
+                            "nickname": {"type": "string", "deprecated": true},
+                            "owner": {"$ref": "#/components/schemas/Owner"}
+                        }
+                    },
+                    "Owner": {"type": "object", "deprecated": true}
+                }
+            }
+        }"##;
+        let dereferencer = OpenApiDereferencer::from_str(spec)?;
+        let current = dereferencer.without_deprecated(false)?;
+        let operations: Vec<(&str, Method)> = current
+            .iter_operations()
+            .map(|(path, method, _)| (path, method))
+            .collect();
+        assert_eq!(vec![("/pets", Method::Get)], operations);
+        let components = current.openapi.components.as_ref().unwrap();
+        assert!(components.request_bodies.is_empty());
+        assert!(components.schemas.contains_key("Owner"));
+
+        let current = dereferencer.without_deprecated(true)?;
+        let pet =
+            serde_json::to_value(&current.openapi.components.as_ref().unwrap().schemas["Pet"])?;
+        let mut properties: Vec<&String> = pet["properties"].as_object().unwrap().keys().collect();
+        properties.sort();
+        assert_eq!(vec!["name"], properties);
+        assert_eq!(serde_json::json!(["name"]), pet["required"]);
+        assert!(!current
+            .openapi
+            .components
+            .as_ref()
+            .unwrap()
+            .schemas
+            .contains_key("Owner"));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_filter_by_operation_ids() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Filter", "version": "1.0.0"},
+            "tags": [{"name": "pets"}, {"name": "owners"}],
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "operationId": "listPets",
+                        "tags": ["pets"],
+                        "parameters": [{"$ref": "#/components/parameters/Limit"}],
+                        "responses": {"200": {"description": "Pets"}}
+                    },
+                    "post": {
+                        "operationId": "createPet",
+                        "tags": ["pets"],
+                        "requestBody": {"$ref": "#/components/requestBodies/Pet"},
+                        "responses": {"201": {"description": "Created"}}
+                    }
+                },
+                "/owners": {
+                    "get": {"operationId": "listOwners", "tags": ["owners"], "responses": {"200": {"description": "Owners"}}}
+                }
+            },
+            "components": {
+                "parameters": {"Limit": {"name": "limit", "in": "query", "schema": {"type": "integer"}}},
+                "requestBodies": {
+                    "Pet": {"content": {"application/json": {"schema": {"type": "object"}}}}
+                }
+            }
+        }"##;
+        let filtered =
+            OpenApiDereferencer::from_str(spec)?.filter_by_operation_ids(["listPets"])?;
+        let operations: Vec<(&str, Method)> = filtered
+            .iter_operations()
+            .map(|(path, method, _)| (path, method))
+            .collect();
+        assert_eq!(vec![("/pets", Method::Get)], operations);
+        let components = filtered.openapi.components.as_ref().unwrap();
+        assert!(components.parameters.contains_key("Limit"));
+        assert!(components.request_bodies.is_empty());
+        let tags: Vec<&str> = filtered
+            .openapi
+            .tags
+            .iter()
+            .map(|tag| tag.name.as_str())
+            .collect();
+        assert_eq!(vec!["pets"], tags);
+        Ok(())
+    }
+}
@@ -0,0 +1,388 @@
+use openapiv3::schemars::schema::{
+    InstanceType, ObjectValidation, Schema as SchemarsSchema, SchemaObject as SchemarsSchemaObject,
+    SingleOrVec,
+};
+
+use crate::visit_mut::{for_each_schema_mut, schema_mut};
+use crate::DereferencedOpenApi;
+
+impl DereferencedOpenApi {
+    ///Merge every `allOf` in the spec into the schema holding it, combining properties, required
+    ///lists and constraints. Branches that can't be merged without changing the meaning of the
+    ///schema (e.g. two different patterns, or a recursive `$ref` that was left in place) stay
+    ///under `allOf`.
+    pub fn flatten_all_of(mut self) -> Self {
+        for_each_schema_mut(&mut self.0.openapi, &mut |schema| {
+            if let SchemarsSchema::Object(object) = schema {
+                flatten_object(object);
+            }
+        });
+        self
+    }
+}
+
+///Merge the `allOf` branches of a single schema, and any nested in it, into their parents.
+pub fn flatten_schema_all_of(schema: &mut SchemarsSchema) {
+    schema_mut(schema, &mut |schema| {
+        if let SchemarsSchema::Object(object) = schema {
+            flatten_object(object);
+        }
+    });
+}
+
+fn flatten_object(object: &mut SchemarsSchemaObject) {
+    let Some(branches) = object
+        .subschemas
+        .as_mut()
+        .and_then(|subschemas| subschemas.all_of.take())
+    else {
+        return;
+    };
+    let mut remaining = vec![];
+    for branch in branches {
+        match branch {
+            SchemarsSchema::Bool(true) => {}
+            SchemarsSchema::Object(branch) if can_merge(object, &branch) => merge(object, branch),
+            branch => remaining.push(branch),
+        }
+    }
+    if let Some(subschemas) = object.subschemas.as_mut() {
+        if !remaining.is_empty() {
+            subschemas.all_of = Some(remaining);
+        }
+    }
+    if object
+        .subschemas
+        .as_ref()
+        .is_some_and(|subschemas| **subschemas == Default::default())
+    {
+        object.subschemas = None;
+    }
+}
+
+fn differ<T: PartialEq>(a: &Option<T>, b: &Option<T>) -> bool {
+    matches!((a, b), (Some(a), Some(b)) if a != b)
+}
+
+fn can_merge(object: &SchemarsSchemaObject, branch: &SchemarsSchemaObject) -> bool {
+    if branch.reference.is_some() || object.reference.is_some() {
+        return false;
+    }
+    if differ(&object.format, &branch.format) || differ(&object.const_value, &branch.const_value) {
+        return false;
+    }
+    if let (Some(a), Some(b)) = (&object.instance_type, &branch.instance_type) {
+        if intersect_types(a, b).is_none() {
+            return false;
+        }
+    }
+    if let (Some(a), Some(b)) = (&object.number, &branch.number) {
+        if differ(&a.multiple_of, &b.multiple_of) {
+            return false;
+        }
+    }
+    if let (Some(a), Some(b)) = (&object.string, &branch.string) {
+        if differ(&a.pattern, &b.pattern) {
+            return false;
+        }
+    }
+    if let (Some(a), Some(b)) = (&object.array, &branch.array) {
+        if differ(&a.items, &b.items)
+            || differ(&a.additional_items, &b.additional_items)
+            || differ(&a.contains, &b.contains)
+        {
+            return false;
+        }
+    }
+    if let (Some(a), Some(b)) = (&object.object, &branch.object) {
+        if differ(&a.additional_properties, &b.additional_properties)
+            || differ(&a.property_names, &b.property_names)
+            || !covers(a, b)
+            || !covers(b, a)
+        {
+            return false;
+        }
+    }
+    if branch
+        .extensions
+        .iter()
+        .any(|(keyword, value)| object.extensions.get(keyword).is_some_and(|v| v != value))
+    {
+        return false;
+    }
+    if let (Some(a), Some(b)) = (&object.subschemas, &branch.subschemas) {
+        //Two sets of anyOf/oneOf/not/if can't be combined without nesting them again.
+        let occupied = |s: &openapiv3::schemars::schema::SubschemaValidation| {
+            s.any_of.is_some() || s.one_of.is_some() || s.not.is_some() || s.if_schema.is_some()
+        };
+        if occupied(a) && occupied(b) {
+            return false;
+        }
+    }
+    true
+}
+
+///Whether `a`'s `additionalProperties`, if it has one, still only applies to the same
+///properties once `b`'s are merged in. Otherwise the merged schema would let through properties
+///`a` turned away.
+fn covers(a: &ObjectValidation, b: &ObjectValidation) -> bool {
+    a.additional_properties.is_none()
+        || (b
+            .properties
+            .keys()
+            .all(|name| a.properties.contains_key(name))
+            && b.pattern_properties
+                .keys()
+                .all(|pattern| a.pattern_properties.contains_key(pattern)))
+}
+
+fn intersect_types(
+    a: &SingleOrVec<InstanceType>,
+    b: &SingleOrVec<InstanceType>,
+) -> Option<SingleOrVec<InstanceType>> {
+    let to_vec = |types: &SingleOrVec<InstanceType>| match types {
+        SingleOrVec::Single(t) => vec![**t],
+        SingleOrVec::Vec(types) => types.clone(),
+    };
+    let b = to_vec(b);
+    let mut both: Vec<InstanceType> = to_vec(a).into_iter().filter(|t| b.contains(t)).collect();
+    match both.len() {
+        0 => None,
+        1 => Some(SingleOrVec::Single(Box::new(both.remove(0)))),
+        _ => Some(SingleOrVec::Vec(both)),
+    }
+}
+
+fn min<T: PartialOrd>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b < a { b } else { a }),
+        (a, b) => a.or(b),
+    }
+}
+
+fn max<T: PartialOrd>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b > a { b } else { a }),
+        (a, b) => a.or(b),
+    }
+}
+
+///Merge a branch into its parent. Only call this when `can_merge` is true.
+fn merge(object: &mut SchemarsSchemaObject, branch: SchemarsSchemaObject) {
+    if let Some(branch_metadata) = branch.metadata {
+        let metadata = object.metadata.get_or_insert_with(Default::default);
+        metadata.id = metadata.id.take().or(branch_metadata.id);
+        metadata.title = metadata.title.take().or(branch_metadata.title);
+        metadata.description = metadata.description.take().or(branch_metadata.description);
+        metadata.default = metadata.default.take().or(branch_metadata.default);
+        metadata.deprecated |= branch_metadata.deprecated;
+        metadata.read_only |= branch_metadata.read_only;
+        metadata.write_only |= branch_metadata.write_only;
+        metadata.examples.extend(branch_metadata.examples);
+    }
+    object.instance_type = match (object.instance_type.take(), branch.instance_type) {
+        (Some(a), Some(b)) => intersect_types(&a, &b),
+        (a, b) => a.or(b),
+    };
+    object.format = object.format.take().or(branch.format);
+    object.const_value = object.const_value.take().or(branch.const_value);
+    object.enum_values = match (object.enum_values.take(), branch.enum_values) {
+        (Some(a), Some(b)) => Some(a.into_iter().filter(|v| b.contains(v)).collect()),
+        (a, b) => a.or(b),
+    };
+    if let Some(branch_number) = branch.number {
+        let number = object.number.get_or_insert_with(Default::default);
+        number.multiple_of = number.multiple_of.or(branch_number.multiple_of);
+        number.maximum = min(number.maximum, branch_number.maximum);
+        number.exclusive_maximum = min(number.exclusive_maximum, branch_number.exclusive_maximum);
+        number.minimum = max(number.minimum, branch_number.minimum);
+        number.exclusive_minimum = max(number.exclusive_minimum, branch_number.exclusive_minimum);
+    }
+    if let Some(branch_string) = branch.string {
+        let string = object.string.get_or_insert_with(Default::default);
+        string.max_length = min(string.max_length, branch_string.max_length);
+        string.min_length = max(string.min_length, branch_string.min_length);
+        string.pattern = string.pattern.take().or(branch_string.pattern);
+    }
+    if let Some(branch_array) = branch.array {
+        let array = object.array.get_or_insert_with(Default::default);
+        array.items = array.items.take().or(branch_array.items);
+        array.additional_items = array
+            .additional_items
+            .take()
+            .or(branch_array.additional_items);
+        array.max_items = min(array.max_items, branch_array.max_items);
+        array.min_items = max(array.min_items, branch_array.min_items);
+        array.unique_items = match (array.unique_items, branch_array.unique_items) {
+            (Some(a), Some(b)) => Some(a || b),
+            (a, b) => a.or(b),
+        };
+        array.contains = array.contains.take().or(branch_array.contains);
+    }
+    if let Some(branch_object) = branch.object {
+        let object_validation = object.object.get_or_insert_with(Default::default);
+        object_validation.max_properties = min(
+            object_validation.max_properties,
+            branch_object.max_properties,
+        );
+        object_validation.min_properties = max(
+            object_validation.min_properties,
+            branch_object.min_properties,
+        );
+        object_validation.required.extend(branch_object.required);
+        for (name, schema) in branch_object.properties {
+            merge_property(&mut object_validation.properties, name, schema);
+        }
+        for (pattern, schema) in branch_object.pattern_properties {
+            merge_property(&mut object_validation.pattern_properties, pattern, schema);
+        }
+        object_validation.additional_properties = object_validation
+            .additional_properties
+            .take()
+            .or(branch_object.additional_properties);
+        object_validation.property_names = object_validation
+            .property_names
+            .take()
+            .or(branch_object.property_names);
+    }
+    if let Some(branch_subschemas) = branch.subschemas {
+        let subschemas = object.subschemas.get_or_insert_with(Default::default);
+        if let Some(all_of) = branch_subschemas.all_of {
+            subschemas
+                .all_of
+                .get_or_insert_with(Default::default)
+                .extend(all_of);
+        }
+        subschemas.any_of = subschemas.any_of.take().or(branch_subschemas.any_of);
+        subschemas.one_of = subschemas.one_of.take().or(branch_subschemas.one_of);
+        subschemas.not = subschemas.not.take().or(branch_subschemas.not);
+        subschemas.if_schema = subschemas.if_schema.take().or(branch_subschemas.if_schema);
+        subschemas.then_schema = subschemas
+            .then_schema
+            .take()
+            .or(branch_subschemas.then_schema);
+        subschemas.else_schema = subschemas
+            .else_schema
+            .take()
+            .or(branch_subschemas.else_schema);
+    }
+    for (keyword, value) in branch.extensions {
+        object.extensions.entry(keyword).or_insert(value);
+    }
+}
+
+///Two branches declaring the same property are both constraints on it, so the property becomes
+///the flattened `allOf` of the two.
+fn merge_property(
+    properties: &mut openapiv3::schemars::Map<String, SchemarsSchema>,
+    name: String,
+    schema: SchemarsSchema,
+) {
+    match properties.remove(&name) {
+        Some(existing) if existing != schema => {
+            let mut combined = SchemarsSchema::Object(SchemarsSchemaObject {
+                subschemas: Some(Box::new(openapiv3::schemars::schema::SubschemaValidation {
+                    all_of: Some(vec![existing, schema]),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            });
+            flatten_schema_all_of(&mut combined);
+            properties.insert(name, combined);
+        }
+        _ => {
+            properties.insert(name, schema);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_flatten_schema_all_of() -> Result<()> {
+        let mut schema: SchemarsSchema = serde_json::from_value(json!({
+            "allOf": [
+                {
+                    "type": "object",
+                    "required": ["id"],
+                    "properties": {"id": {"type": "integer", "minimum": 0}}
+                },
+                {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": {
+                        "id": {"type": "integer", "minimum": 1},
+                        "name": {"type": "string"}
+                    }
+                },
+                {"pattern": "^a"},
+                {"pattern": "^b"}
+            ]
+        }))?;
+        flatten_schema_all_of(&mut schema);
+        let flattened = serde_json::to_value(&schema)?;
+        assert_eq!(json!(["id", "name"]), flattened["required"]);
+        assert_eq!(json!(1.0), flattened["properties"]["id"]["minimum"]);
+        assert_eq!("string", flattened["properties"]["name"]["type"]);
+        assert_eq!("^a", flattened["pattern"]);
+        assert_eq!(json!([{"pattern": "^b"}]), flattened["allOf"]);
+
+        //Members that disagree stay under allOf rather than one side winning
+        let mut schema: SchemarsSchema = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {"id": {"type": "integer"}},
+            "additionalProperties": false,
+            "allOf": [
+                {"properties": {"name": {"type": "string"}}},
+                {"properties": {"id": {"multipleOf": 2}}},
+                {"properties": {"id": {"multipleOf": 3}}}
+            ]
+        }))?;
+        flatten_schema_all_of(&mut schema);
+        let flattened = serde_json::to_value(&schema)?;
+        assert!(flattened["properties"].get("name").is_none());
+        assert_eq!(
+            json!([{"properties": {"name": {"type": "string"}}}]),
+            flattened["allOf"]
+        );
+        assert_eq!(
+            json!([{"multipleOf": 3.0}]),
+            flattened["properties"]["id"]["allOf"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_flatten_all_of() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Flatten", "version": "1.0.0"},
+            "components": {
+                "schemas": {
+                    "Named": {"type": "object", "properties": {"name": {"type": "string"}}},
+                    "Pet": {
+                        "allOf": [
+                            {"$ref": "#/components/schemas/Named"},
+                            {"type": "object", "properties": {"tag": {"type": "string"}}}
+                        ]
+                    }
+                }
+            }
+        }"##;
+        let flattened = OpenApiDereferencer::from_str(spec)?
+            .dereference()?
+            .flatten_all_of();
+        let components = flattened.openapi.components.as_ref().unwrap();
+        let pet = serde_json::to_value(&components.schemas["Pet"])?;
+        assert!(pet.get("allOf").is_none());
+        assert_eq!("string", pet["properties"]["name"]["type"]);
+        assert_eq!("string", pet["properties"]["tag"]["type"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,403 @@
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet};
+
+use indexmap::IndexMap;
+use json_patch::{Patch, PatchOperation};
+use openapiv3::v3_1::{Components, PathItem, ReferenceOr};
+use serde_json::Value;
+
+use crate::cache::overlaps;
+use crate::lenient::refs_in;
+use crate::{
+    pointer, ref_to_json_pointer, DereferencedOpenApi, ExternalRefPolicy, OpenApiDereferencer,
+    OpenApiError, Warning,
+};
+
+impl DereferencedOpenApi {
+    ///Apply a JSON Patch to the input document and redo only the parts of the dereferenced spec
+    ///it affects: the path items, webhooks and components that changed, or that lead to
+    ///something that changed through their refs. Everything else is kept as it was, which is a
+    ///lot cheaper than dereferencing from scratch for editors and file watchers. Cached values
+    ///the patch touches are invalidated, and the state, warnings and failed refs are brought up
+    ///to date.
+    pub fn apply_patch(self, patch: &Patch) -> Result<Self, OpenApiError> {
+        self.0.patched(patch).map(DereferencedOpenApi)
+    }
+}
+
+impl OpenApiDereferencer {
+    fn patched(mut self, patch: &Patch) -> Result<Self, OpenApiError> {
+        if !self.has_input() {
+            return Err(OpenApiError::InputReleased);
+        }
+        let mut json = self.json.clone();
+        json_patch::patch(&mut json, patch).map_err(|e| OpenApiError::ParsingError {
+            msg: format!("Error applying the patch {e}"),
+            location: None,
+        })?;
+        let mut changed = changed_pointers(patch);
+        let mut fresh = OpenApiDereferencer::from_value(json)?;
+        self.json = std::mem::take(&mut fresh.json);
+        self.anchors = OnceCell::new();
+        self.routes = OnceCell::new();
+        #[cfg(feature = "validation")]
+        self.validators.borrow_mut().clear();
+        //Components going over or under the inline budget change everything referring to them
+        let kept_refs = self.over_inline_budget()?;
+        changed.extend(
+            kept_refs
+                .symmetric_difference(&self.kept_refs)
+                .filter_map(|reference| ref_to_json_pointer(reference).ok()),
+        );
+        self.kept_refs = kept_refs;
+        if !changed
+            .iter()
+            .all(|pointer| self.serde_values.invalidate(pointer))
+        {
+            self.serde_values = Box::default();
+        }
+        let affected = self.affected_units(&changed);
+        self.forget_redone(&affected);
+        let old = std::mem::replace(&mut self.openapi, fresh.openapi);
+
+        let paths = self.openapi.paths.take();
+        self.openapi.paths = match (paths, old.paths) {
+            (Some(mut paths), Some(old_paths)) => {
+                let path_items = std::mem::take(&mut paths.paths);
+                paths.paths =
+                    self.redo_path_items(path_items, old_paths.paths, "/paths", &affected)?;
+                Some(paths)
+            }
+            (paths, _) => self.dereference_paths(paths)?,
+        };
+        if self.options.scope.webhooks {
+            let webhooks = std::mem::take(&mut self.openapi.webhooks);
+            self.openapi.webhooks =
+                self.redo_path_items(webhooks, old.webhooks, "/webhooks", &affected)?;
+        }
+        if self.options.scope.components {
+            let components = self.openapi.components.take();
+            self.openapi.components = match (components, old.components) {
+                (Some(components), Some(old)) => {
+                    Some(self.redo_components(components, old, &affected)?)
+                }
+                (components, _) => self.dereference_components(components)?,
+            };
+        }
+        if self.options.external_refs == ExternalRefPolicy::Keep {
+            let kept = refs_in(&self.json).into_iter().any(|reference| {
+                !reference.starts_with('#') && self.locate_reference(reference).is_err()
+            });
+            self.kept_external_refs.set(kept);
+        }
+        self.state = self.dereferenced_state();
+        let mut warnings = self.deref_warnings.take();
+        self.warnings.append(&mut warnings);
+        self.index_operation_ids();
+        self.validate_discriminators();
+        self.collect_document_warnings();
+        Ok(self)
+    }
+
+    ///Drop what's known about the spec that redoing the `affected` units finds again: their
+    ///warnings and the warnings worked out from the whole document. `dereference_lenient` hands
+    ///its failed refs out, so the ones still failing are found again from the document.
+    fn forget_redone(&mut self, affected: &HashSet<String>) {
+        let mut failed_refs = IndexMap::new();
+        if self.options.lenient {
+            for reference in refs_in(&self.json) {
+                let Err(e) = self.locate_reference(reference) else {
+                    continue;
+                };
+                let kept = self.options.external_refs == ExternalRefPolicy::Keep
+                    && !reference.starts_with('#');
+                if !kept {
+                    failed_refs.entry(reference.to_string()).or_insert(e);
+                }
+            }
+        }
+        *self.failed_refs.borrow_mut() = failed_refs;
+        let in_affected = |location: &str| affected.iter().any(|unit| overlaps(unit, location));
+        let warnings = std::mem::take(&mut self.warnings);
+        self.warnings = warnings
+            .into_iter()
+            .filter(|warning| match warning {
+                Warning::MergeConflict { .. } | Warning::ComponentRenamed { .. } => true,
+                Warning::SiblingsDropped { location, .. } => !in_affected(location),
+                _ => false,
+            })
+            .collect();
+    }
+
+    fn redo_path_items(
+        &self,
+        path_items: IndexMap<String, ReferenceOr<PathItem>>,
+        mut old: IndexMap<String, ReferenceOr<PathItem>>,
+        section: &str,
+        affected: &HashSet<String>,
+    ) -> Result<IndexMap<String, ReferenceOr<PathItem>>, OpenApiError> {
+        path_items
+            .into_iter()
+            .map(|(name, path_item)| match old.remove(&name) {
+                Some(previous) if !affected.contains(&unit(section, &name)) => Ok((name, previous)),
+                //Paths out of scope are left as they are
+                _ if section == "/paths" && !self.options.scope.includes_path(&name) => {
+                    Ok((name, path_item))
+                }
+                _ => Ok((
+                    name.clone(),
+                    self.dereference_path_item_reference(path_item)?,
+                )),
+            })
+            .collect()
+    }
+
+    ///Dereference the affected components on their own, then put them together with the
+    ///unaffected ones from before.
+    fn redo_components(
+        &self,
+        components: Components,
+        old: Components,
+        affected: &HashSet<String>,
+    ) -> Result<Components, OpenApiError> {
+        let is_affected = |kind: &str| {
+            let section = format!("/components/{kind}");
+            move |name: &str| affected.contains(&unit(&section, name))
+        };
+        let (schemas, affected_schemas) = split(components.schemas, is_affected("schemas"));
+        let (responses, affected_responses) = split(components.responses, is_affected("responses"));
+        let (parameters, affected_parameters) =
+            split(components.parameters, is_affected("parameters"));
+        let (examples, affected_examples) = split(components.examples, is_affected("examples"));
+        let (request_bodies, affected_request_bodies) =
+            split(components.request_bodies, is_affected("requestBodies"));
+        let (headers, affected_headers) = split(components.headers, is_affected("headers"));
+        let (security_schemes, affected_security_schemes) =
+            split(components.security_schemes, is_affected("securitySchemes"));
+        let (links, affected_links) = split(components.links, is_affected("links"));
+        let (callbacks, affected_callbacks) = split(components.callbacks, is_affected("callbacks"));
+        let (path_items, affected_path_items) =
+            split(components.path_items, is_affected("pathItems"));
+        let redone = self
+            .dereference_components(Some(Components {
+                schemas: affected_schemas,
+                responses: affected_responses,
+                parameters: affected_parameters,
+                examples: affected_examples,
+                request_bodies: affected_request_bodies,
+                headers: affected_headers,
+                security_schemes: affected_security_schemes,
+                links: affected_links,
+                callbacks: affected_callbacks,
+                path_items: affected_path_items,
+                extensions: components.extensions.clone(),
+            }))?
+            .unwrap_or_default();
+        Ok(Components {
+            schemas: splice(schemas, old.schemas, redone.schemas),
+            responses: splice(responses, old.responses, redone.responses),
+            parameters: splice(parameters, old.parameters, redone.parameters),
+            examples: splice(examples, old.examples, redone.examples),
+            request_bodies: splice(request_bodies, old.request_bodies, redone.request_bodies),
+            headers: splice(headers, old.headers, redone.headers),
+            security_schemes: splice(
+                security_schemes,
+                old.security_schemes,
+                redone.security_schemes,
+            ),
+            links: splice(links, old.links, redone.links),
+            callbacks: splice(callbacks, old.callbacks, redone.callbacks),
+            path_items: splice(path_items, old.path_items, redone.path_items),
+            extensions: components.extensions,
+        })
+    }
+
+    ///The JSON pointers of the path items, webhooks and components that need dereferencing
+    ///again after `changed` changed: the ones with a change inside them, plus any with a ref
+    ///leading to a change or to another affected one.
+    fn affected_units(&self, changed: &[String]) -> HashSet<String> {
+        //unit -> JSON pointers its refs point at
+        let mut targets: HashMap<String, Vec<String>> = HashMap::new();
+        for section in ["/paths", "/webhooks"] {
+            self.add_units(section, &mut targets);
+        }
+        let kinds = self.json.pointer("/components").and_then(Value::as_object);
+        for kind in kinds.into_iter().flat_map(|kinds| kinds.keys()) {
+            self.add_units(&pointer::push("/components", kind), &mut targets);
+        }
+        let mut affected: HashSet<String> = targets
+            .keys()
+            .filter(|unit| changed.iter().any(|pointer| overlaps(unit, pointer)))
+            .cloned()
+            .collect();
+        loop {
+            let newly_affected: Vec<String> = targets
+                .iter()
+                .filter(|(unit, _)| !affected.contains(*unit))
+                .filter(|(_, unit_targets)| {
+                    unit_targets.iter().any(|target| {
+                        changed
+                            .iter()
+                            .chain(&affected)
+                            .any(|pointer| overlaps(target, pointer))
+                    })
+                })
+                .map(|(unit, _)| unit.clone())
+                .collect();
+            if newly_affected.is_empty() {
+                return affected;
+            }
+            affected.extend(newly_affected);
+        }
+    }
+
+    fn add_units(&self, section: &str, targets: &mut HashMap<String, Vec<String>>) {
+        let Some(Value::Object(units)) = self.json.pointer(section) else {
+            return;
+        };
+        for (name, value) in units {
+            let unit_targets = refs_in(value)
+                .into_iter()
+                .filter_map(|reference| self.locate_reference(reference).ok())
+                .collect();
+            targets.insert(unit(section, name), unit_targets);
+        }
+    }
+}
+
+fn unit(section: &str, name: &str) -> String {
+    pointer::push(section, name)
+}
+
+///Every pointer a patch changes, or reads from for `move` and `copy`.
+fn changed_pointers(patch: &Patch) -> Vec<String> {
+    patch
+        .0
+        .iter()
+        .flat_map(|operation| match operation {
+            PatchOperation::Add(add) => vec![add.path.clone()],
+            PatchOperation::Remove(remove) => vec![remove.path.clone()],
+            PatchOperation::Replace(replace) => vec![replace.path.clone()],
+            PatchOperation::Move(move_) => vec![move_.from.clone(), move_.path.clone()],
+            PatchOperation::Copy(copy) => vec![copy.path.clone()],
+            PatchOperation::Test(_) => vec![],
+        })
+        .collect()
+}
+
+///Split a component section into the names in it, in order, and the affected entries.
+fn split<T>(
+    section: IndexMap<String, T>,
+    is_affected: impl Fn(&str) -> bool,
+) -> (Vec<String>, IndexMap<String, T>) {
+    let names = section.keys().cloned().collect();
+    let affected = section
+        .into_iter()
+        .filter(|(name, _)| is_affected(name))
+        .collect();
+    (names, affected)
+}
+
+///Put a component section back together, taking the redone entries where there are any and
+///the ones from before for the rest.
+fn splice<T>(
+    names: Vec<String>,
+    mut old: IndexMap<String, T>,
+    mut redone: IndexMap<String, T>,
+) -> IndexMap<String, T> {
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let value = redone.remove(&name).or_else(|| old.remove(&name))?;
+            Some((name, value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{as_item, State};
+    use anyhow::Result;
+    use openapiv3::v3_1::StatusCode;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_apply_patch() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Incremental", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {"responses": {"200": {"$ref": "#/components/responses/Pets"}}}
+                },
+                "/owners": {
+                    "get": {"responses": {"200": {"description": "Owners"}}}
+                }
+            },
+            "components": {
+                "responses": {"Pets": {"description": "Pets"}}
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let changed: Vec<String> = vec!["/components/responses/Pets/description".into()];
+        let affected = dereferenced.affected_units(&changed);
+        assert_eq!(
+            HashSet::from([
+                "/components/responses/Pets".to_string(),
+                "/paths/~1pets".to_string()
+            ]),
+            affected
+        );
+
+        let patch: Patch = serde_json::from_value(json!([
+            {"op": "replace", "path": "/components/responses/Pets/description", "value": "Some pets"}
+        ]))?;
+        let patched = dereferenced.apply_patch(&patch)?;
+        let paths = &patched.openapi.paths.as_ref().unwrap().paths;
+        let get = as_item(&paths["/pets"]).unwrap().get.as_ref().unwrap();
+        let response = &get.responses.as_ref().unwrap().responses[&StatusCode::Code(200)];
+        assert!(matches!(
+            response,
+            ReferenceOr::DereferencedReference { item, .. } if item.description == "Some pets"
+        ));
+        assert_eq!(
+            "Some pets",
+            patched.json["components"]["responses"]["Pets"]["description"]
+        );
+        assert!(as_item(&paths["/owners"]).is_some());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_apply_patch_updates_state() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Incremental", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "operationId": "list",
+                        "responses": {"200": {"$ref": "#/components/responses/Pets"}}
+                    }
+                },
+                "/owners": {
+                    "get": {"operationId": "list", "responses": {"200": {"description": "Owners"}}}
+                }
+            }
+        }"##;
+        let (dereferenced, errors) = OpenApiDereferencer::from_str(spec)?.dereference_lenient()?;
+        assert_eq!(1, errors.len());
+        assert_eq!(State::PartiallyDereferenced, dereferenced.state());
+        assert_eq!(1, dereferenced.warnings().len());
+
+        let patch: Patch = serde_json::from_value(json!([
+            {"op": "add", "path": "/components", "value": {"responses": {"Pets": {"description": "Pets"}}}}
+        ]))?;
+        let patched = dereferenced.apply_patch(&patch)?;
+        assert_eq!(State::Dereferenced, patched.state());
+        assert_eq!(1, patched.warnings().len());
+        Ok(())
+    }
+}
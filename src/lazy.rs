@@ -0,0 +1,258 @@
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::fmt;
+
+use openapiv3::v3_1::{
+    Operation, Parameter, PathItem, ReferenceOr, RequestBody, Response, SchemaObject,
+};
+use serde::de::DeserializeOwned;
+
+use crate::components::component_not_found;
+use crate::{ComponentKind, Method, OpenApiDereferencer, OpenApiError};
+
+enum Source<'d, T: Clone> {
+    Item(Cow<'d, T>),
+    Reference(String),
+}
+
+impl<'d, T: Clone> Source<'d, T> {
+    fn from_reference_or(value: &'d ReferenceOr<T>) -> Self {
+        match value {
+            ReferenceOr::Item(item) | ReferenceOr::DereferencedReference { item, .. } => {
+                Source::Item(Cow::Borrowed(item))
+            }
+            ReferenceOr::Reference { reference, .. } => Source::Reference(reference.clone()),
+        }
+    }
+}
+
+///A handle to part of the spec that's only dereferenced the first time it's accessed. The
+///result is kept so later accesses are free.
+pub struct Resolved<'d, T: Clone> {
+    dereferencer: &'d OpenApiDereferencer,
+    source: Source<'d, T>,
+    resolve: fn(&OpenApiDereferencer, T) -> Result<T, OpenApiError>,
+    resolved: OnceCell<T>,
+}
+
+impl<'d, T: Clone + DeserializeOwned> Resolved<'d, T> {
+    fn new(
+        dereferencer: &'d OpenApiDereferencer,
+        source: Source<'d, T>,
+        resolve: fn(&OpenApiDereferencer, T) -> Result<T, OpenApiError>,
+    ) -> Self {
+        Self {
+            dereferencer,
+            source,
+            resolve,
+            resolved: OnceCell::new(),
+        }
+    }
+
+    ///The ref this handle points at, if it isn't an inline item.
+    pub fn reference(&self) -> Option<&str> {
+        match &self.source {
+            Source::Reference(reference) => Some(reference),
+            Source::Item(_) => None,
+        }
+    }
+
+    ///Whether the item has been dereferenced yet.
+    pub fn is_resolved(&self) -> bool {
+        self.resolved.get().is_some()
+    }
+
+    ///Get the dereferenced item, dereferencing it if this is the first access.
+    pub fn get(&self) -> Result<&T, OpenApiError> {
+        if let Some(resolved) = self.resolved.get() {
+            return Ok(resolved);
+        }
+        let item = match &self.source {
+            Source::Item(item) => item.clone().into_owned(),
+            Source::Reference(reference) => self.dereferencer.dereference_type(reference)?,
+        };
+        let item = (self.resolve)(self.dereferencer, item)?;
+        Ok(self.resolved.get_or_init(|| item))
+    }
+
+    ///Take the dereferenced item out of the handle.
+    pub fn into_inner(self) -> Result<T, OpenApiError> {
+        self.get()?;
+        Ok(self.resolved.into_inner().expect("resolved above"))
+    }
+}
+
+impl<T: Clone> fmt::Debug for Resolved<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reference = match &self.source {
+            Source::Reference(reference) => Some(reference.as_str()),
+            Source::Item(_) => None,
+        };
+        f.debug_struct("Resolved")
+            .field("reference", &reference)
+            .field("resolved", &self.resolved.get().is_some())
+            .finish()
+    }
+}
+
+impl OpenApiDereferencer {
+    ///Get a path item that's dereferenced on first access instead of up front. Handy when only a
+    ///few operations of a big spec are needed; no need to run `dereference` first.
+    pub fn lazy_path_item(&self, path: &str) -> Result<Resolved<'_, PathItem>, OpenApiError> {
+        let path_item = self
+            .openapi
+            .paths
+            .as_ref()
+            .and_then(|paths| paths.paths.get(path))
+            .ok_or_else(|| OpenApiError::PathNotFound { path: path.into() })?;
+        Ok(Resolved::new(
+            self,
+            Source::from_reference_or(path_item),
+            OpenApiDereferencer::dereference_path_item,
+        ))
+    }
+
+    ///Get an operation that's dereferenced on first access. If the path item is itself a ref it's
+    ///looked up now, but nothing inside the operation is resolved until it's accessed.
+    pub fn lazy_operation(
+        &self,
+        path: &str,
+        method: Method,
+    ) -> Result<Resolved<'_, Operation>, OpenApiError> {
+        let not_found = || OpenApiError::OperationNotFound {
+            path: path.into(),
+            method,
+        };
+        let path_item = self.lazy_path_item(path)?;
+        let operation = match path_item.source {
+            Source::Item(Cow::Borrowed(item)) => {
+                Cow::Borrowed(method.operation(item).ok_or_else(not_found)?)
+            }
+            Source::Item(Cow::Owned(item)) => {
+                Cow::Owned(method.operation(&item).ok_or_else(not_found)?.clone())
+            }
+            Source::Reference(reference) => {
+                let item: PathItem = self.dereference_type(&reference)?;
+                Cow::Owned(method.operation(&item).ok_or_else(not_found)?.clone())
+            }
+        };
+        Ok(Resolved::new(
+            self,
+            Source::Item(operation),
+            OpenApiDereferencer::dereference_operation,
+        ))
+    }
+
+    ///Get a schema from `components` that's dereferenced on first access.
+    pub fn lazy_schema(&self, name: &str) -> Result<Resolved<'_, SchemaObject>, OpenApiError> {
+        let schema = self
+            .openapi
+            .components
+            .as_ref()
+            .and_then(|components| components.schemas.get(name))
+            .ok_or_else(|| component_not_found(ComponentKind::Schemas, name))?;
+        Ok(Resolved::new(
+            self,
+            Source::Item(Cow::Borrowed(schema)),
+            OpenApiDereferencer::dereference_schemas,
+        ))
+    }
+
+    ///Get a response from `components` that's dereferenced on first access.
+    pub fn lazy_response(&self, name: &str) -> Result<Resolved<'_, Response>, OpenApiError> {
+        let response = self
+            .openapi
+            .components
+            .as_ref()
+            .and_then(|components| components.responses.get(name))
+            .ok_or_else(|| component_not_found(ComponentKind::Responses, name))?;
+        Ok(Resolved::new(
+            self,
+            Source::from_reference_or(response),
+            OpenApiDereferencer::dereference_response,
+        ))
+    }
+
+    ///Get a parameter from `components` that's dereferenced on first access.
+    pub fn lazy_parameter(&self, name: &str) -> Result<Resolved<'_, Parameter>, OpenApiError> {
+        let parameter = self
+            .openapi
+            .components
+            .as_ref()
+            .and_then(|components| components.parameters.get(name))
+            .ok_or_else(|| component_not_found(ComponentKind::Parameters, name))?;
+        Ok(Resolved::new(
+            self,
+            Source::from_reference_or(parameter),
+            OpenApiDereferencer::dereference_parameter,
+        ))
+    }
+
+    ///Get a request body from `components` that's dereferenced on first access.
+    pub fn lazy_request_body(&self, name: &str) -> Result<Resolved<'_, RequestBody>, OpenApiError> {
+        let request_body = self
+            .openapi
+            .components
+            .as_ref()
+            .and_then(|components| components.request_bodies.get(name))
+            .ok_or_else(|| component_not_found(ComponentKind::RequestBodies, name))?;
+        Ok(Resolved::new(
+            self,
+            Source::from_reference_or(request_body),
+            OpenApiDereferencer::dereference_request_body,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use openapiv3::v3_1::StatusCode;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_lazy_operation() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Lazy", "version": "1.0.0"},
+            "paths": {
+                "/pets": {"$ref": "#/components/pathItems/Pets"},
+                "/broken": {
+                    "get": {
+                        "responses": {"200": {"$ref": "#/components/responses/Missing"}}
+                    }
+                }
+            },
+            "components": {
+                "pathItems": {
+                    "Pets": {
+                        "get": {
+                            "responses": {"200": {"$ref": "#/components/responses/Pets"}}
+                        }
+                    }
+                },
+                "responses": {
+                    "Pets": {"description": "Some pets"}
+                }
+            }
+        }"##;
+        let dereferencer = OpenApiDereferencer::from_str(spec)?;
+        let operation = dereferencer.lazy_operation("/pets", Method::Get)?;
+        assert!(!operation.is_resolved());
+        let first = operation.get()?;
+        assert!(operation.is_resolved());
+        assert!(std::ptr::eq(first, operation.get()?));
+        let response = &first.responses.as_ref().unwrap().responses[&StatusCode::Code(200)];
+        assert!(matches!(
+            response,
+            ReferenceOr::DereferencedReference { item, .. } if item.description == "Some pets"
+        ));
+
+        //The broken ref only matters once someone looks at it
+        let broken = dereferencer.lazy_operation("/broken", Method::Get)?;
+        assert!(broken.get().is_err());
+        assert!(dereferencer.lazy_operation("/pets", Method::Post).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::{pointer, DereferencedOpenApi, OpenApiDereferencer, OpenApiError};
+
+///A ref that couldn't be resolved in lenient mode.
+#[derive(Debug)]
+pub struct DerefError {
+    pub reference: String,
+    ///JSON pointers to everywhere in the input document using this reference
+    pub locations: Vec<String>,
+    pub error: OpenApiError,
+}
+
+impl fmt::Display for DerefError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.error, self.locations.join(", "))
+    }
+}
+
+impl OpenApiDereferencer {
+    ///Dereference without stopping at the first ref that can't be resolved. Failing refs are
+    ///left in place and returned alongside the spec. Errors that aren't about a particular ref
+    ///still fail the whole thing.
+    pub fn dereference_lenient(
+        mut self,
+    ) -> Result<(DereferencedOpenApi, Vec<DerefError>), OpenApiError> {
+        self.options.lenient = true;
+        let dereferenced = self.dereference()?;
+        let errors = dereferenced
+            .failed_refs
+            .take()
+            .into_iter()
+            .map(|(reference, error)| {
+                let mut locations = vec![];
+                ref_sites(&dereferenced.json, &reference, "", &mut locations);
+                DerefError {
+                    reference,
+                    locations,
+                    error,
+                }
+            })
+            .collect();
+        Ok((dereferenced, errors))
+    }
+}
+
+///Call `f` with the location and value of every `$ref` and `$dynamicRef` in `value`.
+pub(crate) fn for_each_ref<'v>(value: &'v Value, location: &str, f: &mut dyn FnMut(&str, &'v str)) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                match (key.as_str(), value) {
+                    ("$ref" | "$dynamicRef", Value::String(reference)) => f(location, reference),
+                    _ => for_each_ref(value, &pointer::push(location, key), f),
+                }
+            }
+        }
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                for_each_ref(value, &format!("{location}/{i}"), f);
+            }
+        }
+        _ => {}
+    }
+}
+
+///Every `$ref` and `$dynamicRef` in `value`.
+pub(crate) fn refs_in(value: &Value) -> Vec<&str> {
+    let mut refs = vec![];
+    for_each_ref(value, "", &mut |_, reference| refs.push(reference));
+    refs
+}
+
+pub(crate) fn ref_sites(value: &Value, reference: &str, location: &str, sites: &mut Vec<String>) {
+    for_each_ref(value, location, &mut |site, r| {
+        if r == reference {
+            sites.push(site.into());
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::as_item;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_dereference_lenient() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Lenient", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "ok",
+                                "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pets"}}}
+                            },
+                            "404": {"$ref": "#/components/responses/Missing"}
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Pets": {"type": "array", "items": {"$ref": "#/components/schemas/Pet"}}
+                }
+            }
+        }"##;
+        assert!(OpenApiDereferencer::from_str(spec)?.dereference().is_err());
+
+        let (dereferenced, errors) = OpenApiDereferencer::from_str(spec)?.dereference_lenient()?;
+        let failures: Vec<(&str, &[String])> = errors
+            .iter()
+            .map(|e| (e.reference.as_str(), e.locations.as_slice()))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "#/components/schemas/Pet",
+                    &["/components/schemas/Pets/items".to_string()][..]
+                ),
+                (
+                    "#/components/responses/Missing",
+                    &["/paths/~1pets/get/responses/404".to_string()][..]
+                ),
+            ],
+            failures
+        );
+        let paths = &dereferenced.openapi.paths.as_ref().unwrap().paths;
+        assert!(as_item(&paths["/pets"]).is_some());
+        Ok(())
+    }
+}
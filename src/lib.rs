@@ -1,48 +1,340 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::ops::Deref;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use indexmap::IndexMap;
-use jsonpath_rust::JsonPathInst;
-use openapiv3::schemars::schema::Schema as SchemarsSchema;
+use openapiv3::schemars::schema::{Schema as SchemarsSchema, SingleOrVec};
+use openapiv3::schemars::Map as SchemarsMap;
 use openapiv3::v3_1::{
-    Callback, Components, Example, Header, Link, OpenApi as OpenApiV3_1, Operation, Parameter,
-    ParameterData, PathItem, Paths, ReferenceOr, RequestBody, Response, SchemaObject,
-    SecurityScheme, Server, StatusCode,
+    Callback, Components, Encoding, Example, Header, Link, MediaType, OpenApi as OpenApiV3_1,
+    Operation, Parameter, ParameterData, ParameterSchemaOrContent, PathItem, Paths, ReferenceOr,
+    RequestBody, Response, SchemaObject, SecurityScheme, Server, StatusCode,
 };
 use openapiv3::versioned::OpenApi;
 use serde_json::Value;
 use snafu::prelude::*;
 
+#[macro_use]
+mod trace;
+
+mod anchors;
+#[cfg(feature = "binary-cache")]
+mod binary_cache;
+mod builder;
+mod bundle;
+mod cache;
+mod callbacks;
+mod canonical;
+mod components;
+mod content;
+mod convert;
+mod descriptor;
+mod diff;
+#[cfg(feature = "fs")]
+mod dir;
+mod discriminator;
+mod duplicates;
+mod enums;
+mod example;
+#[cfg(feature = "validation")]
+mod example_validation;
+mod extensions;
+mod external_examples;
+mod fake;
+mod filter;
+mod flatten;
+mod incremental;
+mod lazy;
+mod lenient;
+mod limits;
+mod links;
+mod lint;
+mod loader;
+mod location;
+#[cfg(feature = "markdown")]
+mod markdown;
+mod memory;
+mod merge;
+mod method;
+#[cfg(feature = "mock")]
+mod mock;
+mod nullable;
+#[cfg(feature = "oas3")]
+mod oas3_spec;
+mod operations;
+mod order;
+mod parameters;
+mod pointer;
+#[cfg(feature = "postman")]
+mod postman;
+mod read;
+mod recover;
+mod redact;
+mod resolve;
+mod router;
+mod runtime_expression;
+mod session;
+mod snippet;
+mod standalone;
+mod state;
+mod stats;
+mod strict;
+mod strip;
+mod typed;
+mod usage;
+#[cfg(feature = "utoipa")]
+mod utoipa_spec;
+#[cfg(feature = "validation")]
+mod validation;
+mod value;
+mod verify;
+mod views;
+mod visit_mut;
+mod visitor;
+mod warning;
+#[cfg(feature = "watch")]
+mod watch;
+
+use anchors::AnchorIndex;
+use builder::DerefOptions;
+use limits::ResourceUsage;
+use operations::path_item_operations;
+use visit_mut::unwrap_dereferenced;
+
+pub use builder::{
+    CyclePolicy, DerefScope, ExternalRefPolicy, OpenApiDereferencerBuilder, RefOutput,
+    DEFAULT_MAX_REF_DEPTH, ORIGINAL_REF_EXTENSION,
+};
+pub use cache::{InMemoryRefCache, RefCache, SharedRefCache};
+pub use callbacks::{callback_requests, CallbackRequest};
+pub use components::ComponentKind;
+pub use content::{negotiate_media_type, OperationExt, ResponseExt};
+pub use convert::ConversionNote;
+pub use descriptor::{
+    describe_schema, ContentDescriptor, OperationDescriptor, ParameterDescriptor,
+    ParameterLocation, PathSegment, PropertyDescriptor, RequestBodyDescriptor, ResponseDescriptor,
+    TypeDescriptor,
+};
+pub use diff::{diff, ChangeKind, SpecChange, SpecDiff};
+pub use discriminator::Discriminator;
+pub use duplicates::DuplicateSchemas;
+pub use enums::enum_values;
+pub use example::{generate_example, OperationExample};
+#[cfg(feature = "validation")]
+pub use example_validation::ExampleViolation;
+pub use external_examples::ExampleFetcher;
+#[cfg(feature = "fs")]
+pub use external_examples::FileExampleFetcher;
+pub use fake::DataGenerator;
+pub use flatten::flatten_schema_all_of;
+pub use lazy::Resolved;
+pub use lenient::DerefError;
+pub use limits::{Limit, ResourceLimits};
+pub use links::FollowedLink;
+pub use lint::{LintConfig, LintFinding, LintRule};
+#[cfg(feature = "fs")]
+pub use loader::FileDocumentLoader;
+pub use loader::{DocumentLoader, InMemoryDocumentLoader};
+pub use location::ParseLocation;
+pub use method::Method;
+#[cfg(feature = "mock")]
+pub use mock::{MockResponse, MockServer};
+pub use nullable::{is_nullable, non_null_types};
+pub use order::DependencyGroup;
+pub use recover::ParseFix;
+pub use redact::Redaction;
+pub use resolve::ResolvedRef;
+pub use router::{PathTemplate, RouteMatch, Router};
+pub use runtime_expression::{
+    CapturedMessage, Exchange, ExpressionSource, ExpressionTemplate, RuntimeExpression,
+    TemplatePart,
+};
+pub use session::{DerefSession, NdjsonSpecs};
+pub use snippet::SnippetFormat;
+pub use state::State;
+pub use stats::SpecStats;
+pub use strict::UnknownField;
+pub use strip::StripOptions;
+pub use typed::DereferencedOpenApi;
+pub use usage::ComponentUsage;
+#[cfg(feature = "validation")]
+pub use validation::{Violation, ViolationLocation};
+pub use verify::{ListedReference, ReferenceKind, RemainingReference};
+pub use views::{request_view, response_view};
+pub use visitor::SpecVisitor;
+pub use warning::Warning;
+#[cfg(feature = "watch")]
+pub use watch::{watch, SpecWatcher};
+
 #[derive(Default)]
 pub struct OpenApiDereferencer {
     pub json: serde_json::Value,
     pub openapi: OpenApiV3_1,
-    pub serde_values: RefCell<HashMap<String, serde_json::Value>>,
-    is_dereferenced: bool,
+    pub serde_values: Box<dyn RefCache>,
+    state: State,
+    ref_stack: RefCell<Vec<String>>,
+    anchors: OnceCell<AnchorIndex>,
+    operation_ids: HashMap<String, (String, Method)>,
+    warnings: Vec<Warning>,
+    example_fetcher: Option<Arc<dyn ExampleFetcher>>,
+    should_cancel: Option<Arc<dyn Fn() -> bool + Send + Sync>>,
+    options: DerefOptions,
+    failed_refs: RefCell<IndexMap<String, OpenApiError>>,
+    deref_warnings: RefCell<Vec<Warning>>,
+    usage: ResourceUsage,
+    kept_refs: HashSet<String>,
+    kept_external_refs: Cell<bool>,
+    routes: OnceCell<Vec<router::Route>>,
+    #[cfg(feature = "validation")]
+    validators: RefCell<HashMap<String, Arc<jsonschema::JSONSchema>>>,
 }
 
+//A dereferencer or session can be moved into a worker thread or a web service's state.
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+    assert_send::<OpenApiDereferencer>();
+    assert_send::<DerefSession>();
+    assert_sync::<DerefSession>();
+};
+
 #[derive(Debug, Snafu)]
 pub enum OpenApiError {
-    #[snafu(display("Error parsing open api spec {msg}"))]
-    ParsingError { msg: String },
+    ///`location` says where in the document parsing failed, when that's known.
+    #[snafu(display(
+        "Error parsing open api spec {msg}{}",
+        location.as_ref().map(|location| format!(" at {location}")).unwrap_or_default()
+    ))]
+    ParsingError {
+        msg: String,
+        location: Option<ParseLocation>,
+    },
     #[snafu(display("References must be in the same file and start with #, found {reference}"))]
     UnsupportedRefFormat { reference: String },
+    #[snafu(display("Nothing found in the spec for reference {reference}"))]
+    RefNotFound { reference: String },
+    #[snafu(display("No $anchor or $id found for reference {reference}"))]
+    AnchorNotFound { reference: String },
+    #[snafu(display("Can't resolve {reference}, $dynamicRef is only supported when the $dynamicAnchor is declared once"))]
+    UnsupportedDynamicRef { reference: String },
     #[snafu(display("Unsupported open api version"))]
     UnsupportedOpenApiVersion,
+    #[snafu(display("The input document was released after dereferencing"))]
+    InputReleased,
     #[snafu(display("Must dereference before getting servers"))]
     DerefBeforeGettingServers,
+    #[snafu(display("Must dereference before accessing the dereferenced spec"))]
+    NotDereferenced,
+    #[snafu(display("Unsupported http method {method}"))]
+    UnsupportedMethod { method: String },
+    #[snafu(display("No path {path} in the spec"))]
+    PathNotFound { path: String },
+    #[snafu(display("No {method} operation for path {path}"))]
+    OperationNotFound { path: String, method: Method },
+    #[snafu(display("No operation with operationId {operation_id}"))]
+    OperationIdNotFound { operation_id: String },
+    #[snafu(display("Schema doesn't have a discriminator"))]
+    NoDiscriminator,
+    #[snafu(display("No schema for discriminator value {value}"))]
+    DiscriminatorValueNotFound { value: String },
+    #[snafu(display("Error fetching external example {url}: {msg}"))]
+    ExampleFetchError { url: String, msg: String },
+    #[snafu(display("Error loading {} {msg}", path.display()))]
+    FileError { path: PathBuf, msg: String },
+    #[snafu(display(
+        "Fields not allowed by the 3.1 spec: {}",
+        fields.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+    ))]
+    UnknownFields { fields: Vec<UnknownField> },
+    #[snafu(display("Nothing at {pointer} in the spec"))]
+    PointerNotFound { pointer: String },
+    #[snafu(display("No $ref at {pointer} in the spec"))]
+    NotARef { pointer: String },
+    #[snafu(display("No document found for {uri}"))]
+    DocumentNotFound { uri: String },
+    #[snafu(display("Invalid path template {template}"))]
+    InvalidPathTemplate { template: String },
+    #[snafu(display("Schema can't be used for validation {msg}"))]
+    InvalidSchema { msg: String },
+    #[snafu(display("Mock server error {msg}"))]
+    MockServerError { msg: String },
+    #[snafu(display("Need at least one spec to merge"))]
+    NothingToMerge,
+    #[snafu(display(
+        "Followed more than {max_ref_depth} refs deep resolving {reference} via [{}]",
+        chain.join(", ")
+    ))]
+    MaxRefDepthExceeded {
+        reference: String,
+        max_ref_depth: usize,
+        chain: Vec<String>,
+    },
+    #[snafu(display("No request body for {method} {path}"))]
+    RequestBodyNotFound { path: String, method: Method },
+    #[snafu(display("No response for status {status}"))]
+    ResponseNotFound { status: u16 },
+    #[snafu(display("No content for media type {media_type}"))]
+    MediaTypeNotFound { media_type: String },
+    #[snafu(display("No {kind} component named {name}"))]
+    ComponentNotFound { kind: ComponentKind, name: String },
+    #[snafu(display("There's already a {kind} component named {name}"))]
+    ComponentExists { kind: ComponentKind, name: String },
+    #[snafu(display("Exceeded the limit of {max} {limit}"))]
+    LimitExceeded { limit: Limit, max: usize },
+    #[snafu(display("Dereferencing was cancelled"))]
+    Cancelled,
+    #[snafu(display("Can't use the cached spec, {msg}"))]
+    StaleCache { msg: String },
+    #[snafu(display("Invalid runtime expression {expression}, {msg}"))]
+    InvalidRuntimeExpression { expression: String, msg: String },
+    #[snafu(display("Nothing captured for runtime expression {expression}"))]
+    RuntimeValueMissing { expression: String },
+    #[snafu(display("Link doesn't point at an operation {msg}"))]
+    InvalidLink { msg: String },
+    #[snafu(display("The value of header {name} has a line break in it"))]
+    InvalidHeaderValue { name: String },
+    #[snafu(display("Schema {reference} refers back to itself"))]
+    CircularReference { reference: String },
+    #[snafu(display("{reference} doesn't point at the right type: {source}"))]
+    RefDeserialization {
+        reference: String,
+        source: serde_json::Error,
+    },
+    ///A ref couldn't be resolved. `location` is the JSON pointer of the object with the `$ref`
+    ///and `chain` the refs that were being followed when it was reached, outermost first.
+    #[snafu(display(
+        "Can't resolve {reference} at {location} (via [{}]): {source}",
+        chain.join(", ")
+    ))]
+    Resolution {
+        reference: String,
+        location: String,
+        chain: Vec<String>,
+        source: Box<OpenApiError>,
+    },
 }
 
-impl OpenApiDereferencer {
-    ///Get a list of servers from all levels of the spec. You _must_ run derefence before calling
-    ///this. Doing otherwise will result in an error.
+///Get the item out of a `ReferenceOr` whether it was inline or has been dereferenced. Returns
+///`None` for references that haven't been resolved.
+pub(crate) fn as_item<T>(reference_or: &ReferenceOr<T>) -> Option<&T> {
+    match reference_or {
+        ReferenceOr::Item(item) => Some(item),
+        ReferenceOr::DereferencedReference {
+            reference: _,
+            summary: _,
+            description: _,
+            item,
+        } => Some(item),
+        _ => None,
+    }
+}
+
+impl DereferencedOpenApi {
+    ///Get a list of servers from all levels of the spec. Fails if a path item is still a ref,
+    ///e.g. one a lenient dereference couldn't resolve.
     pub fn get_servers(&self) -> Result<Vec<Server>, OpenApiError> {
-        if !self.is_dereferenced {
-            return Err(OpenApiError::DerefBeforeGettingServers);
-        }
         let mut servers: Vec<Server> = self
             .openapi
             .servers
@@ -94,151 +386,504 @@ impl OpenApiDereferencer {
                 }
             }
         }
+        for (_, webhook) in &self.openapi.webhooks {
+            let item = as_item(webhook).ok_or(OpenApiError::DerefBeforeGettingServers)?;
+            servers.extend(item.servers.iter().cloned());
+            for (_, operation) in path_item_operations(item) {
+                servers.extend(operation.servers.iter().cloned());
+            }
+        }
         Ok(servers)
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<Self, OpenApiError> {
-        let json: Value =
-            serde_json::from_slice(bytes).map_err(|e| OpenApiError::ParsingError {
-                msg: format!("Error parsing from slice to serde {}", e),
+    ///Get the servers that apply to a single operation. Operation level servers override path
+    ///level servers, which override the root servers. If none are declared anywhere the spec
+    ///default of a single server with a url of `/` is returned.
+    pub fn effective_servers(
+        &self,
+        path: &str,
+        method: Method,
+    ) -> Result<Vec<Server>, OpenApiError> {
+        let path_item = self.path_item(path)?;
+        let operation =
+            method
+                .operation(path_item)
+                .ok_or_else(|| OpenApiError::OperationNotFound {
+                    path: path.into(),
+                    method,
+                })?;
+        if !operation.servers.is_empty() {
+            return Ok(operation.servers.clone());
+        }
+        if !path_item.servers.is_empty() {
+            return Ok(path_item.servers.clone());
+        }
+        if !self.openapi.servers.is_empty() {
+            return Ok(self.openapi.servers.clone());
+        }
+        Ok(vec![Server {
+            url: "/".into(),
+            ..Default::default()
+        }])
+    }
+
+    ///Get a dereferenced path item by its path template, e.g. `/pets/{petId}`.
+    fn path_item(&self, path: &str) -> Result<&PathItem, OpenApiError> {
+        self.openapi
+            .paths
+            .as_ref()
+            .and_then(|paths| paths.paths.get(path))
+            .and_then(as_item)
+            .ok_or_else(|| OpenApiError::PathNotFound { path: path.into() })
+    }
+
+    ///A JSON Patch (RFC 6902) describing what dereferencing changed, going from the input
+    ///document to the dereferenced one.
+    pub fn dereference_patch(&self) -> Result<json_patch::Patch, OpenApiError> {
+        if self.json.is_null() {
+            return Err(OpenApiError::InputReleased);
+        }
+        let dereferenced =
+            serde_json::to_value(&self.openapi).map_err(|e| OpenApiError::ParsingError {
+                msg: format!("Error serializing the dereferenced spec {e}"),
+                location: None,
             })?;
+        Ok(json_patch::diff(&self.json, &dereferenced))
+    }
+}
+
+impl OpenApiDereferencer {
+    ///Warnings collected while dereferencing.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    ///Resolve a ref of your own, e.g. one out of a vendor extension, to whatever type it points
+    ///at. It goes through the same lookup and cache as dereferencing, but nothing inside the
+    ///result is dereferenced.
+    pub fn resolve_ref<T: serde::de::DeserializeOwned>(
+        &self,
+        reference: &str,
+    ) -> Result<T, OpenApiError> {
+        if self.json.is_null() {
+            return Err(OpenApiError::InputReleased);
+        }
+        self.dereference_type(reference)
+    }
+
+    ///The spec with every resolved ref turned into a plain `Item`, so it serializes like a spec
+    ///that never had refs in it. Refs that were left in place, like recursive schemas, stay.
+    pub fn into_openapi(mut self) -> OpenApiV3_1 {
+        unwrap_dereferenced(&mut self.openapi);
+        self.openapi
+    }
+
+    ///The spec as JSON the way `into_openapi` would serialize it, without giving it up.
+    pub(crate) fn to_output_value(&self) -> Result<Value, OpenApiError> {
+        let mut openapi = self.openapi.clone();
+        unwrap_dereferenced(&mut openapi);
+        serde_json::to_value(&openapi).map_err(|e| OpenApiError::ParsingError {
+            msg: format!("Error serializing the spec {e}"),
+            location: None,
+        })
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, OpenApiError> {
+        let json = parse_json(bytes, "slice")?;
+        OpenApiDereferencer::from_value(json)
+    }
+
+    ///Load a spec written as JSON5, so it can have comments, trailing commas and the like.
+    #[cfg(feature = "json5")]
+    pub fn from_json5_str(the_str: &str) -> Result<Self, OpenApiError> {
+        let json = json5::from_str(the_str).map_err(|e| {
+            let json5::Error::Message { msg, location } = e;
+            OpenApiError::ParsingError {
+                location: location
+                    .map(|location| ParseLocation::at(location.line, location.column)),
+                msg: format!("Error parsing from JSON5 to serde {msg}"),
+            }
+        })?;
         OpenApiDereferencer::from_value(json)
     }
 
     pub fn from_value(json: Value) -> Result<Self, OpenApiError> {
-        let openapi: OpenApi =
-            serde_json::from_value(json.clone()).map_err(|e| OpenApiError::ParsingError {
-                msg: format!("Error parsing from serde to OpenApi {}", e),
-            })?;
+        //Deserializing from a reference keeps only the one copy of the input around
+        let openapi: OpenApi = match location::deserialize(&json) {
+            Ok(openapi) => openapi,
+            //The versioned spec can't tell which part of a 3.1 spec was wrong, the 3.1 one can
+            Err(e)
+                if json["openapi"]
+                    .as_str()
+                    .is_some_and(|v| v.starts_with("3.1")) =>
+            {
+                location::deserialize::<OpenApiV3_1>(&json)?;
+                return Err(e);
+            }
+            Err(e) => return Err(e),
+        };
         match openapi {
-            OpenApi::Version31(openapi) => Ok(OpenApiDereferencer {
-                json,
-                openapi,
-                serde_values: HashMap::default().into(),
-                is_dereferenced: false,
-            }),
+            OpenApi::Version31(openapi) => Ok(OpenApiDereferencer::from_parts(json, openapi)),
             _ => Err(OpenApiError::UnsupportedOpenApiVersion),
         }
     }
+
+    ///A dereferencer for an input document and the spec already read out of it.
+    pub(crate) fn from_parts(json: Value, openapi: OpenApiV3_1) -> Self {
+        OpenApiDereferencer {
+            json,
+            openapi,
+            serde_values: Box::default(),
+            state: State::Parsed,
+            ref_stack: RefCell::default(),
+            anchors: OnceCell::new(),
+            operation_ids: HashMap::default(),
+            warnings: Vec::new(),
+            example_fetcher: None,
+            should_cancel: None,
+            options: DerefOptions::default(),
+            failed_refs: RefCell::default(),
+            deref_warnings: RefCell::default(),
+            usage: ResourceUsage::default(),
+            kept_refs: HashSet::new(),
+            kept_external_refs: Cell::default(),
+            routes: OnceCell::new(),
+            #[cfg(feature = "validation")]
+            validators: RefCell::default(),
+        }
+    }
 }
 
 impl FromStr for OpenApiDereferencer {
     type Err = OpenApiError;
 
     fn from_str(the_str: &str) -> Result<Self, OpenApiError> {
-        let json: serde_json::Value =
-            serde_json::from_str(the_str).map_err(|e| OpenApiError::ParsingError {
-                msg: format!("Error parsing from string to serde {}", e),
-            })?;
+        let json = parse_json(the_str.as_bytes(), "string")?;
         OpenApiDereferencer::from_value(json)
     }
 }
 
+#[cfg(not(feature = "simd-json"))]
+fn parse_json(bytes: &[u8], source: &str) -> Result<Value, OpenApiError> {
+    serde_json::from_slice(bytes).map_err(|e| OpenApiError::ParsingError {
+        location: Some(ParseLocation::at(e.line(), e.column())),
+        msg: format!("Error parsing from {source} to serde {e}"),
+    })
+}
+
+///simd-json parses in place, so it gets its own copy of the input.
+#[cfg(feature = "simd-json")]
+fn parse_json(bytes: &[u8], source: &str) -> Result<Value, OpenApiError> {
+    let original = bytes;
+    let mut bytes = bytes.to_vec();
+    simd_json::serde::from_slice(&mut bytes).map_err(|e| OpenApiError::ParsingError {
+        location: Some(ParseLocation::at_offset(original, e.index())),
+        msg: format!("Error parsing from {source} to serde {e}"),
+    })
+}
+
+///Keywords whose values are arrays, so a number after one is an index rather than a key like a
+///status code.
+const ARRAY_KEYWORDS: [&str; 10] = [
+    "allOf",
+    "anyOf",
+    "oneOf",
+    "prefixItems",
+    "parameters",
+    "servers",
+    "security",
+    "tags",
+    "enum",
+    "required",
+];
+
+///Convert a same document reference into a JSONPath, e.g. `#/paths/~1pets/get` becomes
+///`$.paths['/pets'].get`. Keys that aren't plain names are written in brackets, and numbers
+///after keywords like `allOf` are array indices.
 pub fn ref_to_json_path(ref_str: &str) -> Result<String, OpenApiError> {
-    let mut chars = ref_str.chars();
-    let first_char = chars.next();
-    if first_char.is_none() || first_char.unwrap() != '#' {
-        return Err(OpenApiError::UnsupportedRefFormat {
-            reference: ref_str.into(),
-        });
-    }
-    chars.next();
-    let path_str: String = chars.collect();
-    let path = PathBuf::from(&path_str);
+    let json_pointer = ref_to_json_pointer(ref_str)?;
     let mut json_path: String = "$".into();
-    for p in path.iter() {
-        if let Some(p) = p.to_str() {
+    let mut previous = String::new();
+    for segment in json_pointer.split('/').skip(1) {
+        let segment = pointer::unescape(segment);
+        let index = ARRAY_KEYWORDS.contains(&previous.as_str())
+            && !segment.is_empty()
+            && segment.chars().all(|c| c.is_ascii_digit());
+        let plain = !segment.is_empty()
+            && segment
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '$');
+        if index {
+            json_path += &format!("[{segment}]");
+        } else if plain {
             json_path += ".";
-            json_path += p;
+            json_path += &segment;
+        } else {
+            json_path += &format!("['{}']", segment.replace('\\', "\\\\").replace('\'', "\\'"));
         }
+        previous = segment;
     }
     Ok(json_path)
 }
 
+///Convert a same document reference like `#/components/schemas/Pet` into the JSON pointer
+///`/components/schemas/Pet`. The fragment is percent-decoded, so `My%20Pet` becomes `My Pet`.
+pub fn ref_to_json_pointer(ref_str: &str) -> Result<String, OpenApiError> {
+    match ref_str.strip_prefix('#') {
+        Some(pointer) if pointer.is_empty() || pointer.starts_with('/') => {
+            Ok(pointer::percent_decode(pointer))
+        }
+        _ => Err(OpenApiError::UnsupportedRefFormat {
+            reference: ref_str.into(),
+        }),
+    }
+}
+
+///Add an `x-original-ref` extension to an item by round tripping it through JSON. Types that
+///don't take extensions come back as they were.
+fn annotated<T: serde::de::DeserializeOwned + serde::Serialize>(item: T, reference: &str) -> T {
+    let Ok(Value::Object(mut value)) = serde_json::to_value(&item) else {
+        return item;
+    };
+    value.insert(ORIGINAL_REF_EXTENSION.into(), reference.into());
+    serde_json::from_value(Value::Object(value)).unwrap_or(item)
+}
+
+///The pointer of the component schema a reference points into, if it points into one.
+fn schema_resource_root(reference: &str) -> Option<String> {
+    let segments: Vec<&str> = reference.strip_prefix('#')?.split('/').collect();
+    if segments.len() >= 4 && segments[1] == "components" && segments[2] == "schemas" {
+        Some(pointer::percent_decode(&segments[..4].join("/")))
+    } else {
+        None
+    }
+}
+
 impl OpenApiDereferencer {
-    pub fn dereference(mut self) -> Result<Self, OpenApiError> {
-        let components: Option<Components> = self.openapi.components.take();
-        self.openapi.components = self.dereference_components(components)?;
-        let paths: Option<Paths> = self.openapi.paths.take();
-        self.openapi.paths = self.dereference_paths(paths)?;
-        self.is_dereferenced = true;
-        Ok(self)
+    pub fn dereference(mut self) -> Result<DereferencedOpenApi, OpenApiError> {
+        phase_span!("all");
+        self.kept_refs = self.over_inline_budget()?;
+        if self.options.scope.components {
+            phase_span!("components");
+            let components: Option<Components> = self.openapi.components.take();
+            self.openapi.components = self.dereference_components(components)?;
+        }
+        {
+            phase_span!("paths");
+            let paths: Option<Paths> = self.openapi.paths.take();
+            self.openapi.paths = self.dereference_paths(paths)?;
+        }
+        if self.options.scope.webhooks {
+            phase_span!("webhooks");
+            let webhooks = std::mem::take(&mut self.openapi.webhooks);
+            self.openapi.webhooks = self.dereference_path_items(webhooks)?;
+        }
+        self.check_output_size()?;
+        self.state = self.dereferenced_state();
+        let mut warnings = self.deref_warnings.take();
+        self.warnings.append(&mut warnings);
+        self.index_operation_ids();
+        self.validate_discriminators();
+        self.collect_document_warnings();
+        if self.options.release_input {
+            self.release_input();
+        }
+        Ok(DereferencedOpenApi(self))
     }
 
     fn dereference_schemars_schema(
         &self,
         schema: SchemarsSchema,
     ) -> Result<SchemarsSchema, OpenApiError> {
+        self.use_schema_node()?;
         match schema {
             SchemarsSchema::Bool(b) => Ok(SchemarsSchema::Bool(b)),
-            SchemarsSchema::Object(s) => {
-                let mut s = if s.is_ref() {
-                    self.dereference_type(&s.reference.unwrap())?
-                } else {
-                    s
-                };
-                s.subschemas = match s.subschemas {
-                    Some(mut subschemas) => {
-                        subschemas.all_of = subschemas
-                            .all_of
-                            .map(|olives| {
-                                olives
-                                    .iter()
-                                    .map(|olive| self.dereference_schemars_schema(olive.clone()))
-                                    .collect()
-                            })
-                            .transpose()?;
-                        subschemas.any_of = subschemas
-                            .any_of
-                            .map(|any_ofs| {
-                                any_ofs
-                                    .iter()
-                                    .map(|any_of| self.dereference_schemars_schema(any_of.clone()))
-                                    .collect()
-                            })
-                            .transpose()?;
-                        subschemas.one_of = subschemas
-                            .one_of
-                            .map(|one_ofs| {
-                                one_ofs
-                                    .iter()
-                                    .map(|one_of| self.dereference_schemars_schema(one_of.clone()))
-                                    .collect()
-                            })
-                            .transpose()?;
-                        subschemas.if_schema = subschemas
-                            .if_schema
-                            .map(|if_schema| {
-                                self.dereference_schemars_schema(*if_schema).map(Box::new)
-                            })
-                            .transpose()?;
-                        subschemas.else_schema = subschemas
-                            .else_schema
-                            .map(|else_schema| {
-                                self.dereference_schemars_schema(*else_schema).map(Box::new)
-                            })
-                            .transpose()?;
-                        subschemas.then_schema = subschemas
-                            .then_schema
-                            .map(|then_schema| {
-                                self.dereference_schemars_schema(*then_schema).map(Box::new)
-                            })
-                            .transpose()?;
-                        Some(subschemas)
+            SchemarsSchema::Object(mut s) => {
+                let dynamic_ref = s
+                    .extensions
+                    .get("$dynamicRef")
+                    .and_then(Value::as_str)
+                    .map(str::to_owned);
+                if let (None, Some(dynamic_ref)) = (&s.reference, dynamic_ref) {
+                    //Without tracking the dynamic scope the best we can do is treat it like a
+                    //plain ref to the one place the anchor is declared.
+                    let pointer = match self.anchor_index().locate_dynamic(&dynamic_ref) {
+                        Ok(pointer) => pointer,
+                        Err(e) => {
+                            self.skip_failed_ref(&dynamic_ref, e)?;
+                            return Ok(SchemarsSchema::Object(s));
+                        }
+                    };
+                    s.extensions.remove("$dynamicRef");
+                    s.reference = Some(format!("#{pointer}"));
+                }
+                if let Some(reference) = &s.reference {
+                    let reference = reference.clone();
+                    if self.options.cycles == CyclePolicy::KeepRef && self.on_ref_stack(&reference)
+                    {
+                        //This is a recursive schema. Leave the ref in place, otherwise we'd be
+                        //inlining forever.
+                        return Ok(SchemarsSchema::Object(s));
                     }
-                    None => None,
-                };
+                    if self.keeps_ref(&reference) {
+                        return Ok(SchemarsSchema::Object(s));
+                    }
+                    let siblings = match serde_json::to_value(&s) {
+                        Ok(Value::Object(mut siblings)) => {
+                            siblings.remove("$ref");
+                            siblings
+                        }
+                        _ => serde_json::Map::new(),
+                    };
+                    let target: Result<SchemarsSchema, OpenApiError> = self
+                        .check_ref_stack(&reference)
+                        .and_then(|_| self.dereference_type_with_siblings(&reference, siblings));
+                    let target = match target {
+                        Ok(target) => target,
+                        Err(e) => {
+                            self.skip_failed_ref(&reference, e)?;
+                            return Ok(SchemarsSchema::Object(s));
+                        }
+                    };
+                    self.ref_stack.borrow_mut().push(reference.clone());
+                    let target = self.dereference_schemars_schema(target);
+                    self.ref_stack.borrow_mut().pop();
+                    let mut target = target?;
+                    if let (RefOutput::Annotated, SchemarsSchema::Object(target)) =
+                        (self.options.output, &mut target)
+                    {
+                        target
+                            .extensions
+                            .insert(ORIGINAL_REF_EXTENSION.into(), reference.into());
+                    }
+                    return Ok(target);
+                }
+                if let Some(subschemas) = s.subschemas.as_mut() {
+                    subschemas.all_of = self.dereference_schema_vec(subschemas.all_of.take())?;
+                    subschemas.any_of = self.dereference_schema_vec(subschemas.any_of.take())?;
+                    subschemas.one_of = self.dereference_schema_vec(subschemas.one_of.take())?;
+                    subschemas.not = self.dereference_boxed_schema(subschemas.not.take())?;
+                    subschemas.if_schema =
+                        self.dereference_boxed_schema(subschemas.if_schema.take())?;
+                    subschemas.then_schema =
+                        self.dereference_boxed_schema(subschemas.then_schema.take())?;
+                    subschemas.else_schema =
+                        self.dereference_boxed_schema(subschemas.else_schema.take())?;
+                }
+                if let Some(array) = s.array.as_mut() {
+                    array.items = match array.items.take() {
+                        Some(SingleOrVec::Single(item)) => Some(SingleOrVec::Single(Box::new(
+                            self.dereference_schemars_schema(*item)?,
+                        ))),
+                        Some(SingleOrVec::Vec(items)) => Some(SingleOrVec::Vec(
+                            items
+                                .into_iter()
+                                .map(|item| self.dereference_schemars_schema(item))
+                                .collect::<Result<Vec<SchemarsSchema>, OpenApiError>>()?,
+                        )),
+                        None => None,
+                    };
+                    array.additional_items =
+                        self.dereference_boxed_schema(array.additional_items.take())?;
+                    array.contains = self.dereference_boxed_schema(array.contains.take())?;
+                }
+                if let Some(object) = s.object.as_mut() {
+                    object.properties =
+                        self.dereference_schema_map(std::mem::take(&mut object.properties))?;
+                    object.pattern_properties = self
+                        .dereference_schema_map(std::mem::take(&mut object.pattern_properties))?;
+                    object.additional_properties =
+                        self.dereference_boxed_schema(object.additional_properties.take())?;
+                    object.property_names =
+                        self.dereference_boxed_schema(object.property_names.take())?;
+                }
+                self.dereference_extension::<Vec<SchemarsSchema>>(
+                    &mut s.extensions,
+                    "prefixItems",
+                    &|items| {
+                        self.dereference_schema_vec(Some(items))
+                            .map(|items| items.unwrap_or_default())
+                    },
+                )?;
+                self.dereference_extension::<SchemarsMap<String, SchemarsSchema>>(
+                    &mut s.extensions,
+                    "$defs",
+                    &|defs| self.dereference_schema_map(defs),
+                )?;
                 Ok(SchemarsSchema::Object(s))
             }
         }
     }
 
+    fn anchor_index(&self) -> &AnchorIndex {
+        self.anchors.get_or_init(|| AnchorIndex::build(&self.json))
+    }
+
+    ///schemars predates some of the 2020-12 keywords like `prefixItems` and `$defs`, so they end
+    ///up in the extensions. Pull one out, dereference it and put it back.
+    fn dereference_extension<S: serde::Serialize + serde::de::DeserializeOwned>(
+        &self,
+        extensions: &mut SchemarsMap<String, Value>,
+        keyword: &str,
+        dereference: &dyn Fn(S) -> Result<S, OpenApiError>,
+    ) -> Result<(), OpenApiError> {
+        if let Some(value) = extensions.remove(keyword) {
+            let parsed: S =
+                serde_json::from_value(value).map_err(|e| OpenApiError::ParsingError {
+                    msg: format!("Error parsing {keyword} {e}"),
+                    location: None,
+                })?;
+            let value = serde_json::to_value(dereference(parsed)?).map_err(|e| {
+                OpenApiError::ParsingError {
+                    msg: format!("Error serializing {keyword} {e}"),
+                    location: None,
+                }
+            })?;
+            extensions.insert(keyword.into(), value);
+        }
+        Ok(())
+    }
+
+    fn dereference_schema_vec(
+        &self,
+        schemas: Option<Vec<SchemarsSchema>>,
+    ) -> Result<Option<Vec<SchemarsSchema>>, OpenApiError> {
+        schemas
+            .map(|schemas| {
+                schemas
+                    .into_iter()
+                    .map(|schema| self.dereference_schemars_schema(schema))
+                    .collect()
+            })
+            .transpose()
+    }
+
+    fn dereference_boxed_schema(
+        &self,
+        schema: Option<Box<SchemarsSchema>>,
+    ) -> Result<Option<Box<SchemarsSchema>>, OpenApiError> {
+        schema
+            .map(|schema| self.dereference_schemars_schema(*schema).map(Box::new))
+            .transpose()
+    }
+
+    fn dereference_schema_map(
+        &self,
+        schemas: SchemarsMap<String, SchemarsSchema>,
+    ) -> Result<SchemarsMap<String, SchemarsSchema>, OpenApiError> {
+        schemas
+            .into_iter()
+            .map(|(k, v)| Ok((k, self.dereference_schemars_schema(v)?)))
+            .collect()
+    }
+
     fn dereference_schemas(&self, mut schema: SchemaObject) -> Result<SchemaObject, OpenApiError> {
         schema.json_schema = self.dereference_schemars_schema(schema.json_schema)?;
         Ok(schema)
     }
 
     fn dereference_operation(&self, mut operation: Operation) -> Result<Operation, OpenApiError> {
+        self.check_cancelled()?;
         operation.parameters = operation
             .parameters
             .into_iter()
@@ -250,7 +895,11 @@ impl OpenApiDereferencer {
             .collect::<Result<Vec<ReferenceOr<Parameter>>, OpenApiError>>()?;
         operation.request_body = operation
             .request_body
-            .map(|v| self.dereference_reference(v))
+            .map(|v| {
+                self.handle_dereferenced(self.dereference_reference(v)?, &|item| {
+                    self.dereference_request_body(item)
+                })
+            })
             .transpose()?;
         operation.parameters = operation
             .parameters
@@ -277,12 +926,41 @@ impl OpenApiDereferencer {
                     })
                     .collect::<Result<IndexMap<StatusCode, ReferenceOr<Response>>, OpenApiError>>(
                     )?;
+                responses.default = responses
+                    .default
+                    .map(|v| {
+                        self.handle_dereferenced(self.dereference_reference(v)?, &|item| {
+                            self.dereference_response(item)
+                        })
+                    })
+                    .transpose()?;
                 Ok(responses)
             })
             .transpose()?;
+        operation.callbacks = operation
+            .callbacks
+            .into_iter()
+            .map(|(k, v)| {
+                Ok((
+                    k,
+                    self.handle_dereferenced(self.dereference_reference(v)?, &|item| {
+                        self.dereference_callback(item)
+                    })?,
+                ))
+            })
+            .collect::<Result<IndexMap<String, ReferenceOr<Callback>>, OpenApiError>>()?;
         Ok(operation)
     }
 
+    ///Callbacks are a map of runtime expressions to path items, so they get the same treatment
+    ///as the entries under `paths`.
+    fn dereference_callback(&self, callback: Callback) -> Result<Callback, OpenApiError> {
+        callback
+            .into_iter()
+            .map(|(expression, path_item)| Ok((expression, self.dereference_path_item(path_item)?)))
+            .collect()
+    }
+
     fn dereference_path_item(&self, mut path_item: PathItem) -> Result<PathItem, OpenApiError> {
         path_item.get = path_item
             .get
@@ -355,10 +1033,10 @@ impl OpenApiDereferencer {
                 .paths
                 .into_iter()
                 .map(|(k, v)| {
-                    let new_v = self
-                        .handle_dereferenced(self.dereference_reference(v)?, &|item| {
-                            self.dereference_path_item(item)
-                        })?;
+                    if !self.options.scope.includes_path(&k) {
+                        return Ok((k, v));
+                    }
+                    let new_v = self.dereference_path_item_reference(v)?;
                     Ok((k, new_v))
                 })
                 .collect::<Result<IndexMap<String, ReferenceOr<PathItem>>, OpenApiError>>()?;
@@ -368,12 +1046,35 @@ impl OpenApiDereferencer {
         }
     }
 
+    fn dereference_path_items(
+        &self,
+        path_items: IndexMap<String, ReferenceOr<PathItem>>,
+    ) -> Result<IndexMap<String, ReferenceOr<PathItem>>, OpenApiError> {
+        path_items
+            .into_iter()
+            .map(|(k, v)| {
+                let new_v = self.dereference_path_item_reference(v)?;
+                Ok((k, new_v))
+            })
+            .collect()
+    }
+
+    fn dereference_path_item_reference(
+        &self,
+        path_item: ReferenceOr<PathItem>,
+    ) -> Result<ReferenceOr<PathItem>, OpenApiError> {
+        self.handle_dereferenced(self.dereference_reference(path_item)?, &|item| {
+            self.dereference_path_item(item)
+        })
+    }
+
     fn dereference_header(&self, mut header: Header) -> Result<Header, OpenApiError> {
+        header.format = self.dereference_schema_or_content(header.format)?;
         header.examples = header
             .examples
             .into_iter()
             .map(|(k, v)| {
-                let new_v = self.dereference_reference(v)?;
+                let new_v = self.dereference_example(v)?;
                 Ok((k, new_v))
             })
             .collect::<Result<IndexMap<String, ReferenceOr<Example>>, OpenApiError>>()?;
@@ -384,18 +1085,32 @@ impl OpenApiDereferencer {
         &self,
         mut parameter_data: ParameterData,
     ) -> Result<ParameterData, OpenApiError> {
-        //Note examples can have external values, but we don't care at the moment.
+        parameter_data.format = self.dereference_schema_or_content(parameter_data.format)?;
         parameter_data.examples = parameter_data
             .examples
             .into_iter()
             .map(|(k, v)| {
-                let new_v = self.dereference_reference(v)?;
+                let new_v = self.dereference_example(v)?;
                 Ok((k, new_v))
             })
             .collect::<Result<IndexMap<String, ReferenceOr<Example>>, OpenApiError>>()?;
         Ok(parameter_data)
     }
 
+    fn dereference_schema_or_content(
+        &self,
+        format: ParameterSchemaOrContent,
+    ) -> Result<ParameterSchemaOrContent, OpenApiError> {
+        match format {
+            ParameterSchemaOrContent::Schema(schema) => Ok(ParameterSchemaOrContent::Schema(
+                self.dereference_schemas(schema)?,
+            )),
+            ParameterSchemaOrContent::Content(content) => Ok(ParameterSchemaOrContent::Content(
+                self.dereference_content(content)?,
+            )),
+        }
+    }
+
     fn dereference_parameter(&self, parameter: Parameter) -> Result<Parameter, OpenApiError> {
         match parameter {
             Parameter::Query {
@@ -438,7 +1153,9 @@ impl OpenApiDereferencer {
             .headers
             .into_iter()
             .map(|(k, v)| {
-                let new_v = self.dereference_reference(v)?;
+                let new_v = self.handle_dereferenced(self.dereference_reference(v)?, &|item| {
+                    self.dereference_header(item)
+                })?;
                 Ok((k, new_v))
             })
             .collect();
@@ -452,9 +1169,63 @@ impl OpenApiDereferencer {
             })
             .collect();
         response.links = res?;
+        response.content = self.dereference_content(response.content)?;
         Ok(response)
     }
 
+    fn dereference_request_body(
+        &self,
+        mut request_body: RequestBody,
+    ) -> Result<RequestBody, OpenApiError> {
+        request_body.content = self.dereference_content(request_body.content)?;
+        Ok(request_body)
+    }
+
+    fn dereference_content(
+        &self,
+        content: IndexMap<String, MediaType>,
+    ) -> Result<IndexMap<String, MediaType>, OpenApiError> {
+        content
+            .into_iter()
+            .map(|(k, v)| Ok((k, self.dereference_media_type(v)?)))
+            .collect()
+    }
+
+    fn dereference_media_type(&self, mut media_type: MediaType) -> Result<MediaType, OpenApiError> {
+        media_type.schema = media_type
+            .schema
+            .map(|schema| self.dereference_schemas(schema))
+            .transpose()?;
+        media_type.examples = media_type
+            .examples
+            .into_iter()
+            .map(|(k, v)| {
+                let new_v = self.dereference_example(v)?;
+                Ok((k, new_v))
+            })
+            .collect::<Result<IndexMap<String, ReferenceOr<Example>>, OpenApiError>>()?;
+        media_type.encoding = media_type
+            .encoding
+            .into_iter()
+            .map(|(k, mut encoding)| {
+                encoding.headers = encoding
+                    .headers
+                    .into_iter()
+                    .map(|(k, v)| {
+                        Ok((
+                            k,
+                            self.handle_dereferenced(self.dereference_reference(v)?, &|item| {
+                                self.dereference_header(item)
+                            })?,
+                        ))
+                    })
+                    .collect::<Result<IndexMap<String, ReferenceOr<Header>>, OpenApiError>>()?;
+                Ok((k, encoding))
+            })
+            .collect::<Result<IndexMap<String, Encoding>, OpenApiError>>()?;
+        Ok(media_type)
+    }
+
     fn dereference_components(
         &self,
         components: Option<Components>,
@@ -483,7 +1254,16 @@ impl OpenApiDereferencer {
             components.schemas = components
                 .schemas
                 .into_iter()
-                .map(|(k, v)| Ok((k, self.dereference_schemas(v)?)))
+                .map(|(k, v)| {
+                    //Seed the stack with the component itself so a self referencing schema
+                    //stops at its first recursion.
+                    self.ref_stack
+                        .borrow_mut()
+                        .push(format!("#/components/schemas/{}", pointer::escape(&k)));
+                    let v = self.dereference_schemas(v);
+                    self.ref_stack.borrow_mut().pop();
+                    Ok((k, v?))
+                })
                 .collect::<Result<IndexMap<String, SchemaObject>, OpenApiError>>()?;
             components.parameters = components
                 .parameters
@@ -501,7 +1281,7 @@ impl OpenApiDereferencer {
                 .examples
                 .into_iter()
                 .map(|(k, v)| {
-                    let new_v = self.dereference_reference(v)?;
+                    let new_v = self.dereference_example(v)?;
                     Ok((k, new_v))
                 })
                 .collect::<Result<IndexMap<String, ReferenceOr<Example>>, OpenApiError>>()?;
@@ -509,8 +1289,12 @@ impl OpenApiDereferencer {
                 .request_bodies
                 .into_iter()
                 .map(|(k, v)| {
-                    let new_v = self.dereference_reference(v)?;
-                    Ok((k, new_v))
+                    Ok((
+                        k,
+                        self.handle_dereferenced(self.dereference_reference(v)?, &|item| {
+                            self.dereference_request_body(item)
+                        })?,
+                    ))
                 })
                 .collect::<Result<IndexMap<String, ReferenceOr<RequestBody>>, OpenApiError>>()?;
             components.headers = components
@@ -535,27 +1319,20 @@ impl OpenApiDereferencer {
                 })
                 .collect::<Result<IndexMap<String, ReferenceOr<Link>>, OpenApiError>>()?;
 
-            //I don't think we care about callbacks for the moment.
-            let res: Result<IndexMap<String, ReferenceOr<Callback>>, OpenApiError> = components
+            components.callbacks = components
                 .callbacks
                 .into_iter()
                 .map(|(k, v)| {
-                    let new_v = self.dereference_reference(v)?;
-                    Ok((k, new_v))
+                    Ok((
+                        k,
+                        self.handle_dereferenced(self.dereference_reference(v)?, &|item| {
+                            self.dereference_callback(item)
+                        })?,
+                    ))
                 })
-                .collect();
-            components.callbacks = res?;
+                .collect::<Result<IndexMap<String, ReferenceOr<Callback>>, OpenApiError>>()?;
 
-            //TODO handle the path item here. This is a big chunk of refs
-            let res: Result<IndexMap<String, ReferenceOr<PathItem>>, OpenApiError> = components
-                .path_items
-                .into_iter()
-                .map(|(k, v)| {
-                    let new_v = self.dereference_reference(v)?;
-                    Ok((k, new_v))
-                })
-                .collect();
-            components.path_items = res?;
+            components.path_items = self.dereference_path_items(components.path_items)?;
             Ok(Some(components))
         } else {
             Ok(None)
@@ -566,43 +1343,252 @@ impl OpenApiDereferencer {
         &self,
         reference: &str,
     ) -> Result<T, OpenApiError> {
-        let mut cache = self.serde_values.borrow_mut();
-        let value = if let Some(v) = cache.get(reference) {
-            v
-        } else {
-            let jp = ref_to_json_path(reference)?;
-            let query = JsonPathInst::from_str(&jp).map_err(|e| OpenApiError::ParsingError {
-                msg: format!("Error creating json path {jp}, {e}"),
-            })?;
-            let path_result = query.find_slice(&self.json);
-            //TODO Reading the spec, I don't _think_ this needs to work for arrays.
-            let v = path_result.get(0).take().unwrap().deref();
-            cache.insert(reference.into(), v.to_owned());
-            cache.get(reference).unwrap()
+        self.dereference_type_with_siblings(reference, serde_json::Map::new())
+    }
+
+    ///Resolve a reference, laying the keywords that sat next to the `$ref` over the top of the
+    ///target. Per 3.1 they override what's in the target, and they're silently dropped if the
+    ///target type doesn't have them (e.g. a `summary` next to a ref to a schema).
+    fn dereference_type_with_siblings<T: serde::de::DeserializeOwned>(
+        &self,
+        reference: &str,
+        siblings: serde_json::Map<String, Value>,
+    ) -> Result<T, OpenApiError> {
+        trace_event!(debug, reference, "resolving ref");
+        let pointer = self.locate_reference(reference)?;
+        let mut value = match self.serde_values.get(&pointer) {
+            Some(value) => {
+                trace_event!(trace, pointer = %pointer, "ref cache hit");
+                value
+            }
+            None => {
+                trace_event!(trace, pointer = %pointer, "ref cache miss");
+                let value = self.json.pointer(&pointer).cloned().unwrap_or_default();
+                self.serde_values.put(&pointer, value.clone());
+                value
+            }
         };
-        serde_json::from_value(value.clone()).map_err(|e| OpenApiError::ParsingError {
-            msg: format!("Error with serde parsing {e} {reference}"),
+        self.use_ref()?;
+        if let Value::Object(target) = &mut value {
+            target.extend(siblings);
+        }
+        serde_json::from_value(value).map_err(|source| OpenApiError::RefDeserialization {
+            reference: reference.into(),
+            source,
+        })
+    }
+
+    ///Find the JSON pointer a reference resolves to. `#/$defs/...` refs that don't exist at the
+    ///root of the document are resolved against the component schemas currently being
+    ///dereferenced, innermost first.
+    fn locate_reference(&self, reference: &str) -> Result<String, OpenApiError> {
+        let pointer = match ref_to_json_pointer(reference) {
+            Ok(pointer) => pointer,
+            Err(_) => return self.anchor_index().locate(reference),
+        };
+        if self.json.pointer(&pointer).is_some() {
+            return Ok(pointer);
+        }
+        //Keys that really contain a `%` are sometimes referenced without escaping it
+        if let Some(raw) = reference
+            .strip_prefix('#')
+            .filter(|raw| *raw != pointer && self.json.pointer(raw).is_some())
+        {
+            return Ok(raw.into());
+        }
+        if pointer.starts_with("/$defs/") {
+            for scope in self.ref_stack.borrow().iter().rev() {
+                if let Some(root) = schema_resource_root(scope) {
+                    let scoped = format!("{root}{pointer}");
+                    if self.json.pointer(&scoped).is_some() {
+                        return Ok(scoped);
+                    }
+                }
+            }
+        }
+        Err(OpenApiError::RefNotFound {
+            reference: reference.into(),
+        })
+    }
+
+    ///Check following `reference` from the schema currently being dereferenced doesn't go round
+    ///in a circle or too deep.
+    fn check_ref_stack(&self, reference: &str) -> Result<(), OpenApiError> {
+        if self.on_ref_stack(reference) {
+            return Err(OpenApiError::CircularReference {
+                reference: reference.into(),
+            });
+        }
+        let ref_stack = self.ref_stack.borrow();
+        if ref_stack.len() >= self.options.max_ref_depth {
+            return Err(OpenApiError::MaxRefDepthExceeded {
+                reference: reference.into(),
+                max_ref_depth: self.options.max_ref_depth,
+                chain: ref_stack.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    ///Whether `reference` points somewhere a ref being followed already points, however the
+    ///two are spelled.
+    fn on_ref_stack(&self, reference: &str) -> bool {
+        let ref_stack = self.ref_stack.borrow();
+        if ref_stack.iter().any(|r| r == reference) {
+            return true;
+        }
+        let Ok(pointer) = self.locate_reference(reference) else {
+            return false;
+        };
+        ref_stack.iter().any(|r| {
+            self.locate_reference(r)
+                .is_ok_and(|located| located == pointer)
+        })
+    }
+
+    ///Whether the ref is to a component over the inline budget, which stays a ref.
+    fn keeps_ref(&self, reference: &str) -> bool {
+        !self.kept_refs.is_empty() && self.kept_refs.contains(&pointer::percent_decode(reference))
+    }
+
+    ///Decide what to do with a ref that couldn't be resolved. `Ok` means leave it in place.
+    ///Refs to other documents fail with `UnsupportedRefFormat`, which the external ref policy
+    ///can choose to let through, and in lenient mode every failure is recorded and skipped.
+    fn skip_failed_ref(&self, reference: &str, e: OpenApiError) -> Result<(), OpenApiError> {
+        if self.options.external_refs == ExternalRefPolicy::Keep
+            && matches!(e, OpenApiError::UnsupportedRefFormat { .. })
+        {
+            self.kept_external_refs.set(true);
+            return Ok(());
+        }
+        //Hitting a limit means the spec is pathological, so stop rather than carry on even in
+        //lenient mode. Same goes for being cancelled. The errors already say what happened.
+        if matches!(
+            e,
+            OpenApiError::MaxRefDepthExceeded { .. }
+                | OpenApiError::LimitExceeded { .. }
+                | OpenApiError::Cancelled
+        ) {
+            return Err(e);
+        }
+        if self.options.lenient {
+            self.failed_refs
+                .borrow_mut()
+                .entry(reference.into())
+                .or_insert(e);
+            return Ok(());
+        }
+        let chain = self.ref_stack.borrow().clone();
+        Err(OpenApiError::Resolution {
+            reference: reference.into(),
+            location: self.ref_site(reference, &chain),
+            chain,
+            source: Box::new(e),
         })
     }
 
-    fn dereference_reference<T: serde::de::DeserializeOwned>(
+    ///Where a ref that failed was written. We don't track locations while walking the spec, so
+    ///this is the first use of the ref inside whatever the innermost ref being followed points
+    ///to, or in the whole document if that doesn't turn it up.
+    fn ref_site(&self, reference: &str, chain: &[String]) -> String {
+        let mut sites = vec![];
+        if let Some(base) = chain
+            .last()
+            .and_then(|last| self.locate_reference(last).ok())
+        {
+            if let Some(target) = self.json.pointer(&base) {
+                lenient::ref_sites(target, reference, &base, &mut sites);
+            }
+        }
+        if sites.is_empty() {
+            lenient::ref_sites(&self.json, reference, "", &mut sites);
+        }
+        sites.into_iter().next().unwrap_or_default()
+    }
+
+    ///Warn about a `summary` or `description` next to a ref that the target has no room for.
+    fn check_dropped_siblings<T: serde::Serialize>(
+        &self,
+        reference: &str,
+        item: &T,
+        siblings: &[&str],
+    ) {
+        let Ok(item) = serde_json::to_value(item) else {
+            return;
+        };
+        let keywords: Vec<String> = siblings
+            .iter()
+            .filter(|keyword| item.get(keyword).is_none())
+            .map(|keyword| keyword.to_string())
+            .collect();
+        if !keywords.is_empty() {
+            let chain = self.ref_stack.borrow().clone();
+            self.deref_warnings
+                .borrow_mut()
+                .push(Warning::SiblingsDropped {
+                    location: self.ref_site(reference, &chain),
+                    reference: reference.into(),
+                    keywords,
+                });
+        }
+    }
+
+    fn dereference_reference<T: serde::de::DeserializeOwned + serde::Serialize>(
         &self,
         v: ReferenceOr<T>,
     ) -> Result<ReferenceOr<T>, OpenApiError> {
         match v {
             ReferenceOr::Item(i) => Ok(ReferenceOr::Item(i)),
+            ReferenceOr::Reference {
+                reference,
+                summary,
+                description,
+            } if self.keeps_ref(&reference) => Ok(ReferenceOr::Reference {
+                reference,
+                summary,
+                description,
+            }),
             ReferenceOr::Reference {
                 reference,
                 summary,
                 description,
             } => {
-                let item = self.dereference_type(&reference)?;
-                Ok(ReferenceOr::DereferencedReference {
-                    reference,
-                    summary,
-                    description,
-                    item,
-                })
+                let mut siblings = serde_json::Map::new();
+                let keywords: Vec<&str> = [("summary", &summary), ("description", &description)]
+                    .into_iter()
+                    .filter(|(_, sibling)| sibling.is_some())
+                    .map(|(keyword, _)| keyword)
+                    .collect();
+                if let Some(summary) = &summary {
+                    siblings.insert("summary".into(), summary.clone().into());
+                }
+                if let Some(description) = &description {
+                    siblings.insert("description".into(), description.clone().into());
+                }
+                let item = match self.dereference_type_with_siblings(&reference, siblings) {
+                    Ok(item) => item,
+                    Err(e) => {
+                        self.skip_failed_ref(&reference, e)?;
+                        return Ok(ReferenceOr::Reference {
+                            reference,
+                            summary,
+                            description,
+                        });
+                    }
+                };
+                if !keywords.is_empty() {
+                    self.check_dropped_siblings(&reference, &item, &keywords);
+                }
+                match self.options.output {
+                    RefOutput::KeepRef => Ok(ReferenceOr::DereferencedReference {
+                        reference,
+                        summary,
+                        description,
+                        item,
+                    }),
+                    RefOutput::Inline => Ok(ReferenceOr::Item(item)),
+                    RefOutput::Annotated => Ok(ReferenceOr::Item(annotated(item, &reference))),
+                }
             }
             ReferenceOr::DereferencedReference {
                 reference,
@@ -641,6 +1627,30 @@ mod tests {
         let reference = "#/components/parameters/pagination-before";
         let expected = "$.components.parameters.pagination-before";
         assert_eq!(expected, &ref_to_json_path(reference)?);
+        assert_eq!(
+            "$.paths['/pets/{id}'].get.responses.200",
+            &ref_to_json_path("#/paths/~1pets~1{id}/get/responses/200")?
+        );
+        assert_eq!(
+            "$.components.schemas.Foo.allOf[0].properties",
+            &ref_to_json_path("#/components/schemas/Foo/allOf/0/properties")?
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_ref_to_json_pointer() -> Result<()> {
+        assert_eq!(
+            "/components/schemas/Pet/$defs/Name",
+            &ref_to_json_pointer("#/components/schemas/Pet/$defs/Name")?
+        );
+        assert_eq!("", &ref_to_json_pointer("#")?);
+        assert_eq!(
+            "/paths/~1pets~1{id}/get",
+            &ref_to_json_pointer("#/paths/~1pets~1%7Bid%7D/get")?
+        );
+        assert_eq!("/100%/é", &ref_to_json_pointer("#/100%/%C3%A9")?);
+        assert!(ref_to_json_pointer("other.json#/components/schemas/Pet").is_err());
         Ok(())
     }
 
@@ -664,10 +1674,16 @@ mod tests {
         let spec = std::fs::read_to_string("oai_examples/api.github.com.json")?;
         let dereferencer = OpenApiDereferencer::from_str(&spec)?;
         let dereferenced = dereferencer.dereference()?;
-        std::fs::write("dereffed_gh.json", serde_json::to_string(&dereferenced)?)?;
 
-        assert!(dereferenced.components.is_some());
-        let components = dereferenced.components.unwrap();
+        let stats = dereferenced.stats();
+        assert!(stats.paths > 0);
+        assert!(stats.total_operations() >= stats.paths);
+        assert!(stats.largest_path_item > 0);
+        assert!(stats.average_path_item_size() <= stats.largest_path_item as f64);
+        assert!(stats.dereferenced_size > stats.input_size);
+        let openapi = dereferenced.into_openapi();
+        assert!(openapi.components.is_some());
+        let components = openapi.components.unwrap();
         assert!(!components.security_schemes.iter().any(is_reference));
         assert!(!components.responses.iter().any(is_reference));
         assert!(!components.parameters.iter().any(is_reference));
@@ -677,39 +1693,636 @@ mod tests {
         assert!(!components.links.iter().any(is_reference));
         assert!(!components.callbacks.iter().any(is_reference));
         assert!(!components.path_items.iter().any(is_reference));
+        assert!(!openapi.paths.unwrap().paths.iter().any(is_reference));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_effective_servers() -> Result<()> {
+        let spec = r#"{
+            "openapi": "3.1.0",
+            "info": {"title": "Servers", "version": "1.0.0"},
+            "servers": [{"url": "https://root.example.com"}],
+            "paths": {
+                "/pets": {
+                    "servers": [{"url": "https://pets.example.com"}],
+                    "get": {
+                        "servers": [{"url": "https://get.example.com"}],
+                        "responses": {"200": {"description": "ok"}}
+                    },
+                    "post": {"responses": {"200": {"description": "ok"}}}
+                },
+                "/owners": {
+                    "get": {"responses": {"200": {"description": "ok"}}}
+                }
+            }
+        }"#;
+        let dereferencer = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let url = |servers: Vec<Server>| servers[0].url.clone();
+        assert_eq!(
+            "https://get.example.com",
+            url(dereferencer.effective_servers("/pets", Method::Get)?)
+        );
+        assert_eq!(
+            "https://pets.example.com",
+            url(dereferencer.effective_servers("/pets", Method::Post)?)
+        );
+        assert_eq!(
+            "https://root.example.com",
+            url(dereferencer.effective_servers("/owners", Method::Get)?)
+        );
+        assert!(dereferencer
+            .effective_servers("/owners", Method::Delete)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_webhooks() -> Result<()> {
+        let spec = std::fs::read_to_string("oai_examples/webhook-example.json")?;
+        let dereferencer = OpenApiDereferencer::from_str(&spec)?.dereference()?;
+        let webhooks: Vec<(&str, Method)> = dereferencer
+            .iter_webhook_operations()
+            .map(|(name, method, _)| (name, method))
+            .collect();
+        assert_eq!(vec![("newPet", Method::Post)], webhooks);
+        assert!(dereferencer.iter_operations().next().is_none());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_callbacks_are_deeply_dereferenced() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Callbacks", "version": "1.0.0"},
+            "paths": {
+                "/subscribe": {
+                    "post": {
+                        "callbacks": {
+                            "onEvent": {"$ref": "#/components/callbacks/Event"}
+                        },
+                        "responses": {"201": {"description": "Subscribed"}}
+                    }
+                }
+            },
+            "components": {
+                "callbacks": {
+                    "Event": {
+                        "{$request.body#/callbackUrl}": {
+                            "post": {
+                                "requestBody": {"$ref": "#/components/requestBodies/Event"},
+                                "responses": {"200": {"$ref": "#/components/responses/Ok"}}
+                            }
+                        }
+                    }
+                },
+                "requestBodies": {
+                    "Event": {"content": {"application/json": {"schema": {"type": "object"}}}}
+                },
+                "responses": {
+                    "Ok": {"description": "Received"}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let (_, _, operation) = dereferenced.iter_operations().next().unwrap();
+        let callback = as_item(&operation.callbacks["onEvent"]).unwrap();
+        let callback_operation = callback["{$request.body#/callbackUrl}"]
+            .post
+            .as_ref()
+            .unwrap();
+        assert!(as_item(callback_operation.request_body.as_ref().unwrap()).is_some());
+        let responses = callback_operation.responses.as_ref().unwrap();
+        assert!(responses
+            .responses
+            .values()
+            .all(|response| as_item(response).is_some()));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_component_path_items_are_deeply_dereferenced() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Path items", "version": "1.0.0"},
+            "components": {
+                "pathItems": {
+                    "Pets": {
+                        "parameters": [{"$ref": "#/components/parameters/Limit"}],
+                        "get": {
+                            "parameters": [{"$ref": "#/components/parameters/Limit"}],
+                            "responses": {"200": {"$ref": "#/components/responses/Ok"}}
+                        }
+                    }
+                },
+                "parameters": {
+                    "Limit": {"name": "limit", "in": "query", "schema": {"type": "integer"}}
+                },
+                "responses": {
+                    "Ok": {"description": "Ok"}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let components = dereferenced.openapi.components.as_ref().unwrap();
+        let path_item = as_item(&components.path_items["Pets"]).unwrap();
+        assert!(path_item.parameters.iter().all(|p| as_item(p).is_some()));
+        let get = path_item.get.as_ref().unwrap();
+        assert!(get.parameters.iter().all(|p| as_item(p).is_some()));
+        assert!(get
+            .responses
+            .as_ref()
+            .unwrap()
+            .responses
+            .values()
+            .all(|response| as_item(response).is_some()));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_request_body_content_is_dereferenced() -> Result<()> {
+        let spec = std::fs::read_to_string("oai_examples/webhook-example.json")?;
+        let dereferenced = OpenApiDereferencer::from_str(&spec)?.dereference()?;
+        let (_, _, operation) = dereferenced.iter_webhook_operations().next().unwrap();
+        let request_body = as_item(operation.request_body.as_ref().unwrap()).unwrap();
+        let schema = request_body.content["application/json"]
+            .schema
+            .as_ref()
+            .unwrap();
+        match &schema.json_schema {
+            SchemarsSchema::Object(object) => {
+                assert!(!object.is_ref());
+                assert!(object
+                    .object
+                    .as_ref()
+                    .unwrap()
+                    .properties
+                    .contains_key("name"));
+            }
+            SchemarsSchema::Bool(_) => panic!("expected the Pet schema"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_header_and_parameter_schemas_are_dereferenced() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Schemas", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "parameters": [
+                            {"name": "limit", "in": "query", "schema": {"$ref": "#/components/schemas/Limit"}}
+                        ],
+                        "responses": {
+                            "200": {
+                                "description": "Ok",
+                                "headers": {
+                                    "X-Rate-Limit": {"schema": {"$ref": "#/components/schemas/Limit"}}
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Limit": {"type": "integer"}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let is_inlined = |format: &ParameterSchemaOrContent| match format {
+            ParameterSchemaOrContent::Schema(schema) => match &schema.json_schema {
+                SchemarsSchema::Object(object) => !object.is_ref(),
+                SchemarsSchema::Bool(_) => false,
+            },
+            ParameterSchemaOrContent::Content(_) => false,
+        };
+        let (_, _, operation) = dereferenced.iter_operations().next().unwrap();
+        match as_item(&operation.parameters[0]).unwrap() {
+            Parameter::Query { parameter_data, .. } => assert!(is_inlined(&parameter_data.format)),
+            _ => panic!("expected a query parameter"),
+        }
+        let responses = operation.responses.as_ref().unwrap();
+        let response = as_item(responses.responses.values().next().unwrap()).unwrap();
+        let header = as_item(&response.headers["X-Rate-Limit"]).unwrap();
+        assert!(is_inlined(&header.format));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_nested_schemas_are_dereferenced() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Schemas", "version": "1.0.0"},
+            "components": {
+                "schemas": {
+                    "Name": {"type": "string"},
+                    "Pet": {
+                        "type": "object",
+                        "properties": {
+                            "name": {"$ref": "#/components/schemas/Name"},
+                            "aliases": {"type": "array", "items": {"$ref": "#/components/schemas/Name"}},
+                            "labels": {"type": "object", "additionalProperties": {"$ref": "#/components/schemas/Name"}},
+                            "notName": {"not": {"$ref": "#/components/schemas/Name"}}
+                        }
+                    },
+                    "Node": {
+                        "type": "object",
+                        "properties": {
+                            "children": {"type": "array", "items": {"$ref": "#/components/schemas/Node"}}
+                        }
+                    }
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let components = dereferenced.openapi.components.as_ref().unwrap();
+        let pet = serde_json::to_value(&components.schemas["Pet"])?;
+        assert!(!pet.to_string().contains("$ref"));
+        assert_eq!("string", pet["properties"]["aliases"]["items"]["type"]);
+        assert_eq!(
+            "string",
+            pet["properties"]["labels"]["additionalProperties"]["type"]
+        );
+        let node = serde_json::to_value(&components.schemas["Node"])?;
+        assert_eq!(
+            "#/components/schemas/Node",
+            node["properties"]["children"]["items"]["$ref"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_refs_into_paths_are_dereferenced() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Paths", "version": "1.0.0"},
+            "paths": {
+                "/pets/{id}": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "a pet",
+                                "content": {"application/json": {"schema": {"type": "object", "properties": {"name": {"type": "string"}}}}}
+                            }
+                        }
+                    },
+                    "put": {
+                        "requestBody": {
+                            "content": {"application/json": {"schema": {"$ref": "#/paths/~1pets~1{id}/get/responses/200/content/application~1json/schema"}}}
+                        },
+                        "responses": {
+                            "200": {"$ref": "#/paths/~1pets~1%7Bid%7D/get/responses/200"}
+                        }
+                    }
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let path_item = as_item(&dereferenced.openapi.paths.as_ref().unwrap().paths["/pets/{id}"]);
+        let put = path_item
+            .and_then(|path_item| path_item.put.as_ref())
+            .unwrap();
+        let response = put
+            .responses
+            .as_ref()
+            .and_then(|responses| responses.responses.get(&StatusCode::Code(200)))
+            .and_then(as_item)
+            .unwrap();
+        assert_eq!("a pet", response.description);
+        let body = put.request_body.as_ref().and_then(as_item).unwrap();
+        let schema = serde_json::to_value(&body.content["application/json"].schema)?;
+        assert_eq!("string", schema["properties"]["name"]["type"]);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_refs_with_array_indices_are_dereferenced() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Indices", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "parameters": [
+                        {"name": "limit", "in": "query", "schema": {"type": "integer"}}
+                    ],
+                    "get": {
+                        "parameters": [{"$ref": "#/paths/~1pets/parameters/0"}],
+                        "responses": {"200": {"description": "ok"}}
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Foo": {
+                        "allOf": [
+                            {"type": "object", "properties": {"id": {"type": "integer"}}},
+                            {"type": "object", "properties": {"name": {"type": "string"}}}
+                        ]
+                    },
+                    "Named": {"$ref": "#/components/schemas/Foo/allOf/1"}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let components = dereferenced.openapi.components.as_ref().unwrap();
+        let named = serde_json::to_value(&components.schemas["Named"])?;
+        assert_eq!("string", named["properties"]["name"]["type"]);
+        let path_item = as_item(&dereferenced.openapi.paths.as_ref().unwrap().paths["/pets"]);
+        let get = path_item
+            .and_then(|path_item| path_item.get.as_ref())
+            .unwrap();
+        assert!(as_item(&get.parameters[0]).is_some());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_percent_encoded_refs_are_dereferenced() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Encoded", "version": "1.0.0"},
+            "components": {
+                "schemas": {
+                    "My Name": {"type": "string"},
+                    "Rate%": {"type": "integer"},
+                    "Pet": {
+                        "type": "object",
+                        "properties": {
+                            "name": {"$ref": "#/components/schemas/My%20Name"},
+                            "rate": {"$ref": "#/components/schemas/Rate%"}
+                        }
+                    }
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let components = dereferenced.openapi.components.as_ref().unwrap();
+        let pet = serde_json::to_value(&components.schemas["Pet"])?;
+        assert_eq!("string", pet["properties"]["name"]["type"]);
+        assert_eq!("integer", pet["properties"]["rate"]["type"]);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_recursion_is_found_whatever_the_spelling() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Spelling", "version": "1.0.0"},
+            "components": {
+                "schemas": {
+                    "Node": {
+                        "type": "object",
+                        "properties": {"next": {"$ref": "#/components/schemas/No%64e"}}
+                    }
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let components = dereferenced.openapi.components.as_ref().unwrap();
+        let node = serde_json::to_value(&components.schemas["Node"])?;
+        assert_eq!(
+            "#/components/schemas/No%64e",
+            node["properties"]["next"]["$ref"]
+        );
+        Ok(())
+    }
 
-        let mut max = 0;
-        let mut sum = 0;
-        let paths = dereferenced.paths.unwrap();
-        for (_, ref_or_pi) in &paths.paths {
-            match ref_or_pi {
-                ReferenceOr::Item(item) => {
-                    let bytes_size = serde_json::to_string(&item)?.as_bytes().len();
-                    sum += bytes_size;
-                    if bytes_size > max {
-                        max = bytes_size;
+    #[test]
+    pub fn test_defs_are_dereferenced() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Defs", "version": "1.0.0"},
+            "components": {
+                "schemas": {
+                    "Pet": {
+                        "type": "object",
+                        "properties": {
+                            "name": {"$ref": "#/$defs/Name"},
+                            "nickname": {"$ref": "#/components/schemas/Pet/$defs/Name"},
+                            "owner": {"$ref": "#/components/schemas/Owner"}
+                        },
+                        "$defs": {
+                            "Name": {"type": "string"},
+                            "Names": {"type": "array", "items": {"$ref": "#/$defs/Name"}}
+                        }
+                    },
+                    "Owner": {
+                        "type": "object",
+                        "properties": {"name": {"$ref": "#/$defs/Name"}},
+                        "$defs": {"Name": {"type": "string", "maxLength": 10}}
                     }
                 }
-                ReferenceOr::DereferencedReference {
-                    reference: _,
-                    summary: _,
-                    description: _,
-                    item,
-                } => {
-                    let bytes_size = serde_json::to_string(&item)?.as_bytes().len();
-                    sum += bytes_size;
-                    if bytes_size > max {
-                        max = bytes_size;
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let components = dereferenced.openapi.components.as_ref().unwrap();
+        let pet = serde_json::to_value(&components.schemas["Pet"])?;
+        assert_eq!("string", pet["properties"]["name"]["type"]);
+        assert_eq!("string", pet["properties"]["nickname"]["type"]);
+        assert_eq!(
+            10,
+            pet["properties"]["owner"]["properties"]["name"]["maxLength"]
+        );
+        assert_eq!("string", pet["$defs"]["Names"]["items"]["type"]);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_anchor_refs_are_dereferenced() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Anchors", "version": "1.0.0"},
+            "components": {
+                "schemas": {
+                    "Name": {"$anchor": "name", "type": "string"},
+                    "Pet": {
+                        "type": "object",
+                        "properties": {"name": {"$ref": "#name"}}
+                    },
+                    "Broken": {"$ref": "#missing"}
+                }
+            }
+        }"##;
+        assert!(matches!(
+            OpenApiDereferencer::from_str(spec)?.dereference(),
+            Err(OpenApiError::Resolution { source, .. })
+                if matches!(*source, OpenApiError::AnchorNotFound { .. })
+        ));
+        let spec = spec.replace(r##""Broken": {"$ref": "#missing"}"##, r#""Broken": {}"#);
+        let dereferenced = OpenApiDereferencer::from_str(&spec)?.dereference()?;
+        let components = dereferenced.openapi.components.as_ref().unwrap();
+        let pet = serde_json::to_value(&components.schemas["Pet"])?;
+        assert_eq!("string", pet["properties"]["name"]["type"]);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_ref_siblings_override_the_target() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Siblings", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {"$ref": "#/components/responses/Ok", "description": "A list of pets"}
+                        }
+                    }
+                }
+            },
+            "components": {
+                "responses": {
+                    "Ok": {"description": "Ok"}
+                },
+                "schemas": {
+                    "Name": {"type": "string", "description": "A name"},
+                    "Pet": {
+                        "type": "object",
+                        "properties": {
+                            "name": {"$ref": "#/components/schemas/Name", "description": "The pet's name"}
+                        }
                     }
                 }
-                _ => {
-                    assert!(false)
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let (_, _, operation) = dereferenced.iter_operations().next().unwrap();
+        let responses = operation.responses.as_ref().unwrap();
+        let response = as_item(responses.responses.values().next().unwrap()).unwrap();
+        assert_eq!("A list of pets", response.description);
+        let components = dereferenced.openapi.components.as_ref().unwrap();
+        let pet = serde_json::to_value(&components.schemas["Pet"])?;
+        assert_eq!("The pet's name", pet["properties"]["name"]["description"]);
+        assert_eq!("string", pet["properties"]["name"]["type"]);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_resolution_error_context() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Context", "version": "1.0.0"},
+            "components": {
+                "schemas": {
+                    "Pets": {"type": "array", "items": {"$ref": "#/components/schemas/Pet"}},
+                    "Pet": {"type": "object", "properties": {"owner": {"$ref": "#/components/schemas/Owner"}}}
                 }
             }
+        }"##;
+        match OpenApiDereferencer::from_str(spec)?.dereference() {
+            Err(OpenApiError::Resolution {
+                reference,
+                location,
+                chain,
+                source,
+            }) => {
+                assert_eq!("#/components/schemas/Owner", reference);
+                assert_eq!("/components/schemas/Pet/properties/owner", location);
+                assert_eq!(
+                    vec!["#/components/schemas/Pets", "#/components/schemas/Pet"],
+                    chain
+                );
+                assert!(matches!(*source, OpenApiError::RefNotFound { .. }));
+            }
+            _ => panic!("expected a resolution error"),
         }
 
-        println!("Max size {}", max);
-        println!("average size {}", sum as f64 / paths.paths.len() as f64);
+        let spec = spec.replace(
+            r##""#/components/schemas/Owner""##,
+            r##""#/components/schemas/Pets/type""##,
+        );
+        let e = OpenApiDereferencer::from_str(&spec)?
+            .dereference()
+            .err()
+            .unwrap();
+        let source = std::error::Error::source(&e).unwrap();
+        assert!(std::error::Error::source(source).is_some());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_dereference_patch() -> Result<()> {
+        let spec = std::fs::read_to_string("oai_examples/webhook-example.json")?;
+        let dereferenced = OpenApiDereferencer::from_str(&spec)?.dereference()?;
+        let patch = dereferenced.dereference_patch()?;
+        assert!(!patch.0.is_empty());
+        let mut patched = dereferenced.json.clone();
+        json_patch::patch(&mut patched, &patch)?;
+        assert_eq!(serde_json::to_value(&dereferenced.openapi)?, patched);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_resolve_ref() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Resolve", "version": "1.0.0"},
+            "paths": {},
+            "x-default-error": {"$ref": "#/components/responses/Error"},
+            "components": {
+                "responses": {
+                    "Error": {"description": "Something went wrong"}
+                }
+            }
+        }"##;
+        let dereferencer = OpenApiDereferencer::from_str(spec)?;
+        let reference = dereferencer.openapi.extensions["x-default-error"]["$ref"]
+            .as_str()
+            .unwrap()
+            .to_owned();
+        let response: Response = dereferencer.resolve_ref(&reference)?;
+        assert_eq!(response.description, "Something went wrong");
+        assert!(matches!(
+            dereferencer.resolve_ref::<Response>("#/components/responses/Missing"),
+            Err(OpenApiError::RefNotFound { .. })
+        ));
+        assert!(matches!(
+            dereferencer.resolve_ref::<Response>("#/info"),
+            Err(OpenApiError::RefDeserialization { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_into_openapi() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Items", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "parameters": [{"$ref": "#/components/parameters/Limit"}],
+                        "responses": {"200": {"$ref": "#/components/responses/Pets"}}
+                    }
+                }
+            },
+            "components": {
+                "parameters": {
+                    "Limit": {"name": "limit", "in": "query", "schema": {"type": "integer"}}
+                },
+                "responses": {
+                    "Pets": {"description": "pets"}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let openapi = dereferenced.into_openapi();
+        let path_item = as_item(&openapi.paths.as_ref().unwrap().paths["/pets"]);
+        let get = path_item
+            .and_then(|path_item| path_item.get.as_ref())
+            .unwrap();
+        assert!(matches!(get.parameters[0], ReferenceOr::Item(_)));
+        let response = &get.responses.as_ref().unwrap().responses[&StatusCode::Code(200)];
+        assert!(matches!(response, ReferenceOr::Item(_)));
+        let json = serde_json::to_value(&openapi)?;
+        assert_eq!(
+            "pets",
+            json["paths"]["/pets"]["get"]["responses"]["200"]["description"]
+        );
+        assert!(!json["paths"].to_string().contains("$ref"));
         Ok(())
     }
 
@@ -719,4 +2332,36 @@ mod tests {
         assert!(OpenApiDereferencer::from_str(&spec).is_err());
         Ok(())
     }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    pub fn test_from_json5_str() -> Result<()> {
+        let spec = r##"{
+            // Hand written
+            openapi: "3.1.0",
+            info: {title: 'JSON5', version: "1.0.0"},
+            paths: {
+                "/pets": {
+                    get: {
+                        responses: {"200": {$ref: "#/components/responses/Pets"},},
+                    },
+                },
+            },
+            components: {responses: {Pets: {description: "Some pets"}}},
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_json5_str(spec)?.dereference()?;
+        let (_, _, operation) = dereferenced.iter_operations().next().unwrap();
+        let response = &operation.responses.as_ref().unwrap().responses[&StatusCode::Code(200)];
+        assert_eq!("Some pets", as_item(response).unwrap().description);
+
+        let broken = OpenApiDereferencer::from_json5_str("{\n  openapi: ,\n}");
+        assert!(matches!(
+            broken,
+            Err(OpenApiError::ParsingError {
+                location: Some(ParseLocation { line: Some(2), .. }),
+                ..
+            })
+        ));
+        Ok(())
+    }
 }
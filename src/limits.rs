@@ -0,0 +1,209 @@
+use std::cell::Cell;
+use std::fmt;
+use std::io;
+
+use crate::visit_mut::unwrap_dereferenced;
+use crate::{OpenApiDereferencer, OpenApiError};
+
+///Caps on how much work dereferencing a spec can do, for specs that come from people you
+///don't trust. Nothing is limited by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ResourceLimits {
+    ///How many refs can be resolved in total
+    pub max_refs: Option<usize>,
+    ///How many schemas can be visited, counting every copy of an inlined schema
+    pub max_schema_nodes: Option<usize>,
+    ///How many bytes the dereferenced spec can serialize to as JSON. It's measured once
+    ///dereferencing finishes, so pair it with one of the other limits to stop huge expansions
+    ///early.
+    pub max_output_size: Option<usize>,
+}
+
+///The limit that was hit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Refs,
+    SchemaNodes,
+    OutputSize,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Refs => write!(f, "resolved refs"),
+            Limit::SchemaNodes => write!(f, "schema nodes"),
+            Limit::OutputSize => write!(f, "output size"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct ResourceUsage {
+    refs: Cell<usize>,
+    schema_nodes: Cell<usize>,
+}
+
+fn add(
+    counter: &Cell<usize>,
+    amount: usize,
+    max: Option<usize>,
+    limit: Limit,
+) -> Result<(), OpenApiError> {
+    let total = counter.get().saturating_add(amount);
+    counter.set(total);
+    match max {
+        Some(max) if total > max => Err(OpenApiError::LimitExceeded { limit, max }),
+        _ => Ok(()),
+    }
+}
+
+///Counts the bytes written instead of keeping them.
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl OpenApiDereferencer {
+    ///Count a ref being resolved.
+    pub(crate) fn use_ref(&self) -> Result<(), OpenApiError> {
+        self.check_cancelled()?;
+        add(
+            &self.usage.refs,
+            1,
+            self.options.limits.max_refs,
+            Limit::Refs,
+        )
+    }
+
+    ///Check the size of the dereferenced spec, serialized the way `into_openapi` would be.
+    pub(crate) fn check_output_size(&self) -> Result<(), OpenApiError> {
+        let Some(max) = self.options.limits.max_output_size else {
+            return Ok(());
+        };
+        let mut openapi = self.openapi.clone();
+        unwrap_dereferenced(&mut openapi);
+        let mut counter = ByteCounter(0);
+        serde_json::to_writer(&mut counter, &openapi).map_err(|e| OpenApiError::ParsingError {
+            msg: format!("Error serializing the spec {e}"),
+            location: None,
+        })?;
+        match counter.0 > max {
+            true => Err(OpenApiError::LimitExceeded {
+                limit: Limit::OutputSize,
+                max,
+            }),
+            false => Ok(()),
+        }
+    }
+
+    pub(crate) fn use_schema_node(&self) -> Result<(), OpenApiError> {
+        self.check_cancelled()?;
+        add(
+            &self.usage.schema_nodes,
+            1,
+            self.options.limits.max_schema_nodes,
+            Limit::SchemaNodes,
+        )
+    }
+
+    pub(crate) fn check_cancelled(&self) -> Result<(), OpenApiError> {
+        match &self.should_cancel {
+            Some(should_cancel) if should_cancel() => Err(OpenApiError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DereferencedOpenApi;
+    use anyhow::Result;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    pub fn test_resource_limits() -> Result<()> {
+        //Each level doubles the size of the dereferenced output
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Limits", "version": "1.0.0"},
+            "components": {
+                "schemas": {
+                    "A": {"type": "object", "properties": {"x": {"$ref": "#/components/schemas/B"}, "y": {"$ref": "#/components/schemas/B"}}},
+                    "B": {"type": "object", "properties": {"x": {"$ref": "#/components/schemas/C"}, "y": {"$ref": "#/components/schemas/C"}}},
+                    "C": {"type": "object", "properties": {"x": {"$ref": "#/components/schemas/D"}, "y": {"$ref": "#/components/schemas/D"}}},
+                    "D": {"type": "string"}
+                }
+            }
+        }"##;
+        let dereference = |limits: ResourceLimits| -> Result<DereferencedOpenApi, OpenApiError> {
+            OpenApiDereferencer::builder()
+                .limits(limits)
+                .build_from_str(spec)?
+                .dereference()
+        };
+        assert!(dereference(ResourceLimits::default()).is_ok());
+        for (limits, expected) in [
+            (
+                ResourceLimits {
+                    max_refs: Some(10),
+                    ..Default::default()
+                },
+                Limit::Refs,
+            ),
+            (
+                ResourceLimits {
+                    max_schema_nodes: Some(10),
+                    ..Default::default()
+                },
+                Limit::SchemaNodes,
+            ),
+            (
+                ResourceLimits {
+                    max_output_size: Some(500),
+                    ..Default::default()
+                },
+                Limit::OutputSize,
+            ),
+        ] {
+            assert!(matches!(
+                dereference(limits),
+                Err(OpenApiError::LimitExceeded { limit, .. }) if limit == expected
+            ));
+        }
+
+        let size =
+            serde_json::to_vec(&dereference(ResourceLimits::default())?.into_openapi())?.len();
+        let max_output_size = |max| ResourceLimits {
+            max_output_size: Some(max),
+            ..Default::default()
+        };
+        assert!(dereference(max_output_size(size)).is_ok());
+        assert!(dereference(max_output_size(size - 1)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_cancellation() -> Result<()> {
+        let spec = std::fs::read_to_string("oai_examples/api.github.com.json")?;
+        let checks = Arc::new(AtomicUsize::new(0));
+        let counter = checks.clone();
+        //Not even lenient dereferencing carries on
+        let dereferenced = OpenApiDereferencer::builder()
+            .cancel_when(move || counter.fetch_add(1, Ordering::Relaxed) + 1 > 100)
+            .build_from_str(&spec)?
+            .dereference_lenient();
+        assert!(matches!(dereferenced, Err(OpenApiError::Cancelled)));
+        assert_eq!(101, checks.load(Ordering::Relaxed));
+        Ok(())
+    }
+}
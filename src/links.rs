@@ -0,0 +1,191 @@
+use std::str::FromStr;
+
+use indexmap::IndexMap;
+use openapiv3::v3_1::{Link, Operation};
+use serde_json::Value;
+
+use crate::runtime_expression::{Exchange, RuntimeExpression};
+use crate::{as_item, pointer, ref_to_json_pointer, DereferencedOpenApi, Method, OpenApiError};
+
+///Where a link goes and what to send there, see [`DereferencedOpenApi::follow_link`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FollowedLink {
+    pub path: String,
+    pub method: Method,
+    pub operation: Operation,
+    ///The link's parameters with their runtime expressions evaluated, keyed the same as in the
+    ///link, e.g. `petId` or `path.petId`
+    pub parameters: IndexMap<String, Value>,
+    pub request_body: Option<Value>,
+}
+
+impl DereferencedOpenApi {
+    ///The operation a link points at, found by its `operationRef` or `operationId`.
+    pub fn link_operation(&self, link: &Link) -> Result<(&str, Method, &Operation), OpenApiError> {
+        let link = link_json(link)?;
+        if let Some(operation_id) = link.get("operationId").and_then(Value::as_str) {
+            return self.get_operation_by_id(operation_id);
+        }
+        let operation_ref = link
+            .get("operationRef")
+            .and_then(Value::as_str)
+            .ok_or_else(|| OpenApiError::InvalidLink {
+                msg: "it has no operationRef or operationId".into(),
+            })?;
+        let not_under_paths = || OpenApiError::InvalidLink {
+            msg: format!("{operation_ref} isn't an operation under paths"),
+        };
+        let json_pointer = ref_to_json_pointer(operation_ref)?;
+        let (path, method) = json_pointer
+            .strip_prefix("/paths/")
+            .and_then(|rest| rest.rsplit_once('/'))
+            .ok_or_else(not_under_paths)?;
+        let method = Method::from_str(method).map_err(|_| not_under_paths())?;
+        let path = pointer::unescape(path);
+        let (path, path_item) = self
+            .openapi
+            .paths
+            .as_ref()
+            .and_then(|paths| paths.paths.get_key_value(&path))
+            .and_then(|(path, path_item)| Some((path, as_item(path_item)?)))
+            .ok_or(OpenApiError::PathNotFound { path })?;
+        let operation =
+            method
+                .operation(path_item)
+                .ok_or_else(|| OpenApiError::OperationNotFound {
+                    path: path.clone(),
+                    method,
+                })?;
+        Ok((path, method, operation))
+    }
+
+    ///Work out the request a link describes given what was sent and received for the operation
+    ///the link is on. Parameters and request bodies that are runtime expressions like
+    ///`$response.body#/id` are evaluated against `exchange`, anything else is passed through as
+    ///a constant.
+    pub fn follow_link(
+        &self,
+        link: &Link,
+        exchange: &Exchange,
+    ) -> Result<FollowedLink, OpenApiError> {
+        let (path, method, operation) = self.link_operation(link)?;
+        let link = link_json(link)?;
+        let parameters = link
+            .get("parameters")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .map(|(name, value)| Ok((name.clone(), evaluate(value, exchange)?)))
+            .collect::<Result<IndexMap<String, Value>, OpenApiError>>()?;
+        let request_body = link
+            .get("requestBody")
+            .map(|value| evaluate(value, exchange))
+            .transpose()?;
+        Ok(FollowedLink {
+            path: path.into(),
+            method,
+            operation: operation.clone(),
+            parameters,
+            request_body,
+        })
+    }
+}
+
+fn link_json(link: &Link) -> Result<Value, OpenApiError> {
+    serde_json::to_value(link).map_err(|e| OpenApiError::InvalidLink { msg: e.to_string() })
+}
+
+///Evaluate a link value if it's a runtime expression, otherwise it's a constant.
+fn evaluate(value: &Value, exchange: &Exchange) -> Result<Value, OpenApiError> {
+    match value {
+        Value::String(expression) if expression.starts_with('$') => {
+            RuntimeExpression::from_str(expression)?
+                .evaluate(exchange)
+                .ok_or_else(|| OpenApiError::RuntimeValueMissing {
+                    expression: expression.clone(),
+                })
+        }
+        value => Ok(value.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+    use openapiv3::v3_1::{ReferenceOr, StatusCode};
+    use serde_json::json;
+
+    #[test]
+    pub fn test_follow_link() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Links", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "post": {
+                        "operationId": "createPet",
+                        "responses": {
+                            "201": {
+                                "description": "Created",
+                                "links": {
+                                    "GetPet": {"$ref": "#/components/links/GetPet"},
+                                    "GetOwner": {
+                                        "operationRef": "#/paths/~1owners~1{ownerId}/get",
+                                        "parameters": {"ownerId": "$response.body#/owner/id", "verbose": true}
+                                    },
+                                    "Broken": {"operationId": "getPet", "parameters": {"petId": "$request.query.id"}}
+                                }
+                            }
+                        }
+                    }
+                },
+                "/pets/{petId}": {"get": {"operationId": "getPet", "responses": {"200": {"description": "ok"}}}},
+                "/owners/{ownerId}": {"get": {"responses": {"200": {"description": "ok"}}}}
+            },
+            "components": {
+                "links": {
+                    "GetPet": {
+                        "operationId": "getPet",
+                        "parameters": {"path.petId": "$response.body#/id"},
+                        "requestBody": "$request.header.X-Trace"
+                    }
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let (_, _, create) = dereferenced.get_operation_by_id("createPet")?;
+        let links = &create.responses.as_ref().unwrap().responses[&StatusCode::Code(201)];
+        let links = &as_item(links).unwrap().links;
+        let link = |name: &str| match &links[name] {
+            ReferenceOr::Item(link) | ReferenceOr::DereferencedReference { item: link, .. } => link,
+            ReferenceOr::Reference { .. } => panic!("{name} wasn't dereferenced"),
+        };
+
+        let mut exchange = Exchange::new(Method::Post, "https://example.com/pets");
+        exchange.status_code = Some(201);
+        exchange.request.headers = vec![("x-trace".into(), "abc".into())];
+        exchange.response.body = Some(json!({"id": 7, "owner": {"id": "bob"}}));
+
+        let followed = dereferenced.follow_link(link("GetPet"), &exchange)?;
+        assert_eq!(
+            ("/pets/{petId}", Method::Get),
+            (followed.path.as_str(), followed.method)
+        );
+        assert_eq!(Some("getPet"), followed.operation.operation_id.as_deref());
+        assert_eq!(json!(7), followed.parameters["path.petId"]);
+        assert_eq!(Some(json!("abc")), followed.request_body);
+
+        let followed = dereferenced.follow_link(link("GetOwner"), &exchange)?;
+        assert_eq!("/owners/{ownerId}", followed.path);
+        assert_eq!(json!("bob"), followed.parameters["ownerId"]);
+        assert_eq!(json!(true), followed.parameters["verbose"]);
+
+        assert!(matches!(
+            dereferenced.follow_link(link("Broken"), &exchange),
+            Err(OpenApiError::RuntimeValueMissing { .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use openapiv3::v3_1::{Callback, Operation, Parameter, PathItem, ReferenceOr, StatusCode};
+use serde_json::Value;
+
+use crate::lenient::refs_in;
+use crate::operations::path_item_operations;
+use crate::parameters::parameter_data;
+use crate::{as_item, pointer, DereferencedOpenApi, OpenApiDereferencer, OpenApiError};
+
+///The checks `lint` can run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintRule {
+    MissingOperationId,
+    ///An operation with no `2XX` response
+    MissingSuccessResponse,
+    ///A parameter without a description
+    UndescribedParameter,
+    ///A component schema nothing refers to
+    UnusedSchema,
+    ///A tag declared more than once at the root of the spec
+    DuplicateTag,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 5] = [
+        LintRule::MissingOperationId,
+        LintRule::MissingSuccessResponse,
+        LintRule::UndescribedParameter,
+        LintRule::UnusedSchema,
+        LintRule::DuplicateTag,
+    ];
+}
+
+///Which rules to run. Every rule is on by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintConfig {
+    pub rules: HashSet<LintRule>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            rules: LintRule::ALL.into_iter().collect(),
+        }
+    }
+}
+
+impl LintConfig {
+    ///Turn a rule off.
+    pub fn without(mut self, rule: LintRule) -> Self {
+        self.rules.remove(&rule);
+        self
+    }
+
+    fn runs(&self, rule: LintRule) -> bool {
+        self.rules.contains(&rule)
+    }
+}
+
+///A problem found by `lint`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintFinding {
+    pub rule: LintRule,
+    ///JSON pointer to the offending part of the spec
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+impl DereferencedOpenApi {
+    ///Run every lint rule over the spec.
+    pub fn lint(&self) -> Result<Vec<LintFinding>, OpenApiError> {
+        self.lint_with(&LintConfig::default())
+    }
+
+    ///Run the configured lint rules over the spec. `UnusedSchema` reads the input document, so
+    ///with it on this fails with `InputReleased` after a release.
+    pub fn lint_with(&self, config: &LintConfig) -> Result<Vec<LintFinding>, OpenApiError> {
+        let mut findings = vec![];
+        let operations = self
+            .iter_operations()
+            .map(|(path, method, operation)| (pointer::push("/paths", path), method, operation))
+            .chain(
+                self.iter_webhook_operations()
+                    .map(|(name, method, operation)| {
+                        (pointer::push("/webhooks", name), method, operation)
+                    }),
+            );
+        for (path_location, method, operation) in operations {
+            let location = format!("{path_location}/{method}");
+            if config.runs(LintRule::MissingOperationId) && operation.operation_id.is_none() {
+                findings.push(LintFinding {
+                    rule: LintRule::MissingOperationId,
+                    location: location.clone(),
+                    message: "Operation has no operationId".into(),
+                });
+            }
+            if config.runs(LintRule::MissingSuccessResponse) && !has_success_response(operation) {
+                findings.push(LintFinding {
+                    rule: LintRule::MissingSuccessResponse,
+                    location: format!("{location}/responses"),
+                    message: "Operation has no 2XX response".into(),
+                });
+            }
+        }
+        if config.runs(LintRule::UndescribedParameter) {
+            let path_items = self
+                .openapi
+                .paths
+                .iter()
+                .flat_map(|paths| &paths.paths)
+                .map(|(path, path_item)| (pointer::push("/paths", path), path_item))
+                .chain(
+                    self.openapi
+                        .webhooks
+                        .iter()
+                        .map(|(name, path_item)| (pointer::push("/webhooks", name), path_item)),
+                )
+                .chain(
+                    self.openapi
+                        .components
+                        .iter()
+                        .flat_map(|components| &components.path_items)
+                        .map(|(name, path_item)| {
+                            (pointer::push("/components/pathItems", name), path_item)
+                        }),
+                );
+            for (location, path_item) in path_items {
+                if let Some(path_item) = as_item(path_item) {
+                    path_item_parameters(path_item, &location, &mut findings);
+                }
+            }
+            let callbacks = self
+                .openapi
+                .components
+                .iter()
+                .flat_map(|components| &components.callbacks);
+            for (name, callback) in callbacks {
+                if let Some(callback) = as_item(callback) {
+                    let location = pointer::push("/components/callbacks", name);
+                    callback_parameters(callback, &location, &mut findings);
+                }
+            }
+        }
+        if config.runs(LintRule::UnusedSchema) {
+            for name in self.unused_schemas()? {
+                findings.push(LintFinding {
+                    rule: LintRule::UnusedSchema,
+                    location: pointer::push("/components/schemas", &name),
+                    message: format!("Schema {name} is never referenced"),
+                });
+            }
+        }
+        if config.runs(LintRule::DuplicateTag) {
+            let mut seen = HashSet::new();
+            for (i, tag) in self.openapi.tags.iter().enumerate() {
+                if !seen.insert(tag.name.as_str()) {
+                    findings.push(LintFinding {
+                        rule: LintRule::DuplicateTag,
+                        location: format!("/tags/{i}"),
+                        message: format!("Tag {} is declared more than once", tag.name),
+                    });
+                }
+            }
+        }
+        Ok(findings)
+    }
+}
+
+impl OpenApiDereferencer {
+    ///Component schemas that can't be reached from any reference outside the component schemas.
+    ///Fails with `InputReleased` once the input document is gone.
+    fn unused_schemas(&self) -> Result<Vec<String>, OpenApiError> {
+        let input = self.input()?;
+        let Some(Value::Object(schemas)) = input.pointer("/components/schemas") else {
+            return Ok(vec![]);
+        };
+        //schema name -> names of the schemas it refers to
+        let mut schema_refs: HashMap<&str, Vec<String>> = HashMap::new();
+        for (name, schema) in schemas {
+            schema_refs.insert(name, self.referenced_schema_names(&refs_in(schema)));
+        }
+        let mut root_refs = vec![];
+        if let Value::Object(document) = input {
+            for (key, value) in document {
+                match (key.as_str(), value) {
+                    ("components", Value::Object(components)) => {
+                        for (kind, value) in components {
+                            if kind != "schemas" {
+                                root_refs.extend(refs_in(value));
+                            }
+                        }
+                    }
+                    _ => root_refs.extend(refs_in(value)),
+                }
+            }
+        }
+        let mut used: HashSet<String> = HashSet::new();
+        let mut pending = self.referenced_schema_names(&root_refs);
+        while let Some(name) = pending.pop() {
+            if used.insert(name.clone()) {
+                if let Some(refs) = schema_refs.get(name.as_str()) {
+                    pending.extend(refs.iter().cloned());
+                }
+            }
+        }
+        Ok(schemas
+            .keys()
+            .filter(|name| !used.contains(*name))
+            .cloned()
+            .collect())
+    }
+
+    pub(crate) fn referenced_schema_names(&self, refs: &[&str]) -> Vec<String> {
+        refs.iter()
+            .filter_map(|reference| self.locate_reference(reference).ok())
+            .filter_map(|location| {
+                let rest = location.strip_prefix("/components/schemas/")?;
+                let name = rest.split('/').next()?;
+                Some(pointer::unescape(name))
+            })
+            .collect()
+    }
+}
+
+fn has_success_response(operation: &Operation) -> bool {
+    operation.responses.as_ref().is_some_and(|responses| {
+        responses.responses.keys().any(|status| match status {
+            StatusCode::Code(code) => (200..300).contains(code),
+            StatusCode::Range(range) => *range == 2,
+        })
+    })
+}
+
+///Check the parameters of a path item, its operations and their callbacks.
+fn path_item_parameters(path_item: &PathItem, location: &str, findings: &mut Vec<LintFinding>) {
+    undescribed_parameters(&path_item.parameters, location, findings);
+    for (method, operation) in path_item_operations(path_item) {
+        let location = format!("{location}/{method}");
+        undescribed_parameters(&operation.parameters, &location, findings);
+        for (name, callback) in &operation.callbacks {
+            if let Some(callback) = as_item(callback) {
+                let location = pointer::push(&format!("{location}/callbacks"), name);
+                callback_parameters(callback, &location, findings);
+            }
+        }
+    }
+}
+
+fn callback_parameters(callback: &Callback, location: &str, findings: &mut Vec<LintFinding>) {
+    for (expression, path_item) in callback {
+        path_item_parameters(path_item, &pointer::push(location, expression), findings);
+    }
+}
+
+fn undescribed_parameters(
+    parameters: &[ReferenceOr<Parameter>],
+    location: &str,
+    findings: &mut Vec<LintFinding>,
+) {
+    for (i, parameter) in parameters.iter().enumerate() {
+        let Some(parameter) = as_item(parameter) else {
+            continue;
+        };
+        let data = parameter_data(parameter);
+        if data.description.is_none() {
+            findings.push(LintFinding {
+                rule: LintRule::UndescribedParameter,
+                location: format!("{location}/parameters/{i}"),
+                message: format!("Parameter {} has no description", data.name),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_lint() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Lint", "version": "1.0.0"},
+            "tags": [{"name": "pets"}, {"name": "pets"}],
+            "paths": {
+                "/pets/{petId}": {
+                    "parameters": [
+                        {"name": "petId", "in": "path", "required": true, "schema": {"type": "string"}}
+                    ],
+                    "get": {
+                        "operationId": "getPet",
+                        "responses": {
+                            "2XX": {
+                                "description": "ok",
+                                "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+                            }
+                        }
+                    },
+                    "delete": {
+                        "parameters": [
+                            {"name": "force", "in": "query", "description": "Delete even if adopted"}
+                        ],
+                        "responses": {"404": {"description": "missing"}},
+                        "callbacks": {
+                            "onDelete": {
+                                "{$request.query.hook}": {
+                                    "parameters": [{"name": "X-Hook", "in": "header", "schema": {"type": "string"}}]
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "webhooks": {
+                "newPet": {
+                    "parameters": [{"name": "X-Signature", "in": "header", "schema": {"type": "string"}}]
+                }
+            },
+            "components": {
+                "pathItems": {
+                    "Shared": {
+                        "get": {
+                            "parameters": [{"name": "limit", "in": "query", "schema": {"type": "integer"}}]
+                        }
+                    }
+                },
+                "schemas": {
+                    "Pet": {"type": "object", "properties": {"owner": {"$ref": "#/components/schemas/Owner"}}},
+                    "Owner": {"type": "object"},
+                    "Orphan": {"type": "object", "properties": {"pet": {"$ref": "#/components/schemas/Pet"}}}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let findings: Vec<(LintRule, String)> = dereferenced
+            .lint()?
+            .into_iter()
+            .map(|finding| (finding.rule, finding.location))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    LintRule::MissingOperationId,
+                    "/paths/~1pets~1{petId}/delete".into()
+                ),
+                (
+                    LintRule::MissingSuccessResponse,
+                    "/paths/~1pets~1{petId}/delete/responses".into()
+                ),
+                (
+                    LintRule::UndescribedParameter,
+                    "/paths/~1pets~1{petId}/parameters/0".into()
+                ),
+                (
+                    LintRule::UndescribedParameter,
+                    "/paths/~1pets~1{petId}/delete/callbacks/onDelete/{$request.query.hook}/parameters/0"
+                        .into()
+                ),
+                (
+                    LintRule::UndescribedParameter,
+                    "/webhooks/newPet/parameters/0".into()
+                ),
+                (
+                    LintRule::UndescribedParameter,
+                    "/components/pathItems/Shared/get/parameters/0".into()
+                ),
+                (LintRule::UnusedSchema, "/components/schemas/Orphan".into()),
+                (LintRule::DuplicateTag, "/tags/1".into()),
+            ],
+            findings
+        );
+
+        let config = LintConfig::default()
+            .without(LintRule::UnusedSchema)
+            .without(LintRule::DuplicateTag);
+        assert_eq!(6, dereferenced.lint_with(&config)?.len());
+        Ok(())
+    }
+}
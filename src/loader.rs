@@ -0,0 +1,548 @@
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "fs")]
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use indexmap::IndexMap;
+use serde_json::{Map, Value};
+
+use crate::components::ComponentKind;
+use crate::lenient::refs_in;
+use crate::{pointer, OpenApiError};
+
+///Loads the documents external refs point at, e.g. `pets.json` for `pets.json#/Pet`. Relative
+///refs are resolved against the URI of the document they're in before they get here, so a
+///loader sees URIs like `components/schemas/Pet.json` or `https://example.com/pets.json`.
+///Returning `UnsupportedRefFormat` leaves the ref as it is for the `ExternalRefPolicy`.
+pub trait DocumentLoader: Send + Sync {
+    fn load(&self, uri: &str) -> Result<Value, OpenApiError>;
+}
+
+impl<F> DocumentLoader for F
+where
+    F: Fn(&str) -> Result<Value, OpenApiError> + Send + Sync,
+{
+    fn load(&self, uri: &str) -> Result<Value, OpenApiError> {
+        self(uri)
+    }
+}
+
+///Documents kept in a map keyed by URI, for specs that live in a database or object store.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryDocumentLoader {
+    pub documents: HashMap<String, Value>,
+}
+
+impl InMemoryDocumentLoader {
+    pub fn with_document(mut self, uri: impl Into<String>, document: Value) -> Self {
+        self.documents.insert(uri.into(), document);
+        self
+    }
+}
+
+impl DocumentLoader for InMemoryDocumentLoader {
+    fn load(&self, uri: &str) -> Result<Value, OpenApiError> {
+        self.documents
+            .get(uri)
+            .cloned()
+            .ok_or_else(|| OpenApiError::DocumentNotFound { uri: uri.into() })
+    }
+}
+
+///Loads documents from disk, relative to `base`. Anything with a scheme other than `file://` is
+///left alone, and paths outside `base` are refused. YAML needs the `yaml` feature.
+#[cfg(feature = "fs")]
+#[derive(Debug, Clone)]
+pub struct FileDocumentLoader {
+    pub base: PathBuf,
+}
+
+#[cfg(feature = "fs")]
+impl DocumentLoader for FileDocumentLoader {
+    fn load(&self, uri: &str) -> Result<Value, OpenApiError> {
+        let relative = match uri.strip_prefix("file://") {
+            Some(relative) => relative,
+            None if uri.contains("://") => {
+                return Err(OpenApiError::UnsupportedRefFormat {
+                    reference: uri.into(),
+                })
+            }
+            None => uri,
+        };
+        let relative = Path::new(relative);
+        let inside_base = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if !inside_base {
+            return Err(OpenApiError::FileError {
+                path: relative.into(),
+                msg: format!("Outside of {}", self.base.display()),
+            });
+        }
+        let path = self.base.join(relative);
+        let contents = std::fs::read_to_string(&path).map_err(|e| OpenApiError::FileError {
+            path: path.clone(),
+            msg: e.to_string(),
+        })?;
+        parse(&path, &contents)
+    }
+}
+
+#[cfg(feature = "fs")]
+fn parse(path: &Path, contents: &str) -> Result<Value, OpenApiError> {
+    let parse_error = |msg: String| OpenApiError::FileError {
+        path: path.into(),
+        msg,
+    };
+    match path.extension().and_then(|extension| extension.to_str()) {
+        #[cfg(feature = "yaml")]
+        Some("yaml" | "yml") => {
+            serde_yaml::from_str(contents).map_err(|e| parse_error(e.to_string()))
+        }
+        #[cfg(not(feature = "yaml"))]
+        Some("yaml" | "yml") => Err(parse_error("Loading YAML needs the yaml feature".into())),
+        #[cfg(feature = "json5")]
+        Some("json5") => json5::from_str(contents).map_err(|e| parse_error(e.to_string())),
+        _ => serde_json::from_str(contents).map_err(|e| parse_error(e.to_string())),
+    }
+}
+
+///External documents with their refs already rewritten for the combined document, kept between
+///bundles by a `DerefSession` so specs sharing component files only resolve them once. Entries
+///are keyed by URI and fragment, so they only fit entry documents in the same directory.
+#[derive(Default)]
+pub(crate) struct ResolvedDocuments {
+    entries: Mutex<HashMap<(String, String), ResolvedDocument>>,
+}
+
+#[derive(Clone)]
+struct ResolvedDocument {
+    value: Value,
+    ///The hoisted components it refers to, directly or through each other
+    components: Vec<((ComponentKind, String), Value)>,
+    ///Every document that went into it
+    documents: HashSet<String>,
+}
+
+impl ResolvedDocuments {
+    pub(crate) fn len(&self) -> usize {
+        self.entries.lock().map_or(0, |entries| entries.len())
+    }
+
+    pub(crate) fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+
+    ///Drop everything resolved from `uri`.
+    pub(crate) fn evict(&self, uri: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|_, resolved| !resolved.documents.contains(uri));
+        }
+    }
+}
+
+///Pull everything `document` refers to in other documents into it. Documents under
+///`components/<kind>/` next to the entry document become components named after the file, so
+///recursive schemas keep working. Everything else is inlined where it's referenced.
+pub(crate) fn bundle_external(
+    document: Value,
+    entry: &str,
+    loader: &dyn DocumentLoader,
+    resolved: Option<&ResolvedDocuments>,
+) -> Result<Value, OpenApiError> {
+    let entry = normalize_uri(entry);
+    let mut bundler = Bundler {
+        root: parent(&entry).to_string(),
+        entry: entry.clone(),
+        loader,
+        documents: HashMap::from([(entry.clone(), document.clone())]),
+        components: IndexMap::new(),
+        inlining: HashSet::new(),
+        resolved,
+        used: vec![],
+    };
+    let mut document = document;
+    bundler.resolve_refs(&mut document, &entry)?;
+    bundler.add_components(&mut document)?;
+    Ok(document)
+}
+
+struct Bundler<'l> {
+    root: String,
+    entry: String,
+    loader: &'l dyn DocumentLoader,
+    documents: HashMap<String, Value>,
+    ///Documents hoisted into `components`, `None` while they're still being resolved
+    components: IndexMap<(ComponentKind, String), Option<Value>>,
+    ///Refs being inlined, to catch documents that include themselves
+    inlining: HashSet<(String, String)>,
+    resolved: Option<&'l ResolvedDocuments>,
+    ///Every document asked for so far, in order, to tell which ones went into a resolved value
+    used: Vec<String>,
+}
+
+impl Bundler<'_> {
+    fn load(&mut self, uri: &str) -> Result<Value, OpenApiError> {
+        self.used.push(uri.into());
+        if let Some(value) = self.documents.get(uri) {
+            return Ok(value.clone());
+        }
+        let value = self.loader.load(uri)?;
+        self.documents.insert(uri.into(), value.clone());
+        Ok(value)
+    }
+
+    ///Rewrite the refs in a value from `uri` so they make sense in the combined document.
+    fn resolve_refs(&mut self, value: &mut Value, uri: &str) -> Result<(), OpenApiError> {
+        match value {
+            Value::Object(object) => {
+                if let Some(Value::String(reference)) = object.get("$ref") {
+                    let reference = reference.clone();
+                    if let Some(resolved) = self.resolve_ref(&reference, uri)? {
+                        match resolved {
+                            Value::String(reference) => {
+                                object.insert("$ref".into(), Value::String(reference));
+                            }
+                            Value::Object(mut inlined) => {
+                                //Keywords next to the ref win, like summary and description
+                                object.remove("$ref");
+                                inlined.extend(std::mem::take(object));
+                                *object = inlined;
+                            }
+                            inlined => *value = inlined,
+                        }
+                        return Ok(());
+                    }
+                }
+                for value in object.values_mut() {
+                    self.resolve_refs(value, uri)?;
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    self.resolve_refs(value, uri)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    ///A new `$ref` string for refs into the entry document or a component document, the value
+    ///to inline for anything else, or `None` to leave the ref alone.
+    fn resolve_ref(&mut self, reference: &str, uri: &str) -> Result<Option<Value>, OpenApiError> {
+        let (target, fragment) = reference.split_once('#').unwrap_or((reference, ""));
+        let target = if target.is_empty() {
+            uri.to_string()
+        } else {
+            resolve_uri(uri, target)
+        };
+        if target == self.entry {
+            return Ok(Some(Value::String(format!("#{fragment}"))));
+        }
+        if let Some((kind, name)) = self.component(&target) {
+            let key = (kind, name.clone());
+            if !self.components.contains_key(&key) {
+                if let Some(value) = self.reuse(&target, "") {
+                    self.components.insert(key, Some(value));
+                } else {
+                    let start = self.used.len();
+                    self.components.insert(key.clone(), None);
+                    let mut value = match self.load(&target) {
+                        Err(OpenApiError::UnsupportedRefFormat { .. }) => {
+                            self.components.shift_remove(&key);
+                            return Ok(None);
+                        }
+                        value => value?,
+                    };
+                    self.resolve_refs(&mut value, &target)?;
+                    self.remember(&target, "", &value, Some(&key), start);
+                    self.components.insert(key, Some(value));
+                }
+            }
+            return Ok(Some(Value::String(format!(
+                "{}{fragment}",
+                kind.reference(&name)
+            ))));
+        }
+
+        if let Some(value) = self.reuse(&target, fragment) {
+            return Ok(Some(value));
+        }
+        let key = (target.clone(), fragment.to_string());
+        if !self.inlining.insert(key.clone()) {
+            return Err(OpenApiError::CircularReference {
+                reference: reference.into(),
+            });
+        }
+        let start = self.used.len();
+        let document = match self.load(&target) {
+            Err(OpenApiError::UnsupportedRefFormat { .. }) => return Ok(None),
+            document => document?,
+        };
+        let mut value = document
+            .pointer(&pointer::percent_decode(fragment))
+            .cloned()
+            .ok_or_else(|| OpenApiError::RefNotFound {
+                reference: reference.into(),
+            })?;
+        self.resolve_refs(&mut value, &target)?;
+        self.remember(&target, fragment, &value, None, start);
+        self.inlining.remove(&key);
+        Ok(Some(value))
+    }
+
+    ///A value an earlier bundle resolved, with the components it needs hoisted.
+    fn reuse(&mut self, target: &str, fragment: &str) -> Option<Value> {
+        let resolved = self
+            .resolved?
+            .entries
+            .lock()
+            .ok()?
+            .get(&(target.to_string(), fragment.to_string()))?
+            .clone();
+        for (key, value) in resolved.components {
+            self.components.entry(key).or_insert(Some(value));
+        }
+        self.used.extend(resolved.documents);
+        Some(resolved.value)
+    }
+
+    ///Keep a resolved value for later bundles. `start` is where its documents start in `used`.
+    fn remember(
+        &self,
+        target: &str,
+        fragment: &str,
+        value: &Value,
+        own: Option<&(ComponentKind, String)>,
+        start: usize,
+    ) {
+        let Some(resolved) = self.resolved else {
+            return;
+        };
+        let Some(components) = self.dependencies(value, own) else {
+            return;
+        };
+        let documents = self.used[start..].iter().cloned().collect();
+        if let Ok(mut entries) = resolved.entries.lock() {
+            entries.insert(
+                (target.into(), fragment.into()),
+                ResolvedDocument {
+                    value: value.clone(),
+                    components,
+                    documents,
+                },
+            );
+        }
+    }
+
+    ///The hoisted components `value` refers to, directly or through each other, or `None` if one
+    ///of them is still being resolved and can't be kept yet.
+    fn dependencies(
+        &self,
+        value: &Value,
+        own: Option<&(ComponentKind, String)>,
+    ) -> Option<Vec<((ComponentKind, String), Value)>> {
+        let mut seen: HashSet<&(ComponentKind, String)> = own.into_iter().collect();
+        let mut found = vec![];
+        let mut pending = vec![value];
+        while let Some(value) = pending.pop() {
+            for reference in refs_in(value) {
+                for (key, component) in &self.components {
+                    let target = key.0.reference(&key.1);
+                    let points_at = reference
+                        .strip_prefix(target.as_str())
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+                    if !points_at || !seen.insert(key) {
+                        continue;
+                    }
+                    let component = component.as_ref()?;
+                    found.push((key.clone(), component.clone()));
+                    pending.push(component);
+                }
+            }
+        }
+        Some(found)
+    }
+
+    ///The component a document is by convention, `components/schemas/Pet.yaml` is the `Pet`
+    ///schema.
+    fn component(&self, uri: &str) -> Option<(ComponentKind, String)> {
+        let relative = uri.strip_prefix(&self.root)?.strip_prefix("components/")?;
+        let (section, file) = relative.split_once('/')?;
+        if file.contains('/') {
+            return None;
+        }
+        let kind = ComponentKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == section)?;
+        let name = file.rsplit_once('.').map_or(file, |(name, _)| name);
+        Some((kind, name.to_string()))
+    }
+
+    ///Put the hoisted documents into `components`. A slot that's just a ref to the document,
+    ///which is the usual way of listing them in the entry document, is replaced.
+    fn add_components(self, document: &mut Value) -> Result<(), OpenApiError> {
+        let Value::Object(root) = document else {
+            return Ok(());
+        };
+        for ((kind, name), value) in self.components {
+            let Some(value) = value else {
+                continue;
+            };
+            let section = root
+                .entry("components")
+                .or_insert_with(|| Value::Object(Map::new()))
+                .as_object_mut()
+                .and_then(|components| {
+                    components
+                        .entry(kind.as_str())
+                        .or_insert_with(|| Value::Object(Map::new()))
+                        .as_object_mut()
+                });
+            let Some(section) = section else {
+                continue;
+            };
+            let reference = kind.reference(&name);
+            let taken = section.get(&name).is_some_and(|existing| {
+                existing.get("$ref").and_then(Value::as_str) != Some(reference.as_str())
+            });
+            if taken {
+                return Err(OpenApiError::ComponentExists { kind, name });
+            }
+            section.insert(name, value);
+        }
+        Ok(())
+    }
+}
+
+///Everything up to and including the last `/`.
+fn parent(uri: &str) -> &str {
+    uri.rfind('/').map_or("", |i| &uri[..=i])
+}
+
+fn resolve_uri(base: &str, relative: &str) -> String {
+    if relative.contains("://") || relative.starts_with('/') {
+        normalize_uri(relative)
+    } else {
+        normalize_uri(&format!("{}{relative}", parent(base)))
+    }
+}
+
+///Resolve `.` and `..` segments, so the same document always has the same URI.
+fn normalize_uri(uri: &str) -> String {
+    let (prefix, path) = match uri.find("://") {
+        Some(i) => uri.split_at(uri[i + 3..].find('/').map_or(uri.len(), |j| i + 3 + j)),
+        None => ("", uri),
+    };
+    let mut segments: Vec<&str> = vec![];
+    for segment in path.split('/') {
+        match segment {
+            "." => {}
+            ".." if segments
+                .last()
+                .is_some_and(|last| !last.is_empty() && *last != "..") =>
+            {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    format!("{prefix}{}", segments.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{as_item, OpenApiDereferencer};
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    pub fn test_document_loader() -> Result<()> {
+        let spec = json!({
+            "openapi": "3.1.0",
+            "info": {"title": "Loader", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {"$ref": "responses/pets.json#/Pets"}
+                        }
+                    }
+                }
+            }
+        });
+        let loader = InMemoryDocumentLoader::default()
+            .with_document(
+                "responses/pets.json",
+                json!({
+                    "Pets": {"$ref": "#/All%20Pets"},
+                    "All Pets": {
+                        "description": "pets",
+                        "content": {"application/json": {"schema": {"$ref": "../components/schemas/Pet.json"}}}
+                    }
+                }),
+            )
+            .with_document(
+                "components/schemas/Pet.json",
+                json!({"type": "object", "properties": {"parent": {"$ref": "./Pet.json"}}}),
+            );
+        let dereferenced = OpenApiDereferencer::builder()
+            .document_loader(loader)
+            .build_from_value(spec.clone())?
+            .dereference()?;
+        let json = &dereferenced.json;
+        assert_eq!(
+            "#/components/schemas/Pet",
+            json["paths"]["/pets"]["get"]["responses"]["200"]["content"]["application/json"]
+                ["schema"]["$ref"]
+        );
+        assert_eq!(
+            "#/components/schemas/Pet",
+            json["components"]["schemas"]["Pet"]["properties"]["parent"]["$ref"]
+        );
+        let responses = &dereferenced.openapi.paths.as_ref().unwrap().paths["/pets"];
+        assert!(as_item(responses).is_some());
+
+        let missing = OpenApiDereferencer::builder()
+            .document_loader(|uri: &str| -> Result<Value, OpenApiError> {
+                Err(OpenApiError::DocumentNotFound { uri: uri.into() })
+            })
+            .build_from_value(spec);
+        assert!(matches!(
+            missing,
+            Err(OpenApiError::DocumentNotFound { uri }) if uri == "responses/pets.json"
+        ));
+        Ok(())
+    }
+    #[cfg(feature = "fs")]
+    #[test]
+    pub fn test_file_document_loader_stays_in_base() -> Result<()> {
+        let loader = FileDocumentLoader {
+            base: "oai_examples/split".into(),
+        };
+        assert_eq!(
+            json!({"Name": {"type": "string"}}),
+            loader.load("common.json")?
+        );
+        assert_eq!(
+            json!({"Name": {"type": "string"}}),
+            loader.load("file://./common.json")?
+        );
+        for uri in [
+            "../api.github.com.json",
+            "paths/../../api.github.com.json",
+            "/etc/passwd",
+            "file:///etc/passwd",
+        ] {
+            assert!(
+                matches!(loader.load(uri), Err(OpenApiError::FileError { .. })),
+                "{uri}"
+            );
+        }
+        Ok(())
+    }
+}
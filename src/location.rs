@@ -0,0 +1,180 @@
+use std::fmt;
+
+use openapiv3::v3_1::{
+    Callback, Example, Header, Link, Parameter, PathItem, RequestBody, Response, SecurityScheme,
+};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{pointer, OpenApiError};
+
+///Where in a document parsing failed. Syntax errors know the line and column, while a document
+///that's valid JSON or YAML but not a valid spec knows the JSON pointer of the deepest part of
+///it that could be read.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ParseLocation {
+    ///1-based
+    pub line: Option<usize>,
+    ///1-based
+    pub column: Option<usize>,
+    pub pointer: Option<String>,
+}
+
+impl ParseLocation {
+    pub(crate) fn at(line: usize, column: usize) -> Self {
+        ParseLocation {
+            line: Some(line),
+            column: Some(column),
+            pointer: None,
+        }
+    }
+
+    ///The line and column of a byte offset into `content`.
+    #[cfg_attr(not(feature = "simd-json"), allow(dead_code))]
+    pub(crate) fn at_offset(content: &[u8], offset: usize) -> Self {
+        let before = &content[..offset.min(content.len())];
+        let line = before.iter().filter(|byte| **byte == b'\n').count() + 1;
+        let line_start = before
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |i| i + 1);
+        ParseLocation::at(line, offset - line_start + 1)
+    }
+}
+
+impl fmt::Display for ParseLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = vec![];
+        if let Some(line) = self.line {
+            parts.push(format!("line {line}"));
+        }
+        if let Some(column) = self.column {
+            parts.push(format!("column {column}"));
+        }
+        if let Some(pointer) = &self.pointer {
+            parts.push(format!("{pointer:?}"));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+///Deserialize `json`, working out where in it a failure happened.
+pub(crate) fn deserialize<T: DeserializeOwned>(json: &Value) -> Result<T, OpenApiError> {
+    //Tracking the path is a lot slower, so it's only done to explain a failure
+    T::deserialize(json).map_err(|e| {
+        let (pointer, msg) =
+            locate::<T>(json, "").unwrap_or_else(|| (String::new(), e.to_string()));
+        OpenApiError::ParsingError {
+            msg: format!("Error parsing from serde to OpenApi {msg}"),
+            location: Some(ParseLocation {
+                pointer: Some(pointer),
+                ..ParseLocation::default()
+            }),
+        }
+    })
+}
+
+///The pointer, relative to `json`, of the deepest part of it that couldn't be read as `T`, and
+///why. `prefix` is where `json` is in the document.
+fn locate<T: DeserializeOwned>(json: &Value, prefix: &str) -> Option<(String, String)> {
+    let e = serde_path_to_error::deserialize::<_, T>(json).err()?;
+    let mut location = String::new();
+    for segment in e.path().iter() {
+        match segment {
+            serde_path_to_error::Segment::Seq { index } => {
+                location = pointer::push(&location, &index.to_string())
+            }
+            serde_path_to_error::Segment::Map { key } => location = pointer::push(&location, key),
+            _ => break,
+        }
+    }
+    //Untagged enums like `ReferenceOr` hide where inside them things went wrong, so try what
+    //they hold on its own
+    let item = json
+        .pointer(&location)
+        .filter(|item| item.get("$ref").is_none())
+        .and_then(|item| locate_item(item, &format!("{prefix}{location}")));
+    Some(match item {
+        Some((inner, msg)) => (format!("{location}{inner}"), msg),
+        None => (location, e.into_inner().to_string()),
+    })
+}
+
+///Read `item` as whatever a `ReferenceOr` at `location` holds, going by where it is.
+fn locate_item(item: &Value, location: &str) -> Option<(String, String)> {
+    let mut segments = location.rsplit('/');
+    let last = segments.next()?;
+    if last == "requestBody" {
+        return locate::<RequestBody>(item, location);
+    }
+    match segments.next()? {
+        "paths" | "webhooks" | "pathItems" => locate::<PathItem>(item, location),
+        "parameters" => locate::<Parameter>(item, location),
+        "responses" => locate::<Response>(item, location),
+        "requestBodies" => locate::<RequestBody>(item, location),
+        "headers" => locate::<Header>(item, location),
+        "examples" => locate::<Example>(item, location),
+        "links" => locate::<Link>(item, location),
+        "callbacks" => locate::<Callback>(item, location),
+        "securitySchemes" => locate::<SecurityScheme>(item, location),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_parse_error_location() -> Result<()> {
+        let broken = "{\n  \"openapi\": \"3.1.0\",\n  \"info\": {\"title\": \"Broken\",}\n}";
+        let Err(OpenApiError::ParsingError {
+            location: Some(location),
+            ..
+        }) = OpenApiDereferencer::from_str(broken)
+        else {
+            panic!("expected a parsing error");
+        };
+        assert_eq!(Some(3), location.line);
+
+        let invalid = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Invalid", "version": 1},
+            "paths": {}
+        }"##;
+        let Err(OpenApiError::ParsingError {
+            location: Some(location),
+            ..
+        }) = OpenApiDereferencer::from_str(invalid)
+        else {
+            panic!("expected a parsing error");
+        };
+        assert_eq!(Some("/info/version".to_string()), location.pointer);
+        assert_eq!(None, location.line);
+
+        let nested = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Nested", "version": "1.0.0"},
+            "paths": {
+                "/pets": {"get": {"responses": {"200": {"description": 5}}}}
+            }
+        }"##;
+        let Err(OpenApiError::ParsingError {
+            location: Some(location),
+            ..
+        }) = OpenApiDereferencer::from_str(nested)
+        else {
+            panic!("expected a parsing error");
+        };
+        assert_eq!(
+            Some("/paths/~1pets/get/responses/200/description".to_string()),
+            location.pointer
+        );
+        Ok(())
+    }
+}
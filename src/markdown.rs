@@ -0,0 +1,355 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use indexmap::IndexMap;
+use openapiv3::schemars::schema::Schema as SchemarsSchema;
+use openapiv3::v3_1::{MediaType, Operation, Parameter, ParameterSchemaOrContent, Response};
+use serde_json::Value;
+
+use crate::parameters::{parameter_data, parameter_location};
+use crate::{as_item, DereferencedOpenApi, Method, OpenApiError};
+
+///Operations without a tag go in this file.
+const UNTAGGED: &str = "default";
+///Files that are always there, which tags can't have.
+const RESERVED: [&str; 2] = ["index", "schemas"];
+
+impl DereferencedOpenApi {
+    ///Render the spec as Markdown reference docs, returned as file name -> contents. There's an
+    ///`index.md` with the title, servers and a list of tags, a file per tag with its operations,
+    ///and `schemas.md` with every schema in `components`. Since everything's dereferenced,
+    ///schemas are written out in full wherever they're used. Tags whose file names would clash
+    ///with each other or the fixed files get a number on the end, like `pets-2.md`.
+    pub fn to_markdown(&self) -> Result<IndexMap<String, String>, OpenApiError> {
+        //(tag, description, operations)
+        let mut pages: Vec<(String, Option<&String>, Vec<(&str, Method, &Operation)>)> = self
+            .operations_by_tag()
+            .into_iter()
+            .filter(|(_, operations)| !operations.is_empty())
+            .map(|(tag, operations)| {
+                let description = self
+                    .openapi
+                    .tags
+                    .iter()
+                    .find(|declared| declared.name == tag)
+                    .and_then(|declared| declared.description.as_ref());
+                (tag, description, operations)
+            })
+            .collect();
+        let untagged: Vec<_> = self
+            .iter_operations()
+            .filter(|(_, _, operation)| operation.tags.is_empty())
+            .collect();
+        if !untagged.is_empty() {
+            pages.push((UNTAGGED.into(), None, untagged));
+        }
+
+        let info = &self.openapi.info;
+        let mut index = format!("# {}\n\nVersion {}\n", info.title, info.version);
+        if let Some(description) = &info.description {
+            let _ = write!(index, "\n{description}\n");
+        }
+        if !self.openapi.servers.is_empty() {
+            index.push_str("\n## Servers\n\n");
+            for server in &self.openapi.servers {
+                let _ = writeln!(index, "- `{}`", server.url);
+            }
+        }
+
+        let mut files = IndexMap::new();
+        index.push_str("\n## Operations\n\n");
+        let mut used = HashSet::new();
+        for (tag, description, operations) in &pages {
+            let file = format!("{}.md", unique_name(tag, &mut used));
+            let _ = writeln!(index, "- [{tag}]({file})");
+            let mut page = format!("# {tag}\n");
+            if let Some(description) = description {
+                let _ = write!(page, "\n{description}\n");
+            }
+            for (path, method, operation) in operations {
+                page.push('\n');
+                page.push_str(&self.operation_markdown(path, *method, operation)?);
+            }
+            files.insert(file, page);
+        }
+
+        let schemas = self
+            .openapi
+            .components
+            .iter()
+            .flat_map(|components| &components.schemas);
+        let mut schemas_page = String::from("# Schemas\n");
+        let mut any_schemas = false;
+        for (name, schema) in schemas {
+            any_schemas = true;
+            let _ = write!(schemas_page, "\n## {name}\n\n");
+            schemas_page.push_str(&schema_block(&schema.json_schema));
+        }
+        if any_schemas {
+            index.push_str("- [Schemas](schemas.md)\n");
+            files.insert("schemas.md".into(), schemas_page);
+        }
+        let mut all = IndexMap::new();
+        all.insert("index.md".to_string(), index);
+        all.extend(files);
+        Ok(all)
+    }
+
+    fn operation_markdown(
+        &self,
+        path: &str,
+        method: Method,
+        operation: &Operation,
+    ) -> Result<String, OpenApiError> {
+        let mut markdown = format!("## {} `{path}`\n", method.as_str().to_ascii_uppercase());
+        if operation.deprecated {
+            markdown.push_str("\n**Deprecated**\n");
+        }
+        if let Some(summary) = &operation.summary {
+            let _ = write!(markdown, "\n{summary}\n");
+        }
+        if let Some(description) = &operation.description {
+            let _ = write!(markdown, "\n{description}\n");
+        }
+
+        let parameters = self.effective_parameters(path, method)?;
+        if !parameters.is_empty() {
+            markdown.push_str("\n### Parameters\n\n");
+            markdown.push_str("| Name | In | Type | Required | Description |\n");
+            markdown.push_str("| --- | --- | --- | --- | --- |\n");
+            for parameter in parameters {
+                markdown.push_str(&parameter_row(parameter));
+            }
+        }
+
+        if let Some(request_body) = operation.request_body.as_ref().and_then(as_item) {
+            markdown.push_str("\n### Request body\n");
+            if request_body.required {
+                markdown.push_str("\nRequired\n");
+            }
+            if let Some(description) = &request_body.description {
+                let _ = write!(markdown, "\n{description}\n");
+            }
+            markdown.push_str(&content_markdown(&request_body.content));
+        }
+
+        if let Some(responses) = &operation.responses {
+            markdown.push_str("\n### Responses\n");
+            let statuses = responses
+                .responses
+                .iter()
+                .map(|(status, response)| (status.to_string(), response))
+                .chain(
+                    responses
+                        .default
+                        .iter()
+                        .map(|response| ("default".to_string(), response)),
+                );
+            for (status, response) in statuses {
+                if let Some(response) = as_item(response) {
+                    markdown.push_str(&response_markdown(&status, response));
+                }
+            }
+        }
+        Ok(markdown)
+    }
+}
+
+fn parameter_row(parameter: &Parameter) -> String {
+    let data = parameter_data(parameter);
+    let schema_type = match &data.format {
+        ParameterSchemaOrContent::Schema(schema) => schema_type(&schema.json_schema),
+        ParameterSchemaOrContent::Content(content) => content
+            .keys()
+            .next()
+            .map(|media_type| format!("`{media_type}`"))
+            .unwrap_or_default(),
+    };
+    format!(
+        "| `{}` | {} | {} | {} | {} |\n",
+        data.name,
+        parameter_location(parameter),
+        schema_type,
+        if data.required { "yes" } else { "no" },
+        cell(data.description.as_deref().unwrap_or_default()),
+    )
+}
+
+fn response_markdown(status: &str, response: &Response) -> String {
+    let mut markdown = format!("\n#### {status}\n\n{}\n", response.description);
+    markdown.push_str(&content_markdown(&response.content));
+    markdown
+}
+
+fn content_markdown(content: &IndexMap<String, MediaType>) -> String {
+    let mut markdown = String::new();
+    for (media_type, content) in content {
+        let _ = write!(markdown, "\n`{media_type}`\n");
+        if let Some(schema) = &content.schema {
+            markdown.push('\n');
+            markdown.push_str(&schema_block(&schema.json_schema));
+        }
+    }
+    markdown
+}
+
+///The schema as a JSON code block.
+fn schema_block(schema: &SchemarsSchema) -> String {
+    let json = serde_json::to_string_pretty(schema).unwrap_or_default();
+    format!("```json\n{json}\n```\n")
+}
+
+///A short description of a schema's type for a table, e.g. `string (date-time)`.
+fn schema_type(schema: &SchemarsSchema) -> String {
+    let schema = serde_json::to_value(schema).unwrap_or_default();
+    let types = match schema.get("type") {
+        Some(Value::String(instance_type)) => instance_type.clone(),
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(" \\| "),
+        _ => return String::new(),
+    };
+    match schema.get("format").and_then(Value::as_str) {
+        Some(format) => format!("{types} ({format})"),
+        None => types,
+    }
+}
+
+///Text that's safe to put in a table cell.
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+///A file name for a tag that isn't in `used` or one of the fixed files, which is added to
+///`used`.
+fn unique_name(tag: &str, used: &mut HashSet<String>) -> String {
+    let mut base = slug(tag);
+    if base.is_empty() {
+        base = "tag".into();
+    }
+    let mut name = base.clone();
+    let mut n = 1;
+    while RESERVED.contains(&name.as_str()) || used.contains(&name) {
+        n += 1;
+        name = format!("{base}-{n}");
+    }
+    used.insert(name.clone());
+    name
+}
+
+///A file name for a tag.
+fn slug(tag: &str) -> String {
+    let slug: String = tag
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    slug.trim_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_to_markdown() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Pets", "version": "1.0.0", "description": "All about pets"},
+            "servers": [{"url": "https://api.example.com"}],
+            "tags": [{"name": "Pet Store", "description": "Buying pets"}],
+            "paths": {
+                "/pets/{petId}": {
+                    "get": {
+                        "tags": ["Pet Store"],
+                        "summary": "Get a pet",
+                        "parameters": [
+                            {"name": "petId", "in": "path", "required": true, "description": "The pet's id | name", "schema": {"type": "string", "format": "uuid"}}
+                        ],
+                        "responses": {
+                            "200": {
+                                "description": "The pet",
+                                "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+                            }
+                        }
+                    }
+                },
+                "/health": {"get": {"responses": {"default": {"description": "Healthy"}}}}
+            },
+            "components": {
+                "schemas": {"Pet": {"type": "object", "properties": {"name": {"type": "string"}}}}
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let files = dereferenced.to_markdown()?;
+        assert_eq!(
+            vec!["index.md", "pet-store.md", "default.md", "schemas.md"],
+            files.keys().collect::<Vec<_>>()
+        );
+        let index = &files["index.md"];
+        assert!(index.starts_with("# Pets\n\nVersion 1.0.0\n\nAll about pets\n"));
+        assert!(index.contains("- `https://api.example.com`"));
+        assert!(index.contains("- [Pet Store](pet-store.md)"));
+        assert!(index.contains("- [Schemas](schemas.md)"));
+
+        let pets = &files["pet-store.md"];
+        assert!(pets.starts_with("# Pet Store\n\nBuying pets\n"));
+        assert!(pets.contains("## GET `/pets/{petId}`\n\nGet a pet\n"));
+        assert!(pets.contains("| `petId` | path | string (uuid) | yes | The pet's id \\| name |"));
+        //The ref to Pet is written out in full
+        assert!(pets.contains("#### 200\n\nThe pet\n\n`application/json`\n\n```json\n"));
+        assert!(pets.contains("\"name\": {"));
+        assert!(files["default.md"].contains("#### default\n\nHealthy\n"));
+        assert!(files["schemas.md"].contains("## Pet\n\n```json\n"));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_to_markdown_file_names() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Names", "version": "1.0.0"},
+            "tags": [{"name": "default", "description": "Tagged default"}, {"name": "Index"}, {"name": "Pet Store"}, {"name": "pet store"}, {"name": "ペット"}],
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "tags": ["default", "Index", "Pet Store", "pet store", "ペット"],
+                        "responses": {"200": {"description": "ok"}}
+                    }
+                },
+                "/health": {"get": {"responses": {"200": {"description": "ok"}}}}
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let files = dereferenced.to_markdown()?;
+        assert_eq!(
+            vec![
+                "index.md",
+                "default.md",
+                "index-2.md",
+                "pet-store.md",
+                "pet-store-2.md",
+                "tag.md",
+                "default-2.md"
+            ],
+            files.keys().collect::<Vec<_>>()
+        );
+        assert!(files["index.md"].starts_with("# Names\n"));
+        assert!(files["default.md"].contains("Tagged default"));
+        assert!(files["default.md"].contains("`/pets`"));
+        assert!(!files["default-2.md"].contains("Tagged default"));
+        assert!(files["default-2.md"].contains("`/health`"));
+        assert!(files["index.md"].contains("- [ペット](tag.md)"));
+        Ok(())
+    }
+}
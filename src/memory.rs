@@ -0,0 +1,126 @@
+use std::io;
+use std::mem::size_of;
+
+use serde_json::Value;
+
+use crate::{DereferencedOpenApi, OpenApiDereferencer, OpenApiError};
+
+impl OpenApiDereferencer {
+    ///Roughly how many bytes the spec is holding on to: the input document, the typed spec and
+    ///the cache of resolved values. It's an estimate for keeping an eye on long running
+    ///processes, not an exact count of allocations.
+    pub fn memory_usage_estimate(&self) -> usize {
+        let mut typed = ByteCounter(0);
+        //The serialized size tracks the typed spec's size well enough
+        let _ = serde_json::to_writer(&mut typed, &self.openapi);
+        value_size(&self.json) + typed.0 + self.serde_values.size_estimate()
+    }
+
+    ///Drop the input document and the cache of resolved values, which aren't needed to use a
+    ///dereferenced spec. Anything that reads the input document afterwards, like
+    ///`dereference_patch`, `list_references` or `rename_component`, fails with `InputReleased`.
+    pub fn release_input(&mut self) {
+        self.json = Value::Null;
+        self.serde_values = Box::default();
+        self.anchors = Default::default();
+    }
+
+    ///The input document, unless `release_input` dropped it.
+    pub(crate) fn input(&self) -> Result<&Value, OpenApiError> {
+        if self.json.is_null() {
+            return Err(OpenApiError::InputReleased);
+        }
+        Ok(&self.json)
+    }
+}
+
+impl DereferencedOpenApi {
+    ///See `OpenApiDereferencer::release_input`.
+    pub fn release_input(&mut self) {
+        self.0.release_input();
+        self.invalidate_caches();
+    }
+}
+
+///Roughly how many bytes of heap a JSON value takes up.
+pub(crate) fn value_size(value: &Value) -> usize {
+    size_of::<Value>()
+        + match value {
+            Value::String(s) => s.capacity(),
+            Value::Array(values) => values.iter().map(value_size).sum(),
+            Value::Object(object) => object
+                .iter()
+                .map(|(key, value)| size_of::<String>() + key.capacity() + value_size(value))
+                .sum(),
+            _ => 0,
+        }
+}
+
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_release_input() -> Result<()> {
+        let spec = std::fs::read_to_string("oai_examples/webhook-example.json")?;
+        let dereferenced = OpenApiDereferencer::from_str(&spec)?.dereference()?;
+        let kept = dereferenced.memory_usage_estimate();
+
+        let mut released = OpenApiDereferencer::builder()
+            .release_input(true)
+            .build_from_str(&spec)?
+            .dereference()?;
+        assert!(released.json.is_null());
+        assert!(released.memory_usage_estimate() < kept);
+        assert!(matches!(
+            released.dereference_patch(),
+            Err(OpenApiError::InputReleased)
+        ));
+        assert!(matches!(
+            released.list_references(),
+            Err(OpenApiError::InputReleased)
+        ));
+        assert!(matches!(
+            released.component_usage(),
+            Err(OpenApiError::InputReleased)
+        ));
+        assert!(matches!(
+            released.unknown_fields(),
+            Err(OpenApiError::InputReleased)
+        ));
+        assert!(matches!(
+            released.schema_dependency_order(),
+            Err(OpenApiError::InputReleased)
+        ));
+        assert!(matches!(
+            released.duplicate_schemas(),
+            Err(OpenApiError::InputReleased)
+        ));
+        assert!(matches!(
+            released.rename_component(crate::ComponentKind::Schemas, "Pet", "Animal"),
+            Err(OpenApiError::InputReleased)
+        ));
+        assert!(matches!(released.lint(), Err(OpenApiError::InputReleased)));
+
+        let mut dereferenced = dereferenced;
+        dereferenced.release_input();
+        assert!(dereferenced.json.is_null());
+        assert!(dereferenced.memory_usage_estimate() < kept);
+        Ok(())
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+use crate::{pointer, OpenApiDereferencer, OpenApiError, Warning};
+
+const COMPONENT_SECTIONS: [&str; 10] = [
+    "schemas",
+    "responses",
+    "parameters",
+    "examples",
+    "requestBodies",
+    "headers",
+    "securitySchemes",
+    "links",
+    "callbacks",
+    "pathItems",
+];
+
+impl OpenApiDereferencer {
+    ///Combine several specs into one. `info`, `security` and `externalDocs` come from the first
+    ///spec. Paths, webhooks, servers and tags are unioned; when two specs define the same
+    ///operation the first one wins. Components with the same name are deduplicated if they're
+    ///identical and otherwise renamed with the title of the spec they came from, rewriting that
+    ///spec's refs to match. Conflicts are reported as warnings on the merged spec, which still
+    ///needs to be dereferenced.
+    pub fn merge(specs: Vec<Self>) -> Result<Self, OpenApiError> {
+        let mut specs = specs.into_iter();
+        let first = specs.next().ok_or(OpenApiError::NothingToMerge)?;
+        let mut warnings = vec![];
+        let mut merged = match first.json {
+            Value::Object(object) => object,
+            _ => Map::new(),
+        };
+        for (i, spec) in specs.enumerate() {
+            let Value::Object(mut other) = spec.json else {
+                continue;
+            };
+            let prefix = namespace(&other, i + 1);
+            let renames = component_renames(&merged, &other, &prefix, &mut warnings);
+            if !renames.is_empty() {
+                for value in other.values_mut() {
+                    rewrite_refs(value, &renames);
+                }
+            }
+            merge_components(&mut merged, &mut other, &renames);
+            for key in ["paths", "webhooks"] {
+                merge_path_items(&mut merged, &mut other, key, &mut warnings);
+            }
+            merge_list(&mut merged, &mut other, "servers", |a, b| {
+                a.get("url") == b.get("url")
+            });
+            merge_list(&mut merged, &mut other, "tags", |a, b| {
+                a.get("name") == b.get("name")
+            });
+        }
+        let mut merged = OpenApiDereferencer::from_value(Value::Object(merged))?;
+        merged.warnings = warnings;
+        Ok(merged)
+    }
+}
+
+///The prefix used for renamed components, from the spec's title.
+fn namespace(spec: &Map<String, Value>, index: usize) -> String {
+    let title: String = spec
+        .get("info")
+        .and_then(|info| info.get("title"))
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect();
+    if title.is_empty() {
+        format!("Spec{index}")
+    } else {
+        title
+    }
+}
+
+///Work out new names for the components in `other` that clash with different components of the
+///same name in `merged`. Returns old ref -> new ref.
+fn component_renames(
+    merged: &Map<String, Value>,
+    other: &Map<String, Value>,
+    prefix: &str,
+    warnings: &mut Vec<Warning>,
+) -> HashMap<String, String> {
+    let mut renames = HashMap::new();
+    for section in COMPONENT_SECTIONS {
+        let (Some(existing), Some(incoming)) = (
+            merged
+                .get("components")
+                .and_then(|c| c.get(section))
+                .and_then(Value::as_object),
+            other
+                .get("components")
+                .and_then(|c| c.get(section))
+                .and_then(Value::as_object),
+        ) else {
+            continue;
+        };
+        for (name, value) in incoming {
+            if existing.get(name).is_some_and(|existing| existing != value) {
+                let mut renamed = format!("{prefix}_{name}");
+                let mut n = 2;
+                while existing.contains_key(&renamed) || incoming.contains_key(&renamed) {
+                    renamed = format!("{prefix}_{name}{n}");
+                    n += 1;
+                }
+                let base = format!("#/components/{section}");
+                renames.insert(pointer::push(&base, name), pointer::push(&base, &renamed));
+                warnings.push(Warning::ComponentRenamed {
+                    section: section.into(),
+                    name: name.clone(),
+                    renamed_to: renamed,
+                });
+            }
+        }
+    }
+    renames
+}
+
+pub(crate) fn rewrite_refs(value: &mut Value, renames: &HashMap<String, String>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                match value {
+                    Value::String(reference) if key == "$ref" => {
+                        if let Some(renamed) = renamed_ref(reference, renames) {
+                            *reference = renamed;
+                        }
+                    }
+                    value => rewrite_refs(value, renames),
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                rewrite_refs(value, renames);
+            }
+        }
+        _ => {}
+    }
+}
+
+///Refs can point inside a component too, e.g. `#/components/schemas/Pet/properties/name`.
+pub(crate) fn renamed_ref(reference: &str, renames: &HashMap<String, String>) -> Option<String> {
+    let reference = &pointer::percent_decode(reference);
+    if let Some(renamed) = renames.get(reference) {
+        return Some(renamed.clone());
+    }
+    renames.iter().find_map(|(from, to)| {
+        reference
+            .strip_prefix(from.as_str())
+            .filter(|rest| rest.starts_with('/'))
+            .map(|rest| format!("{to}{rest}"))
+    })
+}
+
+fn merge_components(
+    merged: &mut Map<String, Value>,
+    other: &mut Map<String, Value>,
+    renames: &HashMap<String, String>,
+) {
+    let Some(Value::Object(incoming)) = other.remove("components") else {
+        return;
+    };
+    let components = merged
+        .entry("components")
+        .or_insert_with(|| Value::Object(Map::new()));
+    let Value::Object(components) = components else {
+        return;
+    };
+    for (section, values) in incoming {
+        let Value::Object(values) = values else {
+            continue;
+        };
+        let existing = components
+            .entry(section.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+        let Value::Object(existing) = existing else {
+            continue;
+        };
+        let base = format!("#/components/{section}");
+        for (name, value) in values {
+            let name = renames
+                .get(&pointer::push(&base, &name))
+                .and_then(|renamed| renamed.rsplit('/').next())
+                .map(pointer::unescape)
+                .unwrap_or(name);
+            existing.entry(name).or_insert(value);
+        }
+    }
+}
+
+fn merge_path_items(
+    merged: &mut Map<String, Value>,
+    other: &mut Map<String, Value>,
+    key: &str,
+    warnings: &mut Vec<Warning>,
+) {
+    let Some(Value::Object(incoming)) = other.remove(key) else {
+        return;
+    };
+    let Value::Object(existing) = merged
+        .entry(key)
+        .or_insert_with(|| Value::Object(Map::new()))
+    else {
+        return;
+    };
+    for (path, path_item) in incoming {
+        match (existing.get_mut(&path), path_item) {
+            (Some(Value::Object(existing_item)), Value::Object(path_item)) => {
+                for (field, value) in path_item {
+                    match existing_item.get(&field) {
+                        None => {
+                            existing_item.insert(field, value);
+                        }
+                        Some(existing_value) if *existing_value == value => {}
+                        Some(_) => warnings.push(Warning::MergeConflict {
+                            location: format!("/{key}/{}/{field}", pointer::escape(&path)),
+                        }),
+                    }
+                }
+            }
+            (Some(_), _) => warnings.push(Warning::MergeConflict {
+                location: format!("/{key}/{}", pointer::escape(&path)),
+            }),
+            (None, path_item) => {
+                existing.insert(path, path_item);
+            }
+        }
+    }
+}
+
+fn merge_list(
+    merged: &mut Map<String, Value>,
+    other: &mut Map<String, Value>,
+    key: &str,
+    same: impl Fn(&Value, &Value) -> bool,
+) {
+    let Some(Value::Array(incoming)) = other.remove(key) else {
+        return;
+    };
+    let Value::Array(existing) = merged.entry(key).or_insert_with(|| Value::Array(vec![])) else {
+        return;
+    };
+    for value in incoming {
+        if !existing.iter().any(|existing| same(existing, &value)) {
+            existing.push(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_merge() -> Result<()> {
+        let pets = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Pets", "version": "1.0.0"},
+            "tags": [{"name": "pets"}],
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "ok",
+                                "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Item"}}}
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Item": {"type": "object", "properties": {"name": {"type": "string"}}},
+                    "Error": {"type": "object"}
+                }
+            }
+        }"##;
+        let stores = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Store API", "version": "1.0.0"},
+            "tags": [{"name": "pets"}, {"name": "stores"}],
+            "paths": {
+                "/pets": {
+                    "get": {"responses": {"200": {"description": "different"}}},
+                    "post": {"responses": {"201": {"description": "created"}}}
+                },
+                "/stores": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "ok",
+                                "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Item/properties/sku"}}}
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Item": {"type": "object", "properties": {"sku": {"type": "integer"}}},
+                    "Error": {"type": "object"}
+                }
+            }
+        }"##;
+        let merged = OpenApiDereferencer::merge(vec![
+            OpenApiDereferencer::from_str(pets)?,
+            OpenApiDereferencer::from_str(stores)?,
+        ])?;
+        assert_eq!(
+            &[
+                Warning::ComponentRenamed {
+                    section: "schemas".into(),
+                    name: "Item".into(),
+                    renamed_to: "StoreAPI_Item".into(),
+                },
+                Warning::MergeConflict {
+                    location: "/paths/~1pets/get".into(),
+                },
+            ],
+            merged.warnings()
+        );
+        let json = &merged.json;
+        assert_eq!("Pets", json["info"]["title"]);
+        assert_eq!(2, json["tags"].as_array().unwrap().len());
+        assert!(json["paths"]["/pets"].get("post").is_some());
+        assert_eq!(
+            "#/components/schemas/StoreAPI_Item/properties/sku",
+            json["paths"]["/stores"]["get"]["responses"]["200"]["content"]["application/json"]
+                ["schema"]["$ref"]
+        );
+        let schemas = json["components"]["schemas"].as_object().unwrap();
+        assert_eq!(3, schemas.len());
+
+        let dereferenced = merged.dereference()?;
+        assert_eq!(3, dereferenced.iter_operations().count());
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+use std::fmt;
+use std::str::FromStr;
+
+use openapiv3::v3_1::{Operation, PathItem};
+use serde::Serialize;
+
+use crate::OpenApiError;
+
+///The HTTP methods a path item can hold an operation for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    Get,
+    Put,
+    Post,
+    Delete,
+    Options,
+    Head,
+    Patch,
+    Trace,
+}
+
+impl Method {
+    ///Every method in the order the spec lists them on a path item.
+    pub const ALL: [Method; 8] = [
+        Method::Get,
+        Method::Put,
+        Method::Post,
+        Method::Delete,
+        Method::Options,
+        Method::Head,
+        Method::Patch,
+        Method::Trace,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "get",
+            Method::Put => "put",
+            Method::Post => "post",
+            Method::Delete => "delete",
+            Method::Options => "options",
+            Method::Head => "head",
+            Method::Patch => "patch",
+            Method::Trace => "trace",
+        }
+    }
+
+    ///Get the operation for this method off of a path item, if there is one.
+    pub fn operation<'a>(&self, path_item: &'a PathItem) -> Option<&'a Operation> {
+        match self {
+            Method::Get => path_item.get.as_ref(),
+            Method::Put => path_item.put.as_ref(),
+            Method::Post => path_item.post.as_ref(),
+            Method::Delete => path_item.delete.as_ref(),
+            Method::Options => path_item.options.as_ref(),
+            Method::Head => path_item.head.as_ref(),
+            Method::Patch => path_item.patch.as_ref(),
+            Method::Trace => path_item.trace.as_ref(),
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Method {
+    type Err = OpenApiError;
+
+    fn from_str(method: &str) -> Result<Self, OpenApiError> {
+        Method::ALL
+            .into_iter()
+            .find(|m| m.as_str().eq_ignore_ascii_case(method))
+            .ok_or_else(|| OpenApiError::UnsupportedMethod {
+                method: method.into(),
+            })
+    }
+}
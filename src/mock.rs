@@ -0,0 +1,216 @@
+use std::cmp::Reverse;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode, Uri};
+use axum::response::{IntoResponse, Response as AxumResponse};
+use openapiv3::v3_1::{Operation, StatusCode as SpecStatusCode};
+use serde_json::Value;
+
+use crate::example::media_type_example;
+use crate::{as_item, DereferencedOpenApi, Method, OpenApiError, PathTemplate};
+
+///What the mock server sends back for a request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Option<Value>,
+}
+
+#[derive(Debug)]
+struct MockRoute {
+    template: PathTemplate,
+    method: Method,
+    response: MockResponse,
+}
+
+///Serves the operations of a dereferenced spec, responding with the examples from the spec or
+///generated from the response schemas. The responses are worked out up front, so the server
+///doesn't hold on to the dereferencer.
+#[derive(Debug, Clone)]
+pub struct MockServer {
+    routes: Arc<Vec<MockRoute>>,
+}
+
+impl MockServer {
+    ///The response for a method and concrete path, if any operation matches.
+    pub fn respond(&self, method: Method, path: &str) -> Option<&MockResponse> {
+        self.routes
+            .iter()
+            .filter(|route| route.method == method)
+            .find(|route| route.template.matches(path).is_some())
+            .map(|route| &route.response)
+    }
+
+    ///An axum router answering every request from the spec. Unknown routes get a 404.
+    pub fn into_router(self) -> axum::Router {
+        axum::Router::new().fallback(move |method: axum::http::Method, uri: Uri| {
+            let server = self.clone();
+            async move {
+                let response = Method::from_str(method.as_str())
+                    .ok()
+                    .and_then(|method| server.respond(method, uri.path()));
+                match response {
+                    Some(response) => mock_response(response),
+                    None => StatusCode::NOT_FOUND.into_response(),
+                }
+            }
+        })
+    }
+
+    ///Serve the mock on `addr` until the process is stopped.
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), OpenApiError> {
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(|e| OpenApiError::MockServerError { msg: e.to_string() })?;
+        axum::serve(listener, self.into_router())
+            .await
+            .map_err(|e| OpenApiError::MockServerError { msg: e.to_string() })
+    }
+}
+
+fn mock_response(response: &MockResponse) -> AxumResponse {
+    let status = StatusCode::from_u16(response.status).unwrap_or(StatusCode::OK);
+    let body = match &response.body {
+        Some(Value::String(text)) if !is_json(response.content_type.as_deref()) => text.clone(),
+        Some(body) => body.to_string(),
+        None => String::new(),
+    };
+    match &response.content_type {
+        Some(content_type) => {
+            (status, [(header::CONTENT_TYPE, content_type.clone())], body).into_response()
+        }
+        None => (status, body).into_response(),
+    }
+}
+
+fn is_json(content_type: Option<&str>) -> bool {
+    content_type.is_some_and(|content_type| content_type.contains("json"))
+}
+
+///Mock the lowest 2XX response, falling back to the first response declared and then
+///`default`.
+fn operation_response(operation: &Operation) -> MockResponse {
+    let responses = operation.responses.as_ref();
+    let declared = responses.map(|responses| {
+        let mut codes: Vec<(u16, _)> = responses
+            .responses
+            .iter()
+            .filter_map(|(status, response)| {
+                let code = match status {
+                    SpecStatusCode::Code(code) => *code,
+                    SpecStatusCode::Range(range) => range * 100,
+                };
+                as_item(response).map(|response| (code, response))
+            })
+            .collect();
+        let first = codes.first().copied();
+        codes.sort_by_key(|(code, _)| *code);
+        codes
+            .iter()
+            .find(|(code, _)| (200..300).contains(code))
+            .copied()
+            .or(first)
+            .or_else(|| {
+                responses
+                    .default
+                    .as_ref()
+                    .and_then(as_item)
+                    .map(|r| (200, r))
+            })
+    });
+    match declared.flatten() {
+        Some((status, response)) => {
+            let media_type = response.content.iter().next();
+            MockResponse {
+                status,
+                content_type: media_type.map(|(content_type, _)| content_type.clone()),
+                body: media_type.and_then(|(_, media_type)| media_type_example(media_type)),
+            }
+        }
+        None => MockResponse {
+            status: 200,
+            content_type: None,
+            body: None,
+        },
+    }
+}
+
+impl DereferencedOpenApi {
+    ///Build a mock server for the spec's paths.
+    pub fn mock_server(&self) -> Result<MockServer, OpenApiError> {
+        let mut routes = self
+            .iter_operations()
+            .map(|(path, method, operation)| {
+                Ok(MockRoute {
+                    template: path.parse()?,
+                    method,
+                    response: operation_response(operation),
+                })
+            })
+            .collect::<Result<Vec<_>, OpenApiError>>()?;
+        routes.sort_by_cached_key(|route| Reverse(route.template.specificity()));
+        Ok(MockServer {
+            routes: Arc::new(routes),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    pub fn test_mock_responses() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Mock", "version": "1.0.0"},
+            "paths": {
+                "/pets/{petId}": {
+                    "get": {
+                        "responses": {
+                            "404": {"description": "missing"},
+                            "200": {
+                                "description": "ok",
+                                "content": {
+                                    "application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}
+                                }
+                            }
+                        }
+                    },
+                    "delete": {"responses": {"204": {"description": "deleted"}}}
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Pet": {"type": "object", "properties": {"name": {"type": "string", "examples": ["Rex"]}}}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let server = dereferenced.mock_server()?;
+        assert_eq!(
+            Some(&MockResponse {
+                status: 200,
+                content_type: Some("application/json".into()),
+                body: Some(json!({"name": "Rex"})),
+            }),
+            server.respond(Method::Get, "/pets/1")
+        );
+        assert_eq!(
+            Some(&MockResponse {
+                status: 204,
+                content_type: None,
+                body: None,
+            }),
+            server.respond(Method::Delete, "/pets/1")
+        );
+        assert!(server.respond(Method::Post, "/pets/1").is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use openapiv3::schemars::schema::{InstanceType, Schema as SchemarsSchema, SingleOrVec};
+use serde_json::Value;
+
+///Whether a schema says `null` is allowed, going by `type: ["string", "null"]`, an `enum` with
+///`null` in it, a 3.0 style `nullable: true` or a `oneOf` or `anyOf` branch that does. Schemas
+///that allow anything without saying so, `{}` and `true`, aren't counted.
+pub fn is_nullable(schema: &SchemarsSchema) -> bool {
+    let SchemarsSchema::Object(object) = schema else {
+        return false;
+    };
+    if types(&object.instance_type).contains(&InstanceType::Null) {
+        return true;
+    }
+    if object.extensions.get("nullable") == Some(&Value::Bool(true)) {
+        return true;
+    }
+    if object.const_value == Some(Value::Null)
+        || object
+            .enum_values
+            .as_ref()
+            .is_some_and(|values| values.contains(&Value::Null))
+    {
+        return true;
+    }
+    object.subschemas.as_ref().is_some_and(|subschemas| {
+        [&subschemas.one_of, &subschemas.any_of]
+            .into_iter()
+            .flatten()
+            .flatten()
+            .any(is_nullable)
+    })
+}
+
+///The types a schema allows other than `null`, looking through a `oneOf` or `anyOf` that's
+///only there to add `null`. Empty when the schema doesn't say, e.g. `{}`.
+pub fn non_null_types(schema: &SchemarsSchema) -> Vec<InstanceType> {
+    let SchemarsSchema::Object(object) = schema else {
+        return vec![];
+    };
+    let mut non_null: Vec<InstanceType> = types(&object.instance_type)
+        .into_iter()
+        .filter(|instance_type| *instance_type != InstanceType::Null)
+        .collect();
+    if non_null.is_empty() {
+        let branches = object
+            .subschemas
+            .as_ref()
+            .into_iter()
+            .flat_map(|subschemas| {
+                [&subschemas.one_of, &subschemas.any_of]
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+            });
+        for branch in branches.filter(|branch| !is_null(branch)) {
+            for instance_type in non_null_types(branch) {
+                if !non_null.contains(&instance_type) {
+                    non_null.push(instance_type);
+                }
+            }
+        }
+    }
+    non_null
+}
+
+///Whether a schema only allows `null`.
+pub(crate) fn is_null(schema: &SchemarsSchema) -> bool {
+    match schema {
+        SchemarsSchema::Object(object) => {
+            types(&object.instance_type) == [InstanceType::Null]
+                || object.const_value == Some(Value::Null)
+                || object.enum_values.as_deref() == Some(&[Value::Null][..])
+        }
+        SchemarsSchema::Bool(_) => false,
+    }
+}
+
+fn types(instance_type: &Option<SingleOrVec<InstanceType>>) -> Vec<InstanceType> {
+    match instance_type {
+        Some(SingleOrVec::Single(instance_type)) => vec![**instance_type],
+        Some(SingleOrVec::Vec(types)) => types.clone(),
+        None => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    pub fn test_nullable() -> Result<()> {
+        let schema =
+            |value: Value| -> Result<SchemarsSchema> { Ok(serde_json::from_value(value)?) };
+        let type_list = schema(json!({"type": ["string", "null"]}))?;
+        assert!(is_nullable(&type_list));
+        assert_eq!(vec![InstanceType::String], non_null_types(&type_list));
+
+        let one_of = schema(json!({
+            "oneOf": [{"type": "null"}, {"type": "object", "properties": {}}]
+        }))?;
+        assert!(is_nullable(&one_of));
+        assert_eq!(vec![InstanceType::Object], non_null_types(&one_of));
+
+        let v3_0 = schema(json!({"type": "integer", "nullable": true}))?;
+        assert!(is_nullable(&v3_0));
+        assert_eq!(vec![InstanceType::Integer], non_null_types(&v3_0));
+
+        let not_nullable = schema(json!({"type": "string"}))?;
+        assert!(!is_nullable(&not_nullable));
+        assert_eq!(vec![InstanceType::String], non_null_types(&not_nullable));
+
+        let any_of = schema(json!({
+            "anyOf": [{"type": ["string", "null"]}, {"type": "integer"}]
+        }))?;
+        assert!(is_nullable(&any_of));
+        assert!(is_nullable(&schema(json!({"enum": ["a", null]}))?));
+        assert!(!is_nullable(&schema(json!({"enum": ["a", "b"]}))?));
+        assert!(is_nullable(&schema(
+            json!({"oneOf": [{"enum": [null]}, {"type": "string"}]})
+        )?));
+        assert_eq!(
+            vec![InstanceType::String],
+            non_null_types(&schema(
+                json!({"oneOf": [{"enum": [null]}, {"type": "string"}]})
+            )?)
+        );
+        assert!(!is_nullable(&schema(json!({}))?));
+        assert!(!is_nullable(&SchemarsSchema::Bool(true)));
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{DereferencedOpenApi, OpenApiDereferencer, OpenApiError};
+
+//Both `oas3::Spec` and the openapiv3 `OpenApi` are foreign types, so the conversions hang off of
+//the dereferencer instead. They go through JSON since both sides are serde models of the same
+//document.
+
+impl TryFrom<oas3::Spec> for OpenApiDereferencer {
+    type Error = OpenApiError;
+
+    ///A dereferencer for an oas3 spec, ready to dereference.
+    fn try_from(spec: oas3::Spec) -> Result<Self, OpenApiError> {
+        let json = serde_json::to_value(&spec).map_err(|e| OpenApiError::ParsingError {
+            msg: format!("Error serializing the oas3 spec {e}"),
+            location: None,
+        })?;
+        OpenApiDereferencer::from_value(json)
+    }
+}
+
+impl TryFrom<&DereferencedOpenApi> for oas3::Spec {
+    type Error = OpenApiError;
+
+    ///The dereferenced spec as an oas3 spec, serialized like `into_openapi`.
+    fn try_from(dereferenced: &DereferencedOpenApi) -> Result<Self, OpenApiError> {
+        let json = dereferenced.to_output_value()?;
+        serde_json::from_value(json).map_err(|e| OpenApiError::ParsingError {
+            msg: format!("Error converting to an oas3 spec {e}"),
+            location: None,
+        })
+    }
+}
+
+impl TryFrom<DereferencedOpenApi> for oas3::Spec {
+    type Error = OpenApiError;
+
+    fn try_from(dereferenced: DereferencedOpenApi) -> Result<Self, OpenApiError> {
+        oas3::Spec::try_from(&dereferenced)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::Value;
+
+    #[test]
+    pub fn test_oas3_round_trip() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "oas3", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {"200": {"$ref": "#/components/responses/Pets"}}
+                    }
+                }
+            },
+            "components": {
+                "responses": {"Pets": {"description": "Some pets"}}
+            }
+        }"##;
+        let spec: oas3::Spec = serde_json::from_str(spec)?;
+        let dereferenced = OpenApiDereferencer::try_from(spec)?.dereference()?;
+        let converted = oas3::Spec::try_from(&dereferenced)?;
+        let json = serde_json::to_value(&converted)?;
+        let response = &json["paths"]["/pets"]["get"]["responses"]["200"];
+        assert_eq!("Some pets", response["description"]);
+        assert_eq!(None, response.get("$ref").and_then(Value::as_str));
+        Ok(())
+    }
+}
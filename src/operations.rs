@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+
+use indexmap::IndexMap;
+use openapiv3::v3_1::{OpenApi, Operation, PathItem, ReferenceOr};
+
+use crate::{as_item, DereferencedOpenApi, Method, OpenApiDereferencer, OpenApiError, Warning};
+
+///Get every operation on a path item along with the method it's for.
+pub(crate) fn path_item_operations(
+    path_item: &PathItem,
+) -> impl Iterator<Item = (Method, &Operation)> + '_ {
+    Method::ALL.into_iter().filter_map(move |method| {
+        method
+            .operation(path_item)
+            .map(|operation| (method, operation))
+    })
+}
+
+fn iter_operations(openapi: &OpenApi) -> impl Iterator<Item = (&str, Method, &Operation)> + '_ {
+    openapi
+        .paths
+        .iter()
+        .flat_map(|paths| iter_path_item_operations(&paths.paths))
+}
+
+fn iter_path_item_operations(
+    path_items: &IndexMap<String, ReferenceOr<PathItem>>,
+) -> impl Iterator<Item = (&str, Method, &Operation)> + '_ {
+    path_items
+        .iter()
+        .filter_map(|(path, path_item)| as_item(path_item).map(|item| (path.as_str(), item)))
+        .flat_map(|(path, path_item)| {
+            path_item_operations(path_item)
+                .map(move |(method, operation)| (path, method, operation))
+        })
+}
+
+impl DereferencedOpenApi {
+    ///Iterate over every operation in the spec as `(path, method, operation)`.
+    pub fn iter_operations(&self) -> impl Iterator<Item = (&str, Method, &Operation)> + '_ {
+        iter_operations(&self.openapi)
+    }
+
+    ///Iterate over every webhook operation in the spec as `(webhook name, method, operation)`.
+    pub fn iter_webhook_operations(&self) -> impl Iterator<Item = (&str, Method, &Operation)> + '_ {
+        iter_path_item_operations(&self.openapi.webhooks)
+    }
+
+    ///Look up an operation by its operationId. If more than one operation uses the id the first
+    ///one in the spec is returned and a warning is recorded during dereferencing.
+    pub fn get_operation_by_id(
+        &self,
+        operation_id: &str,
+    ) -> Result<(&str, Method, &Operation), OpenApiError> {
+        let (path, method) = self.operation_ids.get(operation_id).ok_or_else(|| {
+            OpenApiError::OperationIdNotFound {
+                operation_id: operation_id.into(),
+            }
+        })?;
+        let operation = method.operation(self.path_item(path)?).ok_or_else(|| {
+            OpenApiError::OperationNotFound {
+                path: path.clone(),
+                method: *method,
+            }
+        })?;
+        Ok((path, *method, operation))
+    }
+
+    ///Group operations by the tags they carry. Tags declared at the root of the spec come first
+    ///in the order they're declared, even if no operation uses them, followed by any tags that
+    ///are only used on operations. Untagged operations are left out.
+    pub fn operations_by_tag(&self) -> IndexMap<String, Vec<(&str, Method, &Operation)>> {
+        let mut by_tag: IndexMap<String, Vec<(&str, Method, &Operation)>> = self
+            .openapi
+            .tags
+            .iter()
+            .map(|tag| (tag.name.clone(), vec![]))
+            .collect();
+        for (path, method, operation) in self.iter_operations() {
+            for tag in &operation.tags {
+                by_tag
+                    .entry(tag.clone())
+                    .or_default()
+                    .push((path, method, operation));
+            }
+        }
+        by_tag
+    }
+}
+
+impl OpenApiDereferencer {
+    pub(crate) fn index_operation_ids(&mut self) {
+        let mut operation_ids: HashMap<String, (String, Method)> = HashMap::new();
+        let mut warnings = vec![];
+        for (path, method, operation) in iter_operations(&self.openapi) {
+            if let Some(operation_id) = &operation.operation_id {
+                if let Some((first_path, first_method)) = operation_ids.get(operation_id) {
+                    warnings.push(Warning::DuplicateOperationId {
+                        operation_id: operation_id.clone(),
+                        path: path.into(),
+                        method,
+                        first_path: first_path.clone(),
+                        first_method: *first_method,
+                    });
+                } else {
+                    operation_ids.insert(operation_id.clone(), (path.into(), method));
+                }
+            }
+        }
+        self.operation_ids = operation_ids;
+        self.warnings.append(&mut warnings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_iter_operations() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Operations", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {"operationId": "listPets"},
+                    "post": {"operationId": "createPet"}
+                },
+                "/owners": {"$ref": "#/components/pathItems/Owners"}
+            },
+            "components": {
+                "pathItems": {
+                    "Owners": {"delete": {"operationId": "deleteOwners"}}
+                }
+            }
+        }"##;
+        let dereferencer = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let operations: Vec<(&str, Method, Option<&str>)> = dereferencer
+            .iter_operations()
+            .map(|(path, method, operation)| (path, method, operation.operation_id.as_deref()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("/pets", Method::Get, Some("listPets")),
+                ("/pets", Method::Post, Some("createPet")),
+                ("/owners", Method::Delete, Some("deleteOwners")),
+            ],
+            operations
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_get_operation_by_id() -> Result<()> {
+        let spec = r#"{
+            "openapi": "3.1.0",
+            "info": {"title": "Operations", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {"operationId": "listPets"},
+                    "post": {"operationId": "listPets"}
+                }
+            }
+        }"#;
+        let dereferencer = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let (path, method, _) = dereferencer.get_operation_by_id("listPets")?;
+        assert_eq!(("/pets", Method::Get), (path, method));
+        assert!(dereferencer.get_operation_by_id("createPet").is_err());
+        assert_eq!(
+            vec![Warning::DuplicateOperationId {
+                operation_id: "listPets".into(),
+                path: "/pets".into(),
+                method: Method::Post,
+                first_path: "/pets".into(),
+                first_method: Method::Get,
+            }],
+            dereferencer.warnings()
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_operations_by_tag() -> Result<()> {
+        let spec = r#"{
+            "openapi": "3.1.0",
+            "info": {"title": "Operations", "version": "1.0.0"},
+            "tags": [{"name": "owners"}, {"name": "pets"}, {"name": "unused"}],
+            "paths": {
+                "/pets": {
+                    "get": {"operationId": "listPets", "tags": ["pets", "public"]},
+                    "post": {"operationId": "createPet", "tags": ["pets"]}
+                },
+                "/owners": {
+                    "get": {"operationId": "listOwners", "tags": ["owners"]},
+                    "delete": {"operationId": "deleteOwners"}
+                }
+            }
+        }"#;
+        let dereferencer = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let by_tag = dereferencer.operations_by_tag();
+        let ids = |tag: &str| -> Vec<&str> {
+            by_tag[tag]
+                .iter()
+                .filter_map(|(_, _, operation)| operation.operation_id.as_deref())
+                .collect()
+        };
+        assert_eq!(
+            vec!["owners", "pets", "unused", "public"],
+            by_tag.keys().collect::<Vec<&String>>()
+        );
+        assert_eq!(vec!["listPets", "createPet"], ids("pets"));
+        assert_eq!(vec!["listOwners"], ids("owners"));
+        assert!(ids("unused").is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::{HashMap, HashSet};
+
+use indexmap::IndexMap;
+use serde_json::Value;
+
+use crate::lenient::refs_in;
+use crate::{OpenApiDereferencer, OpenApiError};
+
+///Component schemas that have to be emitted together, see
+///[`OpenApiDereferencer::schema_dependency_order`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyGroup {
+    pub schemas: Vec<String>,
+    ///Whether the schemas refer to each other in a loop, or the one schema refers to itself
+    pub cyclic: bool,
+}
+
+impl OpenApiDereferencer {
+    ///The component schemas ordered so every schema comes after the ones it refers to, for
+    ///emitting types in an order that compiles. Schemas that refer to each other in a loop
+    ///can't be ordered, so they're grouped and the group is marked cyclic. Works on the input
+    ///document.
+    pub fn schema_dependency_order(&self) -> Result<Vec<DependencyGroup>, OpenApiError> {
+        let Some(Value::Object(schemas)) = self.input()?.pointer("/components/schemas") else {
+            return Ok(vec![]);
+        };
+        //schema name -> names of the schemas it refers to
+        let dependencies: IndexMap<&str, Vec<String>> = schemas
+            .iter()
+            .map(|(name, schema)| {
+                let refs = refs_in(schema);
+                (name.as_str(), self.referenced_schema_names(&refs))
+            })
+            .collect();
+        let mut tarjan = Tarjan {
+            dependencies: &dependencies,
+            index: HashMap::new(),
+            low_link: HashMap::new(),
+            stack: vec![],
+            on_stack: HashSet::new(),
+            groups: vec![],
+        };
+        for name in dependencies.keys() {
+            if !tarjan.index.contains_key(name) {
+                tarjan.visit(name);
+            }
+        }
+        Ok(tarjan.groups)
+    }
+}
+
+///Tarjan's strongly connected components algorithm. It finishes a component only after every
+///component reachable from it, so the groups come out dependencies first.
+struct Tarjan<'d> {
+    dependencies: &'d IndexMap<&'d str, Vec<String>>,
+    index: HashMap<&'d str, usize>,
+    low_link: HashMap<&'d str, usize>,
+    stack: Vec<&'d str>,
+    on_stack: HashSet<&'d str>,
+    groups: Vec<DependencyGroup>,
+}
+
+impl<'d> Tarjan<'d> {
+    ///Visit `root` and everything it refers to. The schemas being visited are kept on a stack
+    ///of our own rather than recursing, so a long chain of refs can't overflow the thread's.
+    fn visit(&mut self, root: &'d str) {
+        let dependencies = self.dependencies;
+        //Each schema being visited with how many of its dependencies have been looked at
+        let mut visiting = vec![(root, 0)];
+        self.open(root);
+        while let Some(&(name, next)) = visiting.last() {
+            if let Some(dependency) = dependencies[name].get(next) {
+                let top = visiting.len() - 1;
+                visiting[top].1 += 1;
+                let Some((&dependency, _)) = dependencies.get_key_value(dependency.as_str()) else {
+                    continue;
+                };
+                match self.index.get(dependency) {
+                    None => {
+                        self.open(dependency);
+                        visiting.push((dependency, 0));
+                    }
+                    Some(&dependency_index) if self.on_stack.contains(dependency) => {
+                        self.lower(name, dependency_index);
+                    }
+                    Some(_) => {}
+                }
+                continue;
+            }
+            visiting.pop();
+            self.close(name);
+            if let Some(&(parent, _)) = visiting.last() {
+                self.lower(parent, self.low_link[name]);
+            }
+        }
+    }
+
+    fn open(&mut self, name: &'d str) {
+        let index = self.index.len();
+        self.index.insert(name, index);
+        self.low_link.insert(name, index);
+        self.stack.push(name);
+        self.on_stack.insert(name);
+    }
+
+    fn lower(&mut self, name: &'d str, low_link: usize) {
+        let low_link = self.low_link[name].min(low_link);
+        self.low_link.insert(name, low_link);
+    }
+
+    ///Once every dependency of `name` has been visited, finish its group if it's the first of
+    ///the group to have been visited.
+    fn close(&mut self, name: &'d str) {
+        if self.low_link[name] != self.index[name] {
+            return;
+        }
+        let mut schemas = vec![];
+        while let Some(member) = self.stack.pop() {
+            self.on_stack.remove(member);
+            schemas.push(member.to_string());
+            if member == name {
+                break;
+            }
+        }
+        schemas.reverse();
+        let cyclic = schemas.len() > 1 || self.dependencies[name].iter().any(|d| d == name);
+        self.groups.push(DependencyGroup { schemas, cyclic });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_schema_dependency_order() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Order", "version": "1.0.0"},
+            "components": {
+                "schemas": {
+                    "Owner": {"type": "object", "properties": {"pets": {"type": "array", "items": {"$ref": "#/components/schemas/Pet"}}}},
+                    "Pet": {"type": "object", "properties": {"tag": {"$ref": "#/components/schemas/Tag"}, "parent": {"$ref": "#/components/schemas/Pet"}}},
+                    "Tag": {"type": "string"},
+                    "Node": {"type": "object", "properties": {"edges": {"type": "array", "items": {"$ref": "#/components/schemas/Edge"}}}},
+                    "Edge": {"type": "object", "properties": {"to": {"$ref": "#/components/schemas/Node"}}}
+                }
+            }
+        }"##;
+        let order = OpenApiDereferencer::from_str(spec)?.schema_dependency_order()?;
+        let group = |schemas: &[&str], cyclic: bool| DependencyGroup {
+            schemas: schemas.iter().map(|schema| schema.to_string()).collect(),
+            cyclic,
+        };
+        assert_eq!(
+            vec![
+                group(&["Tag"], false),
+                group(&["Pet"], true),
+                group(&["Owner"], false),
+                group(&["Node", "Edge"], true),
+            ],
+            order
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_long_chain_of_schemas() -> Result<()> {
+        let length = 100_000;
+        let schemas: serde_json::Map<String, Value> = (0..length)
+            .map(|i| {
+                let schema = if i + 1 < length {
+                    json!({"$ref": format!("#/components/schemas/S{}", i + 1)})
+                } else {
+                    json!({"type": "string"})
+                };
+                (format!("S{i}"), schema)
+            })
+            .collect();
+        let spec = json!({
+            "openapi": "3.1.0",
+            "info": {"title": "Chain", "version": "1.0.0"},
+            "components": {"schemas": schemas}
+        });
+        let order = OpenApiDereferencer::from_value(spec)?.schema_dependency_order()?;
+        assert_eq!(length, order.len());
+        assert_eq!(format!("S{}", length - 1), order[0].schemas[0]);
+        assert_eq!("S0", order[length - 1].schemas[0]);
+        Ok(())
+    }
+}
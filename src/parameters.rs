@@ -0,0 +1,134 @@
+use openapiv3::v3_1::{Operation, Parameter, ParameterData, PathItem};
+
+use crate::{as_item, DereferencedOpenApi, Method, OpenApiError};
+
+///The data shared by every kind of parameter.
+pub(crate) fn parameter_data(parameter: &Parameter) -> &ParameterData {
+    match parameter {
+        Parameter::Query { parameter_data, .. } => parameter_data,
+        Parameter::Header { parameter_data, .. } => parameter_data,
+        Parameter::Path { parameter_data, .. } => parameter_data,
+        Parameter::Cookie { parameter_data, .. } => parameter_data,
+    }
+}
+
+///Where a parameter goes, the `in` of the parameter object.
+pub(crate) fn parameter_location(parameter: &Parameter) -> &'static str {
+    match parameter {
+        Parameter::Query { .. } => "query",
+        Parameter::Header { .. } => "header",
+        Parameter::Path { .. } => "path",
+        Parameter::Cookie { .. } => "cookie",
+    }
+}
+
+fn same_parameter(a: &Parameter, b: &Parameter) -> bool {
+    parameter_location(a) == parameter_location(b)
+        && parameter_data(a).name == parameter_data(b).name
+}
+
+///The parameters that apply to an operation on a path item, see
+///[`DereferencedOpenApi::effective_parameters`].
+pub(crate) fn operation_parameters<'a>(
+    path_item: &'a PathItem,
+    operation: &'a Operation,
+) -> Vec<&'a Parameter> {
+    let operation_parameters: Vec<&Parameter> =
+        operation.parameters.iter().filter_map(as_item).collect();
+    path_item
+        .parameters
+        .iter()
+        .filter_map(as_item)
+        .filter(|parameter| {
+            !operation_parameters
+                .iter()
+                .any(|overridden| same_parameter(parameter, overridden))
+        })
+        .chain(operation_parameters.iter().copied())
+        .collect()
+}
+
+impl DereferencedOpenApi {
+    ///Get the parameters that apply to a single operation. Path level parameters apply unless
+    ///the operation declares a parameter with the same name and location. Path level parameters
+    ///come first, followed by the operation's.
+    pub fn effective_parameters(
+        &self,
+        path: &str,
+        method: Method,
+    ) -> Result<Vec<&Parameter>, OpenApiError> {
+        let path_item = self.path_item(path)?;
+        let operation =
+            method
+                .operation(path_item)
+                .ok_or_else(|| OpenApiError::OperationNotFound {
+                    path: path.into(),
+                    method,
+                })?;
+        Ok(operation_parameters(path_item, operation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_effective_parameters() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Parameters", "version": "1.0.0"},
+            "paths": {
+                "/pets/{petId}": {
+                    "parameters": [
+                        {"name": "petId", "in": "path", "required": true, "schema": {"type": "string"}},
+                        {"name": "limit", "in": "query", "description": "path level", "schema": {"type": "integer"}},
+                        {"$ref": "#/components/parameters/Trace"}
+                    ],
+                    "get": {
+                        "parameters": [
+                            {"name": "limit", "in": "query", "description": "operation level", "schema": {"type": "integer"}},
+                            {"name": "limit", "in": "header", "schema": {"type": "integer"}}
+                        ],
+                        "responses": {"200": {"description": "ok"}}
+                    }
+                }
+            },
+            "components": {
+                "parameters": {
+                    "Trace": {"name": "X-Trace", "in": "header", "schema": {"type": "string"}}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let parameters = dereferenced.effective_parameters("/pets/{petId}", Method::Get)?;
+        let summary: Vec<(&str, &str, Option<&str>)> = parameters
+            .iter()
+            .map(|parameter| {
+                let data = parameter_data(parameter);
+                (
+                    parameter_location(parameter),
+                    data.name.as_str(),
+                    data.description.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("path", "petId", None),
+                ("header", "X-Trace", None),
+                ("query", "limit", Some("operation level")),
+                ("header", "limit", None),
+            ],
+            summary
+        );
+        assert!(matches!(
+            dereferenced.effective_parameters("/pets/{petId}", Method::Post),
+            Err(OpenApiError::OperationNotFound { .. })
+        ));
+        Ok(())
+    }
+}
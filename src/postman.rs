@@ -0,0 +1,257 @@
+use indexmap::IndexMap;
+use openapiv3::v3_1::{Operation, Parameter};
+use serde_json::{json, Value};
+
+use crate::content::is_json;
+use crate::example::{example_text, media_type_example, parameter_example, server_variables};
+use crate::parameters::parameter_data;
+use crate::pointer::percent_encode;
+use crate::{as_item, DereferencedOpenApi, Method, OpenApiError};
+
+const COLLECTION_SCHEMA: &str =
+    "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+impl DereferencedOpenApi {
+    ///A Postman Collection v2.1 with a request for every operation, in a folder for the
+    ///operation's first tag. The first server becomes the `baseUrl` variable and its variables
+    ///become collection variables. Parameter values and request bodies are the examples in the
+    ///spec, or generated from the schemas. An operation taking more than one content type gets
+    ///a request for each.
+    pub fn to_postman_collection(&self) -> Result<Value, OpenApiError> {
+        let mut variables = vec![];
+        let mut base_url = String::new();
+        if let Some(server) = self.openapi.servers.first() {
+            base_url = server.url.trim_end_matches('/').to_string();
+            for (name, default) in server_variables(server) {
+                base_url = base_url.replace(&format!("{{{name}}}"), &format!("{{{{{name}}}}}"));
+                variables.push(json!({"key": name, "value": default}));
+            }
+        }
+        variables.insert(0, json!({"key": "baseUrl", "value": base_url}));
+
+        let mut folders: IndexMap<&str, Vec<Value>> = self
+            .openapi
+            .tags
+            .iter()
+            .map(|tag| (tag.name.as_str(), vec![]))
+            .collect();
+        let mut items = vec![];
+        for (path, method, operation) in self.iter_operations() {
+            let operation_items = self.postman_items(path, method, operation)?;
+            match operation.tags.first() {
+                Some(tag) => folders
+                    .entry(tag.as_str())
+                    .or_default()
+                    .extend(operation_items),
+                None => items.extend(operation_items),
+            }
+        }
+        let folders = folders
+            .into_iter()
+            .filter(|(_, items)| !items.is_empty())
+            .map(|(name, items)| json!({"name": name, "item": items}));
+        let mut info = json!({"name": self.openapi.info.title, "schema": COLLECTION_SCHEMA});
+        if let Some(description) = &self.openapi.info.description {
+            info["description"] = json!(description);
+        }
+        Ok(json!({
+            "info": info,
+            "variable": variables,
+            "item": folders.chain(items).collect::<Vec<Value>>(),
+        }))
+    }
+
+    fn postman_items(
+        &self,
+        path: &str,
+        method: Method,
+        operation: &Operation,
+    ) -> Result<Vec<Value>, OpenApiError> {
+        let mut headers = vec![];
+        let mut query = vec![];
+        let mut path_variables = vec![];
+        let mut cookies = vec![];
+        for parameter in self.effective_parameters(path, method)? {
+            let name = &parameter_data(parameter).name;
+            let value = example_text(&parameter_example(parameter));
+            match parameter {
+                Parameter::Query { .. } => {
+                    query.push((percent_encode(name), percent_encode(&value)))
+                }
+                Parameter::Header { .. } => headers.push(json!({"key": name, "value": value})),
+                Parameter::Path { .. } => path_variables.push(json!({"key": name, "value": value})),
+                Parameter::Cookie { .. } => cookies.push(format!("{name}={value}")),
+            }
+        }
+        if !cookies.is_empty() {
+            headers.push(json!({"key": "Cookie", "value": cookies.join("; ")}));
+        }
+
+        let segments: Vec<String> = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| segment.replace('{', ":").replace('}', ""))
+            .collect();
+        let mut raw = format!("{{{{baseUrl}}}}/{}", segments.join("/"));
+        if !query.is_empty() {
+            let pairs: Vec<String> = query
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect();
+            raw = format!("{raw}?{}", pairs.join("&"));
+        }
+        let mut url = json!({"raw": raw, "host": ["{{baseUrl}}"], "path": segments});
+        if !query.is_empty() {
+            url["query"] = query
+                .iter()
+                .map(|(name, value)| json!({"key": name, "value": value}))
+                .collect();
+        }
+        if !path_variables.is_empty() {
+            url["variable"] = json!(path_variables);
+        }
+
+        let mut request = json!({"method": method.as_str().to_ascii_uppercase(), "url": url});
+        if let Some(description) = &operation.description {
+            request["description"] = json!(description);
+        }
+        let name = operation
+            .summary
+            .clone()
+            .or_else(|| operation.operation_id.clone())
+            .unwrap_or_else(|| format!("{} {path}", method.as_str().to_ascii_uppercase()));
+        let content = operation
+            .request_body
+            .as_ref()
+            .and_then(as_item)
+            .map(|request_body| &request_body.content)
+            .filter(|content| !content.is_empty());
+        let Some(content) = content else {
+            request["header"] = json!(headers);
+            return Ok(vec![json!({"name": name, "request": request})]);
+        };
+        let mut items = vec![];
+        for (media_type, media_type_object) in content {
+            let mut request = request.clone();
+            let mut headers = headers.clone();
+            headers.push(json!({"key": "Content-Type", "value": media_type}));
+            let raw = match media_type_example(media_type_object) {
+                Some(Value::String(text)) => text,
+                Some(example) => serde_json::to_string_pretty(&example).unwrap_or_default(),
+                None => String::new(),
+            };
+            let language = if is_json(media_type) { "json" } else { "text" };
+            request["body"] = json!({
+                "mode": "raw",
+                "raw": raw,
+                "options": {"raw": {"language": language}},
+            });
+            request["header"] = json!(headers);
+            let name = match content.len() {
+                1 => name.clone(),
+                _ => format!("{name} ({media_type})"),
+            };
+            items.push(json!({"name": name, "request": request}));
+        }
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_postman_collection() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Pets", "version": "1.0.0"},
+            "servers": [{"url": "https://{env}.example.com/v1/", "variables": {"env": {"default": "api"}}}],
+            "tags": [{"name": "pets"}, {"name": "unused"}],
+            "paths": {
+                "/pets/{petId}": {
+                    "parameters": [{"name": "petId", "in": "path", "required": true, "schema": {"type": "integer"}, "example": 7}],
+                    "put": {
+                        "tags": ["pets"],
+                        "summary": "Update a pet",
+                        "parameters": [
+                            {"name": "dryRun", "in": "query", "schema": {"type": "boolean"}},
+                            {"name": "reason", "in": "query", "schema": {"type": "string"}, "example": "a&b c"},
+                            {"name": "X-Trace", "in": "header", "schema": {"type": "string"}}
+                        ],
+                        "requestBody": {
+                            "content": {
+                                "application/json": {"schema": {"$ref": "#/components/schemas/Pet"}},
+                                "text/plain": {"example": "Rex"}
+                            }
+                        },
+                        "responses": {"200": {"description": "ok"}}
+                    }
+                },
+                "/health": {"get": {"responses": {"200": {"description": "ok"}}}}
+            },
+            "components": {
+                "schemas": {"Pet": {"type": "object", "properties": {"name": {"type": "string", "examples": ["Rex"]}}}}
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let collection = dereferenced.to_postman_collection()?;
+        assert_eq!(COLLECTION_SCHEMA, collection["info"]["schema"]);
+        assert_eq!(
+            json!([
+                {"key": "baseUrl", "value": "https://{{env}}.example.com/v1"},
+                {"key": "env", "value": "api"}
+            ]),
+            collection["variable"]
+        );
+
+        let items = collection["item"].as_array().unwrap();
+        assert_eq!(2, items.len());
+        assert_eq!("pets", items[0]["name"]);
+        assert_eq!("GET /health", items[1]["name"]);
+
+        let update = &items[0]["item"][0];
+        assert_eq!("Update a pet (application/json)", update["name"]);
+        let request = &update["request"];
+        assert_eq!("PUT", request["method"]);
+        assert_eq!(
+            "{{baseUrl}}/pets/:petId?dryRun=true&reason=a%26b%20c",
+            request["url"]["raw"]
+        );
+        assert_eq!(
+            json!({"key": "reason", "value": "a%26b%20c"}),
+            request["url"]["query"][1]
+        );
+        assert_eq!(json!(["pets", ":petId"]), request["url"]["path"]);
+        assert_eq!(
+            json!([{"key": "petId", "value": "7"}]),
+            request["url"]["variable"]
+        );
+        assert_eq!(
+            json!([
+                {"key": "X-Trace", "value": "string"},
+                {"key": "Content-Type", "value": "application/json"}
+            ]),
+            request["header"]
+        );
+        assert_eq!("json", request["body"]["options"]["raw"]["language"]);
+        let body: Value = serde_json::from_str(request["body"]["raw"].as_str().unwrap())?;
+        assert_eq!(json!({"name": "Rex"}), body);
+
+        let text = &items[0]["item"][1];
+        assert_eq!("Update a pet (text/plain)", text["name"]);
+        assert_eq!("Rex", text["request"]["body"]["raw"]);
+        assert_eq!(
+            "text",
+            text["request"]["body"]["options"]["raw"]["language"]
+        );
+        assert_eq!(
+            json!({"key": "Content-Type", "value": "text/plain"}),
+            text["request"]["header"][1]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+use std::io::Read;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use serde_json::Value;
+
+#[cfg(feature = "yaml")]
+use crate::ParseLocation;
+use crate::{parse_json, OpenApiDereferencer, OpenApiError};
+
+impl OpenApiDereferencer {
+    ///Read a spec from `reader`. JSON or YAML is picked by looking at the content, YAML needs
+    ///the `yaml` feature.
+    pub fn from_reader(mut reader: impl Read) -> Result<Self, OpenApiError> {
+        let mut bytes = vec![];
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(|e| OpenApiError::ParsingError {
+                msg: format!("Error reading the spec {e}"),
+                location: None,
+            })?;
+        OpenApiDereferencer::from_value(parse_document(&bytes, "reader")?)
+    }
+
+    ///Read a spec from a file. JSON or YAML is picked by looking at the content rather than the
+    ///extension, YAML needs the `yaml` feature.
+    #[cfg(feature = "fs")]
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, OpenApiError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| OpenApiError::FileError {
+            path: path.into(),
+            msg: e.to_string(),
+        })?;
+        OpenApiDereferencer::from_value(parse_document(&bytes, &path.display().to_string())?)
+    }
+}
+
+///JSON documents start with `{`, anything else is taken to be YAML.
+pub(crate) fn parse_document(bytes: &[u8], source: &str) -> Result<Value, OpenApiError> {
+    let content = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    if is_json(content) {
+        return parse_json(content, source);
+    }
+    parse_yaml(content, source)
+}
+
+fn is_json(content: &[u8]) -> bool {
+    content
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        .is_some_and(|byte| *byte == b'{')
+}
+
+#[cfg(feature = "yaml")]
+fn parse_yaml(content: &[u8], source: &str) -> Result<Value, OpenApiError> {
+    serde_yaml::from_slice(content).map_err(|e| OpenApiError::ParsingError {
+        location: e
+            .location()
+            .map(|mark| ParseLocation::at(mark.line(), mark.column())),
+        msg: format!("Error parsing YAML from {source} to serde {e}"),
+    })
+}
+
+#[cfg(not(feature = "yaml"))]
+fn parse_yaml(_content: &[u8], source: &str) -> Result<Value, OpenApiError> {
+    Err(OpenApiError::ParsingError {
+        msg: format!("{source} isn't JSON, parsing YAML needs the yaml feature"),
+        location: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    pub fn test_from_reader() -> Result<()> {
+        let spec = std::fs::read("oai_examples/webhook-example.json")?;
+        let dereferencer = OpenApiDereferencer::from_reader(spec.as_slice())?;
+        assert_eq!(1, dereferencer.openapi.webhooks.len());
+        #[cfg(feature = "fs")]
+        assert_eq!(
+            1,
+            OpenApiDereferencer::from_file("oai_examples/webhook-example.json")?
+                .openapi
+                .webhooks
+                .len()
+        );
+
+        let yaml = "openapi: 3.1.0\ninfo:\n  title: YAML\n  version: 1.0.0\npaths: {}\n";
+        let dereferencer = OpenApiDereferencer::from_reader(yaml.as_bytes());
+        #[cfg(feature = "yaml")]
+        assert_eq!("YAML", dereferencer?.openapi.info.title);
+        #[cfg(not(feature = "yaml"))]
+        assert!(matches!(
+            dereferencer,
+            Err(OpenApiError::ParsingError { .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+use crate::read::parse_document;
+use crate::{pointer, Method, OpenApiDereferencer, OpenApiError};
+
+///Something lenient parsing changed in the input document so it would parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFix {
+    ///JSON pointer to what was fixed
+    pub location: String,
+    pub fix: String,
+}
+
+impl fmt::Display for ParseFix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.fix, self.location)
+    }
+}
+
+///Schema keywords that 2.0 style parameters have directly on them.
+const PARAMETER_SCHEMA_KEYWORDS: [&str; 14] = [
+    "type",
+    "format",
+    "items",
+    "enum",
+    "default",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "minItems",
+    "maxItems",
+];
+
+impl OpenApiDereferencer {
+    ///Parse a spec that breaks some of the rules in ways that are easy to fix, returning what
+    ///was fixed alongside it. Things like a numeric `info.version`, a missing `info` or response
+    ///`description`, lowercase status code ranges like `2xx` and 2.0 style parameters with a
+    ///`type` instead of a `schema` are patched before parsing.
+    pub fn from_value_lenient(mut json: Value) -> Result<(Self, Vec<ParseFix>), OpenApiError> {
+        let mut fixes = vec![];
+        recover(&mut json, &mut fixes);
+        Ok((OpenApiDereferencer::from_value(json)?, fixes))
+    }
+
+    ///See `from_value_lenient`. JSON or YAML is picked by looking at the content, YAML needs
+    ///the `yaml` feature.
+    pub fn from_str_lenient(the_str: &str) -> Result<(Self, Vec<ParseFix>), OpenApiError> {
+        OpenApiDereferencer::from_value_lenient(parse_document(the_str.as_bytes(), "string")?)
+    }
+}
+
+fn recover(json: &mut Value, fixes: &mut Vec<ParseFix>) {
+    let Some(root) = json.as_object_mut() else {
+        return;
+    };
+    if let Some(Value::Number(version)) = root.get("openapi") {
+        let version = match version.to_string().as_str() {
+            "3.1" => "3.1.0".to_string(),
+            version => version.to_string(),
+        };
+        fix(fixes, "/openapi", "Made the version a string");
+        root.insert("openapi".into(), version.into());
+    }
+    let info = root.entry("info").or_insert_with(|| {
+        fix(fixes, "/info", "Added a missing info");
+        Value::Object(Map::new())
+    });
+    if let Some(info) = info.as_object_mut() {
+        for field in ["title", "version"] {
+            let location = format!("/info/{field}");
+            match info.get(field) {
+                Some(Value::String(_)) => {}
+                Some(Value::Number(number)) => {
+                    let number = number.to_string();
+                    fix(fixes, &location, "Made the number a string");
+                    info.insert(field.into(), number.into());
+                }
+                _ => {
+                    fix(fixes, &location, "Added a missing field");
+                    info.insert(field.into(), "".into());
+                }
+            }
+        }
+    }
+    if let Some(paths) = root.get_mut("paths").and_then(Value::as_object_mut) {
+        for (path, path_item) in paths.iter_mut() {
+            recover_path_item(path_item, &pointer::push("/paths", path), fixes);
+        }
+    }
+    if let Some(webhooks) = root.get_mut("webhooks").and_then(Value::as_object_mut) {
+        for (name, path_item) in webhooks.iter_mut() {
+            recover_path_item(path_item, &pointer::push("/webhooks", name), fixes);
+        }
+    }
+    let Some(components) = root.get_mut("components").and_then(Value::as_object_mut) else {
+        return;
+    };
+    for (section, recover_component) in [
+        (
+            "pathItems",
+            recover_path_item as fn(&mut Value, &str, &mut Vec<ParseFix>),
+        ),
+        ("parameters", recover_parameter),
+        ("responses", recover_response),
+    ] {
+        let location = pointer::push("/components", section);
+        let components = components.get_mut(section).and_then(Value::as_object_mut);
+        for (name, component) in components.into_iter().flatten() {
+            recover_component(component, &pointer::push(&location, name), fixes);
+        }
+    }
+}
+
+fn recover_path_item(path_item: &mut Value, location: &str, fixes: &mut Vec<ParseFix>) {
+    let Some(path_item) = path_item.as_object_mut() else {
+        return;
+    };
+    recover_parameters(path_item, location, fixes);
+    for method in Method::ALL {
+        let location = pointer::push(location, method.as_str());
+        let Some(operation) = path_item
+            .get_mut(method.as_str())
+            .and_then(Value::as_object_mut)
+        else {
+            continue;
+        };
+        recover_parameters(operation, &location, fixes);
+        let location = pointer::push(&location, "responses");
+        if let Some(responses) = operation
+            .get_mut("responses")
+            .and_then(Value::as_object_mut)
+        {
+            recover_responses(responses, &location, fixes);
+        }
+    }
+}
+
+fn recover_parameters(object: &mut Map<String, Value>, location: &str, fixes: &mut Vec<ParseFix>) {
+    let parameters = object.get_mut("parameters").and_then(Value::as_array_mut);
+    for (i, parameter) in parameters.into_iter().flatten().enumerate() {
+        recover_parameter(parameter, &format!("{location}/parameters/{i}"), fixes);
+    }
+}
+
+fn recover_parameter(parameter: &mut Value, location: &str, fixes: &mut Vec<ParseFix>) {
+    let Some(parameter) = parameter.as_object_mut() else {
+        return;
+    };
+    if parameter.contains_key("$ref") {
+        return;
+    }
+    if !parameter.contains_key("schema") && !parameter.contains_key("content") {
+        let mut schema = Map::new();
+        for keyword in PARAMETER_SCHEMA_KEYWORDS {
+            if let Some(value) = parameter.remove(keyword) {
+                schema.insert(keyword.into(), value);
+            }
+        }
+        if schema.is_empty() {
+            fix(fixes, location, "Added a missing schema");
+        } else {
+            fix(fixes, location, "Moved the parameter's type into a schema");
+        }
+        parameter.insert("schema".into(), Value::Object(schema));
+    }
+    if parameter.get("in").and_then(Value::as_str) == Some("path")
+        && parameter.get("required") != Some(&Value::Bool(true))
+    {
+        fix(fixes, location, "Made the path parameter required");
+        parameter.insert("required".into(), true.into());
+    }
+}
+
+fn recover_responses(
+    responses: &mut Map<String, Value>,
+    location: &str,
+    fixes: &mut Vec<ParseFix>,
+) {
+    let statuses: HashSet<String> = responses.keys().cloned().collect();
+    let mut recovered = Map::new();
+    for (status, mut response) in std::mem::take(responses) {
+        let fixed = status.trim().to_uppercase();
+        let status = match fixed.as_str() {
+            "DEFAULT" => status,
+            _ if fixed == status => status,
+            //`2xx` next to a `2XX` can't become a second `2XX`
+            _ if statuses.contains(&fixed) || recovered.contains_key(&fixed) => {
+                fix(
+                    fixes,
+                    &pointer::push(location, &status),
+                    &format!("Dropped a response that repeats {fixed}"),
+                );
+                continue;
+            }
+            _ => {
+                fix(
+                    fixes,
+                    &pointer::push(location, &status),
+                    "Fixed the status code",
+                );
+                fixed
+            }
+        };
+        recover_response(&mut response, &pointer::push(location, &status), fixes);
+        recovered.insert(status, response);
+    }
+    *responses = recovered;
+}
+
+fn recover_response(response: &mut Value, location: &str, fixes: &mut Vec<ParseFix>) {
+    let Some(response) = response.as_object_mut() else {
+        return;
+    };
+    if !response.contains_key("$ref") && !response.contains_key("description") {
+        fix(fixes, location, "Added a missing description");
+        response.insert("description".into(), "".into());
+    }
+}
+
+fn fix(fixes: &mut Vec<ParseFix>, location: &str, fix: &str) {
+    fixes.push(ParseFix {
+        location: location.into(),
+        fix: fix.into(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::as_item;
+    use anyhow::Result;
+    use openapiv3::v3_1::StatusCode;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_from_str_lenient() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Messy", "version": 2},
+            "paths": {
+                "/pets/{petId}": {
+                    "get": {
+                        "parameters": [
+                            {"name": "petId", "in": "path", "type": "string"},
+                            {"name": "limit", "in": "query", "schema": {"type": "integer"}}
+                        ],
+                        "responses": {
+                            "2xx": {"description": "ok"},
+                            "4xx": {"description": "Lowercase"},
+                            "4XX": {"description": "Uppercase"},
+                            "default": {}
+                        }
+                    }
+                }
+            }
+        }"##;
+        assert!(OpenApiDereferencer::from_str(spec).is_err());
+        let (dereferencer, fixes) = OpenApiDereferencer::from_str_lenient(spec)?;
+        let mut locations: Vec<&str> = fixes.iter().map(|fix| fix.location.as_str()).collect();
+        locations.sort();
+        assert_eq!(
+            vec![
+                "/info/version",
+                "/paths/~1pets~1{petId}/get/parameters/0",
+                "/paths/~1pets~1{petId}/get/parameters/0",
+                "/paths/~1pets~1{petId}/get/responses/2xx",
+                "/paths/~1pets~1{petId}/get/responses/4xx",
+                "/paths/~1pets~1{petId}/get/responses/default",
+            ],
+            locations
+        );
+        assert_eq!("2", dereferencer.openapi.info.version);
+        let dereferenced = dereferencer.dereference()?;
+        let (_, _, get) = dereferenced.iter_operations().next().expect("an operation");
+        let responses = get.responses.as_ref().unwrap();
+        assert!(responses.responses.contains_key(&StatusCode::Range(2)));
+        assert!(matches!(
+            responses.responses.get(&StatusCode::Range(4)).and_then(as_item),
+            Some(response) if response.description == "Uppercase"
+        ));
+        #[cfg(feature = "yaml")]
+        {
+            let yaml = "openapi: 3.1.0\ninfo:\n  title: YAML\n  version: 2\npaths: {}\n";
+            let (_, fixes) = OpenApiDereferencer::from_str_lenient(yaml)?;
+            assert_eq!("/info/version", fixes[0].location);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,256 @@
+use serde_json::{Map, Value};
+
+use crate::strip::for_each_spec_object;
+use crate::{Method, OpenApiDereferencer, OpenApiError};
+
+///What to take out of a spec before handing it to people outside, see
+///[`OpenApiDereferencer::to_redacted_value`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redaction {
+    ///Servers whose url contains any of these are dropped, e.g. `internal.example.com`
+    pub server_urls: Vec<String>,
+    ///Operations and path items with any of these extensions set to `true` are dropped, e.g.
+    ///`x-internal`
+    pub extensions: Vec<String>,
+    ///Fields dropped wherever they show up, e.g. `email`
+    pub removed_fields: Vec<String>,
+    ///Fields whose values are replaced with `mask` wherever they show up
+    pub masked_fields: Vec<String>,
+    pub mask: String,
+}
+
+impl Default for Redaction {
+    fn default() -> Self {
+        Self {
+            server_urls: vec![],
+            extensions: vec![],
+            removed_fields: vec![],
+            masked_fields: vec![],
+            mask: "REDACTED".into(),
+        }
+    }
+}
+
+impl Redaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl OpenApiDereferencer {
+    ///The spec as JSON with what `redaction` asks for taken out. Fields are only matched where
+    ///they're part of the spec, so example values and properties with the same names are kept,
+    ///except inside extensions, which could hold anything. Components only the dropped
+    ///operations used are dropped too, which needs the input document to still be around.
+    pub fn to_redacted_value(&self, redaction: &Redaction) -> Result<Value, OpenApiError> {
+        let mut document = self.to_output_value()?;
+        if !redaction.extensions.is_empty() {
+            let keep = |_, object: &Map<String, Value>| {
+                !redaction
+                    .extensions
+                    .iter()
+                    .any(|extension| object.get(extension) == Some(&Value::Bool(true)))
+            };
+            retain_operations(&mut document, keep);
+            //The output has no refs left to follow, the input still does
+            let needed = self.filtered(keep)?;
+            retain_components(&mut document, &needed);
+        }
+        for_each_spec_object(&mut document, "", &mut |object, _| {
+            if let Some(Value::Array(servers)) = object.get_mut("servers") {
+                servers.retain(|server| {
+                    let url = server
+                        .get("url")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default();
+                    !redaction
+                        .server_urls
+                        .iter()
+                        .any(|internal| url.contains(internal.as_str()))
+                });
+            }
+            redact_fields(object, redaction);
+            for (key, value) in object.iter_mut() {
+                if key.starts_with("x-") {
+                    redact_data(value, redaction);
+                }
+            }
+        });
+        Ok(document)
+    }
+}
+
+fn redact_fields(object: &mut Map<String, Value>, redaction: &Redaction) {
+    object.retain(|key, _| !redaction.removed_fields.contains(key));
+    for (key, value) in object.iter_mut() {
+        if redaction.masked_fields.contains(key) {
+            *value = Value::String(redaction.mask.clone());
+        }
+    }
+}
+
+///Redact every object in an extension's value.
+fn redact_data(value: &mut Value, redaction: &Redaction) {
+    match value {
+        Value::Object(object) => {
+            redact_fields(object, redaction);
+            for value in object.values_mut() {
+                redact_data(value, redaction);
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                redact_data(value, redaction);
+            }
+        }
+        _ => {}
+    }
+}
+
+///Drop the components of `document` that aren't in `needed`.
+fn retain_components(document: &mut Value, needed: &Value) {
+    let Some(Value::Object(components)) = document.get_mut("components") else {
+        return;
+    };
+    for (section, named) in components.iter_mut() {
+        let Value::Object(named) = named else {
+            continue;
+        };
+        let needed = needed["components"][section.as_str()].as_object();
+        named.retain(|name, _| {
+            name.starts_with("x-") || needed.is_some_and(|needed| needed.contains_key(name))
+        });
+    }
+}
+
+///Drop the path items and operations under `paths` and `webhooks` that `keep` turns down. `keep`
+///gets the method for operations and `None` for path items. Path items left without any
+///operations are dropped too.
+pub(crate) fn retain_operations(
+    document: &mut Value,
+    mut keep: impl FnMut(Option<Method>, &Map<String, Value>) -> bool,
+) {
+    for section in ["paths", "webhooks"] {
+        let Some(Value::Object(path_items)) = document.get_mut(section) else {
+            continue;
+        };
+        path_items.retain(|path, path_item| {
+            let Value::Object(path_item) = path_item else {
+                return true;
+            };
+            if path.starts_with("x-") {
+                return true;
+            }
+            if !keep(None, path_item) {
+                return false;
+            }
+            let had_operations = has_operations(path_item);
+            for method in Method::ALL {
+                if let Some(Value::Object(operation)) = path_item.get(method.as_str()) {
+                    if !keep(Some(method), operation) {
+                        path_item.remove(method.as_str());
+                    }
+                }
+            }
+            !had_operations || has_operations(path_item)
+        });
+    }
+}
+
+fn has_operations(path_item: &Map<String, Value>) -> bool {
+    Method::ALL
+        .iter()
+        .any(|method| path_item.contains_key(method.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_to_redacted_value() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {
+                "title": "Redact",
+                "version": "1.0.0",
+                "contact": {"name": "Pets team", "email": "pets@internal.example.com"}
+            },
+            "servers": [
+                {"url": "https://api.example.com"},
+                {"url": "https://pets.internal.example.com"}
+            ],
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "x-owner": {"team": "Pets", "email": "pets@internal.example.com"},
+                        "responses": {
+                            "200": {
+                                "description": "Pets",
+                                "content": {
+                                    "application/json": {
+                                        "schema": {
+                                            "type": "object",
+                                            "properties": {"email": {"type": "string"}}
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "delete": {"x-internal": true, "responses": {"204": {"description": "Gone"}}}
+                },
+                "/admin": {
+                    "get": {
+                        "x-internal": true,
+                        "responses": {
+                            "200": {
+                                "description": "Admin",
+                                "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Admin"}}}
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Admin": {"type": "object", "properties": {"root": {"type": "boolean"}}}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let redaction = Redaction {
+            server_urls: vec!["internal.example.com".into()],
+            extensions: vec!["x-internal".into()],
+            masked_fields: vec!["email".into()],
+            ..Redaction::new()
+        };
+        let redacted = dereferenced.to_redacted_value(&redaction)?;
+        assert_eq!(
+            serde_json::json!([{"url": "https://api.example.com"}]),
+            redacted["servers"]
+        );
+        assert_eq!("REDACTED", redacted["info"]["contact"]["email"]);
+        assert_eq!("Pets team", redacted["info"]["contact"]["name"]);
+        let paths = redacted["paths"].as_object().unwrap();
+        assert_eq!(vec!["/pets"], paths.keys().collect::<Vec<_>>());
+        assert!(paths["/pets"].get("delete").is_none());
+        assert_eq!("REDACTED", paths["/pets"]["get"]["x-owner"]["email"]);
+        assert_eq!("Pets", paths["/pets"]["get"]["x-owner"]["team"]);
+        assert!(redacted["components"]["schemas"].get("Admin").is_none());
+        let schema =
+            &paths["/pets"]["get"]["responses"]["200"]["content"]["application/json"]["schema"];
+        assert_eq!("string", schema["properties"]["email"]["type"]);
+
+        let redaction = Redaction {
+            removed_fields: vec!["contact".into()],
+            ..Redaction::new()
+        };
+        let redacted = dereferenced.to_redacted_value(&redaction)?;
+        assert!(redacted["info"].get("contact").is_none());
+        assert_eq!(2, redacted["servers"].as_array().unwrap().len());
+        Ok(())
+    }
+}
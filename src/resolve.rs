@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use crate::{OpenApiDereferencer, OpenApiError};
+
+///Where a ref in the input document ends up, see [`OpenApiDereferencer::resolve_at`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedRef {
+    ///The value the last ref in the chain points at, as it is in the input document
+    pub value: Value,
+    ///JSON pointer to `value`
+    pub pointer: String,
+    ///Every ref followed to get there, starting with the one at the location asked about
+    pub chain: Vec<String>,
+}
+
+impl OpenApiDereferencer {
+    ///What the `$ref` at `json_pointer` in the input document points at. Refs to refs are
+    ///followed to the end. Fails with `NotARef` if there's no `$ref` there, and with
+    ///`CircularReference` if the refs go round in a loop.
+    pub fn resolve_at(&self, json_pointer: &str) -> Result<ResolvedRef, OpenApiError> {
+        if !self.has_input() {
+            return Err(OpenApiError::InputReleased);
+        }
+        let node =
+            self.json
+                .pointer(json_pointer)
+                .ok_or_else(|| OpenApiError::PointerNotFound {
+                    pointer: json_pointer.into(),
+                })?;
+        let mut reference = reference_in(node).ok_or_else(|| OpenApiError::NotARef {
+            pointer: json_pointer.into(),
+        })?;
+        let mut chain = vec![];
+        let mut visited = HashSet::from([json_pointer.to_string()]);
+        loop {
+            chain.push(reference.to_string());
+            let pointer = self.locate_reference(reference)?;
+            //However the refs are spelled, ending up somewhere twice means a loop
+            if !visited.insert(pointer.clone()) {
+                return Err(OpenApiError::CircularReference {
+                    reference: reference.into(),
+                });
+            }
+            let value = self
+                .json
+                .pointer(&pointer)
+                .ok_or_else(|| OpenApiError::RefNotFound {
+                    reference: reference.into(),
+                })?;
+            match reference_in(value) {
+                Some(next) => reference = next,
+                None => {
+                    return Ok(ResolvedRef {
+                        value: value.clone(),
+                        pointer,
+                        chain,
+                    })
+                }
+            }
+        }
+    }
+}
+
+fn reference_in(node: &Value) -> Option<&str> {
+    node.get("$ref").and_then(Value::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_resolve_at() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Resolve", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {"$ref": "#/components/responses/Pets"},
+                            "default": {"$ref": "#/components/responses/Loop"}
+                        }
+                    }
+                }
+            },
+            "components": {
+                "responses": {
+                    "Pets": {"$ref": "#/components/responses/AllPets"},
+                    "AllPets": {"description": "All the pets"},
+                    "Loop": {"$ref": "#/components/responses/Loop"},
+                    "Twice": {"$ref": "#/components/responses/Again"},
+                    "Again": {"$ref": "#/components/responses/%54wice"}
+                }
+            }
+        }"##;
+        let dereferencer = OpenApiDereferencer::from_str(spec)?;
+        let resolved = dereferencer.resolve_at("/paths/~1pets/get/responses/200")?;
+        assert_eq!(json!({"description": "All the pets"}), resolved.value);
+        assert_eq!("/components/responses/AllPets", resolved.pointer);
+        assert_eq!(
+            vec![
+                "#/components/responses/Pets",
+                "#/components/responses/AllPets"
+            ],
+            resolved.chain
+        );
+        assert!(matches!(
+            dereferencer.resolve_at("/paths/~1pets/get"),
+            Err(OpenApiError::NotARef { .. })
+        ));
+        assert!(matches!(
+            dereferencer.resolve_at("/paths/~1pets/get/responses/default"),
+            Err(OpenApiError::CircularReference { .. })
+        ));
+        assert!(matches!(
+            dereferencer.resolve_at("/components/responses/Twice"),
+            Err(OpenApiError::CircularReference { reference }) if reference == "#/components/responses/%54wice"
+        ));
+        Ok(())
+    }
+}
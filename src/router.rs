@@ -0,0 +1,288 @@
+use std::cmp::Reverse;
+use std::fmt;
+use std::str::FromStr;
+
+use indexmap::IndexMap;
+use openapiv3::v3_1::{Operation, Paths};
+
+use crate::{as_item, DereferencedOpenApi, Method, OpenApiError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    Param(String),
+}
+
+///A path template like `/pets/{petId}` or `/reports/{id}.{format}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathTemplate {
+    template: String,
+    segments: Vec<Vec<Piece>>,
+}
+
+impl PathTemplate {
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    ///The names of the template's parameters, in order.
+    pub fn params(&self) -> impl Iterator<Item = &str> {
+        self.segments
+            .iter()
+            .flatten()
+            .filter_map(|piece| match piece {
+                Piece::Param(name) => Some(name.as_str()),
+                Piece::Literal(_) => None,
+            })
+    }
+
+    ///Match a concrete path like `/pets/123` against the template, returning the value of each
+    ///path parameter. Values aren't percent-decoded.
+    pub fn matches(&self, path: &str) -> Option<IndexMap<String, String>> {
+        let path_segments = split_path(path);
+        if path_segments.len() != self.segments.len() {
+            return None;
+        }
+        let mut params = IndexMap::new();
+        for (pieces, segment) in self.segments.iter().zip(path_segments) {
+            match_segment(pieces, segment, &mut params)?;
+        }
+        Some(params)
+    }
+
+    ///Templates with literal segments earlier in the path sort first, so concrete paths like
+    ///`/pets/mine` win over `/pets/{petId}`.
+    pub(crate) fn specificity(&self) -> Vec<bool> {
+        self.segments
+            .iter()
+            .map(|pieces| matches!(pieces.as_slice(), [Piece::Literal(_)]))
+            .collect()
+    }
+}
+
+impl FromStr for PathTemplate {
+    type Err = OpenApiError;
+
+    fn from_str(template: &str) -> Result<Self, OpenApiError> {
+        let segments = split_path(template)
+            .into_iter()
+            .map(|segment| parse_segment(template, segment))
+            .collect::<Result<_, _>>()?;
+        Ok(PathTemplate {
+            template: template.into(),
+            segments,
+        })
+    }
+}
+
+impl fmt::Display for PathTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+///Split a path into its segments, ignoring any query string and a trailing slash.
+fn split_path(path: &str) -> Vec<&str> {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let path = path.trim_start_matches('/');
+    let path = path.strip_suffix('/').unwrap_or(path);
+    if path.is_empty() {
+        vec![]
+    } else {
+        path.split('/').collect()
+    }
+}
+
+fn parse_segment(template: &str, mut segment: &str) -> Result<Vec<Piece>, OpenApiError> {
+    let invalid = || OpenApiError::InvalidPathTemplate {
+        template: template.into(),
+    };
+    let mut pieces = vec![];
+    while !segment.is_empty() {
+        match segment.find('{') {
+            Some(0) => {
+                let end = segment.find('}').ok_or_else(invalid)?;
+                let name = &segment[1..end];
+                if name.is_empty() || name.contains('{') {
+                    return Err(invalid());
+                }
+                //Two params in a row can't be told apart
+                if let Some(Piece::Param(_)) = pieces.last() {
+                    return Err(invalid());
+                }
+                pieces.push(Piece::Param(name.into()));
+                segment = &segment[end + 1..];
+            }
+            Some(start) => {
+                pieces.push(Piece::Literal(segment[..start].into()));
+                segment = &segment[start..];
+            }
+            None => {
+                if segment.contains('}') {
+                    return Err(invalid());
+                }
+                pieces.push(Piece::Literal(segment.into()));
+                segment = "";
+            }
+        }
+    }
+    Ok(pieces)
+}
+
+fn match_segment(
+    pieces: &[Piece],
+    mut segment: &str,
+    params: &mut IndexMap<String, String>,
+) -> Option<()> {
+    let mut pieces = pieces.iter().peekable();
+    while let Some(piece) = pieces.next() {
+        match piece {
+            Piece::Literal(literal) => segment = segment.strip_prefix(literal.as_str())?,
+            Piece::Param(name) => {
+                //A param runs up to the next literal in the segment, or the end of it
+                let end = match pieces.peek() {
+                    Some(Piece::Literal(literal)) => segment.find(literal.as_str())?,
+                    _ => segment.len(),
+                };
+                if end == 0 {
+                    return None;
+                }
+                params.insert(name.clone(), segment[..end].into());
+                segment = &segment[end..];
+            }
+        }
+    }
+    segment.is_empty().then_some(())
+}
+
+///An operation matched by the router.
+#[derive(Debug, Clone)]
+pub struct RouteMatch<'a> {
+    ///The path template from the spec, e.g. `/pets/{petId}`
+    pub path: &'a str,
+    pub method: Method,
+    pub operation: &'a Operation,
+    ///Path parameter name -> the value from the concrete path
+    pub path_params: IndexMap<String, String>,
+}
+
+///A parsed template for each operation, most specific first. Built once per dereferencer.
+#[derive(Debug)]
+pub(crate) struct Route {
+    template: PathTemplate,
+    method: Method,
+}
+
+///Matches concrete requests like `GET /pets/123` to the operations in a dereferenced spec.
+///Paths are relative to the server url, so strip any server base path first.
+#[derive(Debug)]
+pub struct Router<'a> {
+    paths: Option<&'a Paths>,
+    routes: &'a [Route],
+}
+
+impl<'a> Router<'a> {
+    ///Find the operation for a method and concrete path. Concrete paths are matched before
+    ///templated ones.
+    pub fn route(&self, method: Method, path: &str) -> Option<RouteMatch<'a>> {
+        let paths = self.paths?;
+        self.routes
+            .iter()
+            .filter(|route| route.method == method)
+            .find_map(|route| {
+                let path_params = route.template.matches(path)?;
+                let (path, path_item) = paths.paths.get_key_value(route.template.as_str())?;
+                Some(RouteMatch {
+                    path: path.as_str(),
+                    method,
+                    operation: method.operation(as_item(path_item)?)?,
+                    path_params,
+                })
+            })
+    }
+}
+
+impl DereferencedOpenApi {
+    ///A router over the spec's paths. The templates are parsed the first time and reused after
+    ///that, so it's cheap to call per request.
+    pub fn router(&self) -> Result<Router<'_>, OpenApiError> {
+        let routes = match self.routes.get() {
+            Some(routes) => routes,
+            None => {
+                let mut routes = self
+                    .iter_operations()
+                    .map(|(path, method, _)| {
+                        Ok(Route {
+                            template: path.parse()?,
+                            method,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, OpenApiError>>()?;
+                routes.sort_by_cached_key(|route| Reverse(route.template.specificity()));
+                self.routes.get_or_init(|| routes)
+            }
+        };
+        Ok(Router {
+            paths: self.openapi.paths.as_ref(),
+            routes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+
+    #[test]
+    pub fn test_path_template() -> Result<()> {
+        let template = PathTemplate::from_str("/reports/{id}.{format}")?;
+        assert_eq!(vec!["id", "format"], template.params().collect::<Vec<_>>());
+        let params = template.matches("/reports/42.csv?download=true").unwrap();
+        assert_eq!("42", params["id"]);
+        assert_eq!("csv", params["format"]);
+        assert!(template.matches("/reports/42").is_none());
+        assert!(template.matches("/reports/.csv").is_none());
+        assert!(PathTemplate::from_str("/pets/{petId").is_err());
+        assert!(PathTemplate::from_str("/pets/{a}{b}").is_err());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_router() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Router", "version": "1.0.0"},
+            "paths": {
+                "/pets/{petId}": {
+                    "get": {"operationId": "getPet", "responses": {"200": {"description": "ok"}}}
+                },
+                "/pets/mine": {
+                    "get": {"operationId": "getMyPets", "responses": {"200": {"description": "ok"}}}
+                },
+                "/": {
+                    "get": {"operationId": "root", "responses": {"200": {"description": "ok"}}}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let router = dereferenced.router()?;
+
+        let matched = router.route(Method::Get, "/pets/123").unwrap();
+        assert_eq!("/pets/{petId}", matched.path);
+        assert_eq!(Some("getPet"), matched.operation.operation_id.as_deref());
+        assert_eq!("123", matched.path_params["petId"]);
+
+        let matched = router.route(Method::Get, "/pets/mine/").unwrap();
+        assert_eq!(Some("getMyPets"), matched.operation.operation_id.as_deref());
+        assert!(matched.path_params.is_empty());
+
+        let matched = router.route(Method::Get, "/").unwrap();
+        assert_eq!(Some("root"), matched.operation.operation_id.as_deref());
+
+        assert!(router.route(Method::Post, "/pets/123").is_none());
+        assert!(router.route(Method::Get, "/owners/1").is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::pointer::percent_encode;
+use crate::{Method, OpenApiError};
+
+///A runtime expression as used by links and callbacks, e.g. `$request.path.petId` or
+///`$response.body#/id`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RuntimeExpression {
+    Url,
+    Method,
+    StatusCode,
+    Request(ExpressionSource),
+    Response(ExpressionSource),
+}
+
+///The part of a request or response a runtime expression reads from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ExpressionSource {
+    Header(String),
+    Query(String),
+    Path(String),
+    ///A JSON pointer into the body, empty for the whole body
+    Body(String),
+}
+
+///What was sent and received for an operation, for evaluating runtime expressions against.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exchange {
+    pub url: String,
+    pub method: Method,
+    pub status_code: Option<u16>,
+    pub request: CapturedMessage,
+    pub response: CapturedMessage,
+}
+
+///Headers, parameters and body of a request or response. Header names are matched ignoring
+///case.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CapturedMessage {
+    pub headers: Vec<(String, String)>,
+    pub query: Vec<(String, String)>,
+    pub path: HashMap<String, String>,
+    pub body: Option<Value>,
+}
+
+impl Exchange {
+    pub fn new(method: Method, url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            method,
+            status_code: None,
+            request: CapturedMessage::default(),
+            response: CapturedMessage::default(),
+        }
+    }
+}
+
+impl RuntimeExpression {
+    ///The value the expression points at in `exchange`, `None` if it wasn't captured.
+    pub fn evaluate(&self, exchange: &Exchange) -> Option<Value> {
+        match self {
+            RuntimeExpression::Url => Some(Value::String(exchange.url.clone())),
+            RuntimeExpression::Method => {
+                Some(Value::String(exchange.method.as_str().to_ascii_uppercase()))
+            }
+            RuntimeExpression::StatusCode => exchange.status_code.map(Value::from),
+            RuntimeExpression::Request(source) => source.evaluate(&exchange.request),
+            RuntimeExpression::Response(source) => source.evaluate(&exchange.response),
+        }
+    }
+}
+
+impl ExpressionSource {
+    fn evaluate(&self, message: &CapturedMessage) -> Option<Value> {
+        let text = |value: &String| Value::String(value.clone());
+        match self {
+            ExpressionSource::Header(name) => message
+                .headers
+                .iter()
+                .find(|(header, _)| header.eq_ignore_ascii_case(name))
+                .map(|(_, value)| text(value)),
+            ExpressionSource::Query(name) => message
+                .query
+                .iter()
+                .find(|(query, _)| query == name)
+                .map(|(_, value)| text(value)),
+            ExpressionSource::Path(name) => message.path.get(name).map(text),
+            ExpressionSource::Body(pointer) => message.body.as_ref()?.pointer(pointer).cloned(),
+        }
+    }
+}
+
+impl FromStr for RuntimeExpression {
+    type Err = OpenApiError;
+
+    fn from_str(expression: &str) -> Result<Self, OpenApiError> {
+        let invalid = |msg: &str| OpenApiError::InvalidRuntimeExpression {
+            expression: expression.into(),
+            msg: msg.into(),
+        };
+        match expression {
+            "$url" => return Ok(RuntimeExpression::Url),
+            "$method" => return Ok(RuntimeExpression::Method),
+            "$statusCode" => return Ok(RuntimeExpression::StatusCode),
+            _ => {}
+        }
+        let (wrap, source): (fn(ExpressionSource) -> Self, _) =
+            if let Some(source) = expression.strip_prefix("$request.") {
+                (RuntimeExpression::Request, source)
+            } else if let Some(source) = expression.strip_prefix("$response.") {
+                (RuntimeExpression::Response, source)
+            } else {
+                return Err(invalid(
+                    "expected $url, $method, $statusCode, $request. or $response.",
+                ));
+            };
+        let named = |name: &str| (!name.is_empty()).then(|| name.to_string());
+        let source = if let Some(name) = source.strip_prefix("header.") {
+            if name.is_empty() || !name.chars().all(is_token_char) {
+                return Err(invalid("header names must be a token"));
+            }
+            ExpressionSource::Header(name.into())
+        } else if let Some(name) = source.strip_prefix("query.") {
+            ExpressionSource::Query(named(name).ok_or_else(|| invalid("missing query name"))?)
+        } else if let Some(name) = source.strip_prefix("path.") {
+            ExpressionSource::Path(named(name).ok_or_else(|| invalid("missing path name"))?)
+        } else if source == "body" {
+            ExpressionSource::Body(String::new())
+        } else if let Some(body_pointer) = source.strip_prefix("body#") {
+            if !body_pointer.is_empty() && !body_pointer.starts_with('/') {
+                return Err(invalid("body pointers must start with /"));
+            }
+            ExpressionSource::Body(body_pointer.into())
+        } else {
+            return Err(invalid("expected header., query., path. or body"));
+        };
+        Ok(wrap(source))
+    }
+}
+
+///A string with runtime expressions in braces, like the callback key
+///`{$request.body#/callbackUrl}` or `https://example.com/hooks?id={$request.path.id}`. A string
+///that's just an expression without braces is taken as that expression.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExpressionTemplate {
+    pub parts: Vec<TemplatePart>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TemplatePart {
+    Literal(String),
+    Expression(RuntimeExpression),
+}
+
+impl ExpressionTemplate {
+    ///Every expression in the template.
+    pub fn expressions(&self) -> impl Iterator<Item = &RuntimeExpression> {
+        self.parts.iter().filter_map(|part| match part {
+            TemplatePart::Expression(expression) => Some(expression),
+            TemplatePart::Literal(_) => None,
+        })
+    }
+
+    ///Fill in the expressions from `exchange`. Strings are put in as they are and anything else
+    ///as JSON. Fails with `RuntimeValueMissing` if an expression has nothing captured for it.
+    pub fn substitute(&self, exchange: &Exchange) -> Result<String, OpenApiError> {
+        self.substitute_with(exchange, &|value| value.into())
+    }
+
+    ///`substitute` for a URL. A template that's only an expression is the whole URL and is put
+    ///in as it is, otherwise the values are percent encoded so they can't change the URL around
+    ///them.
+    pub fn substitute_url(&self, exchange: &Exchange) -> Result<String, OpenApiError> {
+        match self.parts.as_slice() {
+            [TemplatePart::Expression(_)] => self.substitute(exchange),
+            _ => self.substitute_with(exchange, &percent_encode),
+        }
+    }
+
+    fn substitute_with(
+        &self,
+        exchange: &Exchange,
+        encode: &dyn Fn(&str) -> String,
+    ) -> Result<String, OpenApiError> {
+        let mut substituted = String::new();
+        for part in &self.parts {
+            match part {
+                TemplatePart::Literal(literal) => substituted.push_str(literal),
+                TemplatePart::Expression(expression) => match expression.evaluate(exchange) {
+                    Some(Value::String(value)) => substituted.push_str(&encode(&value)),
+                    Some(value) => substituted.push_str(&encode(&value.to_string())),
+                    None => {
+                        return Err(OpenApiError::RuntimeValueMissing {
+                            expression: expression.to_string(),
+                        })
+                    }
+                },
+            }
+        }
+        Ok(substituted)
+    }
+}
+
+impl FromStr for ExpressionTemplate {
+    type Err = OpenApiError;
+
+    fn from_str(template: &str) -> Result<Self, OpenApiError> {
+        if template.starts_with('$') && !template.contains('{') {
+            return Ok(Self {
+                parts: vec![TemplatePart::Expression(template.parse()?)],
+            });
+        }
+        let mut parts = vec![];
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let end = closing_brace(&rest[start..])
+                .map(|end| start + end)
+                .ok_or_else(|| OpenApiError::InvalidRuntimeExpression {
+                    expression: template.into(),
+                    msg: "unclosed {".into(),
+                })?;
+            if start > 0 {
+                parts.push(TemplatePart::Literal(rest[..start].into()));
+            }
+            parts.push(TemplatePart::Expression(rest[start + 1..end].parse()?));
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Literal(rest.into()));
+        }
+        Ok(Self { parts })
+    }
+}
+
+///Where the expression starting at the `{` that begins `template` ends. A body's JSON pointer
+///can have a `}` in it, so that runs to the last `}` before the next expression.
+fn closing_brace(template: &str) -> Option<usize> {
+    let expression = &template[1..];
+    if !["$request.body#", "$response.body#"]
+        .iter()
+        .any(|body| expression.starts_with(body))
+    {
+        return template.find('}');
+    }
+    let next = expression.find('{').map_or(template.len(), |next| next + 1);
+    template[..next].rfind('}')
+}
+
+impl fmt::Display for ExpressionTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for part in &self.parts {
+            match part {
+                TemplatePart::Literal(literal) => f.write_str(literal)?,
+                TemplatePart::Expression(expression) => write!(f, "{{{expression}}}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+///The characters allowed in an HTTP header name.
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+impl fmt::Display for RuntimeExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeExpression::Url => f.write_str("$url"),
+            RuntimeExpression::Method => f.write_str("$method"),
+            RuntimeExpression::StatusCode => f.write_str("$statusCode"),
+            RuntimeExpression::Request(source) => write!(f, "$request.{source}"),
+            RuntimeExpression::Response(source) => write!(f, "$response.{source}"),
+        }
+    }
+}
+
+impl fmt::Display for ExpressionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpressionSource::Header(name) => write!(f, "header.{name}"),
+            ExpressionSource::Query(name) => write!(f, "query.{name}"),
+            ExpressionSource::Path(name) => write!(f, "path.{name}"),
+            ExpressionSource::Body(pointer) if pointer.is_empty() => f.write_str("body"),
+            ExpressionSource::Body(pointer) => write!(f, "body#{pointer}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    pub fn test_runtime_expression() -> Result<()> {
+        let expressions = [
+            "$url",
+            "$method",
+            "$statusCode",
+            "$request.header.X-Request-Id",
+            "$request.query.limit",
+            "$request.path.petId",
+            "$request.body",
+            "$response.body#/owner/id",
+        ];
+        for expression in expressions {
+            assert_eq!(
+                expression,
+                RuntimeExpression::from_str(expression)?.to_string()
+            );
+        }
+        assert_eq!(
+            RuntimeExpression::Response(ExpressionSource::Body("/id".into())),
+            RuntimeExpression::from_str("$response.body#/id")?
+        );
+        for invalid in [
+            "url",
+            "$request",
+            "$request.cookie.id",
+            "$request.path.",
+            "$request.header.bad header",
+            "$response.body#id",
+        ] {
+            assert!(RuntimeExpression::from_str(invalid).is_err(), "{invalid}");
+        }
+
+        let mut exchange = Exchange::new(Method::Post, "https://example.com/pets/7");
+        exchange.status_code = Some(201);
+        exchange.request.headers = vec![("x-request-id".into(), "abc".into())];
+        exchange.request.path = HashMap::from([("petId".into(), "7".into())]);
+        exchange.response.body = Some(json!({"id": 7, "owner": {"id": "bob"}}));
+        let evaluate = |expression: &str| -> Result<Option<Value>> {
+            Ok(RuntimeExpression::from_str(expression)?.evaluate(&exchange))
+        };
+        assert_eq!(Some(json!("POST")), evaluate("$method")?);
+        assert_eq!(Some(json!(201)), evaluate("$statusCode")?);
+        assert_eq!(
+            Some(json!("abc")),
+            evaluate("$request.header.X-Request-Id")?
+        );
+        assert_eq!(Some(json!("7")), evaluate("$request.path.petId")?);
+        assert_eq!(Some(json!("bob")), evaluate("$response.body#/owner/id")?);
+        assert_eq!(None, evaluate("$request.query.limit")?);
+        assert_eq!(None, evaluate("$request.body")?);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_expression_template() -> Result<()> {
+        let template = ExpressionTemplate::from_str(
+            "https://example.com/hooks/{$request.path.petId}?status={$statusCode}",
+        )?;
+        assert_eq!(
+            "https://example.com/hooks/{$request.path.petId}?status={$statusCode}",
+            template.to_string()
+        );
+        assert_eq!(2, template.expressions().count());
+
+        let mut exchange = Exchange::new(Method::Post, "https://example.com/pets");
+        exchange.status_code = Some(201);
+        exchange.request.path = HashMap::from([("petId".into(), "7".into())]);
+        exchange.request.body = Some(json!({"callbackUrl": "https://client.example.com/cb"}));
+        assert_eq!(
+            "https://example.com/hooks/7?status=201",
+            template.substitute(&exchange)?
+        );
+        assert_eq!(
+            "https://client.example.com/cb",
+            ExpressionTemplate::from_str("{$request.body#/callbackUrl}")?.substitute(&exchange)?
+        );
+        assert_eq!(
+            "https://example.com/pets",
+            ExpressionTemplate::from_str("$url")?.substitute(&exchange)?
+        );
+        assert!(ExpressionTemplate::from_str("{$request.query.id}")?
+            .substitute(&exchange)
+            .is_err());
+        assert!(ExpressionTemplate::from_str("https://example.com/{$url").is_err());
+
+        exchange.request.body = Some(json!({"a}b": "x y", "id": "1/2"}));
+        let template = ExpressionTemplate::from_str(
+            "https://example.com/{$request.body#/a}b}?id={$request.body#/id}",
+        )?;
+        assert_eq!(
+            "https://example.com/x%20y?id=1%2F2",
+            template.substitute_url(&exchange)?
+        );
+        assert_eq!(
+            "https://example.com/x y?id=1/2",
+            template.substitute(&exchange)?
+        );
+        Ok(())
+    }
+}
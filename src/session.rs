@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+use std::io::{BufRead, ErrorKind, Lines};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+
+use crate::loader::ResolvedDocuments;
+use crate::{
+    parse_json, DereferencedOpenApi, DocumentLoader, OpenApiDereferencer,
+    OpenApiDereferencerBuilder, OpenApiError, ParseLocation,
+};
+
+///Caches shared by every spec dereferenced through it, for a platform dereferencing lots of
+///specs that pull in the same component files. Each external document is loaded and resolved
+///once, by whichever spec refers to it first, and kept until it's evicted.
+pub struct DerefSession {
+    loader: Option<SessionLoader>,
+    resolved: Arc<ResolvedDocuments>,
+}
+
+///Keeps every document its loader returns, keyed by URI. Clones share the documents.
+#[derive(Clone)]
+struct SessionLoader(Arc<LoadedDocuments>);
+
+struct LoadedDocuments {
+    loader: Box<dyn DocumentLoader>,
+    documents: Mutex<HashMap<String, Value>>,
+    loads: AtomicUsize,
+}
+
+impl DocumentLoader for SessionLoader {
+    fn load(&self, uri: &str) -> Result<Value, OpenApiError> {
+        let loaded = &self.0;
+        if let Some(document) = lock(&loaded.documents).get(uri) {
+            return Ok(document.clone());
+        }
+        loaded.loads.fetch_add(1, Ordering::Relaxed);
+        let document = loaded.loader.load(uri)?;
+        lock(&loaded.documents).insert(uri.into(), document.clone());
+        Ok(document)
+    }
+}
+
+///The maps only ever hold finished entries, so one left behind by a panic is still good.
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+impl DerefSession {
+    ///A session for specs without refs to other documents.
+    pub fn new() -> Self {
+        Self {
+            loader: None,
+            resolved: Arc::default(),
+        }
+    }
+
+    ///A session loading the documents external refs point at with `loader`.
+    pub fn with_loader(loader: impl DocumentLoader + 'static) -> Self {
+        Self {
+            loader: Some(SessionLoader(Arc::new(LoadedDocuments {
+                loader: Box::new(loader),
+                documents: Mutex::default(),
+                loads: AtomicUsize::new(0),
+            }))),
+            resolved: Arc::default(),
+        }
+    }
+
+    pub fn dereference_value(&self, json: Value) -> Result<DereferencedOpenApi, OpenApiError> {
+        self.dereference_with(OpenApiDereferencer::builder(), json)
+    }
+
+    pub fn dereference_str(&self, the_str: &str) -> Result<DereferencedOpenApi, OpenApiError> {
+        let json = parse_json(the_str.as_bytes(), "string")?;
+        self.dereference_value(json)
+    }
+
+    ///Dereference with options of your own. The builder's document loader is replaced by the
+    ///session's.
+    pub fn dereference_with(
+        &self,
+        mut builder: OpenApiDereferencerBuilder,
+        json: Value,
+    ) -> Result<DereferencedOpenApi, OpenApiError> {
+        if let Some(loader) = &self.loader {
+            builder = builder
+                .document_loader(loader.clone())
+                .resolved_documents(self.resolved.clone());
+        }
+        builder.build_from_value(json)?.dereference()
+    }
+
+    ///Dereference a stream of specs one at a time as they're pulled from the iterator.
+    pub fn dereference_all<'s, I>(
+        &'s self,
+        documents: I,
+    ) -> impl Iterator<Item = Result<DereferencedOpenApi, OpenApiError>> + 's
+    where
+        I: IntoIterator<Item = Value>,
+        I::IntoIter: 's,
+    {
+        documents
+            .into_iter()
+            .map(move |json| self.dereference_value(json))
+    }
+
+    ///Dereference newline delimited JSON, e.g. a dump from a spec registry, with a spec per
+    ///line. Blank lines are skipped. A line that isn't JSON gives a `ParsingError` with its line
+    ///number and the lines after it carry on as normal.
+    pub fn dereference_ndjson<R: BufRead>(&self, reader: R) -> NdjsonSpecs<'_, R> {
+        NdjsonSpecs {
+            session: self,
+            lines: reader.lines(),
+            line: 0,
+            done: false,
+        }
+    }
+
+    ///How many times the loader has been asked for a document, each URI counting once.
+    pub fn documents_loaded(&self) -> usize {
+        self.loader
+            .as_ref()
+            .map_or(0, |loader| loader.0.loads.load(Ordering::Relaxed))
+    }
+
+    ///How many external documents, or the parts of them refs point at, are kept resolved.
+    pub fn cached_documents(&self) -> usize {
+        self.resolved.len()
+    }
+
+    ///Forget `uri` and everything resolved from it, e.g. after the document changed. It's loaded
+    ///again the next time a spec refers to it.
+    pub fn evict(&self, uri: &str) {
+        if let Some(loader) = &self.loader {
+            lock(&loader.0.documents).remove(uri);
+        }
+        self.resolved.evict(uri);
+    }
+
+    ///Forget every document, e.g. to free the memory between batches of specs.
+    pub fn clear(&self) {
+        if let Some(loader) = &self.loader {
+            lock(&loader.0.documents).clear();
+        }
+        self.resolved.clear();
+    }
+}
+
+///The specs in newline delimited JSON, see [`DerefSession::dereference_ndjson`].
+pub struct NdjsonSpecs<'s, R> {
+    session: &'s DerefSession,
+    lines: Lines<R>,
+    line: usize,
+    done: bool,
+}
+
+impl<R: BufRead> Iterator for NdjsonSpecs<'_, R> {
+    type Item = Result<DereferencedOpenApi, OpenApiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let line = self.lines.next()?;
+            self.line += 1;
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    //A line that isn't UTF-8 has still been read past, anything else won't go
+                    //any better reading again
+                    self.done = e.kind() != ErrorKind::InvalidData;
+                    return Some(Err(OpenApiError::ParsingError {
+                        msg: format!("Error reading line {e}"),
+                        location: Some(ParseLocation::at(self.line, 1)),
+                    }));
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Some(match serde_json::from_str(&line) {
+                Ok(json) => self.session.dereference_value(json),
+                Err(e) => Err(OpenApiError::ParsingError {
+                    location: Some(ParseLocation::at(self.line, e.column())),
+                    msg: format!("Error parsing from line to serde {e}"),
+                }),
+            });
+        }
+        None
+    }
+}
+
+impl Default for DerefSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryDocumentLoader;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    pub fn test_deref_session() -> Result<()> {
+        let loader = InMemoryDocumentLoader::default().with_document(
+            "common.json",
+            json!({"Error": {"description": "Something went wrong"}}),
+        );
+        let spec = |title: &str| {
+            json!({
+                "openapi": "3.1.0",
+                "info": {"title": title, "version": "1.0.0"},
+                "paths": {
+                    "/pets": {
+                        "get": {"responses": {"default": {"$ref": "common.json#/Error"}}}
+                    }
+                }
+            })
+        };
+        let session = DerefSession::with_loader(loader);
+        let pets = session.dereference_value(spec("Pets"))?;
+        let owners = session.dereference_value(spec("Owners"))?;
+        session.dereference_value(spec("Pets"))?;
+        assert_eq!(1, session.documents_loaded());
+        assert_eq!(1, session.cached_documents());
+        for dereferenced in [pets, owners] {
+            let json = serde_json::to_value(dereferenced.into_openapi())?;
+            assert_eq!(
+                "Something went wrong",
+                json["paths"]["/pets"]["get"]["responses"]["default"]["description"]
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_dereference_ndjson() -> Result<()> {
+        let loader = InMemoryDocumentLoader::default().with_document(
+            "common.json",
+            json!({"Error": {"description": "Something went wrong"}}),
+        );
+        let spec = |title: &str| {
+            json!({
+                "openapi": "3.1.0",
+                "info": {"title": title, "version": "1.0.0"},
+                "paths": {
+                    "/pets": {
+                        "get": {"responses": {"default": {"$ref": "common.json#/Error"}}}
+                    }
+                }
+            })
+            .to_string()
+        };
+        let mut ndjson = [
+            spec("Pets"),
+            String::new(),
+            "{not json".into(),
+            spec("Owners"),
+        ]
+        .join("\n")
+        .into_bytes();
+        ndjson.extend_from_slice(b"\n\xff\xfe\n");
+        ndjson.extend_from_slice(spec("Stores").as_bytes());
+        let session = DerefSession::with_loader(loader);
+        let results: Vec<_> = session.dereference_ndjson(&ndjson[..]).collect();
+        assert_eq!(5, results.len());
+        assert!(matches!(
+            &results[1],
+            Err(OpenApiError::ParsingError {
+                location: Some(ParseLocation { line: Some(3), .. }),
+                ..
+            })
+        ));
+        assert!(matches!(
+            &results[3],
+            Err(OpenApiError::ParsingError {
+                location: Some(ParseLocation { line: Some(5), .. }),
+                ..
+            })
+        ));
+        let titles: Vec<String> = results
+            .into_iter()
+            .filter_map(Result::ok)
+            .map(|dereferenced| dereferenced.into_openapi().info.title)
+            .collect();
+        assert_eq!(vec!["Pets", "Owners", "Stores"], titles);
+        assert_eq!(1, session.documents_loaded());
+        assert_eq!(1, session.cached_documents());
+
+        let specs =
+            vec![json!({"openapi": "3.1.0", "info": {"title": "Empty", "version": "1.0.0"}}); 2];
+        let session = DerefSession::new();
+        let dereferenced: Vec<_> = session.dereference_all(specs).collect();
+        assert!(dereferenced.iter().all(Result::is_ok));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_deref_session_shares_component_files() -> Result<()> {
+        let loader = InMemoryDocumentLoader::default()
+            .with_document(
+                "components/schemas/Pet.json",
+                json!({"type": "object", "properties": {"owner": {"$ref": "./Owner.json"}}}),
+            )
+            .with_document(
+                "components/schemas/Owner.json",
+                json!({"type": "object", "properties": {"name": {"type": "string"}}}),
+            );
+        let spec = |path: &str| {
+            json!({
+                "openapi": "3.1.0",
+                "info": {"title": path, "version": "1.0.0"},
+                "paths": {
+                    path: {
+                        "get": {
+                            "responses": {
+                                "200": {
+                                    "description": "ok",
+                                    "content": {"application/json": {"schema": {"$ref": "components/schemas/Pet.json"}}}
+                                }
+                            }
+                        }
+                    }
+                }
+            })
+        };
+        let session = DerefSession::with_loader(loader);
+        session.dereference_value(spec("/pets"))?;
+        assert_eq!(2, session.documents_loaded());
+        assert_eq!(2, session.cached_documents());
+
+        let owners = session.dereference_value(spec("/owners"))?;
+        assert_eq!(2, session.documents_loaded());
+        assert_eq!(
+            json!({"type": "object", "properties": {"owner": {"$ref": "#/components/schemas/Owner"}}}),
+            owners.json["components"]["schemas"]["Pet"]
+        );
+        assert!(owners.json["components"]["schemas"]["Owner"].is_object());
+
+        session.evict("components/schemas/Owner.json");
+        assert_eq!(0, session.cached_documents());
+        session.dereference_value(spec("/stores"))?;
+        assert_eq!(3, session.documents_loaded());
+
+        session.clear();
+        assert_eq!(0, session.cached_documents());
+        Ok(())
+    }
+}
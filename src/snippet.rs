@@ -0,0 +1,391 @@
+use std::fmt::Write;
+
+use openapiv3::v3_1::{Parameter, PathStyle, QueryStyle};
+use serde_json::Value;
+
+use crate::content::is_json;
+use crate::example::{example_text, media_type_example, parameter_example, server_variables};
+use crate::parameters::parameter_data;
+use crate::pointer::percent_encode;
+use crate::{as_item, DereferencedOpenApi, Method, OpenApiError};
+
+///What kind of example request [`DereferencedOpenApi::snippet_for`] writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SnippetFormat {
+    ///A curl command
+    Curl,
+    ///The raw HTTP/1.1 request
+    Http,
+}
+
+///An example request put together from the spec, before it's written out.
+struct ExampleRequest {
+    method: Method,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Option<Value>,
+    ///Whether the body is JSON, otherwise string examples are sent as they are
+    json_body: bool,
+}
+
+impl DereferencedOpenApi {
+    ///A ready to run example request for an operation. The URL uses the closest server to the
+    ///operation with its variables at their defaults, and parameters and the request body are
+    ///the examples in the spec, or generated from the schemas. Parameters are written out the
+    ///way their `style` and `explode` say, and a JSON request body is picked over the others.
+    ///Fails with `InvalidHeaderValue` if a header example has a line break in it.
+    pub fn snippet_for(
+        &self,
+        path: &str,
+        method: Method,
+        format: SnippetFormat,
+    ) -> Result<String, OpenApiError> {
+        let request = self.example_request(path, method)?;
+        Ok(match format {
+            SnippetFormat::Curl => curl(&request),
+            SnippetFormat::Http => http(&request),
+        })
+    }
+
+    fn example_request(&self, path: &str, method: Method) -> Result<ExampleRequest, OpenApiError> {
+        let path_item = self.path_item(path)?;
+        let operation =
+            method
+                .operation(path_item)
+                .ok_or_else(|| OpenApiError::OperationNotFound {
+                    path: path.into(),
+                    method,
+                })?;
+        let server = [
+            &operation.servers,
+            &path_item.servers,
+            &self.openapi.servers,
+        ]
+        .into_iter()
+        .find_map(|servers| servers.first());
+        let mut url = match server {
+            Some(server) => {
+                let mut url = server.url.trim_end_matches('/').to_string();
+                for (name, default) in server_variables(server) {
+                    url = url.replace(&format!("{{{name}}}"), &default);
+                }
+                url
+            }
+            None => String::new(),
+        };
+
+        let mut target = path.to_string();
+        let mut query = vec![];
+        let mut headers = vec![];
+        let mut cookies = vec![];
+        for parameter in self.effective_parameters(path, method)? {
+            let data = parameter_data(parameter);
+            let name = &data.name;
+            let parts = Parts::of(&parameter_example(parameter));
+            match parameter {
+                Parameter::Path { style, .. } => {
+                    let explode = data.explode.unwrap_or(false);
+                    let value = match style {
+                        PathStyle::Simple => simple(parts, explode, percent_encode),
+                        PathStyle::Label => label(parts, explode),
+                        PathStyle::Matrix => {
+                            format!(";{}", form(name, parts, explode, ",").join(";"))
+                        }
+                    };
+                    target = target.replace(&format!("{{{name}}}"), &value);
+                }
+                Parameter::Query { style, .. } => {
+                    let explode = data.explode.unwrap_or(true);
+                    query.extend(match style {
+                        QueryStyle::Form => form(name, parts, explode, ","),
+                        QueryStyle::SpaceDelimited => form(name, parts, explode, "%20"),
+                        QueryStyle::PipeDelimited => form(name, parts, explode, "%7C"),
+                        QueryStyle::DeepObject => deep_object(name, parts),
+                    })
+                }
+                Parameter::Header { .. } => {
+                    let value = simple(parts, data.explode.unwrap_or(false), str::to_string);
+                    if value.contains(['\r', '\n']) {
+                        return Err(OpenApiError::InvalidHeaderValue { name: name.clone() });
+                    }
+                    headers.push((name.clone(), value));
+                }
+                Parameter::Cookie { .. } => {
+                    cookies.extend(form(name, parts, data.explode.unwrap_or(true), ","))
+                }
+            }
+        }
+        url.push_str(&target);
+        if !query.is_empty() {
+            url = format!("{url}?{}", query.join("&"));
+        }
+        if !cookies.is_empty() {
+            headers.push(("Cookie".into(), cookies.join("; ")));
+        }
+
+        let content = operation
+            .request_body
+            .as_ref()
+            .and_then(as_item)
+            .and_then(|request_body| {
+                let content = &request_body.content;
+                let json = content.iter().find(|(media_type, _)| is_json(media_type));
+                json.or_else(|| content.iter().next())
+            });
+        let (mut body, mut json_body) = (None, false);
+        if let Some((media_type, content)) = content {
+            headers.push(("Content-Type".into(), media_type.clone()));
+            body = media_type_example(content);
+            json_body = is_json(media_type);
+        }
+        Ok(ExampleRequest {
+            method,
+            url,
+            headers,
+            body,
+            json_body,
+        })
+    }
+}
+
+///A parameter example split up the way the parameter styles need it.
+enum Parts {
+    Value(String),
+    List(Vec<String>),
+    Fields(Vec<(String, String)>),
+}
+
+impl Parts {
+    fn of(example: &Value) -> Self {
+        match example {
+            Value::Array(items) => Parts::List(items.iter().map(example_text).collect()),
+            Value::Object(fields) => Parts::Fields(
+                fields
+                    .iter()
+                    .map(|(name, value)| (name.clone(), example_text(value)))
+                    .collect(),
+            ),
+            value => Parts::Value(example_text(value)),
+        }
+    }
+}
+
+///The `simple` style, `a,b` for a list and `x,1,y,2`, or `x=1,y=2` exploded, for an object.
+fn simple(parts: Parts, explode: bool, encode: fn(&str) -> String) -> String {
+    match parts {
+        Parts::Value(value) => encode(&value),
+        Parts::List(items) => items
+            .iter()
+            .map(|item| encode(item))
+            .collect::<Vec<_>>()
+            .join(","),
+        Parts::Fields(fields) => fields
+            .iter()
+            .map(|(name, value)| {
+                let separator = if explode { "=" } else { "," };
+                format!("{}{separator}{}", encode(name), encode(value))
+            })
+            .collect::<Vec<_>>()
+            .join(","),
+    }
+}
+
+///The `label` style, `.a.b` exploded or `.a,b` not.
+fn label(parts: Parts, explode: bool) -> String {
+    let separator = if explode { "." } else { "," };
+    format!(
+        ".{}",
+        simple(parts, explode, percent_encode).replace(',', separator)
+    )
+}
+
+///`name=value` pairs for the `form` style and the delimited styles based on it. Exploded, a list
+///is a pair for each item and an object a pair for each field, otherwise the values are joined
+///with `separator`.
+fn form(name: &str, parts: Parts, explode: bool, separator: &str) -> Vec<String> {
+    let name = percent_encode(name);
+    match parts {
+        Parts::Value(value) => vec![format!("{name}={}", percent_encode(&value))],
+        Parts::List(items) if explode => items
+            .iter()
+            .map(|item| format!("{name}={}", percent_encode(item)))
+            .collect(),
+        Parts::List(items) => {
+            let items: Vec<String> = items.iter().map(|item| percent_encode(item)).collect();
+            vec![format!("{name}={}", items.join(separator))]
+        }
+        Parts::Fields(fields) if explode => fields
+            .iter()
+            .map(|(field, value)| format!("{}={}", percent_encode(field), percent_encode(value)))
+            .collect(),
+        fields => vec![format!("{name}={}", simple(fields, false, percent_encode))],
+    }
+}
+
+///The `deepObject` style, `name[field]=value` for each field.
+fn deep_object(name: &str, parts: Parts) -> Vec<String> {
+    match parts {
+        Parts::Fields(fields) => fields
+            .iter()
+            .map(|(field, value)| {
+                let key = format!("{name}[{field}]");
+                format!("{}={}", percent_encode(&key), percent_encode(value))
+            })
+            .collect(),
+        parts => form(name, parts, true, ","),
+    }
+}
+
+///Quote for a POSIX shell.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+fn body_text(request: &ExampleRequest, pretty: bool) -> Option<String> {
+    Some(match request.body.as_ref()? {
+        Value::String(text) if !request.json_body => text.clone(),
+        body if pretty => serde_json::to_string_pretty(body).unwrap_or_default(),
+        body => body.to_string(),
+    })
+}
+
+fn curl(request: &ExampleRequest) -> String {
+    let mut lines = vec![];
+    let mut command = String::from("curl");
+    if request.method != Method::Get {
+        let _ = write!(
+            command,
+            " -X {}",
+            request.method.as_str().to_ascii_uppercase()
+        );
+    }
+    let _ = write!(command, " {}", shell_quote(&request.url));
+    lines.push(command);
+    for (name, value) in &request.headers {
+        lines.push(format!("  -H {}", shell_quote(&format!("{name}: {value}"))));
+    }
+    if let Some(body) = body_text(request, false) {
+        lines.push(format!("  --data {}", shell_quote(&body)));
+    }
+    lines.join(" \\\n")
+}
+
+fn http(request: &ExampleRequest) -> String {
+    let (host, target) = match request.url.split_once("://") {
+        Some((_, rest)) => match rest.find('/') {
+            Some(slash) => (Some(&rest[..slash]), &rest[slash..]),
+            None => (Some(rest), "/"),
+        },
+        None => (None, request.url.as_str()),
+    };
+    let method = request.method.as_str().to_ascii_uppercase();
+    let mut snippet = format!("{method} {target} HTTP/1.1\r\n");
+    if let Some(host) = host {
+        let _ = write!(snippet, "Host: {host}\r\n");
+    }
+    for (name, value) in &request.headers {
+        let _ = write!(snippet, "{name}: {value}\r\n");
+    }
+    let body = body_text(request, true);
+    if let Some(body) = &body {
+        let _ = write!(snippet, "Content-Length: {}\r\n", body.len());
+    }
+    snippet.push_str("\r\n");
+    if let Some(body) = &body {
+        snippet.push_str(body);
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_snippet_for() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Pets", "version": "1.0.0"},
+            "servers": [{"url": "https://{env}.example.com/v1", "variables": {"env": {"default": "api"}}}],
+            "paths": {
+                "/pets/{petId}": {
+                    "parameters": [{"name": "petId", "in": "path", "required": true, "schema": {"type": "integer"}, "example": 7}],
+                    "put": {
+                        "parameters": [
+                            {"name": "note", "in": "query", "schema": {"type": "string"}, "example": "it's me"},
+                            {"name": "ids", "in": "query", "schema": {"type": "array"}, "example": [1, 2]},
+                            {"name": "tags", "in": "query", "explode": false, "schema": {"type": "array"}, "example": ["a", "b c"]},
+                            {"name": "filter", "in": "query", "style": "deepObject", "schema": {"type": "object"}, "example": {"color": "red"}},
+                            {"name": "X-Trace", "in": "header", "schema": {"type": "string"}},
+                            {"name": "session", "in": "cookie", "schema": {"type": "string"}, "example": "a;b"}
+                        ],
+                        "requestBody": {
+                            "content": {
+                                "text/plain": {"example": "Rex"},
+                                "application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}
+                            }
+                        },
+                        "responses": {"200": {"description": "ok"}}
+                    },
+                    "get": {
+                        "servers": [{"url": "http://localhost:8080"}],
+                        "parameters": [
+                            {"name": "fields", "in": "query", "style": "pipeDelimited", "explode": false, "schema": {"type": "array"}, "example": ["id", "name"]}
+                        ],
+                        "responses": {"200": {"description": "ok"}}
+                    },
+                    "post": {
+                        "parameters": [
+                            {"name": "X-Trace", "in": "header", "schema": {"type": "string"}, "example": "a\r\nX-Injected: yes"}
+                        ],
+                        "responses": {"200": {"description": "ok"}}
+                    }
+                }
+            },
+            "components": {
+                "schemas": {"Pet": {"type": "object", "properties": {"name": {"type": "string", "examples": ["Rex"]}}}}
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let query = "note=it%27s%20me&ids=1&ids=2&tags=a,b%20c&filter%5Bcolor%5D=red";
+        assert_eq!(
+            [
+                format!("curl -X PUT 'https://api.example.com/v1/pets/7?{query}' \\"),
+                "  -H 'X-Trace: string' \\".to_string(),
+                "  -H 'Cookie: session=a%3Bb' \\".to_string(),
+                "  -H 'Content-Type: application/json' \\".to_string(),
+                r#"  --data '{"name":"Rex"}'"#.to_string(),
+            ]
+            .join("\n"),
+            dereferenced.snippet_for("/pets/{petId}", Method::Put, SnippetFormat::Curl)?
+        );
+        assert_eq!(
+            "curl 'http://localhost:8080/pets/7?fields=id%7Cname'",
+            dereferenced.snippet_for("/pets/{petId}", Method::Get, SnippetFormat::Curl)?
+        );
+        assert_eq!(
+            format!(
+                "PUT /v1/pets/7?{query} HTTP/1.1\r\n\
+                 Host: api.example.com\r\n\
+                 X-Trace: string\r\n\
+                 Cookie: session=a%3Bb\r\n\
+                 Content-Type: application/json\r\n\
+                 Content-Length: 19\r\n\
+                 \r\n\
+                 {{\n  \"name\": \"Rex\"\n}}"
+            ),
+            dereferenced.snippet_for("/pets/{petId}", Method::Put, SnippetFormat::Http)?
+        );
+        assert!(matches!(
+            dereferenced.snippet_for("/pets/{petId}", Method::Post, SnippetFormat::Curl),
+            Err(OpenApiError::InvalidHeaderValue { .. })
+        ));
+        assert!(dereferenced
+            .snippet_for("/pets/{petId}", Method::Delete, SnippetFormat::Curl)
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+use indexmap::IndexMap;
+use openapiv3::schemars::schema::Schema as SchemarsSchema;
+use openapiv3::v3_1::{MediaType, Operation, StatusCode};
+use serde_json::{Map, Value};
+
+use crate::{as_item, pointer, ref_to_json_pointer, DereferencedOpenApi, Method, OpenApiError};
+
+const DRAFT_2020_12: &str = "https://json-schema.org/draft/2020-12/schema";
+
+impl DereferencedOpenApi {
+    ///The schema of an operation's request body for `media_type`, as a JSON Schema document that
+    ///doesn't need the rest of the spec. Refs left by dereferencing (i.e. recursive schemas) are
+    ///pointed at copies of their targets under `$defs`.
+    pub fn request_schema(
+        &self,
+        path: &str,
+        method: Method,
+        media_type: &str,
+    ) -> Result<Value, OpenApiError> {
+        let operation = self.operation(path, method)?;
+        let request_body = operation
+            .request_body
+            .as_ref()
+            .and_then(as_item)
+            .ok_or_else(|| OpenApiError::RequestBodyNotFound {
+                path: path.into(),
+                method,
+            })?;
+        self.media_type_schema(&request_body.content, media_type)
+    }
+
+    ///The schema of the response to an operation for `status` and `media_type`, as a JSON Schema
+    ///document that doesn't need the rest of the spec. The response is picked the same way a
+    ///server would, exact status first, then the `2XX` style range, then `default`.
+    pub fn response_schema(
+        &self,
+        path: &str,
+        method: Method,
+        status: u16,
+        media_type: &str,
+    ) -> Result<Value, OpenApiError> {
+        let operation = self.operation(path, method)?;
+        let response = operation
+            .responses
+            .as_ref()
+            .and_then(|responses| {
+                responses
+                    .responses
+                    .get(&StatusCode::Code(status))
+                    .or_else(|| responses.responses.get(&StatusCode::Range(status / 100)))
+                    .or(responses.default.as_ref())
+            })
+            .and_then(as_item)
+            .ok_or(OpenApiError::ResponseNotFound { status })?;
+        self.media_type_schema(&response.content, media_type)
+    }
+
+    fn operation(&self, path: &str, method: Method) -> Result<&Operation, OpenApiError> {
+        method
+            .operation(self.path_item(path)?)
+            .ok_or_else(|| OpenApiError::OperationNotFound {
+                path: path.into(),
+                method,
+            })
+    }
+
+    fn media_type_schema(
+        &self,
+        content: &IndexMap<String, MediaType>,
+        media_type: &str,
+    ) -> Result<Value, OpenApiError> {
+        let schema = content
+            .get(media_type)
+            .and_then(|media_type| media_type.schema.as_ref())
+            .ok_or_else(|| OpenApiError::MediaTypeNotFound {
+                media_type: media_type.into(),
+            })?;
+        self.standalone_schema(&schema.json_schema)
+    }
+
+    ///Serialize a dereferenced schema, bringing along whatever its remaining refs point at.
+    pub(crate) fn standalone_schema(&self, schema: &SchemarsSchema) -> Result<Value, OpenApiError> {
+        let mut schema = to_value(schema)?;
+        let mut defs = Map::new();
+        let mut pending = vec![];
+        //`#/$defs` refs into the schema's own `$defs` still work once it's the root
+        let own_defs = schema.get("$defs").cloned();
+        self.point_refs_at_defs(&mut schema, None, own_defs.as_ref(), &mut pending)?;
+        while let Some(name) = pending.pop() {
+            if defs.contains_key(&name) {
+                continue;
+            }
+            let (mut target, component) = self.def_target(&name)?;
+            let scope = component.then_some(name.as_str());
+            self.point_refs_at_defs(&mut target, scope, None, &mut pending)?;
+            defs.insert(name, target);
+        }
+        if let Value::Object(object) = &mut schema {
+            if !defs.is_empty() {
+                match object.get_mut("$defs") {
+                    Some(Value::Object(own_defs)) => own_defs.extend(defs),
+                    _ => {
+                        object.insert("$defs".into(), Value::Object(defs));
+                    }
+                }
+            }
+            object.insert("$schema".into(), DRAFT_2020_12.into());
+        }
+        Ok(schema)
+    }
+
+    ///The dereferenced component schema, or the raw JSON for anything else, along with whether
+    ///it's a component.
+    fn def_target(&self, name: &str) -> Result<(Value, bool), OpenApiError> {
+        let component = self
+            .openapi
+            .components
+            .as_ref()
+            .and_then(|components| components.schemas.get(name));
+        match component {
+            Some(schema) => Ok((to_value(&schema.json_schema)?, true)),
+            None => self
+                .json
+                .pointer(name)
+                .cloned()
+                .map(|target| (target, false))
+                .ok_or_else(|| OpenApiError::RefNotFound {
+                    reference: format!("#{name}"),
+                }),
+        }
+    }
+
+    ///Point refs at `$defs`, queueing the names of the defs they need. Component schemas keep
+    ///their name; anything else is named after its pointer. `scope` is the component the value
+    ///came from, which `#/$defs` refs are resolved against.
+    fn point_refs_at_defs(
+        &self,
+        value: &mut Value,
+        scope: Option<&str>,
+        own_defs: Option<&Value>,
+        pending: &mut Vec<String>,
+    ) -> Result<(), OpenApiError> {
+        match value {
+            Value::Object(object) => {
+                //Anchor refs become pointers, and copies of the schema would repeat the anchor
+                object.remove("$anchor");
+                for (key, value) in object.iter_mut() {
+                    match value {
+                        Value::String(reference) if key == "$ref" => {
+                            if !reference.starts_with('#') {
+                                continue;
+                            }
+                            let is_own_def = reference
+                                .strip_prefix("#/$defs")
+                                .zip(own_defs)
+                                .is_some_and(|(def, own_defs)| {
+                                    own_defs.pointer(&pointer::percent_decode(def)).is_some()
+                                });
+                            if is_own_def {
+                                continue;
+                            }
+                            let target = self.def_pointer(reference, scope)?;
+                            let (name, rest) = match target.strip_prefix("/components/schemas/") {
+                                Some(component) => match component.split_once('/') {
+                                    Some((name, rest)) => {
+                                        (pointer::unescape(name), format!("/{rest}"))
+                                    }
+                                    None => (pointer::unescape(component), String::new()),
+                                },
+                                None => (target, String::new()),
+                            };
+                            *reference = format!("{}{rest}", pointer::push("#/$defs", &name));
+                            pending.push(name);
+                        }
+                        //Payloads aren't schemas, so refs in them are left alone
+                        _ if key.starts_with("x-")
+                            || ["example", "examples", "default", "const", "enum"]
+                                .contains(&key.as_str()) => {}
+                        value => self.point_refs_at_defs(value, scope, own_defs, pending)?,
+                    }
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    self.point_refs_at_defs(value, scope, own_defs, pending)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    ///Find the pointer a ref resolves to, including anchors and `#/$defs` refs inside the
+    ///component `scope`.
+    fn def_pointer(&self, reference: &str, scope: Option<&str>) -> Result<String, OpenApiError> {
+        self.locate_reference(reference).or_else(|e| {
+            let scoped = scope
+                .zip(ref_to_json_pointer(reference).ok())
+                .filter(|(_, target)| target.starts_with("/$defs/"))
+                .map(|(name, target)| {
+                    format!("{}{target}", pointer::push("/components/schemas", name))
+                });
+            scoped
+                .filter(|target| self.json.pointer(target).is_some())
+                .ok_or(e)
+        })
+    }
+}
+
+fn to_value(schema: &SchemarsSchema) -> Result<Value, OpenApiError> {
+    serde_json::to_value(schema).map_err(|e| OpenApiError::ParsingError {
+        msg: format!("Error serializing schema {e}"),
+        location: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_standalone_schemas() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Standalone", "version": "1.0.0"},
+            "paths": {
+                "/nodes": {
+                    "post": {
+                        "requestBody": {
+                            "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Node"}}}
+                        },
+                        "responses": {
+                            "2XX": {
+                                "description": "ok",
+                                "content": {"application/json": {"schema": {"type": "array", "items": {"$ref": "#/components/schemas/Pet"}}}}
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Node": {"type": "object", "properties": {"next": {"$ref": "#/components/schemas/Node"}}},
+                    "Pet": {"type": "object", "properties": {"name": {"type": "string"}}}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let request = dereferenced.request_schema("/nodes", Method::Post, "application/json")?;
+        assert_eq!(DRAFT_2020_12, request["$schema"]);
+        assert_eq!("#/$defs/Node", request["properties"]["next"]["$ref"]);
+        assert_eq!(
+            "#/$defs/Node",
+            request["$defs"]["Node"]["properties"]["next"]["$ref"]
+        );
+
+        let response =
+            dereferenced.response_schema("/nodes", Method::Post, 201, "application/json")?;
+        assert_eq!("string", response["items"]["properties"]["name"]["type"]);
+        assert!(response.get("$defs").is_none());
+
+        assert!(matches!(
+            dereferenced.request_schema("/nodes", Method::Post, "text/plain"),
+            Err(OpenApiError::MediaTypeNotFound { .. })
+        ));
+        assert!(matches!(
+            dereferenced.response_schema("/nodes", Method::Get, 200, "application/json"),
+            Err(OpenApiError::OperationNotFound { .. })
+        ));
+        Ok(())
+    }
+    fn refs(value: &Value, found: &mut Vec<String>) {
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object {
+                    match value {
+                        Value::String(reference) if key == "$ref" => found.push(reference.clone()),
+                        value => refs(value, found),
+                    }
+                }
+            }
+            Value::Array(values) => values.iter().for_each(|value| refs(value, found)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    pub fn test_standalone_schemas_keep_anchor_and_defs_refs() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Standalone", "version": "1.0.0"},
+            "paths": {
+                "/trees": {
+                    "post": {
+                        "requestBody": {
+                            "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Tree"}}}
+                        },
+                        "responses": {
+                            "200": {
+                                "description": "ok",
+                                "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Linked"}}}
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Tree": {
+                        "type": "object",
+                        "properties": {"leaf": {"$ref": "#/$defs/Leaf"}},
+                        "$defs": {
+                            "Leaf": {"type": "object", "properties": {"next": {"$ref": "#/$defs/Leaf"}}}
+                        }
+                    },
+                    "Linked": {"$anchor": "linked", "type": "object", "properties": {"next": {"$ref": "#linked"}}}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        for schema in [
+            dereferenced.request_schema("/trees", Method::Post, "application/json")?,
+            dereferenced.response_schema("/trees", Method::Post, 200, "application/json")?,
+        ] {
+            let mut found = vec![];
+            refs(&schema, &mut found);
+            assert!(!found.is_empty());
+            for reference in found {
+                let target = reference.strip_prefix('#').unwrap_or_default();
+                assert!(
+                    schema.pointer(&pointer::percent_decode(target)).is_some(),
+                    "{reference} {schema}"
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use crate::OpenApiDereferencer;
+
+///How far along a dereferencer is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    ///Parsed but not dereferenced yet
+    Parsed,
+    ///Every ref in the spec that can be resolved has been
+    Dereferenced,
+    ///Dereferenced, but some of the spec was left out of scope, some refs failed in lenient
+    ///mode or some were kept on purpose by the inline budget or the external ref policy, so
+    ///refs may remain
+    PartiallyDereferenced,
+}
+
+impl OpenApiDereferencer {
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    ///Whether dereference has run, so things like `get_servers` and `iter_operations` can be
+    ///called.
+    pub fn is_dereferenced(&self) -> bool {
+        self.state != State::Parsed
+    }
+
+    ///Whether the input document is still around for things like `dereference_patch`, or has
+    ///been released.
+    pub fn has_input(&self) -> bool {
+        !self.json.is_null()
+    }
+
+    ///The state to be in once dereference is done.
+    pub(crate) fn dereferenced_state(&self) -> State {
+        let scope = &self.options.scope;
+        let whole_spec =
+            scope.components && scope.paths && scope.webhooks && scope.path_prefixes.is_empty();
+        let all_refs = self.failed_refs.borrow().is_empty()
+            && self.kept_refs.is_empty()
+            && !self.kept_external_refs.get();
+        if whole_spec && all_refs {
+            State::Dereferenced
+        } else {
+            State::PartiallyDereferenced
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_state() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "State", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {"responses": {"200": {"$ref": "#/components/responses/Missing"}}}
+                }
+            }
+        }"##;
+        let dereferencer = OpenApiDereferencer::from_str(spec)?;
+        assert_eq!(State::Parsed, dereferencer.state());
+        assert!(!dereferencer.is_dereferenced());
+        assert!(dereferencer.has_input());
+
+        let (dereferenced, errors) = dereferencer.dereference_lenient()?;
+        assert_eq!(1, errors.len());
+        assert_eq!(State::PartiallyDereferenced, dereferenced.state());
+        assert!(dereferenced.is_dereferenced());
+
+        let spec = spec.replace(
+            "/components/responses/Missing",
+            "/components/responses/Pets",
+        );
+        let spec = spec.replacen(
+            r#""paths""#,
+            r#""components": {"responses": {"Pets": {"description": "Pets"}}}, "paths""#,
+            1,
+        );
+        let dereferenced = OpenApiDereferencer::builder()
+            .release_input(true)
+            .build_from_str(&spec)?
+            .dereference()?;
+        assert_eq!(State::Dereferenced, dereferenced.state());
+        assert!(!dereferenced.has_input());
+
+        let budgeted = OpenApiDereferencer::builder()
+            .inline_max_references(0)
+            .build_from_str(&spec)?
+            .dereference()?;
+        assert_eq!(State::PartiallyDereferenced, budgeted.state());
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+
+use openapiv3::v3_1::{PathItem, StatusCode};
+
+use crate::operations::path_item_operations;
+use crate::{as_item, Method, OpenApiDereferencer};
+
+///Counts of what's in a spec, see [`OpenApiDereferencer::stats`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpecStats {
+    pub paths: usize,
+    pub operations: BTreeMap<Method, usize>,
+    ///Schemas in `components`
+    pub schemas: usize,
+    ///Parameters on path items and operations under `paths`
+    pub parameters: usize,
+    ///How many operations use each response status, e.g. `200`, `4XX` or `default`
+    pub response_codes: BTreeMap<String, usize>,
+    pub tags: usize,
+    pub security_schemes: usize,
+    ///Size of the input document as JSON, `None` once it's been released
+    pub input_size: Option<usize>,
+    ///Size of the dereferenced spec as JSON, `None` before dereferencing
+    pub dereferenced_size: Option<usize>,
+    ///Size of the biggest path item as JSON
+    pub largest_path_item: usize,
+    ///Size of all the path items together as JSON
+    pub total_path_item_size: usize,
+}
+
+impl SpecStats {
+    pub fn total_operations(&self) -> usize {
+        self.operations.values().sum()
+    }
+
+    pub fn average_path_item_size(&self) -> f64 {
+        if self.paths == 0 {
+            return 0.0;
+        }
+        self.total_path_item_size as f64 / self.paths as f64
+    }
+}
+
+impl OpenApiDereferencer {
+    ///Count the paths, operations, schemas and so on in the spec. Path items that are still refs
+    ///aren't looked into, so run dereference first to count those.
+    pub fn stats(&self) -> SpecStats {
+        let mut stats = SpecStats {
+            tags: self.openapi.tags.len(),
+            input_size: (!self.json.is_null()).then(|| json_size(&self.json)),
+            dereferenced_size: self.is_dereferenced().then(|| json_size(&self.openapi)),
+            ..SpecStats::default()
+        };
+        if let Some(components) = &self.openapi.components {
+            stats.schemas = components.schemas.len();
+            stats.security_schemes = components.security_schemes.len();
+        }
+        let path_items = self.openapi.paths.iter().flat_map(|paths| &paths.paths);
+        for (_, path_item) in path_items {
+            stats.paths += 1;
+            if let Some(path_item) = as_item(path_item) {
+                count_path_item(path_item, &mut stats);
+            }
+        }
+        stats
+    }
+}
+
+fn count_path_item(path_item: &PathItem, stats: &mut SpecStats) {
+    let size = json_size(path_item);
+    stats.largest_path_item = stats.largest_path_item.max(size);
+    stats.total_path_item_size += size;
+    stats.parameters += path_item.parameters.len();
+    for (method, operation) in path_item_operations(path_item) {
+        *stats.operations.entry(method).or_default() += 1;
+        stats.parameters += operation.parameters.len();
+        let Some(responses) = &operation.responses else {
+            continue;
+        };
+        if responses.default.is_some() {
+            *stats.response_codes.entry("default".into()).or_default() += 1;
+        }
+        for status in responses.responses.keys() {
+            let status = match status {
+                StatusCode::Code(code) => code.to_string(),
+                StatusCode::Range(range) => format!("{range}XX"),
+            };
+            *stats.response_codes.entry(status).or_default() += 1;
+        }
+    }
+}
+
+fn json_size<T: serde::Serialize>(value: &T) -> usize {
+    serde_json::to_vec(value).map_or(0, |bytes| bytes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_stats() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Stats", "version": "1.0.0"},
+            "tags": [{"name": "pets"}],
+            "paths": {
+                "/pets": {
+                    "parameters": [{"$ref": "#/components/parameters/Limit"}],
+                    "get": {
+                        "responses": {"200": {"description": "ok"}, "4XX": {"description": "bad"}}
+                    },
+                    "post": {
+                        "responses": {"201": {"description": "created"}, "default": {"description": "error"}}
+                    }
+                },
+                "/pets/{petId}": {"$ref": "#/components/pathItems/Pet"}
+            },
+            "components": {
+                "parameters": {
+                    "Limit": {"name": "limit", "in": "query", "schema": {"type": "integer"}}
+                },
+                "pathItems": {
+                    "Pet": {"get": {"responses": {"200": {"description": "ok"}}}}
+                },
+                "schemas": {"Pet": {"type": "object"}},
+                "securitySchemes": {"key": {"type": "apiKey", "name": "key", "in": "header"}}
+            }
+        }"##;
+        let dereferencer = OpenApiDereferencer::from_str(spec)?;
+        let stats = dereferencer.stats();
+        //The referenced path item isn't looked into before dereferencing
+        assert_eq!(2, stats.total_operations());
+        assert!(stats.input_size.is_some());
+        assert!(stats.dereferenced_size.is_none());
+
+        let stats = dereferencer.dereference()?.stats();
+        assert_eq!(2, stats.paths);
+        assert_eq!(
+            BTreeMap::from([(Method::Get, 2), (Method::Post, 1)]),
+            stats.operations
+        );
+        assert_eq!(1, stats.schemas);
+        assert_eq!(1, stats.parameters);
+        assert_eq!(
+            BTreeMap::from([
+                ("200".to_string(), 2),
+                ("201".to_string(), 1),
+                ("4XX".to_string(), 1),
+                ("default".to_string(), 1),
+            ]),
+            stats.response_codes
+        );
+        assert_eq!(1, stats.tags);
+        assert_eq!(1, stats.security_schemes);
+        assert!(stats.dereferenced_size.is_some());
+        assert!(stats.largest_path_item > 0);
+        Ok(())
+    }
+}
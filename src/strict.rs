@@ -0,0 +1,398 @@
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::{pointer, OpenApiDereferencer, OpenApiError};
+
+///A field the 3.1 spec doesn't allow, found by strict parsing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownField {
+    ///JSON pointer to the object with the field
+    pub location: String,
+    pub field: String,
+}
+
+impl fmt::Display for UnknownField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.location, pointer::escape(&self.field))
+    }
+}
+
+///The objects of the 3.1 spec that have a fixed set of fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    OpenApi,
+    Info,
+    Contact,
+    License,
+    Server,
+    ServerVariable,
+    Components,
+    Paths,
+    PathItem,
+    Operation,
+    ExternalDocs,
+    Parameter,
+    RequestBody,
+    MediaType,
+    Encoding,
+    Responses,
+    Response,
+    Callback,
+    Example,
+    Link,
+    Header,
+    Tag,
+    SecurityScheme,
+    OAuthFlows,
+    OAuthFlow,
+    ///Anything goes, like JSON schemas, which allow unknown keywords
+    Open,
+}
+
+///What a field holds.
+#[derive(Clone, Copy)]
+enum Field {
+    Value,
+    One(Kind),
+    Map(Kind),
+    List(Kind),
+}
+
+impl Kind {
+    fn fields(&self) -> &'static [(&'static str, Field)] {
+        match self {
+            Kind::OpenApi => &[
+                ("openapi", Field::Value),
+                ("info", Field::One(Kind::Info)),
+                ("jsonSchemaDialect", Field::Value),
+                ("servers", Field::List(Kind::Server)),
+                ("paths", Field::One(Kind::Paths)),
+                ("webhooks", Field::Map(Kind::PathItem)),
+                ("components", Field::One(Kind::Components)),
+                ("security", Field::Value),
+                ("tags", Field::List(Kind::Tag)),
+                ("externalDocs", Field::One(Kind::ExternalDocs)),
+            ],
+            Kind::Info => &[
+                ("title", Field::Value),
+                ("summary", Field::Value),
+                ("description", Field::Value),
+                ("termsOfService", Field::Value),
+                ("contact", Field::One(Kind::Contact)),
+                ("license", Field::One(Kind::License)),
+                ("version", Field::Value),
+            ],
+            Kind::Contact => &[
+                ("name", Field::Value),
+                ("url", Field::Value),
+                ("email", Field::Value),
+            ],
+            Kind::License => &[
+                ("name", Field::Value),
+                ("identifier", Field::Value),
+                ("url", Field::Value),
+            ],
+            Kind::Server => &[
+                ("url", Field::Value),
+                ("description", Field::Value),
+                ("variables", Field::Map(Kind::ServerVariable)),
+            ],
+            Kind::ServerVariable => &[
+                ("enum", Field::Value),
+                ("default", Field::Value),
+                ("description", Field::Value),
+            ],
+            Kind::Components => &[
+                ("schemas", Field::Map(Kind::Open)),
+                ("responses", Field::Map(Kind::Response)),
+                ("parameters", Field::Map(Kind::Parameter)),
+                ("examples", Field::Map(Kind::Example)),
+                ("requestBodies", Field::Map(Kind::RequestBody)),
+                ("headers", Field::Map(Kind::Header)),
+                ("securitySchemes", Field::Map(Kind::SecurityScheme)),
+                ("links", Field::Map(Kind::Link)),
+                ("callbacks", Field::Map(Kind::Callback)),
+                ("pathItems", Field::Map(Kind::PathItem)),
+            ],
+            Kind::PathItem => &[
+                ("$ref", Field::Value),
+                ("summary", Field::Value),
+                ("description", Field::Value),
+                ("get", Field::One(Kind::Operation)),
+                ("put", Field::One(Kind::Operation)),
+                ("post", Field::One(Kind::Operation)),
+                ("delete", Field::One(Kind::Operation)),
+                ("options", Field::One(Kind::Operation)),
+                ("head", Field::One(Kind::Operation)),
+                ("patch", Field::One(Kind::Operation)),
+                ("trace", Field::One(Kind::Operation)),
+                ("servers", Field::List(Kind::Server)),
+                ("parameters", Field::List(Kind::Parameter)),
+            ],
+            Kind::Operation => &[
+                ("tags", Field::Value),
+                ("summary", Field::Value),
+                ("description", Field::Value),
+                ("externalDocs", Field::One(Kind::ExternalDocs)),
+                ("operationId", Field::Value),
+                ("parameters", Field::List(Kind::Parameter)),
+                ("requestBody", Field::One(Kind::RequestBody)),
+                ("responses", Field::One(Kind::Responses)),
+                ("callbacks", Field::Map(Kind::Callback)),
+                ("deprecated", Field::Value),
+                ("security", Field::Value),
+                ("servers", Field::List(Kind::Server)),
+            ],
+            Kind::ExternalDocs => &[("description", Field::Value), ("url", Field::Value)],
+            Kind::Parameter => &[
+                ("name", Field::Value),
+                ("in", Field::Value),
+                ("description", Field::Value),
+                ("required", Field::Value),
+                ("deprecated", Field::Value),
+                ("allowEmptyValue", Field::Value),
+                ("style", Field::Value),
+                ("explode", Field::Value),
+                ("allowReserved", Field::Value),
+                ("schema", Field::One(Kind::Open)),
+                ("example", Field::Value),
+                ("examples", Field::Map(Kind::Example)),
+                ("content", Field::Map(Kind::MediaType)),
+            ],
+            Kind::RequestBody => &[
+                ("description", Field::Value),
+                ("content", Field::Map(Kind::MediaType)),
+                ("required", Field::Value),
+            ],
+            Kind::MediaType => &[
+                ("schema", Field::One(Kind::Open)),
+                ("example", Field::Value),
+                ("examples", Field::Map(Kind::Example)),
+                ("encoding", Field::Map(Kind::Encoding)),
+            ],
+            Kind::Encoding => &[
+                ("contentType", Field::Value),
+                ("headers", Field::Map(Kind::Header)),
+                ("style", Field::Value),
+                ("explode", Field::Value),
+                ("allowReserved", Field::Value),
+            ],
+            Kind::Response => &[
+                ("description", Field::Value),
+                ("headers", Field::Map(Kind::Header)),
+                ("content", Field::Map(Kind::MediaType)),
+                ("links", Field::Map(Kind::Link)),
+            ],
+            Kind::Example => &[
+                ("summary", Field::Value),
+                ("description", Field::Value),
+                ("value", Field::Value),
+                ("externalValue", Field::Value),
+            ],
+            Kind::Link => &[
+                ("operationRef", Field::Value),
+                ("operationId", Field::Value),
+                ("parameters", Field::Value),
+                ("requestBody", Field::Value),
+                ("description", Field::Value),
+                ("server", Field::One(Kind::Server)),
+            ],
+            Kind::Header => &[
+                ("description", Field::Value),
+                ("required", Field::Value),
+                ("deprecated", Field::Value),
+                ("allowEmptyValue", Field::Value),
+                ("style", Field::Value),
+                ("explode", Field::Value),
+                ("allowReserved", Field::Value),
+                ("schema", Field::One(Kind::Open)),
+                ("example", Field::Value),
+                ("examples", Field::Map(Kind::Example)),
+                ("content", Field::Map(Kind::MediaType)),
+            ],
+            Kind::Tag => &[
+                ("name", Field::Value),
+                ("description", Field::Value),
+                ("externalDocs", Field::One(Kind::ExternalDocs)),
+            ],
+            Kind::SecurityScheme => &[
+                ("type", Field::Value),
+                ("description", Field::Value),
+                ("name", Field::Value),
+                ("in", Field::Value),
+                ("scheme", Field::Value),
+                ("bearerFormat", Field::Value),
+                ("flows", Field::One(Kind::OAuthFlows)),
+                ("openIdConnectUrl", Field::Value),
+            ],
+            Kind::OAuthFlows => &[
+                ("implicit", Field::One(Kind::OAuthFlow)),
+                ("password", Field::One(Kind::OAuthFlow)),
+                ("clientCredentials", Field::One(Kind::OAuthFlow)),
+                ("authorizationCode", Field::One(Kind::OAuthFlow)),
+            ],
+            Kind::OAuthFlow => &[
+                ("authorizationUrl", Field::Value),
+                ("tokenUrl", Field::Value),
+                ("refreshUrl", Field::Value),
+                ("scopes", Field::Value),
+            ],
+            Kind::Paths | Kind::Responses | Kind::Callback | Kind::Open => &[],
+        }
+    }
+
+    ///What the keys of objects without fixed fields hold, e.g. paths under `paths`.
+    fn patterned(&self, key: &str) -> Option<Field> {
+        match self {
+            Kind::Paths if key.starts_with('/') => Some(Field::One(Kind::PathItem)),
+            Kind::Responses if key == "default" || is_status_code(key) => {
+                Some(Field::One(Kind::Response))
+            }
+            Kind::Callback => Some(Field::One(Kind::PathItem)),
+            _ => None,
+        }
+    }
+
+    ///Path items have their own `$ref` field, everything else that can be a reference object
+    ///only has the fields of one.
+    fn can_be_reference(&self) -> bool {
+        matches!(
+            self,
+            Kind::Parameter
+                | Kind::RequestBody
+                | Kind::Response
+                | Kind::Example
+                | Kind::Link
+                | Kind::Header
+                | Kind::SecurityScheme
+                | Kind::Callback
+        )
+    }
+}
+
+const REFERENCE_FIELDS: &[(&str, Field)] = &[
+    ("$ref", Field::Value),
+    ("summary", Field::Value),
+    ("description", Field::Value),
+];
+
+///`200` or a range like `2XX`.
+fn is_status_code(key: &str) -> bool {
+    let bytes = key.as_bytes();
+    bytes.len() == 3
+        && (b'1'..=b'5').contains(&bytes[0])
+        && (bytes[1..].iter().all(u8::is_ascii_digit) || &bytes[1..] == b"XX")
+}
+
+impl OpenApiDereferencer {
+    ///Every field in the input document that the 3.1 spec doesn't allow. `x-` extensions are
+    ///always allowed and schemas aren't checked, since JSON schema allows unknown keywords.
+    pub fn unknown_fields(&self) -> Result<Vec<UnknownField>, OpenApiError> {
+        let mut unknown = vec![];
+        check(self.input()?, Kind::OpenApi, "", &mut unknown);
+        Ok(unknown)
+    }
+}
+
+fn check(value: &Value, kind: Kind, location: &str, unknown: &mut Vec<UnknownField>) {
+    let Some(object) = value.as_object() else {
+        return;
+    };
+    if kind == Kind::Open {
+        return;
+    }
+    let is_reference = kind.can_be_reference() && object.contains_key("$ref");
+    let fields = if is_reference {
+        REFERENCE_FIELDS
+    } else {
+        kind.fields()
+    };
+    for (key, value) in object {
+        if key.starts_with("x-") {
+            continue;
+        }
+        let field = fields
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, field)| *field)
+            .or_else(|| (!is_reference).then(|| kind.patterned(key)).flatten());
+        match field {
+            Some(field) => check_field(value, field, &pointer::push(location, key), unknown),
+            None => unknown.push(UnknownField {
+                location: location.into(),
+                field: key.clone(),
+            }),
+        }
+    }
+}
+
+fn check_field(value: &Value, field: Field, location: &str, unknown: &mut Vec<UnknownField>) {
+    match field {
+        Field::Value => {}
+        Field::One(kind) => check(value, kind, location, unknown),
+        Field::Map(kind) => {
+            for (key, value) in value.as_object().into_iter().flatten() {
+                check(value, kind, &pointer::push(location, key), unknown);
+            }
+        }
+        Field::List(kind) => {
+            for (i, value) in value.as_array().into_iter().flatten().enumerate() {
+                check(value, kind, &format!("{location}/{i}"), unknown);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_strict() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Strict", "version": "1.0.0", "x-logo": "logo.png", "owner": "pets team"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "summry": "List pets",
+                        "parameters": [{"$ref": "#/components/parameters/Limit", "required": true}],
+                        "responses": {
+                            "200": {"description": "ok", "content": {"application/json": {"schema": {"type": "array", "madeUp": true}}}},
+                            "4XX": {"description": "bad"}
+                        }
+                    }
+                }
+            },
+            "components": {
+                "parameters": {
+                    "Limit": {"name": "limit", "in": "query", "schema": {"type": "integer"}, "type": "integer"}
+                }
+            }
+        }"##;
+        let mut unknown: Vec<String> = OpenApiDereferencer::from_str(spec)?
+            .unknown_fields()?
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        unknown.sort();
+        assert_eq!(
+            vec![
+                "/components/parameters/Limit/type",
+                "/info/owner",
+                "/paths/~1pets/get/parameters/0/required",
+                "/paths/~1pets/get/summry",
+            ],
+            unknown
+        );
+        assert!(matches!(
+            OpenApiDereferencer::builder().strict(true).build_from_str(spec),
+            Err(OpenApiError::UnknownFields { fields }) if fields.len() == 4
+        ));
+        assert!(OpenApiDereferencer::builder().build_from_str(spec).is_ok());
+        Ok(())
+    }
+}
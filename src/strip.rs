@@ -0,0 +1,197 @@
+use serde_json::{Map, Value};
+
+use crate::{pointer, OpenApiDereferencer, OpenApiError};
+
+///Fields to leave out of the output, for specs that are only read by machines like gateways
+///and validators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StripOptions {
+    pub descriptions: bool,
+    pub summaries: bool,
+    ///Both `example` and `examples`
+    pub examples: bool,
+}
+
+///Keys whose values map names to objects, so their keys aren't fields.
+pub(crate) const NAME_MAPS: [&str; 19] = [
+    "paths",
+    "webhooks",
+    "schemas",
+    "responses",
+    "parameters",
+    "examples",
+    "requestBodies",
+    "headers",
+    "securitySchemes",
+    "links",
+    "pathItems",
+    "content",
+    "encoding",
+    "variables",
+    "properties",
+    "patternProperties",
+    "$defs",
+    "definitions",
+    "dependentSchemas",
+];
+
+///Keys holding data rather than more of the spec, e.g. example values.
+pub(crate) const DATA: [&str; 8] = [
+    "example", "default", "enum", "const", "value", "security", "mapping", "scopes",
+];
+
+impl OpenApiDereferencer {
+    ///The spec as JSON without the fields `strip` says to leave out. Properties that happen to
+    ///be called `description` and the like are kept.
+    pub fn to_stripped_value(&self, strip: StripOptions) -> Result<Value, OpenApiError> {
+        let mut document = self.to_output_value()?;
+        let mut fields = vec![];
+        if strip.descriptions {
+            fields.push("description");
+        }
+        if strip.summaries {
+            fields.push("summary");
+        }
+        if strip.examples {
+            fields.extend(["example", "examples"]);
+        }
+        for_each_spec_object(&mut document, "", &mut |object, _| {
+            object.retain(|key, _| !fields.contains(&key.as_str()));
+        });
+        Ok(document)
+    }
+}
+
+///Call `f` on every object in an OpenAPI document that has fields, like operations, responses
+///and schemas, parents before their children. Maps of names, like `properties`, and data,
+///like example values, aren't passed to `f`.
+pub(crate) fn for_each_spec_object(
+    document: &mut Value,
+    location: &str,
+    f: &mut dyn FnMut(&mut Map<String, Value>, &str),
+) {
+    match document {
+        Value::Object(object) => {
+            f(object, location);
+            for (key, value) in object.iter_mut() {
+                let child = pointer::push(location, key);
+                if key.starts_with("x-") || DATA.contains(&key.as_str()) {
+                    continue;
+                }
+                match (key.as_str(), value) {
+                    ("callbacks", Value::Object(callbacks)) => {
+                        for (name, callback) in callbacks.iter_mut() {
+                            let callback_location = pointer::push(&child, name);
+                            for_each_named(callback, &callback_location, f);
+                        }
+                    }
+                    //`examples` in a schema is a list of values
+                    ("examples", Value::Array(_)) => {}
+                    (key, value) if NAME_MAPS.contains(&key) => for_each_named(value, &child, f),
+                    (_, value) => for_each_spec_object(value, &child, f),
+                }
+            }
+        }
+        Value::Array(values) => {
+            for (i, value) in values.iter_mut().enumerate() {
+                for_each_spec_object(value, &format!("{location}/{i}"), f);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn for_each_named(
+    names: &mut Value,
+    location: &str,
+    f: &mut dyn FnMut(&mut Map<String, Value>, &str),
+) {
+    match names {
+        Value::Object(names) => {
+            for (name, value) in names.iter_mut() {
+                if !name.starts_with("x-") {
+                    for_each_spec_object(value, &pointer::push(location, name), f);
+                }
+            }
+        }
+        value => for_each_spec_object(value, location, f),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_to_stripped_value() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Strip", "version": "1.0.0", "description": "All about pets"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "summary": "List pets",
+                        "description": "Lists the pets",
+                        "responses": {
+                            "200": {
+                                "description": "Pets",
+                                "content": {
+                                    "application/json": {
+                                        "schema": {"$ref": "#/components/schemas/Pet"},
+                                        "examples": {"one": {"value": [{"description": "kept"}]}}
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Pet": {
+                        "type": "object",
+                        "description": "A pet",
+                        "examples": [{"description": "kept"}],
+                        "properties": {
+                            "description": {"type": "string", "description": "What the pet is like"}
+                        }
+                    }
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let stripped = dereferenced.to_stripped_value(StripOptions {
+            descriptions: true,
+            summaries: true,
+            examples: false,
+        })?;
+        assert!(!stripped.to_string().contains("Lists the pets"));
+        let get = &stripped["paths"]["/pets"]["get"];
+        assert!(get.get("summary").is_none());
+        let media_type = &get["responses"]["200"]["content"]["application/json"];
+        assert_eq!(
+            "kept",
+            media_type["examples"]["one"]["value"][0]["description"]
+        );
+        let pet = &media_type["schema"];
+        assert!(pet.get("description").is_none());
+        assert_eq!("string", pet["properties"]["description"]["type"]);
+        assert!(pet["properties"]["description"]
+            .get("description")
+            .is_none());
+        assert_eq!("kept", pet["examples"][0]["description"]);
+
+        let stripped = dereferenced.to_stripped_value(StripOptions {
+            examples: true,
+            ..StripOptions::default()
+        })?;
+        let get = &stripped["paths"]["/pets"]["get"];
+        let media_type = &get["responses"]["200"]["content"]["application/json"];
+        assert!(media_type.get("examples").is_none());
+        assert!(media_type["schema"].get("examples").is_none());
+        assert_eq!("Lists the pets", get["description"]);
+        Ok(())
+    }
+}
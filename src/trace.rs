@@ -0,0 +1,27 @@
+//Optional `tracing` instrumentation. With the `tracing` feature off these expand to nothing.
+
+///Emit a tracing event, e.g. `trace_event!(debug, reference, "resolving ref")`.
+#[cfg(feature = "tracing")]
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {
+        tracing::$level!($($arg)+)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {};
+}
+
+///Enter a span for a phase of dereferencing until the end of the enclosing block.
+#[cfg(feature = "tracing")]
+macro_rules! phase_span {
+    ($phase:literal) => {
+        let _span = tracing::info_span!("dereference", phase = $phase).entered();
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! phase_span {
+    ($phase:literal) => {};
+}
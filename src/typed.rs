@@ -0,0 +1,102 @@
+use std::cell::OnceCell;
+use std::ops::Deref;
+
+use openapiv3::v3_1::OpenApi;
+
+use crate::{OpenApiDereferencer, OpenApiError};
+
+///A spec that's been dereferenced, which is what `OpenApiDereferencer::dereference` returns.
+///Everything that needs refs to be resolved first, like `iter_operations`, lives here, so calling
+///it too early doesn't compile. The rest of `OpenApiDereferencer` is available through `Deref`,
+///and the few methods that change the spec are wrapped here so they can keep the caches in sync.
+pub struct DereferencedOpenApi(pub(crate) OpenApiDereferencer);
+
+impl TryFrom<OpenApiDereferencer> for DereferencedOpenApi {
+    type Error = OpenApiError;
+
+    ///Fails with `NotDereferenced` unless dereference has already run, e.g. for a dereferencer
+    ///that came out of `into_inner`.
+    fn try_from(dereferencer: OpenApiDereferencer) -> Result<Self, OpenApiError> {
+        if !dereferencer.is_dereferenced() {
+            return Err(OpenApiError::NotDereferenced);
+        }
+        Ok(DereferencedOpenApi(dereferencer))
+    }
+}
+
+impl Deref for DereferencedOpenApi {
+    type Target = OpenApiDereferencer;
+
+    fn deref(&self) -> &OpenApiDereferencer {
+        &self.0
+    }
+}
+
+impl DereferencedOpenApi {
+    pub fn into_inner(self) -> OpenApiDereferencer {
+        self.0
+    }
+
+    pub fn into_openapi(self) -> OpenApi {
+        self.0.into_openapi()
+    }
+
+    ///Forget the routes, validators and operation ids worked out from the spec after changing it.
+    pub(crate) fn invalidate_caches(&mut self) {
+        self.0.routes = OnceCell::new();
+        #[cfg(feature = "validation")]
+        self.0.validators.borrow_mut().clear();
+        self.0.index_operation_ids();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Method;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_dereferenced_openapi() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Typed", "version": "1.0.0"},
+            "servers": [{"url": "https://api.example.com"}],
+            "paths": {
+                "/pets": {"$ref": "#/components/pathItems/Pets"}
+            },
+            "components": {
+                "pathItems": {
+                    "Pets": {
+                        "servers": [{"url": "https://pets.example.com"}],
+                        "get": {"responses": {"200": {"description": "Pets"}}}
+                    }
+                }
+            }
+        }"##;
+        assert!(matches!(
+            DereferencedOpenApi::try_from(OpenApiDereferencer::from_str(spec)?),
+            Err(OpenApiError::NotDereferenced)
+        ));
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let urls: Vec<String> = dereferenced
+            .get_servers()?
+            .into_iter()
+            .map(|server| server.url)
+            .collect();
+        assert_eq!(
+            vec!["https://api.example.com", "https://pets.example.com"],
+            urls
+        );
+        let operations: Vec<(&str, Method)> = dereferenced
+            .iter_operations()
+            .map(|(path, method, _)| (path, method))
+            .collect();
+        assert_eq!(vec![("/pets", Method::Get)], operations);
+        //Everything else is still there through Deref
+        assert!(dereferenced.warnings().is_empty());
+        assert!(DereferencedOpenApi::try_from(dereferenced.into_inner()).is_ok());
+        Ok(())
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::HashSet;
+
+use indexmap::IndexMap;
+use serde_json::Value;
+
+use crate::{
+    pointer, ref_to_json_pointer, ComponentKind, OpenApiDereferencer, OpenApiError, ReferenceKind,
+};
+
+///How much a component is used, for deciding what's worth keeping shared instead of inlining.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentUsage {
+    pub kind: ComponentKind,
+    pub name: String,
+    ///How many refs point at the component, or into it
+    pub references: usize,
+    ///Size of the component in the input document, serialized as JSON
+    pub size: usize,
+}
+
+impl ComponentUsage {
+    ///Roughly how many bytes inlining the component everywhere it's used adds to the spec.
+    pub fn inlined_size(&self) -> usize {
+        self.size * self.references
+    }
+}
+
+impl OpenApiDereferencer {
+    ///How many times every component in the input document is referenced, most used first.
+    ///Components that are never referenced are included with a count of zero.
+    pub fn component_usage(&self) -> Result<Vec<ComponentUsage>, OpenApiError> {
+        let json = self.input()?;
+        let mut usage: IndexMap<(ComponentKind, String), ComponentUsage> = IndexMap::new();
+        for kind in ComponentKind::ALL {
+            let section = json.pointer(&format!("/components/{}", kind.as_str()));
+            for (name, component) in section.and_then(Value::as_object).into_iter().flatten() {
+                let size = serde_json::to_vec(component).map_or(0, |bytes| bytes.len());
+                usage.insert(
+                    (kind, name.clone()),
+                    ComponentUsage {
+                        kind,
+                        name: name.clone(),
+                        references: 0,
+                        size,
+                    },
+                );
+            }
+        }
+        for listed in self.list_references()? {
+            if listed.kind != ReferenceKind::Internal {
+                continue;
+            }
+            if let Some(key) = referenced_component(&listed.reference) {
+                if let Some(component) = usage.get_mut(&key) {
+                    component.references += 1;
+                }
+            }
+        }
+        let mut usage: Vec<ComponentUsage> = usage.into_values().collect();
+        usage.sort_by(|a, b| b.references.cmp(&a.references));
+        Ok(usage)
+    }
+
+    ///JSON pointers to every object in the input document with a `$ref` to `component_ref`,
+    ///e.g. `#/components/schemas/Pet`, or to somewhere inside it. Check this before deleting or
+    ///renaming a component.
+    pub fn referenced_by(&self, component_ref: &str) -> Result<Vec<String>, OpenApiError> {
+        let component = self.locate_reference(component_ref)?;
+        Ok(self
+            .list_references()?
+            .into_iter()
+            .filter(|listed| listed.kind == ReferenceKind::Internal)
+            .filter(|listed| {
+                self.locate_reference(&listed.reference)
+                    .is_ok_and(|target| within(&target, &component))
+            })
+            .map(|listed| listed.location)
+            .collect())
+    }
+
+    ///The refs to components that are over the inline budget set on the builder, so they're
+    ///kept as refs.
+    pub(crate) fn over_inline_budget(&self) -> Result<HashSet<String>, OpenApiError> {
+        let (max_references, max_size) = (
+            self.options.inline_max_references,
+            self.options.inline_max_size,
+        );
+        if max_references.is_none() && max_size.is_none() {
+            return Ok(HashSet::new());
+        }
+        Ok(self
+            .component_usage()?
+            .into_iter()
+            .filter(|usage| usage.kind != ComponentKind::PathItems)
+            .filter(|usage| {
+                max_references.is_some_and(|max| usage.references > max)
+                    || max_size.is_some_and(|max| usage.size > max)
+            })
+            .map(|usage| usage.kind.reference(&usage.name))
+            .collect())
+    }
+}
+
+fn within(pointer: &str, outer: &str) -> bool {
+    pointer
+        .strip_prefix(outer)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+///The component a ref points at or into, e.g. `#/components/schemas/Pet/properties/name` is
+///the `Pet` schema.
+fn referenced_component(reference: &str) -> Option<(ComponentKind, String)> {
+    let pointer = ref_to_json_pointer(reference).ok()?;
+    let mut segments = pointer.strip_prefix("/components/")?.split('/');
+    let section = segments.next()?;
+    let kind = ComponentKind::ALL
+        .into_iter()
+        .find(|kind| kind.as_str() == section)?;
+    Some((kind, pointer::unescape(segments.next()?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::as_item;
+    use anyhow::Result;
+    use openapiv3::v3_1::{ReferenceOr, Response};
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_component_usage() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Usage", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "Pets",
+                                "content": {"application/json": {"schema": {"type": "array", "items": {"$ref": "#/components/schemas/Pet"}}}}
+                            },
+                            "default": {"$ref": "#/components/responses/Error"}
+                        }
+                    },
+                    "post": {
+                        "requestBody": {
+                            "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+                        },
+                        "responses": {"default": {"$ref": "#/components/responses/Error"}}
+                    }
+                }
+            },
+            "components": {
+                "responses": {"Error": {"description": "Something went wrong"}},
+                "schemas": {
+                    "Pet": {"type": "object", "properties": {"name": {"$ref": "#/components/schemas/Name"}}},
+                    "Name": {"type": "string"},
+                    "Unused": {"type": "object"}
+                }
+            }
+        }"##;
+        let usage = OpenApiDereferencer::from_str(spec)?.component_usage()?;
+        let counts: Vec<(&str, usize)> = usage
+            .iter()
+            .map(|usage| (usage.name.as_str(), usage.references))
+            .collect();
+        assert_eq!(
+            vec![("Pet", 2), ("Error", 2), ("Name", 1), ("Unused", 0)],
+            counts
+        );
+        assert_eq!(
+            r#"{"description":"Something went wrong"}"#.len() * 2,
+            usage[1].inlined_size()
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_referenced_by() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Referenced", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "Pets",
+                                "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Pet": {"type": "object", "properties": {"name": {"type": "string"}}},
+                    "Owner": {
+                        "type": "object",
+                        "properties": {
+                            "pets": {"type": "array", "items": {"$ref": "#/components/schemas/Pet"}},
+                            "name": {"$ref": "#/components/schemas/Pet/properties/name"}
+                        }
+                    }
+                }
+            }
+        }"##;
+        let dereferencer = OpenApiDereferencer::from_str(spec)?;
+        let mut locations = dereferencer.referenced_by("#/components/schemas/Pet")?;
+        locations.sort();
+        assert_eq!(
+            vec![
+                "/components/schemas/Owner/properties/name",
+                "/components/schemas/Owner/properties/pets/items",
+                "/paths/~1pets/get/responses/200/content/application~1json/schema",
+            ],
+            locations
+        );
+        assert!(dereferencer
+            .referenced_by("#/components/schemas/Owner")?
+            .is_empty());
+        assert!(dereferencer
+            .referenced_by("#/components/schemas/Missing")
+            .is_err());
+        Ok(())
+    }
+
+    fn default_response(dereferenced: &OpenApiDereferencer) -> Option<&ReferenceOr<Response>> {
+        let paths = dereferenced.openapi.paths.as_ref()?;
+        let get = as_item(&paths.paths["/pets"])?.get.as_ref()?;
+        get.responses.as_ref()?.default.as_ref()
+    }
+
+    #[test]
+    pub fn test_inline_budget() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Budget", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "Pets",
+                                "content": {"application/json": {"schema": {"type": "array", "items": {"$ref": "#/components/schemas/Pet"}}}}
+                            },
+                            "default": {"$ref": "#/components/responses/Error"}
+                        }
+                    },
+                    "post": {
+                        "requestBody": {
+                            "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+                        },
+                        "responses": {"default": {"$ref": "#/components/responses/Error"}}
+                    }
+                }
+            },
+            "components": {
+                "responses": {"Error": {"description": "Something went wrong"}},
+                "schemas": {
+                    "Pet": {"type": "object", "properties": {"name": {"$ref": "#/components/schemas/Name"}}},
+                    "Name": {"type": "string"}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::builder()
+            .inline_max_references(1)
+            .build_from_str(spec)?
+            .dereference()?;
+        let openapi = serde_json::to_value(&dereferenced.openapi)?;
+        let get = &openapi["paths"]["/pets"]["get"];
+        assert_eq!(
+            "#/components/schemas/Pet",
+            get["responses"]["200"]["content"]["application/json"]["schema"]["items"]["$ref"]
+        );
+        assert!(matches!(
+            default_response(&dereferenced),
+            Some(ReferenceOr::Reference { .. })
+        ));
+        assert_eq!(
+            "string",
+            openapi["components"]["schemas"]["Pet"]["properties"]["name"]["type"]
+        );
+
+        let dereferenced = OpenApiDereferencer::builder()
+            .inline_max_size(40)
+            .build_from_str(spec)?
+            .dereference()?;
+        let openapi = serde_json::to_value(&dereferenced.openapi)?;
+        let get = &openapi["paths"]["/pets"]["get"];
+        assert_eq!(
+            "#/components/schemas/Pet",
+            get["responses"]["200"]["content"]["application/json"]["schema"]["items"]["$ref"]
+        );
+        assert!(matches!(
+            default_response(&dereferenced),
+            Some(ReferenceOr::DereferencedReference { .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use crate::{OpenApiDereferencer, OpenApiError};
+
+impl OpenApiDereferencer {
+    ///A dereferencer for a spec generated by utoipa, converted straight to JSON in memory
+    ///instead of going through a string.
+    pub fn from_utoipa(openapi: &utoipa::openapi::OpenApi) -> Result<Self, OpenApiError> {
+        let json = serde_json::to_value(openapi).map_err(|e| OpenApiError::ParsingError {
+            msg: format!("Error serializing the utoipa spec {e}"),
+            location: None,
+        })?;
+        OpenApiDereferencer::from_value(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Method;
+    use anyhow::Result;
+    use utoipa::openapi::path::{HttpMethod, OperationBuilder, PathItemBuilder};
+    use utoipa::openapi::{
+        ComponentsBuilder, ContentBuilder, InfoBuilder, ObjectBuilder, OpenApiBuilder,
+        PathsBuilder, Ref, ResponseBuilder, Type,
+    };
+
+    #[test]
+    pub fn test_from_utoipa() -> Result<()> {
+        let pet = ObjectBuilder::new()
+            .property("name", ObjectBuilder::new().schema_type(Type::String))
+            .build();
+        let get = OperationBuilder::new()
+            .operation_id(Some("listPets"))
+            .response(
+                "200",
+                ResponseBuilder::new().description("Pets").content(
+                    "application/json",
+                    ContentBuilder::new()
+                        .schema(Some(Ref::from_schema_name("Pet")))
+                        .build(),
+                ),
+            )
+            .build();
+        let openapi = OpenApiBuilder::new()
+            .info(InfoBuilder::new().title("Utoipa").version("1.0.0").build())
+            .paths(
+                PathsBuilder::new()
+                    .path(
+                        "/pets",
+                        PathItemBuilder::new()
+                            .operation(HttpMethod::Get, get)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .components(Some(ComponentsBuilder::new().schema("Pet", pet).build()))
+            .build();
+
+        let dereferenced = OpenApiDereferencer::from_utoipa(&openapi)?.dereference()?;
+        let (path, method, _) = dereferenced.get_operation_by_id("listPets")?;
+        assert_eq!(("/pets", Method::Get), (path, method));
+        let json = dereferenced.to_output_value()?;
+        let schema = &json["paths"]["/pets"]["get"]["responses"]["200"]["content"]
+            ["application/json"]["schema"];
+        assert_eq!("string", schema["properties"]["name"]["type"]);
+        Ok(())
+    }
+}
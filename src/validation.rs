@@ -0,0 +1,699 @@
+use std::fmt;
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+use jsonschema::{Draft, JSONSchema};
+use openapiv3::schemars::schema::Schema as SchemarsSchema;
+use openapiv3::v3_1::{MediaType, Parameter, ParameterSchemaOrContent, StatusCode};
+use serde_json::Value;
+
+use crate::components::ComponentKind;
+use crate::parameters::parameter_data;
+use crate::{as_item, pointer, DereferencedOpenApi, Method, OpenApiError};
+
+///The part of a request or response a violation was found in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViolationLocation {
+    ///No operation matches the method and path
+    Route,
+    ///No response matches the status code
+    Status,
+    Path(String),
+    Query(String),
+    Header(String),
+    Cookie(String),
+    Body,
+}
+
+impl fmt::Display for ViolationLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ViolationLocation::Route => write!(f, "route"),
+            ViolationLocation::Status => write!(f, "status"),
+            ViolationLocation::Path(name) => write!(f, "path parameter {name}"),
+            ViolationLocation::Query(name) => write!(f, "query parameter {name}"),
+            ViolationLocation::Header(name) => write!(f, "header {name}"),
+            ViolationLocation::Cookie(name) => write!(f, "cookie {name}"),
+            ViolationLocation::Body => write!(f, "body"),
+        }
+    }
+}
+
+///Something in a request or response that doesn't match the spec.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub location: ViolationLocation,
+    ///JSON pointer to the offending part of the value, empty for the value itself
+    pub instance_path: String,
+    pub message: String,
+}
+
+impl Violation {
+    fn new(location: ViolationLocation, message: impl Into<String>) -> Self {
+        Violation {
+            location,
+            instance_path: String::new(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.instance_path.is_empty() {
+            write!(f, "{}: {}", self.location, self.message)
+        } else {
+            write!(
+                f,
+                "{} at {}: {}",
+                self.location, self.instance_path, self.message
+            )
+        }
+    }
+}
+
+///These are handled by the transport and ignored as header parameters.
+const IGNORED_HEADERS: [&str; 3] = ["accept", "content-type", "authorization"];
+
+impl DereferencedOpenApi {
+    ///Check a request's parameters and body against its operation. Headers are matched case
+    ///insensitively and cookies are read from the `Cookie` header. An empty list means the
+    ///request is valid.
+    pub fn validate_request(
+        &self,
+        method: Method,
+        path: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+        body: Option<&Value>,
+    ) -> Result<Vec<Violation>, OpenApiError> {
+        let router = self.router()?;
+        let Some(route) = router.route(method, path) else {
+            return Ok(vec![Violation::new(
+                ViolationLocation::Route,
+                format!("No operation for {method} {path}"),
+            )]);
+        };
+        let mut violations = vec![];
+        for parameter in self.effective_parameters(route.path, method)? {
+            let data = parameter_data(parameter);
+            let name = data.name.clone();
+            let (location, values): (ViolationLocation, Vec<&str>) = match parameter {
+                Parameter::Path { .. } => (
+                    ViolationLocation::Path(name),
+                    route
+                        .path_params
+                        .get(&data.name)
+                        .map(String::as_str)
+                        .into_iter()
+                        .collect(),
+                ),
+                Parameter::Query { .. } => (
+                    ViolationLocation::Query(name),
+                    query
+                        .iter()
+                        .filter(|(key, _)| *key == data.name)
+                        .map(|(_, value)| *value)
+                        .collect(),
+                ),
+                Parameter::Header { .. } => {
+                    if IGNORED_HEADERS.contains(&data.name.to_ascii_lowercase().as_str()) {
+                        continue;
+                    }
+                    (
+                        ViolationLocation::Header(name),
+                        header_values(headers, &data.name),
+                    )
+                }
+                Parameter::Cookie { .. } => (
+                    ViolationLocation::Cookie(name),
+                    cookie_values(headers, &data.name),
+                ),
+            };
+            if values.is_empty() {
+                if data.required {
+                    violations.push(Violation::new(location, "Missing required parameter"));
+                }
+                continue;
+            }
+            self.validate_values(&data.format, &values, &location, &mut violations)?;
+        }
+        if let Some(request_body) = route.operation.request_body.as_ref().and_then(as_item) {
+            match body {
+                None if request_body.required => violations.push(Violation::new(
+                    ViolationLocation::Body,
+                    "Missing required request body",
+                )),
+                None => {}
+                Some(body) => {
+                    let content_type = header_values(headers, "content-type").first().copied();
+                    self.validate_content(
+                        &request_body.content,
+                        content_type,
+                        body,
+                        &mut violations,
+                    )?;
+                }
+            }
+        }
+        Ok(violations)
+    }
+
+    ///Check a response against its operation. The status code is matched exactly, then
+    ///against ranges like `4XX`, then `default`. An empty list means the response is valid.
+    pub fn validate_response(
+        &self,
+        method: Method,
+        path: &str,
+        status: u16,
+        headers: &[(&str, &str)],
+        body: Option<&Value>,
+    ) -> Result<Vec<Violation>, OpenApiError> {
+        let router = self.router()?;
+        let Some(route) = router.route(method, path) else {
+            return Ok(vec![Violation::new(
+                ViolationLocation::Route,
+                format!("No operation for {method} {path}"),
+            )]);
+        };
+        let Some(response) = route.operation.responses.as_ref().and_then(|responses| {
+            let response = |status| responses.responses.get(&status).and_then(as_item);
+            response(StatusCode::Code(status))
+                .or_else(|| response(StatusCode::Range(status / 100)))
+                .or_else(|| responses.default.as_ref().and_then(as_item))
+        }) else {
+            return Ok(vec![Violation::new(
+                ViolationLocation::Status,
+                format!("No response for status {status}"),
+            )]);
+        };
+        let mut violations = vec![];
+        for (name, header) in &response.headers {
+            let Some(header) = as_item(header) else {
+                continue;
+            };
+            if name.eq_ignore_ascii_case("content-type") {
+                continue;
+            }
+            let location = ViolationLocation::Header(name.clone());
+            let values = header_values(headers, name);
+            if values.is_empty() {
+                if header.required {
+                    violations.push(Violation::new(location, "Missing required header"));
+                }
+                continue;
+            }
+            self.validate_values(&header.format, &values, &location, &mut violations)?;
+        }
+        match body {
+            Some(body) => {
+                let content_type = header_values(headers, "content-type").first().copied();
+                self.validate_content(&response.content, content_type, body, &mut violations)?;
+            }
+            None if !response.content.is_empty() => violations.push(Violation::new(
+                ViolationLocation::Body,
+                "Missing response body",
+            )),
+            None => {}
+        }
+        Ok(violations)
+    }
+
+    ///A compiled validator for a component schema. Validators are compiled the first time
+    ///they're asked for and reused after that.
+    pub fn validator_for_schema(&self, name: &str) -> Result<Arc<JSONSchema>, OpenApiError> {
+        //Component validators are keyed by their ref, everything else by its JSON
+        let key = format!("#/components/schemas/{}", pointer::escape(name));
+        if let Some(validator) = self.validators.borrow().get(&key) {
+            return Ok(validator.clone());
+        }
+        let schema = self
+            .openapi
+            .components
+            .as_ref()
+            .and_then(|components| components.schemas.get(name))
+            .ok_or_else(|| OpenApiError::ComponentNotFound {
+                kind: ComponentKind::Schemas,
+                name: name.into(),
+            })?;
+        let validator = Arc::new(compile(&self.validation_schema(&schema.json_schema)?)?);
+        self.validators.borrow_mut().insert(key, validator.clone());
+        Ok(validator)
+    }
+
+    ///A compiled validator for an inline schema, reused for every request that needs it.
+    pub(crate) fn compiled(&self, schema: &Value) -> Result<Arc<JSONSchema>, OpenApiError> {
+        let key = schema.to_string();
+        if let Some(validator) = self.validators.borrow().get(&key) {
+            return Ok(validator.clone());
+        }
+        let validator = Arc::new(compile(schema)?);
+        self.validators.borrow_mut().insert(key, validator.clone());
+        Ok(validator)
+    }
+
+    ///Validate the string values of a parameter or header.
+    fn validate_values(
+        &self,
+        format: &ParameterSchemaOrContent,
+        values: &[&str],
+        location: &ViolationLocation,
+        violations: &mut Vec<Violation>,
+    ) -> Result<(), OpenApiError> {
+        match format {
+            ParameterSchemaOrContent::Schema(schema) => {
+                let schema = self.validation_schema(&schema.json_schema)?;
+                let instance = coerce_parameter(values, &schema);
+                self.validate_instance(&schema, &instance, location, violations)
+            }
+            ParameterSchemaOrContent::Content(content) => {
+                match content.values().next().and_then(|m| m.schema.as_ref()) {
+                    Some(schema) => {
+                        let schema = self.validation_schema(&schema.json_schema)?;
+                        let instance = serde_json::from_str(values[0])
+                            .unwrap_or_else(|_| Value::String(values[0].into()));
+                        self.validate_instance(&schema, &instance, location, violations)
+                    }
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+
+    ///Validate a body against the media type matching its content type.
+    pub(crate) fn validate_content(
+        &self,
+        content: &IndexMap<String, MediaType>,
+        content_type: Option<&str>,
+        body: &Value,
+        violations: &mut Vec<Violation>,
+    ) -> Result<(), OpenApiError> {
+        match select_media_type(content, content_type) {
+            Some(media_type) => {
+                if let Some(schema) = media_type.schema.as_ref() {
+                    let schema = self.validation_schema(&schema.json_schema)?;
+                    self.validate_instance(&schema, body, &ViolationLocation::Body, violations)?;
+                }
+            }
+            None => violations.push(Violation::new(
+                ViolationLocation::Body,
+                format!(
+                    "Unsupported content type {}",
+                    content_type.unwrap_or("(none)")
+                ),
+            )),
+        }
+        Ok(())
+    }
+
+    ///Turn a dereferenced schema into something jsonschema can compile. Recursive refs are left
+    ///as `$ref` by dereferencing, so the component schemas ride along for them to resolve against.
+    pub(crate) fn validation_schema(&self, schema: &SchemarsSchema) -> Result<Value, OpenApiError> {
+        let schema = serde_json::to_value(schema).map_err(|e| OpenApiError::ParsingError {
+            msg: format!("Error serializing schema {e}"),
+            location: None,
+        })?;
+        Ok(self.with_components(schema))
+    }
+
+    ///Add the component schemas to a JSON schema if it has refs that need them. Once the input
+    ///has been released they come from the dereferenced spec instead.
+    pub(crate) fn with_components(&self, mut schema: Value) -> Value {
+        if !contains_ref(&schema) {
+            return schema;
+        }
+        let components = match self.input() {
+            Ok(input) => input.get("components").cloned(),
+            Err(_) => self
+                .openapi
+                .components
+                .as_ref()
+                .and_then(|components| serde_json::to_value(&components.schemas).ok())
+                .map(|schemas| serde_json::json!({ "schemas": schemas })),
+        };
+        if let (Value::Object(object), Some(components)) = (&mut schema, components) {
+            object.insert("components".into(), components);
+        }
+        schema
+    }
+
+    pub(crate) fn validate_instance(
+        &self,
+        schema: &Value,
+        instance: &Value,
+        location: &ViolationLocation,
+        violations: &mut Vec<Violation>,
+    ) -> Result<(), OpenApiError> {
+        if let Err(errors) = self.compiled(schema)?.validate(instance) {
+            violations.extend(errors.map(|e| Violation {
+                location: location.clone(),
+                instance_path: e.instance_path.to_string(),
+                message: e.to_string(),
+            }));
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn compile(schema: &Value) -> Result<JSONSchema, OpenApiError> {
+    JSONSchema::options()
+        .with_draft(Draft::Draft202012)
+        .compile(schema)
+        .map_err(|e| OpenApiError::InvalidSchema { msg: e.to_string() })
+}
+
+fn contains_ref(value: &Value) -> bool {
+    match value {
+        Value::Object(object) => object.contains_key("$ref") || object.values().any(contains_ref),
+        Value::Array(values) => values.iter().any(contains_ref),
+        _ => false,
+    }
+}
+
+pub(crate) fn header_values<'h>(headers: &[(&str, &'h str)], name: &str) -> Vec<&'h str> {
+    headers
+        .iter()
+        .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| *value)
+        .collect()
+}
+
+fn cookie_values<'h>(headers: &[(&str, &'h str)], name: &str) -> Vec<&'h str> {
+    header_values(headers, "cookie")
+        .into_iter()
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .filter(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+        .collect()
+}
+
+///Pick the media type for a content type, falling back to `type/*` then `*/*`. Without a
+///content type the first media type is used.
+pub(crate) fn select_media_type<'m>(
+    content: &'m IndexMap<String, MediaType>,
+    content_type: Option<&str>,
+) -> Option<&'m MediaType> {
+    let Some(content_type) = content_type else {
+        return content.values().next();
+    };
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    content
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(&essence))
+        .or_else(|| {
+            let (kind, _) = essence.split_once('/')?;
+            content
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(&format!("{kind}/*")))
+        })
+        .or_else(|| content.iter().find(|(key, _)| *key == "*/*"))
+        .map(|(_, media_type)| media_type)
+}
+
+///Parameters arrive as strings, so convert them to the JSON type the schema expects. Anything
+///that doesn't convert is left as a string for the validator to complain about.
+fn coerce_parameter(values: &[&str], schema: &Value) -> Value {
+    match schema_type(schema) {
+        Some("array") => {
+            let items = schema.get("items").unwrap_or(&Value::Null);
+            let values: Vec<&str> = match values {
+                [value] => value.split(',').collect(),
+                values => values.to_vec(),
+            };
+            Value::Array(
+                values
+                    .into_iter()
+                    .map(|value| coerce_scalar(value, items))
+                    .collect(),
+            )
+        }
+        _ => coerce_scalar(values[0], schema),
+    }
+}
+
+fn coerce_scalar(value: &str, schema: &Value) -> Value {
+    match schema_type(schema) {
+        Some("integer" | "number") => serde_json::from_str::<serde_json::Number>(value)
+            .map(Value::Number)
+            .unwrap_or_else(|_| value.into()),
+        Some("boolean") => value
+            .parse::<bool>()
+            .map(Value::Bool)
+            .unwrap_or_else(|_| value.into()),
+        Some("object") => serde_json::from_str(value).unwrap_or_else(|_| value.into()),
+        _ => Value::String(value.into()),
+    }
+}
+
+fn schema_type(schema: &Value) -> Option<&str> {
+    match schema.get("type")? {
+        Value::String(kind) => Some(kind),
+        Value::Array(kinds) => kinds
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|kind| *kind != "null"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenApiDereferencer;
+    use anyhow::Result;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    const SPEC: &str = r##"{
+        "openapi": "3.1.0",
+        "info": {"title": "Validation", "version": "1.0.0"},
+        "paths": {
+            "/pets/{petId}": {
+                "parameters": [
+                    {"name": "petId", "in": "path", "required": true, "schema": {"type": "integer"}}
+                ],
+                "put": {
+                    "parameters": [
+                        {"name": "tags", "in": "query", "schema": {"type": "array", "items": {"type": "string"}, "maxItems": 2}},
+                        {"name": "X-Request-Id", "in": "header", "required": true, "schema": {"type": "string"}}
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "ok",
+                            "headers": {"X-Rate-Limit": {"required": true, "schema": {"type": "integer"}}},
+                            "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+                        },
+                        "4XX": {
+                            "description": "client error",
+                            "content": {"application/problem+json": {"schema": {"type": "object", "required": ["title"]}}}
+                        }
+                    }
+                }
+            }
+        },
+        "components": {
+            "schemas": {
+                "Pet": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": {
+                        "name": {"type": "string"},
+                        "children": {"type": "array", "items": {"$ref": "#/components/schemas/Pet"}}
+                    }
+                }
+            }
+        }
+    }"##;
+
+    #[test]
+    pub fn test_validate_request() -> Result<()> {
+        let dereferenced = OpenApiDereferencer::from_str(SPEC)?.dereference()?;
+        let headers = [
+            ("x-request-id", "abc"),
+            ("Content-Type", "application/json; charset=utf-8"),
+        ];
+        let violations = dereferenced.validate_request(
+            Method::Put,
+            "/pets/1",
+            &headers,
+            &[("tags", "a,b")],
+            Some(&json!({"name": "Rex", "children": [{"name": "Pup"}]})),
+        )?;
+        assert!(violations.is_empty(), "{violations:?}");
+
+        let violations = dereferenced.validate_request(
+            Method::Put,
+            "/pets/one",
+            &[],
+            &[("tags", "a"), ("tags", "b"), ("tags", "c")],
+            Some(&json!({"children": [{}]})),
+        )?;
+        let locations: Vec<(ViolationLocation, &str)> = violations
+            .iter()
+            .map(|v| (v.location.clone(), v.instance_path.as_str()))
+            .collect();
+        for expected in [
+            (ViolationLocation::Path("petId".into()), ""),
+            (ViolationLocation::Query("tags".into()), ""),
+            (ViolationLocation::Header("X-Request-Id".into()), ""),
+            (ViolationLocation::Body, ""),
+            (ViolationLocation::Body, "/children/0"),
+        ] {
+            assert!(locations.contains(&expected), "{expected:?} {violations:?}");
+        }
+        assert_eq!(5, violations.len());
+
+        let violations = dereferenced.validate_request(Method::Get, "/pets/1", &[], &[], None)?;
+        assert_eq!(ViolationLocation::Route, violations[0].location);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_validate_response() -> Result<()> {
+        let dereferenced = OpenApiDereferencer::from_str(SPEC)?.dereference()?;
+        let violations = dereferenced.validate_response(
+            Method::Put,
+            "/pets/1",
+            200,
+            &[("X-Rate-Limit", "10")],
+            Some(&json!({"name": "Rex"})),
+        )?;
+        assert!(violations.is_empty(), "{violations:?}");
+
+        let violations = dereferenced.validate_response(
+            Method::Put,
+            "/pets/1",
+            200,
+            &[("X-Rate-Limit", "lots")],
+            Some(&json!({})),
+        )?;
+        let locations: Vec<&ViolationLocation> = violations.iter().map(|v| &v.location).collect();
+        assert_eq!(
+            vec![
+                &ViolationLocation::Header("X-Rate-Limit".into()),
+                &ViolationLocation::Body
+            ],
+            locations
+        );
+
+        let violations = dereferenced.validate_response(
+            Method::Put,
+            "/pets/1",
+            404,
+            &[("content-type", "application/problem+json")],
+            Some(&json!({"title": "Not found"})),
+        )?;
+        assert!(violations.is_empty(), "{violations:?}");
+
+        let violations = dereferenced.validate_response(Method::Put, "/pets/1", 500, &[], None)?;
+        assert_eq!(ViolationLocation::Status, violations[0].location);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_validator_for_schema() -> Result<()> {
+        let dereferenced = OpenApiDereferencer::from_str(SPEC)?.dereference()?;
+        let validator = dereferenced.validator_for_schema("Pet")?;
+        assert!(validator.is_valid(&json!({"name": "Rex", "children": [{"name": "Pup"}]})));
+        assert!(!validator.is_valid(&json!({"children": [{}]})));
+        assert!(Arc::ptr_eq(
+            &validator,
+            &dereferenced.validator_for_schema("Pet")?
+        ));
+        assert!(matches!(
+            dereferenced.validator_for_schema("Owner"),
+            Err(OpenApiError::ComponentNotFound { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_validate_default_response_ref() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Default", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {"description": "ok"},
+                            "default": {"$ref": "#/components/responses/Error"}
+                        }
+                    }
+                }
+            },
+            "components": {
+                "responses": {
+                    "Error": {
+                        "description": "error",
+                        "content": {
+                            "application/json": {
+                                "schema": {"type": "object", "required": ["message"]}
+                            }
+                        }
+                    }
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let violations = dereferenced.validate_response(
+            Method::Get,
+            "/pets",
+            500,
+            &[("content-type", "application/json")],
+            Some(&json!({"message": "Broken"})),
+        )?;
+        assert!(violations.is_empty(), "{violations:?}");
+
+        let violations = dereferenced.validate_response(
+            Method::Get,
+            "/pets",
+            500,
+            &[("content-type", "application/json")],
+            Some(&json!({})),
+        )?;
+        assert_eq!(1, violations.len());
+        assert_eq!(ViolationLocation::Body, violations[0].location);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_validate_after_release_input() -> Result<()> {
+        let dereferenced = OpenApiDereferencer::builder()
+            .release_input(true)
+            .build_from_str(SPEC)?
+            .dereference()?;
+        let validator = dereferenced.validator_for_schema("Pet")?;
+        assert!(validator.is_valid(&json!({"name": "Rex", "children": [{"name": "Pup"}]})));
+        assert!(!validator.is_valid(&json!({"name": "Rex", "children": [{}]})));
+
+        let violations = dereferenced.validate_request(
+            Method::Put,
+            "/pets/1",
+            &[
+                ("x-request-id", "abc"),
+                ("content-type", "application/json"),
+            ],
+            &[],
+            Some(&json!({"name": "Rex", "children": [{"children": []}]})),
+        )?;
+        let locations: Vec<(ViolationLocation, &str)> = violations
+            .iter()
+            .map(|v| (v.location.clone(), v.instance_path.as_str()))
+            .collect();
+        assert_eq!(vec![(ViolationLocation::Body, "/children/0")], locations);
+        Ok(())
+    }
+}
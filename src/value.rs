@@ -0,0 +1,225 @@
+use std::collections::HashSet;
+
+use serde_json::{Map, Value};
+
+use crate::strip::{DATA, NAME_MAPS};
+use crate::{
+    pointer, CyclePolicy, OpenApiDereferencer, OpenApiError, RefOutput, ORIGINAL_REF_EXTENSION,
+};
+
+impl OpenApiDereferencer {
+    ///Expand the refs in the input document itself and return it as JSON, rather than going
+    ///through the typed spec. Everything the typed spec doesn't know about is kept. Recursive
+    ///refs, refs kept by the inline budget and refs let through by the builder options are
+    ///left as `$ref`s.
+    pub fn dereference_value(&self) -> Result<Value, OpenApiError> {
+        if self.json.is_null() {
+            return Err(OpenApiError::InputReleased);
+        }
+        //The budget is only worked out by dereference, which this doesn't need to have run
+        let kept = self.over_inline_budget()?;
+        self.expand_value(self.json.clone(), false, &kept)
+    }
+
+    ///`names` is whether the keys of an object are names, e.g. of properties, rather than
+    ///keywords.
+    fn expand_value(
+        &self,
+        value: Value,
+        names: bool,
+        kept: &HashSet<String>,
+    ) -> Result<Value, OpenApiError> {
+        match value {
+            Value::Object(object) => match object.get("$ref") {
+                Some(Value::String(reference)) if !names => {
+                    let reference = reference.clone();
+                    self.expand_reference(object, reference, kept)
+                }
+                _ => object
+                    .into_iter()
+                    .map(|(key, value)| {
+                        if !names && is_payload(&key, &value) {
+                            return Ok((key, value));
+                        }
+                        let names =
+                            !names && (NAME_MAPS.contains(&key.as_str()) || key == "callbacks");
+                        let value = self.expand_value(value, names, kept)?;
+                        Ok((key, value))
+                    })
+                    .collect::<Result<Map<String, Value>, OpenApiError>>()
+                    .map(Value::Object),
+            },
+            Value::Array(values) => values
+                .into_iter()
+                .map(|value| self.expand_value(value, false, kept))
+                .collect::<Result<Vec<Value>, OpenApiError>>()
+                .map(Value::Array),
+            value => Ok(value),
+        }
+    }
+
+    fn expand_reference(
+        &self,
+        object: Map<String, Value>,
+        reference: String,
+        kept: &HashSet<String>,
+    ) -> Result<Value, OpenApiError> {
+        if kept.contains(&pointer::percent_decode(&reference))
+            || (self.options.cycles == CyclePolicy::KeepRef && self.on_ref_stack(&reference))
+        {
+            return Ok(Value::Object(object));
+        }
+        let mut siblings = object.clone();
+        siblings.remove("$ref");
+        let target = self
+            .check_ref_stack(&reference)
+            .and_then(|_| self.dereference_type_with_siblings::<Value>(&reference, siblings));
+        let target = match target {
+            Ok(target) => target,
+            Err(e) => {
+                self.skip_failed_ref(&reference, e)?;
+                return Ok(Value::Object(object));
+            }
+        };
+        self.ref_stack.borrow_mut().push(reference.clone());
+        let expanded = self.expand_value(target, false, kept);
+        self.ref_stack.borrow_mut().pop();
+        let mut expanded = expanded?;
+        if let (RefOutput::Annotated, Value::Object(expanded)) =
+            (self.options.output, &mut expanded)
+        {
+            expanded.insert(ORIGINAL_REF_EXTENSION.into(), reference.into());
+        }
+        Ok(expanded)
+    }
+}
+
+///Whether a keyword's value is data rather than part of the spec, so a `$ref` in it is just
+///data too.
+fn is_payload(key: &str, value: &Value) -> bool {
+    key.starts_with("x-")
+        || DATA.contains(&key)
+        //Schema examples, rather than the map of named examples
+        || (key == "examples" && value.is_array())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_dereference_value() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Value", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "x-rate-limit": {"requests": 10},
+                        "responses": {
+                            "200": {"$ref": "#/components/responses/Pets", "description": "Some pets"}
+                        }
+                    }
+                }
+            },
+            "components": {
+                "responses": {
+                    "Pets": {
+                        "description": "Pets",
+                        "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Node"}}}
+                    }
+                },
+                "schemas": {
+                    "Node": {"type": "object", "x-unknown": true, "properties": {"next": {"$ref": "#/components/schemas/Node"}}}
+                }
+            }
+        }"##;
+        let value = OpenApiDereferencer::from_str(spec)?.dereference_value()?;
+        let get = &value["paths"]["/pets"]["get"];
+        assert_eq!(10, get["x-rate-limit"]["requests"]);
+        let response = &get["responses"]["200"];
+        assert_eq!("Some pets", response["description"]);
+        assert!(response.get("$ref").is_none());
+        let node = &response["content"]["application/json"]["schema"];
+        assert_eq!(true, node["x-unknown"]);
+        assert_eq!(
+            "#/components/schemas/Node",
+            node["properties"]["next"]["$ref"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_dereference_value_payloads_and_budget() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Value", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "x-links": {"$ref": "#/components/schemas/Pet"},
+                        "responses": {
+                            "default": {"$ref": "#/components/responses/Error"},
+                            "200": {
+                                "description": "Pets",
+                                "content": {
+                                    "application/json": {
+                                        "schema": {"$ref": "#/components/schemas/Pet"},
+                                        "example": {"$ref": "#/components/schemas/Pet"}
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "post": {
+                        "requestBody": {
+                            "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Pet"}}}
+                        },
+                        "responses": {"default": {"$ref": "#/components/responses/Error"}}
+                    }
+                }
+            },
+            "components": {
+                "responses": {"Error": {"description": "Something went wrong"}},
+                "schemas": {
+                    "Pet": {
+                        "type": "object",
+                        "properties": {"default": {"type": "string"}},
+                        "default": {"$ref": "#/components/schemas/Pet"}
+                    }
+                }
+            }
+        }"##;
+        let value = OpenApiDereferencer::from_str(spec)?.dereference_value()?;
+        let get = &value["paths"]["/pets"]["get"];
+        assert_eq!("#/components/schemas/Pet", get["x-links"]["$ref"]);
+        assert_eq!(
+            "Something went wrong",
+            get["responses"]["default"]["description"]
+        );
+        let media_type = &get["responses"]["200"]["content"]["application/json"];
+        assert_eq!("#/components/schemas/Pet", media_type["example"]["$ref"]);
+        assert_eq!(
+            "string",
+            media_type["schema"]["properties"]["default"]["type"]
+        );
+        assert_eq!(
+            "#/components/schemas/Pet",
+            media_type["schema"]["default"]["$ref"]
+        );
+
+        let value = OpenApiDereferencer::builder()
+            .inline_max_references(1)
+            .build_from_str(spec)?
+            .dereference_value()?;
+        let responses = &value["paths"]["/pets"]["get"]["responses"];
+        assert_eq!("#/components/responses/Error", responses["default"]["$ref"]);
+        assert_eq!(
+            "#/components/schemas/Pet",
+            responses["200"]["content"]["application/json"]["schema"]["$ref"]
+        );
+        Ok(())
+    }
+}
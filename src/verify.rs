@@ -0,0 +1,233 @@
+use crate::lenient::for_each_ref;
+use crate::{ref_to_json_pointer, OpenApiDereferencer, OpenApiError, SpecVisitor};
+
+///A `$ref` still in the spec after dereferencing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemainingReference {
+    ///JSON pointer to where the ref is used
+    pub location: String,
+    pub reference: String,
+}
+
+///Where a `$ref` points, going by what the ref looks like.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReferenceKind {
+    ///Somewhere in the spec, e.g. `#/components/schemas/Pet`, `#pet` or a `$id` in the spec
+    Internal,
+    ///Another file, e.g. `pets.json#/Pet`
+    External,
+    ///A document that has to be fetched, e.g. `https://example.com/pets.json#/Pet`
+    Remote,
+    ///Not a ref that can be resolved at all, e.g. `#components/schemas/Pet`
+    InvalidFormat,
+}
+
+///A `$ref` in the input document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListedReference {
+    ///JSON pointer to the object with the `$ref`
+    pub location: String,
+    pub reference: String,
+    pub kind: ReferenceKind,
+}
+
+#[derive(Default)]
+struct ReferenceCollector {
+    references: Vec<RemainingReference>,
+}
+
+impl<'a> SpecVisitor<'a> for ReferenceCollector {
+    fn visit_reference(&mut self, location: &str, reference: &'a str) {
+        self.references.push(RemainingReference {
+            location: location.into(),
+            reference: reference.into(),
+        });
+    }
+}
+
+impl OpenApiDereferencer {
+    ///Every ref left anywhere in the spec, including nested schemas, media types, callbacks and
+    ///webhooks. After a successful dereference only recursive schemas, and refs let through by
+    ///the builder options, should be left.
+    pub fn verify_no_references(&self) -> Vec<RemainingReference> {
+        let mut collector = ReferenceCollector::default();
+        self.walk(&mut collector);
+        collector.references
+    }
+
+    ///Every `$ref` and `$dynamicRef` in the input document with where it points, without
+    ///dereferencing anything. Handy for checking up front whether a spec can be dereferenced
+    ///offline.
+    pub fn list_references(&self) -> Result<Vec<ListedReference>, OpenApiError> {
+        let mut references = vec![];
+        for_each_ref(self.input()?, "", &mut |location, reference| {
+            references.push(ListedReference {
+                location: location.into(),
+                reference: reference.into(),
+                kind: self.reference_kind(reference),
+            })
+        });
+        Ok(references)
+    }
+
+    fn reference_kind(&self, reference: &str) -> ReferenceKind {
+        if let Some(fragment) = reference.strip_prefix('#') {
+            return if ref_to_json_pointer(reference).is_ok() || is_anchor(fragment) {
+                ReferenceKind::Internal
+            } else {
+                ReferenceKind::InvalidFormat
+            };
+        }
+        if self.anchor_index().locate(reference).is_ok() {
+            return ReferenceKind::Internal;
+        }
+        if reference.chars().any(char::is_whitespace) || reference.starts_with("//") {
+            return ReferenceKind::InvalidFormat;
+        }
+        match reference.split_once("://") {
+            Some(("file", _)) | None => ReferenceKind::External,
+            Some((scheme, _))
+                if scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c)) =>
+            {
+                ReferenceKind::Remote
+            }
+            Some(_) => ReferenceKind::InvalidFormat,
+        }
+    }
+}
+
+///Anchor names look like `pet` or `pet-name_2`.
+fn is_anchor(fragment: &str) -> bool {
+    let mut chars = fragment.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ExternalRefPolicy;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_verify_no_references() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Verify", "version": "1.0.0"},
+            "webhooks": {
+                "newPet": {
+                    "post": {
+                        "callbacks": {
+                            "done": {
+                                "{$request.body#/url}": {
+                                    "post": {
+                                        "requestBody": {
+                                            "content": {"application/json": {"schema": {"$ref": "other.json#/Pet"}}}
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "responses": {"200": {"$ref": "#/components/responses/Ok"}}
+                    }
+                }
+            },
+            "components": {
+                "responses": {"Ok": {"description": "ok"}},
+                "schemas": {
+                    "Node": {"type": "object", "properties": {"next": {"$ref": "#/components/schemas/Node"}}}
+                }
+            }
+        }"##;
+        let dereferencer = OpenApiDereferencer::from_str(spec)?;
+        assert_eq!(3, dereferencer.verify_no_references().len());
+
+        let dereferenced = OpenApiDereferencer::builder()
+            .external_refs(ExternalRefPolicy::Keep)
+            .build_from_str(spec)?
+            .dereference()?;
+        assert_eq!(
+            vec![
+                RemainingReference {
+                    location: "/webhooks/newPet/post/callbacks/done/{$request.body#~1url}/post/requestBody/content/application~1json/schema".into(),
+                    reference: "other.json#/Pet".into(),
+                },
+                RemainingReference {
+                    location: "/components/schemas/Node/properties/next".into(),
+                    reference: "#/components/schemas/Node".into(),
+                },
+            ],
+            dereferenced.verify_no_references()
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_list_references() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "List", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "parameters": [{"$ref": "common.json#/Limit"}],
+                        "responses": {
+                            "200": {"$ref": "#/components/responses/Ok"},
+                            "404": {"$ref": "https://example.com/errors.json#/NotFound"},
+                            "500": {"$ref": "#components/responses/Ok"}
+                        }
+                    }
+                }
+            },
+            "components": {
+                "responses": {"Ok": {"description": "ok"}},
+                "schemas": {
+                    "Pet": {"$id": "https://example.com/pet", "$anchor": "pet", "type": "object"},
+                    "Pets": {"type": "array", "items": {"$ref": "https://example.com/pet"}},
+                    "Cat": {"$ref": "#pet"}
+                }
+            }
+        }"##;
+        let mut references: Vec<(String, ReferenceKind)> = OpenApiDereferencer::from_str(spec)?
+            .list_references()?
+            .into_iter()
+            .map(|listed| (listed.location, listed.kind))
+            .collect();
+        references.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            vec![
+                (
+                    "/components/schemas/Cat".to_string(),
+                    ReferenceKind::Internal
+                ),
+                (
+                    "/components/schemas/Pets/items".to_string(),
+                    ReferenceKind::Internal
+                ),
+                (
+                    "/paths/~1pets/get/parameters/0".to_string(),
+                    ReferenceKind::External
+                ),
+                (
+                    "/paths/~1pets/get/responses/200".to_string(),
+                    ReferenceKind::Internal
+                ),
+                (
+                    "/paths/~1pets/get/responses/404".to_string(),
+                    ReferenceKind::Remote
+                ),
+                (
+                    "/paths/~1pets/get/responses/500".to_string(),
+                    ReferenceKind::InvalidFormat
+                ),
+            ],
+            references
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashSet;
+
+use openapiv3::schemars::schema::{Metadata, Schema as SchemarsSchema, SchemaObject};
+
+use crate::visit_mut::schema_mut;
+
+///The schema as it applies to request bodies: properties marked `readOnly` are dropped, from
+///nested schemas too, along with their entries in `required`.
+pub fn request_view(schema: &SchemarsSchema) -> SchemarsSchema {
+    view(schema, |metadata| metadata.read_only)
+}
+
+///The schema as it applies to responses: properties marked `writeOnly` are dropped, from
+///nested schemas too, along with their entries in `required`.
+pub fn response_view(schema: &SchemarsSchema) -> SchemarsSchema {
+    view(schema, |metadata| metadata.write_only)
+}
+
+fn view(schema: &SchemarsSchema, hidden: fn(&Metadata) -> bool) -> SchemarsSchema {
+    let mut schema = schema.clone();
+    //`required` can name a property another `allOf` member declares, so prune it across the
+    //whole `allOf` before any properties go
+    schema_mut(&mut schema, &mut |schema| {
+        let (mut hidden_names, mut visible) = (HashSet::new(), HashSet::new());
+        all_of_properties(schema, hidden, &mut hidden_names, &mut visible);
+        let names: HashSet<String> = hidden_names.difference(&visible).cloned().collect();
+        if !names.is_empty() {
+            remove_required(schema, &names);
+        }
+    });
+    schema_mut(&mut schema, &mut |schema| {
+        let SchemarsSchema::Object(object) = schema else {
+            return;
+        };
+        let Some(object_validation) = object.object.as_mut() else {
+            return;
+        };
+        let hidden_properties: Vec<String> = object_validation
+            .properties
+            .iter()
+            .filter(|(_, property)| is_hidden(property, hidden))
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in hidden_properties {
+            object_validation.properties.remove(&name);
+            object_validation.required.remove(&name);
+        }
+    });
+    schema
+}
+
+fn is_hidden(property: &SchemarsSchema, hidden: fn(&Metadata) -> bool) -> bool {
+    match property {
+        SchemarsSchema::Object(property) => property.metadata.as_deref().is_some_and(hidden),
+        SchemarsSchema::Bool(_) => false,
+    }
+}
+
+///The properties declared by `schema` and its `allOf` members, all the way down, split into
+///hidden and visible ones.
+fn all_of_properties(
+    schema: &SchemarsSchema,
+    hidden: fn(&Metadata) -> bool,
+    hidden_names: &mut HashSet<String>,
+    visible: &mut HashSet<String>,
+) {
+    let SchemarsSchema::Object(object) = schema else {
+        return;
+    };
+    for (name, property) in object.object.iter().flat_map(|o| &o.properties) {
+        if is_hidden(property, hidden) {
+            hidden_names.insert(name.clone());
+        } else {
+            visible.insert(name.clone());
+        }
+    }
+    for member in all_of(object) {
+        all_of_properties(member, hidden, hidden_names, visible);
+    }
+}
+
+fn remove_required(schema: &mut SchemarsSchema, names: &HashSet<String>) {
+    let SchemarsSchema::Object(object) = schema else {
+        return;
+    };
+    if let Some(object_validation) = object.object.as_mut() {
+        object_validation
+            .required
+            .retain(|name| !names.contains(name));
+    }
+    let members = object
+        .subschemas
+        .as_mut()
+        .and_then(|subschemas| subschemas.all_of.as_mut());
+    for member in members.into_iter().flatten() {
+        remove_required(member, names);
+    }
+}
+
+fn all_of(object: &SchemaObject) -> impl Iterator<Item = &SchemarsSchema> {
+    object
+        .subschemas
+        .iter()
+        .flat_map(|subschemas| subschemas.all_of.iter().flatten())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    pub fn test_views() -> Result<()> {
+        let schema: SchemarsSchema = serde_json::from_value(json!({
+            "type": "object",
+            "required": ["id", "name", "password"],
+            "properties": {
+                "id": {"type": "integer", "readOnly": true},
+                "name": {"type": "string"},
+                "password": {"type": "string", "writeOnly": true},
+                "owner": {
+                    "type": "object",
+                    "properties": {
+                        "id": {"type": "integer", "readOnly": true},
+                        "secret": {"type": "string", "writeOnly": true}
+                    }
+                }
+            }
+        }))?;
+        let request = serde_json::to_value(request_view(&schema))?;
+        assert_eq!(json!(["name", "password"]), request["required"]);
+        assert!(request["properties"].get("id").is_none());
+        assert!(request["properties"]["password"].is_object());
+        assert!(request["properties"]["owner"]["properties"]
+            .get("id")
+            .is_none());
+        assert!(request["properties"]["owner"]["properties"]["secret"].is_object());
+
+        let response = serde_json::to_value(response_view(&schema))?;
+        assert_eq!(json!(["id", "name"]), response["required"]);
+        assert!(response["properties"].get("password").is_none());
+        assert!(response["properties"]["owner"]["properties"]
+            .get("secret")
+            .is_none());
+        assert!(response["properties"]["owner"]["properties"]["id"].is_object());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_views_prune_required_across_all_of() -> Result<()> {
+        let schema: SchemarsSchema = serde_json::from_value(json!({
+            "required": ["id", "name"],
+            "allOf": [
+                {"properties": {"id": {"type": "integer", "readOnly": true}}},
+                {
+                    "required": ["id", "name"],
+                    "properties": {"name": {"type": "string"}}
+                }
+            ]
+        }))?;
+        let request = serde_json::to_value(request_view(&schema))?;
+        assert_eq!(json!(["name"]), request["required"]);
+        assert_eq!(json!(["name"]), request["allOf"][1]["required"]);
+        assert!(request["allOf"][0]["properties"].get("id").is_none());
+        Ok(())
+    }
+}
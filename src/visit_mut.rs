@@ -0,0 +1,511 @@
+use openapiv3::schemars::schema::{Schema as SchemarsSchema, SingleOrVec};
+use openapiv3::schemars::Map as SchemarsMap;
+use openapiv3::v3_1::{
+    Callback, Components, Header, MediaType, OpenApi as OpenApiV3_1, Operation, Parameter,
+    ParameterSchemaOrContent, PathItem, ReferenceOr, RequestBody, Response,
+};
+use serde_json::Value;
+
+///The mutable version of `as_item`.
+pub(crate) fn as_item_mut<T>(reference_or: &mut ReferenceOr<T>) -> Option<&mut T> {
+    match reference_or {
+        ReferenceOr::Item(item) => Some(item),
+        ReferenceOr::DereferencedReference {
+            reference: _,
+            summary: _,
+            description: _,
+            item,
+        } => Some(item),
+        _ => None,
+    }
+}
+
+///Call `f` on every schema in the spec, nested schemas included. Children are visited before
+///the schema holding them, so `f` always sees schemas whose subschemas it has already handled.
+pub(crate) fn for_each_schema_mut(
+    openapi: &mut OpenApiV3_1,
+    f: &mut dyn FnMut(&mut SchemarsSchema),
+) {
+    if let Some(paths) = openapi.paths.as_mut() {
+        for path_item in paths.paths.values_mut().filter_map(as_item_mut) {
+            path_item_schemas(path_item, f);
+        }
+    }
+    for path_item in openapi.webhooks.values_mut().filter_map(as_item_mut) {
+        path_item_schemas(path_item, f);
+    }
+    if let Some(components) = openapi.components.as_mut() {
+        components_schemas(components, f);
+    }
+}
+
+fn components_schemas(components: &mut Components, f: &mut dyn FnMut(&mut SchemarsSchema)) {
+    for schema in components.schemas.values_mut() {
+        schema_mut(&mut schema.json_schema, f);
+    }
+    for response in components.responses.values_mut().filter_map(as_item_mut) {
+        response_schemas(response, f);
+    }
+    for parameter in components.parameters.values_mut().filter_map(as_item_mut) {
+        parameter_schemas(parameter, f);
+    }
+    for request_body in components
+        .request_bodies
+        .values_mut()
+        .filter_map(as_item_mut)
+    {
+        request_body_schemas(request_body, f);
+    }
+    for header in components.headers.values_mut().filter_map(as_item_mut) {
+        header_schemas(header, f);
+    }
+    for callback in components.callbacks.values_mut().filter_map(as_item_mut) {
+        callback_schemas(callback, f);
+    }
+    for path_item in components.path_items.values_mut().filter_map(as_item_mut) {
+        path_item_schemas(path_item, f);
+    }
+}
+
+fn path_item_schemas(path_item: &mut PathItem, f: &mut dyn FnMut(&mut SchemarsSchema)) {
+    for parameter in path_item.parameters.iter_mut().filter_map(as_item_mut) {
+        parameter_schemas(parameter, f);
+    }
+    for operation in [
+        &mut path_item.get,
+        &mut path_item.put,
+        &mut path_item.post,
+        &mut path_item.delete,
+        &mut path_item.options,
+        &mut path_item.head,
+        &mut path_item.patch,
+        &mut path_item.trace,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        operation_schemas(operation, f);
+    }
+}
+
+fn operation_schemas(operation: &mut Operation, f: &mut dyn FnMut(&mut SchemarsSchema)) {
+    for parameter in operation.parameters.iter_mut().filter_map(as_item_mut) {
+        parameter_schemas(parameter, f);
+    }
+    if let Some(request_body) = operation.request_body.as_mut().and_then(as_item_mut) {
+        request_body_schemas(request_body, f);
+    }
+    if let Some(responses) = operation.responses.as_mut() {
+        if let Some(response) = responses.default.as_mut().and_then(as_item_mut) {
+            response_schemas(response, f);
+        }
+        for response in responses.responses.values_mut().filter_map(as_item_mut) {
+            response_schemas(response, f);
+        }
+    }
+    for callback in operation.callbacks.values_mut().filter_map(as_item_mut) {
+        callback_schemas(callback, f);
+    }
+}
+
+fn callback_schemas(callback: &mut Callback, f: &mut dyn FnMut(&mut SchemarsSchema)) {
+    for path_item in callback.values_mut() {
+        path_item_schemas(path_item, f);
+    }
+}
+
+fn parameter_schemas(parameter: &mut Parameter, f: &mut dyn FnMut(&mut SchemarsSchema)) {
+    let parameter_data = match parameter {
+        Parameter::Query { parameter_data, .. } => parameter_data,
+        Parameter::Header { parameter_data, .. } => parameter_data,
+        Parameter::Path { parameter_data, .. } => parameter_data,
+        Parameter::Cookie { parameter_data, .. } => parameter_data,
+    };
+    schema_or_content_schemas(&mut parameter_data.format, f);
+}
+
+fn header_schemas(header: &mut Header, f: &mut dyn FnMut(&mut SchemarsSchema)) {
+    schema_or_content_schemas(&mut header.format, f);
+}
+
+fn schema_or_content_schemas(
+    format: &mut ParameterSchemaOrContent,
+    f: &mut dyn FnMut(&mut SchemarsSchema),
+) {
+    match format {
+        ParameterSchemaOrContent::Schema(schema) => schema_mut(&mut schema.json_schema, f),
+        ParameterSchemaOrContent::Content(content) => {
+            for media_type in content.values_mut() {
+                media_type_schemas(media_type, f);
+            }
+        }
+    }
+}
+
+fn request_body_schemas(request_body: &mut RequestBody, f: &mut dyn FnMut(&mut SchemarsSchema)) {
+    for media_type in request_body.content.values_mut() {
+        media_type_schemas(media_type, f);
+    }
+}
+
+fn response_schemas(response: &mut Response, f: &mut dyn FnMut(&mut SchemarsSchema)) {
+    for header in response.headers.values_mut().filter_map(as_item_mut) {
+        header_schemas(header, f);
+    }
+    for media_type in response.content.values_mut() {
+        media_type_schemas(media_type, f);
+    }
+}
+
+fn media_type_schemas(media_type: &mut MediaType, f: &mut dyn FnMut(&mut SchemarsSchema)) {
+    if let Some(schema) = media_type.schema.as_mut() {
+        schema_mut(&mut schema.json_schema, f);
+    }
+    for encoding in media_type.encoding.values_mut() {
+        for header in encoding.headers.values_mut().filter_map(as_item_mut) {
+            header_schemas(header, f);
+        }
+    }
+}
+
+///Call `f` on a schema and everything nested in it, children first.
+pub(crate) fn schema_mut(schema: &mut SchemarsSchema, f: &mut dyn FnMut(&mut SchemarsSchema)) {
+    if let SchemarsSchema::Object(object) = schema {
+        if let Some(subschemas) = object.subschemas.as_mut() {
+            for schemas in [
+                &mut subschemas.all_of,
+                &mut subschemas.any_of,
+                &mut subschemas.one_of,
+            ] {
+                for schema in schemas.iter_mut().flatten() {
+                    schema_mut(schema, f);
+                }
+            }
+            for schema in [
+                &mut subschemas.not,
+                &mut subschemas.if_schema,
+                &mut subschemas.then_schema,
+                &mut subschemas.else_schema,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                schema_mut(schema, f);
+            }
+        }
+        if let Some(array) = object.array.as_mut() {
+            match array.items.as_mut() {
+                Some(SingleOrVec::Single(schema)) => schema_mut(schema, f),
+                Some(SingleOrVec::Vec(schemas)) => {
+                    for schema in schemas {
+                        schema_mut(schema, f);
+                    }
+                }
+                None => {}
+            }
+            for schema in [&mut array.additional_items, &mut array.contains]
+                .into_iter()
+                .flatten()
+            {
+                schema_mut(schema, f);
+            }
+        }
+        if let Some(object_validation) = object.object.as_mut() {
+            for schema in object_validation
+                .properties
+                .values_mut()
+                .chain(object_validation.pattern_properties.values_mut())
+            {
+                schema_mut(schema, f);
+            }
+            for schema in [
+                &mut object_validation.additional_properties,
+                &mut object_validation.property_names,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                schema_mut(schema, f);
+            }
+        }
+        extension_mut::<Vec<SchemarsSchema>>(&mut object.extensions, "prefixItems", &mut |items| {
+            for schema in items {
+                schema_mut(schema, f);
+            }
+        });
+        extension_mut::<SchemarsMap<String, SchemarsSchema>>(
+            &mut object.extensions,
+            "$defs",
+            &mut |defs| {
+                for schema in defs.values_mut() {
+                    schema_mut(schema, f);
+                }
+            },
+        );
+    }
+    f(schema);
+}
+
+///`prefixItems` and `$defs` live in the schemars extensions as plain JSON. Parse one, walk it and
+///write it back. Anything that doesn't parse is left alone.
+fn extension_mut<S: serde::Serialize + serde::de::DeserializeOwned>(
+    extensions: &mut SchemarsMap<String, Value>,
+    keyword: &str,
+    walk: &mut dyn FnMut(&mut S),
+) {
+    let Some(value) = extensions.get_mut(keyword) else {
+        return;
+    };
+    if let Ok(mut parsed) = serde_json::from_value::<S>(value.clone()) {
+        walk(&mut parsed);
+        if let Ok(walked) = serde_json::to_value(parsed) {
+            *value = walked;
+        }
+    }
+}
+
+///Call `f` on every ref in the spec, resolved or not, including the refs left in schemas.
+pub(crate) fn for_each_reference_mut(openapi: &mut OpenApiV3_1, f: &mut dyn FnMut(&mut String)) {
+    let mut walk = ReferenceWalk {
+        f,
+        unwrap_dereferenced: false,
+    };
+    walk_references(openapi, &mut walk);
+}
+
+///Turn every `DereferencedReference` into a plain `Item`.
+pub(crate) fn unwrap_dereferenced(openapi: &mut OpenApiV3_1) {
+    let mut walk = ReferenceWalk {
+        f: &mut |_: &mut String| {},
+        unwrap_dereferenced: true,
+    };
+    walk_references(openapi, &mut walk);
+}
+
+///Turn every `DereferencedReference` in a path item, and the path item itself, into a plain
+///`Item`.
+pub(crate) fn unwrap_dereferenced_path_item(path_item: &mut ReferenceOr<PathItem>) {
+    let mut walk = ReferenceWalk {
+        f: &mut |_: &mut String| {},
+        unwrap_dereferenced: true,
+    };
+    if let Some(path_item) = reference_mut(path_item, &mut walk) {
+        path_item_references(path_item, &mut walk);
+    }
+}
+
+struct ReferenceWalk<'f> {
+    f: &'f mut dyn FnMut(&mut String),
+    unwrap_dereferenced: bool,
+}
+
+fn walk_references(openapi: &mut OpenApiV3_1, walk: &mut ReferenceWalk) {
+    if let Some(paths) = openapi.paths.as_mut() {
+        for path_item in paths.paths.values_mut() {
+            if let Some(path_item) = reference_mut(path_item, walk) {
+                path_item_references(path_item, walk);
+            }
+        }
+    }
+    for path_item in openapi.webhooks.values_mut() {
+        if let Some(path_item) = reference_mut(path_item, walk) {
+            path_item_references(path_item, walk);
+        }
+    }
+    if let Some(components) = openapi.components.as_mut() {
+        for response in components.responses.values_mut() {
+            if let Some(response) = reference_mut(response, walk) {
+                response_references(response, walk);
+            }
+        }
+        for parameter in components.parameters.values_mut() {
+            if let Some(parameter) = reference_mut(parameter, walk) {
+                parameter_references(parameter, walk);
+            }
+        }
+        for example in components.examples.values_mut() {
+            reference_mut(example, walk);
+        }
+        for request_body in components.request_bodies.values_mut() {
+            if let Some(request_body) = reference_mut(request_body, walk) {
+                for media_type in request_body.content.values_mut() {
+                    media_type_references(media_type, walk);
+                }
+            }
+        }
+        for header in components.headers.values_mut() {
+            if let Some(header) = reference_mut(header, walk) {
+                header_references(header, walk);
+            }
+        }
+        for security_scheme in components.security_schemes.values_mut() {
+            reference_mut(security_scheme, walk);
+        }
+        for link in components.links.values_mut() {
+            reference_mut(link, walk);
+        }
+        for callback in components.callbacks.values_mut() {
+            if let Some(callback) = reference_mut(callback, walk) {
+                for path_item in callback.values_mut() {
+                    path_item_references(path_item, walk);
+                }
+            }
+        }
+        for path_item in components.path_items.values_mut() {
+            if let Some(path_item) = reference_mut(path_item, walk) {
+                path_item_references(path_item, walk);
+            }
+        }
+    }
+    for_each_schema_mut(openapi, &mut |schema| {
+        if let SchemarsSchema::Object(object) = schema {
+            if let Some(reference) = object.reference.as_mut() {
+                (walk.f)(reference);
+            }
+        }
+    });
+}
+
+fn reference_mut<'a, T>(
+    reference_or: &'a mut ReferenceOr<T>,
+    walk: &mut ReferenceWalk,
+) -> Option<&'a mut T> {
+    if walk.unwrap_dereferenced {
+        if let ReferenceOr::DereferencedReference { .. } = reference_or {
+            let placeholder = ReferenceOr::Reference {
+                reference: String::new(),
+                summary: None,
+                description: None,
+            };
+            if let ReferenceOr::DereferencedReference { item, .. } =
+                std::mem::replace(reference_or, placeholder)
+            {
+                *reference_or = ReferenceOr::Item(item);
+            }
+        }
+    }
+    match reference_or {
+        ReferenceOr::Reference { reference, .. } => {
+            (walk.f)(reference);
+            None
+        }
+        ReferenceOr::DereferencedReference {
+            reference, item, ..
+        } => {
+            (walk.f)(reference);
+            Some(item)
+        }
+        ReferenceOr::Item(item) => Some(item),
+    }
+}
+
+fn path_item_references(path_item: &mut PathItem, walk: &mut ReferenceWalk) {
+    for parameter in path_item.parameters.iter_mut() {
+        if let Some(parameter) = reference_mut(parameter, walk) {
+            parameter_references(parameter, walk);
+        }
+    }
+    for operation in [
+        &mut path_item.get,
+        &mut path_item.put,
+        &mut path_item.post,
+        &mut path_item.delete,
+        &mut path_item.options,
+        &mut path_item.head,
+        &mut path_item.patch,
+        &mut path_item.trace,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        operation_references(operation, walk);
+    }
+}
+
+fn operation_references(operation: &mut Operation, walk: &mut ReferenceWalk) {
+    for parameter in operation.parameters.iter_mut() {
+        if let Some(parameter) = reference_mut(parameter, walk) {
+            parameter_references(parameter, walk);
+        }
+    }
+    if let Some(request_body) = operation.request_body.as_mut() {
+        if let Some(request_body) = reference_mut(request_body, walk) {
+            for media_type in request_body.content.values_mut() {
+                media_type_references(media_type, walk);
+            }
+        }
+    }
+    if let Some(responses) = operation.responses.as_mut() {
+        for response in responses
+            .default
+            .iter_mut()
+            .chain(responses.responses.values_mut())
+        {
+            if let Some(response) = reference_mut(response, walk) {
+                response_references(response, walk);
+            }
+        }
+    }
+    for callback in operation.callbacks.values_mut() {
+        if let Some(callback) = reference_mut(callback, walk) {
+            for path_item in callback.values_mut() {
+                path_item_references(path_item, walk);
+            }
+        }
+    }
+}
+
+fn parameter_references(parameter: &mut Parameter, walk: &mut ReferenceWalk) {
+    let parameter_data = match parameter {
+        Parameter::Query { parameter_data, .. } => parameter_data,
+        Parameter::Header { parameter_data, .. } => parameter_data,
+        Parameter::Path { parameter_data, .. } => parameter_data,
+        Parameter::Cookie { parameter_data, .. } => parameter_data,
+    };
+    for example in parameter_data.examples.values_mut() {
+        reference_mut(example, walk);
+    }
+    if let ParameterSchemaOrContent::Content(content) = &mut parameter_data.format {
+        for media_type in content.values_mut() {
+            media_type_references(media_type, walk);
+        }
+    }
+}
+
+fn header_references(header: &mut Header, walk: &mut ReferenceWalk) {
+    for example in header.examples.values_mut() {
+        reference_mut(example, walk);
+    }
+    if let ParameterSchemaOrContent::Content(content) = &mut header.format {
+        for media_type in content.values_mut() {
+            media_type_references(media_type, walk);
+        }
+    }
+}
+
+fn response_references(response: &mut Response, walk: &mut ReferenceWalk) {
+    for header in response.headers.values_mut() {
+        if let Some(header) = reference_mut(header, walk) {
+            header_references(header, walk);
+        }
+    }
+    for media_type in response.content.values_mut() {
+        media_type_references(media_type, walk);
+    }
+    for link in response.links.values_mut() {
+        reference_mut(link, walk);
+    }
+}
+
+fn media_type_references(media_type: &mut MediaType, walk: &mut ReferenceWalk) {
+    for example in media_type.examples.values_mut() {
+        reference_mut(example, walk);
+    }
+    for encoding in media_type.encoding.values_mut() {
+        for header in encoding.headers.values_mut() {
+            if let Some(header) = reference_mut(header, walk) {
+                header_references(header, walk);
+            }
+        }
+    }
+}
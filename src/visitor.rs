@@ -0,0 +1,697 @@
+use openapiv3::schemars::schema::{Schema as SchemarsSchema, SingleOrVec};
+use openapiv3::v3_1::{
+    Callback, Components, Example, Header, Link, MediaType, Operation, Parameter,
+    ParameterSchemaOrContent, PathItem, ReferenceOr, RequestBody, Response, SchemaObject,
+    SecurityScheme,
+};
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::borrow::Cow;
+
+use crate::convert::{SCHEMA_KEYWORDS, SCHEMA_LIST_KEYWORDS, SCHEMA_MAP_KEYWORDS};
+use crate::operations::path_item_operations;
+use crate::{as_item, pointer, DereferencedOpenApi, Method, OpenApiDereferencer};
+
+///Callbacks for walking a spec with [`OpenApiDereferencer::walk`]. Every callback has an empty
+///default so visitors only need to implement the ones they care about. `location` is the JSON
+///pointer of the visited node in the spec (after dereferencing this is where the node is used,
+///not where it was defined).
+#[allow(unused_variables)]
+pub trait SpecVisitor<'a> {
+    fn visit_path_item(&mut self, location: &str, path_item: &'a PathItem) {}
+
+    ///`path` is the path template for operations under `paths`, the webhook name for webhooks,
+    ///or the callback expression for operations inside callbacks.
+    fn visit_operation(
+        &mut self,
+        location: &str,
+        path: &'a str,
+        method: Method,
+        operation: &'a Operation,
+    ) {
+    }
+
+    fn visit_parameter(&mut self, location: &str, parameter: &'a Parameter) {}
+
+    fn visit_request_body(&mut self, location: &str, request_body: &'a RequestBody) {}
+
+    fn visit_response(&mut self, location: &str, response: &'a Response) {}
+
+    fn visit_header(&mut self, location: &str, header: &'a Header) {}
+
+    fn visit_media_type(&mut self, location: &str, media_type: &'a MediaType) {}
+
+    fn visit_example(&mut self, location: &str, example: &'a Example) {}
+
+    fn visit_link(&mut self, location: &str, link: &'a Link) {}
+
+    fn visit_security_scheme(&mut self, location: &str, security_scheme: &'a SecurityScheme) {}
+
+    ///Called for every schema, including the nested ones under properties, items, allOf, etc.
+    fn visit_schema(&mut self, location: &str, schema: &'a SchemarsSchema) {}
+
+    ///Called for the schemas under `prefixItems` and `$defs`. schemars keeps those as plain JSON,
+    ///so they're parsed on the fly and only borrowed for the call.
+    fn visit_parsed_schema(&mut self, location: &str, schema: &SchemarsSchema) {}
+
+    ///Called for every `$ref` that's still in the spec, either an unresolved `ReferenceOr` or a
+    ///schema that wasn't inlined.
+    fn visit_reference(&mut self, location: &str, reference: &'a str) {}
+}
+
+impl OpenApiDereferencer {
+    ///Walk the spec, calling back into the visitor for every node. This works before
+    ///dereferencing too, in which case references are passed to `visit_reference` and not
+    ///followed.
+    pub fn walk<'a, V: SpecVisitor<'a>>(&'a self, visitor: &mut V) {
+        if let Some(paths) = &self.openapi.paths {
+            for (path, path_item) in &paths.paths {
+                let location = pointer::push("/paths", path);
+                if let Some(path_item) = item(path_item, &location, visitor) {
+                    walk_path_item(path, path_item, &location, visitor);
+                }
+            }
+        }
+        for (name, path_item) in &self.openapi.webhooks {
+            let location = pointer::push("/webhooks", name);
+            if let Some(path_item) = item(path_item, &location, visitor) {
+                walk_path_item(name, path_item, &location, visitor);
+            }
+        }
+        if let Some(components) = &self.openapi.components {
+            walk_components(components, visitor);
+        }
+    }
+}
+
+impl DereferencedOpenApi {
+    ///Every schema in the dereferenced spec along with its JSON pointer, including the nested
+    ///ones under properties, items, allOf, etc.
+    pub fn iter_schemas(&self) -> impl Iterator<Item = (String, Cow<'_, SchemarsSchema>)> + '_ {
+        let mut schemas = SchemaCollector::default();
+        self.walk(&mut schemas);
+        schemas.0.into_iter()
+    }
+}
+
+#[derive(Default)]
+struct SchemaCollector<'a>(Vec<(String, Cow<'a, SchemarsSchema>)>);
+
+impl<'a> SpecVisitor<'a> for SchemaCollector<'a> {
+    fn visit_schema(&mut self, location: &str, schema: &'a SchemarsSchema) {
+        self.0.push((location.into(), Cow::Borrowed(schema)));
+    }
+
+    fn visit_parsed_schema(&mut self, location: &str, schema: &SchemarsSchema) {
+        self.0.push((location.into(), Cow::Owned(schema.clone())));
+    }
+}
+
+fn item<'a, T, V: SpecVisitor<'a> + ?Sized>(
+    reference_or: &'a ReferenceOr<T>,
+    location: &str,
+    visitor: &mut V,
+) -> Option<&'a T> {
+    if let ReferenceOr::Reference {
+        reference,
+        summary: _,
+        description: _,
+    } = reference_or
+    {
+        visitor.visit_reference(location, reference);
+    }
+    as_item(reference_or)
+}
+
+fn walk_components<'a, V: SpecVisitor<'a> + ?Sized>(components: &'a Components, visitor: &mut V) {
+    for (name, schema) in &components.schemas {
+        walk_schema_object(schema, &pointer::push("/components/schemas", name), visitor);
+    }
+    for (name, response) in &components.responses {
+        let location = pointer::push("/components/responses", name);
+        if let Some(response) = item(response, &location, visitor) {
+            walk_response(response, &location, visitor);
+        }
+    }
+    for (name, parameter) in &components.parameters {
+        let location = pointer::push("/components/parameters", name);
+        if let Some(parameter) = item(parameter, &location, visitor) {
+            walk_parameter(parameter, &location, visitor);
+        }
+    }
+    for (name, example) in &components.examples {
+        let location = pointer::push("/components/examples", name);
+        if let Some(example) = item(example, &location, visitor) {
+            visitor.visit_example(&location, example);
+        }
+    }
+    for (name, request_body) in &components.request_bodies {
+        let location = pointer::push("/components/requestBodies", name);
+        if let Some(request_body) = item(request_body, &location, visitor) {
+            walk_request_body(request_body, &location, visitor);
+        }
+    }
+    for (name, header) in &components.headers {
+        let location = pointer::push("/components/headers", name);
+        if let Some(header) = item(header, &location, visitor) {
+            walk_header(header, &location, visitor);
+        }
+    }
+    for (name, security_scheme) in &components.security_schemes {
+        let location = pointer::push("/components/securitySchemes", name);
+        if let Some(security_scheme) = item(security_scheme, &location, visitor) {
+            visitor.visit_security_scheme(&location, security_scheme);
+        }
+    }
+    for (name, link) in &components.links {
+        let location = pointer::push("/components/links", name);
+        if let Some(link) = item(link, &location, visitor) {
+            visitor.visit_link(&location, link);
+        }
+    }
+    for (name, callback) in &components.callbacks {
+        let location = pointer::push("/components/callbacks", name);
+        if let Some(callback) = item(callback, &location, visitor) {
+            walk_callback(callback, &location, visitor);
+        }
+    }
+    for (name, path_item) in &components.path_items {
+        let location = pointer::push("/components/pathItems", name);
+        if let Some(path_item) = item(path_item, &location, visitor) {
+            walk_path_item(name, path_item, &location, visitor);
+        }
+    }
+}
+
+pub(crate) fn walk_path_item<'a, V: SpecVisitor<'a> + ?Sized>(
+    path: &'a str,
+    path_item: &'a PathItem,
+    location: &str,
+    visitor: &mut V,
+) {
+    visitor.visit_path_item(location, path_item);
+    walk_parameters(&path_item.parameters, location, visitor);
+    for (method, operation) in path_item_operations(path_item) {
+        let location = pointer::push(location, method.as_str());
+        visitor.visit_operation(&location, path, method, operation);
+        walk_operation(operation, &location, visitor);
+    }
+}
+
+fn walk_operation<'a, V: SpecVisitor<'a> + ?Sized>(
+    operation: &'a Operation,
+    location: &str,
+    visitor: &mut V,
+) {
+    walk_parameters(&operation.parameters, location, visitor);
+    if let Some(request_body) = &operation.request_body {
+        let location = pointer::push(location, "requestBody");
+        if let Some(request_body) = item(request_body, &location, visitor) {
+            walk_request_body(request_body, &location, visitor);
+        }
+    }
+    if let Some(responses) = &operation.responses {
+        let location = pointer::push(location, "responses");
+        if let Some(response) = &responses.default {
+            let location = pointer::push(&location, "default");
+            if let Some(response) = item(response, &location, visitor) {
+                walk_response(response, &location, visitor);
+            }
+        }
+        for (status, response) in &responses.responses {
+            let location = pointer::push(&location, &status.to_string());
+            if let Some(response) = item(response, &location, visitor) {
+                walk_response(response, &location, visitor);
+            }
+        }
+    }
+    for (name, callback) in &operation.callbacks {
+        let location = pointer::push(&pointer::push(location, "callbacks"), name);
+        if let Some(callback) = item(callback, &location, visitor) {
+            walk_callback(callback, &location, visitor);
+        }
+    }
+}
+
+fn walk_callback<'a, V: SpecVisitor<'a> + ?Sized>(
+    callback: &'a Callback,
+    location: &str,
+    visitor: &mut V,
+) {
+    for (expression, path_item) in callback {
+        walk_path_item(
+            expression,
+            path_item,
+            &pointer::push(location, expression),
+            visitor,
+        );
+    }
+}
+
+fn walk_parameters<'a, V: SpecVisitor<'a> + ?Sized>(
+    parameters: &'a [ReferenceOr<Parameter>],
+    location: &str,
+    visitor: &mut V,
+) {
+    for (i, parameter) in parameters.iter().enumerate() {
+        let location = format!("{location}/parameters/{i}");
+        if let Some(parameter) = item(parameter, &location, visitor) {
+            walk_parameter(parameter, &location, visitor);
+        }
+    }
+}
+
+fn walk_parameter<'a, V: SpecVisitor<'a> + ?Sized>(
+    parameter: &'a Parameter,
+    location: &str,
+    visitor: &mut V,
+) {
+    visitor.visit_parameter(location, parameter);
+    let parameter_data = match parameter {
+        Parameter::Query { parameter_data, .. } => parameter_data,
+        Parameter::Header { parameter_data, .. } => parameter_data,
+        Parameter::Path { parameter_data, .. } => parameter_data,
+        Parameter::Cookie { parameter_data, .. } => parameter_data,
+    };
+    walk_schema_or_content(&parameter_data.format, location, visitor);
+    for (name, example) in &parameter_data.examples {
+        let location = pointer::push(&pointer::push(location, "examples"), name);
+        if let Some(example) = item(example, &location, visitor) {
+            visitor.visit_example(&location, example);
+        }
+    }
+}
+
+fn walk_header<'a, V: SpecVisitor<'a> + ?Sized>(
+    header: &'a Header,
+    location: &str,
+    visitor: &mut V,
+) {
+    visitor.visit_header(location, header);
+    walk_schema_or_content(&header.format, location, visitor);
+    for (name, example) in &header.examples {
+        let location = pointer::push(&pointer::push(location, "examples"), name);
+        if let Some(example) = item(example, &location, visitor) {
+            visitor.visit_example(&location, example);
+        }
+    }
+}
+
+fn walk_schema_or_content<'a, V: SpecVisitor<'a> + ?Sized>(
+    format: &'a ParameterSchemaOrContent,
+    location: &str,
+    visitor: &mut V,
+) {
+    match format {
+        ParameterSchemaOrContent::Schema(schema) => {
+            walk_schema_object(schema, &pointer::push(location, "schema"), visitor)
+        }
+        ParameterSchemaOrContent::Content(content) => {
+            for (media_type_name, media_type) in content {
+                let location = pointer::push(&pointer::push(location, "content"), media_type_name);
+                walk_media_type(media_type, &location, visitor);
+            }
+        }
+    }
+}
+
+fn walk_request_body<'a, V: SpecVisitor<'a> + ?Sized>(
+    request_body: &'a RequestBody,
+    location: &str,
+    visitor: &mut V,
+) {
+    visitor.visit_request_body(location, request_body);
+    for (media_type_name, media_type) in &request_body.content {
+        let location = pointer::push(&pointer::push(location, "content"), media_type_name);
+        walk_media_type(media_type, &location, visitor);
+    }
+}
+
+fn walk_response<'a, V: SpecVisitor<'a> + ?Sized>(
+    response: &'a Response,
+    location: &str,
+    visitor: &mut V,
+) {
+    visitor.visit_response(location, response);
+    for (name, header) in &response.headers {
+        let location = pointer::push(&pointer::push(location, "headers"), name);
+        if let Some(header) = item(header, &location, visitor) {
+            walk_header(header, &location, visitor);
+        }
+    }
+    for (media_type_name, media_type) in &response.content {
+        let location = pointer::push(&pointer::push(location, "content"), media_type_name);
+        walk_media_type(media_type, &location, visitor);
+    }
+    for (name, link) in &response.links {
+        let location = pointer::push(&pointer::push(location, "links"), name);
+        if let Some(link) = item(link, &location, visitor) {
+            visitor.visit_link(&location, link);
+        }
+    }
+}
+
+fn walk_media_type<'a, V: SpecVisitor<'a> + ?Sized>(
+    media_type: &'a MediaType,
+    location: &str,
+    visitor: &mut V,
+) {
+    visitor.visit_media_type(location, media_type);
+    if let Some(schema) = &media_type.schema {
+        walk_schema_object(schema, &pointer::push(location, "schema"), visitor);
+    }
+    for (name, example) in &media_type.examples {
+        let location = pointer::push(&pointer::push(location, "examples"), name);
+        if let Some(example) = item(example, &location, visitor) {
+            visitor.visit_example(&location, example);
+        }
+    }
+    for (property, encoding) in &media_type.encoding {
+        let location = pointer::push(&pointer::push(location, "encoding"), property);
+        for (name, header) in &encoding.headers {
+            let location = pointer::push(&pointer::push(&location, "headers"), name);
+            if let Some(header) = item(header, &location, visitor) {
+                walk_header(header, &location, visitor);
+            }
+        }
+    }
+}
+
+fn walk_schema_object<'a, V: SpecVisitor<'a> + ?Sized>(
+    schema: &'a SchemaObject,
+    location: &str,
+    visitor: &mut V,
+) {
+    walk_schema(&schema.json_schema, location, visitor);
+}
+
+fn walk_schema<'a, V: SpecVisitor<'a> + ?Sized>(
+    schema: &'a SchemarsSchema,
+    location: &str,
+    visitor: &mut V,
+) {
+    visitor.visit_schema(location, schema);
+    let SchemarsSchema::Object(object) = schema else {
+        return;
+    };
+    if let Some(reference) = &object.reference {
+        visitor.visit_reference(location, reference);
+    }
+    if let Some(subschemas) = &object.subschemas {
+        for (keyword, schemas) in [
+            ("allOf", &subschemas.all_of),
+            ("anyOf", &subschemas.any_of),
+            ("oneOf", &subschemas.one_of),
+        ] {
+            for (i, schema) in schemas.iter().flatten().enumerate() {
+                walk_schema(schema, &format!("{location}/{keyword}/{i}"), visitor);
+            }
+        }
+        for (keyword, schema) in [
+            ("not", &subschemas.not),
+            ("if", &subschemas.if_schema),
+            ("then", &subschemas.then_schema),
+            ("else", &subschemas.else_schema),
+        ] {
+            if let Some(schema) = schema {
+                walk_schema(schema, &pointer::push(location, keyword), visitor);
+            }
+        }
+    }
+    if let Some(array) = &object.array {
+        match &array.items {
+            Some(SingleOrVec::Single(schema)) => {
+                walk_schema(schema, &pointer::push(location, "items"), visitor)
+            }
+            Some(SingleOrVec::Vec(schemas)) => {
+                for (i, schema) in schemas.iter().enumerate() {
+                    walk_schema(schema, &format!("{location}/items/{i}"), visitor);
+                }
+            }
+            None => {}
+        }
+        for (keyword, schema) in [
+            ("additionalItems", &array.additional_items),
+            ("contains", &array.contains),
+        ] {
+            if let Some(schema) = schema {
+                walk_schema(schema, &pointer::push(location, keyword), visitor);
+            }
+        }
+    }
+    if let Some(object_validation) = &object.object {
+        for (keyword, schemas) in [
+            ("properties", &object_validation.properties),
+            ("patternProperties", &object_validation.pattern_properties),
+        ] {
+            for (name, schema) in schemas {
+                let location = pointer::push(&pointer::push(location, keyword), name);
+                walk_schema(schema, &location, visitor);
+            }
+        }
+        for (keyword, schema) in [
+            (
+                "additionalProperties",
+                &object_validation.additional_properties,
+            ),
+            ("propertyNames", &object_validation.property_names),
+        ] {
+            if let Some(schema) = schema {
+                walk_schema(schema, &pointer::push(location, keyword), visitor);
+            }
+        }
+    }
+    if let Some(Value::Array(items)) = object.extensions.get("prefixItems") {
+        for (i, value) in items.iter().enumerate() {
+            walk_schema_value(value, &format!("{location}/prefixItems/{i}"), visitor);
+        }
+    }
+    if let Some(Value::Object(defs)) = object.extensions.get("$defs") {
+        let location = pointer::push(location, "$defs");
+        for (name, value) in defs {
+            walk_schema_value(value, &pointer::push(&location, name), visitor);
+        }
+    }
+}
+
+///Walk a schema schemars left as JSON, the same way `convert` finds subschemas.
+fn walk_schema_value<'a, V: SpecVisitor<'a> + ?Sized>(
+    value: &'a Value,
+    location: &str,
+    visitor: &mut V,
+) {
+    if let Ok(schema) = SchemarsSchema::deserialize(value) {
+        visitor.visit_parsed_schema(location, &schema);
+    }
+    let Value::Object(object) = value else {
+        return;
+    };
+    if let Some(Value::String(reference)) = object.get("$ref") {
+        visitor.visit_reference(location, reference);
+    }
+    for (key, value) in object {
+        let child = pointer::push(location, key);
+        let list = match value {
+            Value::Array(values) if key == "items" => Some(values),
+            Value::Array(values) if SCHEMA_LIST_KEYWORDS.contains(&key.as_str()) => Some(values),
+            _ => None,
+        };
+        if let Some(values) = list {
+            for (i, value) in values.iter().enumerate() {
+                walk_schema_value(value, &format!("{child}/{i}"), visitor);
+            }
+        } else if SCHEMA_KEYWORDS.contains(&key.as_str()) {
+            walk_schema_value(value, &child, visitor);
+        } else if SCHEMA_MAP_KEYWORDS.contains(&key.as_str()) {
+            if let Value::Object(values) = value {
+                for (name, value) in values {
+                    walk_schema_value(value, &pointer::push(&child, name), visitor);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[derive(Default)]
+    struct Counter<'a> {
+        operations: Vec<(&'a str, Method)>,
+        schemas: Vec<String>,
+        references: Vec<(String, &'a str)>,
+    }
+
+    impl<'a> SpecVisitor<'a> for Counter<'a> {
+        fn visit_operation(
+            &mut self,
+            _location: &str,
+            path: &'a str,
+            method: Method,
+            _operation: &'a Operation,
+        ) {
+            self.operations.push((path, method));
+        }
+
+        fn visit_schema(&mut self, location: &str, _schema: &'a SchemarsSchema) {
+            self.schemas.push(location.into());
+        }
+
+        fn visit_parsed_schema(&mut self, location: &str, _schema: &SchemarsSchema) {
+            self.schemas.push(location.into());
+        }
+
+        fn visit_reference(&mut self, location: &str, reference: &'a str) {
+            self.references.push((location.into(), reference));
+        }
+    }
+
+    #[test]
+    pub fn test_walk() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Walk", "version": "1.0.0"},
+            "paths": {
+                "/pets/{petId}": {
+                    "parameters": [{"$ref": "#/components/parameters/PetId"}],
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "A pet",
+                                "content": {
+                                    "application/json": {
+                                        "schema": {"$ref": "#/components/schemas/Pet"}
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "parameters": {
+                    "PetId": {"name": "petId", "in": "path", "required": true, "schema": {"type": "string"}}
+                },
+                "schemas": {
+                    "Pet": {
+                        "type": "object",
+                        "properties": {"name": {"type": "string"}}
+                    }
+                }
+            }
+        }"##;
+        let dereferencer = OpenApiDereferencer::from_str(spec)?;
+        let mut counter = Counter::default();
+        dereferencer.walk(&mut counter);
+        assert_eq!(vec![("/pets/{petId}", Method::Get)], counter.operations);
+        assert_eq!(
+            vec![
+                (
+                    "/paths/~1pets~1{petId}/parameters/0".to_string(),
+                    "#/components/parameters/PetId"
+                ),
+                (
+                    "/paths/~1pets~1{petId}/get/responses/200/content/application~1json/schema"
+                        .to_string(),
+                    "#/components/schemas/Pet"
+                ),
+            ],
+            counter.references
+        );
+        assert!(counter
+            .schemas
+            .contains(&"/components/schemas/Pet/properties/name".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_iter_schemas() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Schemas", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "parameters": [{"name": "limit", "in": "query", "schema": {"type": "integer"}}],
+                        "responses": {
+                            "200": {
+                                "description": "Pets",
+                                "content": {
+                                    "application/json": {
+                                        "schema": {"type": "array", "items": {"$ref": "#/components/schemas/Pet"}}
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Pet": {"type": "object", "properties": {"name": {"type": "string"}}}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let mut locations: Vec<String> = dereferenced
+            .iter_schemas()
+            .map(|(location, _)| location)
+            .collect();
+        locations.sort();
+        let content = "/paths/~1pets/get/responses/200/content/application~1json/schema";
+        let mut expected = vec![
+            "/components/schemas/Pet".to_string(),
+            "/components/schemas/Pet/properties/name".to_string(),
+            "/paths/~1pets/get/parameters/0/schema".to_string(),
+            content.to_string(),
+            format!("{content}/items"),
+            format!("{content}/items/properties/name"),
+        ];
+        expected.sort();
+        assert_eq!(expected, locations);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_walk_defs_and_prefix_items() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Defs", "version": "1.0.0"},
+            "paths": {},
+            "components": {
+                "schemas": {
+                    "Node": {
+                        "type": "object",
+                        "$defs": {"Next": {"$ref": "#/components/schemas/Node"}},
+                        "properties": {
+                            "pair": {"type": "array", "prefixItems": [{"type": "string"}, {"type": "integer"}]}
+                        }
+                    }
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        let mut counter = Counter::default();
+        dereferenced.walk(&mut counter);
+        assert!(counter.references.contains(&(
+            "/components/schemas/Node/$defs/Next".to_string(),
+            "#/components/schemas/Node"
+        )));
+        let locations: Vec<String> = dereferenced
+            .iter_schemas()
+            .map(|(location, _)| location)
+            .collect();
+        for location in [
+            "/components/schemas/Node/$defs/Next",
+            "/components/schemas/Node/properties/pair/prefixItems/0",
+            "/components/schemas/Node/properties/pair/prefixItems/1",
+        ] {
+            assert!(locations.contains(&location.to_string()), "{location}");
+        }
+        Ok(())
+    }
+}
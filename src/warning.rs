@@ -0,0 +1,224 @@
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+use crate::{pointer, Method, OpenApiDereferencer};
+
+///Something found while dereferencing that isn't an error, but probably isn't what the spec
+///author intended either.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    ///Two operations share an operationId. Lookups by id return the first one.
+    DuplicateOperationId {
+        operation_id: String,
+        path: String,
+        method: Method,
+        first_path: String,
+        first_method: Method,
+    },
+    ///A discriminator mapping points at a schema that doesn't exist.
+    UnresolvedDiscriminatorMapping {
+        location: String,
+        value: String,
+        reference: String,
+    },
+    ///A discriminator mapping points at a schema that isn't one of the `oneOf`/`anyOf` schemas.
+    DiscriminatorMappingNotAMember {
+        location: String,
+        value: String,
+        reference: String,
+    },
+    ///Two merged specs define the same thing differently. The first spec's version is kept.
+    MergeConflict { location: String },
+    ///A component was renamed while merging because another spec has a different component
+    ///with the same name.
+    ComponentRenamed {
+        section: String,
+        name: String,
+        renamed_to: String,
+    },
+    ///A ref points somewhere other than `components`, e.g. into another operation.
+    RefOutsideComponents { location: String, reference: String },
+    ///An example's `externalValue` wasn't inlined, either because there's no example fetcher
+    ///or because the example already has a `value`.
+    ExternalExampleIgnored { location: String, url: String },
+    ///A `summary` or `description` next to a ref was dropped because the type it points to
+    ///doesn't have that field.
+    SiblingsDropped {
+        location: String,
+        reference: String,
+        keywords: Vec<String>,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::DuplicateOperationId {
+                operation_id,
+                path,
+                method,
+                first_path,
+                first_method,
+            } => write!(
+                f,
+                "Duplicate operationId {operation_id} on {method} {path}, first used on {first_method} {first_path}"
+            ),
+            Warning::UnresolvedDiscriminatorMapping {
+                location,
+                value,
+                reference,
+            } => write!(
+                f,
+                "Discriminator mapping {value} at {location} points to {reference}, which doesn't exist"
+            ),
+            Warning::DiscriminatorMappingNotAMember {
+                location,
+                value,
+                reference,
+            } => write!(
+                f,
+                "Discriminator mapping {value} at {location} points to {reference}, which isn't one of its oneOf/anyOf schemas"
+            ),
+            Warning::MergeConflict { location } => {
+                write!(f, "Conflicting definitions of {location}, kept the first")
+            }
+            Warning::ComponentRenamed {
+                section,
+                name,
+                renamed_to,
+            } => write!(f, "Renamed {section} component {name} to {renamed_to}"),
+            Warning::RefOutsideComponents {
+                location,
+                reference,
+            } => write!(
+                f,
+                "Ref {reference} at {location} points outside of components"
+            ),
+            Warning::ExternalExampleIgnored { location, url } => {
+                write!(f, "External example {url} at {location} wasn't inlined")
+            }
+            Warning::SiblingsDropped {
+                location,
+                reference,
+                keywords,
+            } => write!(
+                f,
+                "Dropped {} next to {reference} at {location}, the target doesn't have them",
+                keywords.join(", ")
+            ),
+        }
+    }
+}
+
+impl OpenApiDereferencer {
+    ///Warnings found by looking over the input document once dereferencing is done.
+    pub(crate) fn collect_document_warnings(&mut self) {
+        let mut warnings = vec![];
+        for_each_object(&self.json, "", &mut |location, object| {
+            if let Some(Value::String(reference)) = object.get("$ref") {
+                let outside = self
+                    .locate_reference(reference)
+                    .is_ok_and(|target| !target.starts_with("/components/"));
+                if outside {
+                    warnings.push(Warning::RefOutsideComponents {
+                        location: location.into(),
+                        reference: reference.clone(),
+                    });
+                }
+            }
+            if let Some(Value::String(url)) = object.get("externalValue") {
+                if self.example_fetcher.is_none() || object.contains_key("value") {
+                    warnings.push(Warning::ExternalExampleIgnored {
+                        location: location.into(),
+                        url: url.clone(),
+                    });
+                }
+            }
+        });
+        self.warnings.append(&mut warnings);
+    }
+}
+
+fn for_each_object(value: &Value, location: &str, f: &mut impl FnMut(&str, &Map<String, Value>)) {
+    match value {
+        Value::Object(object) => {
+            f(location, object);
+            for (key, value) in object {
+                for_each_object(value, &pointer::push(location, key), f);
+            }
+        }
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                for_each_object(value, &format!("{location}/{i}"), f);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    pub fn test_document_warnings() -> Result<()> {
+        let spec = r##"{
+            "openapi": "3.1.0",
+            "info": {"title": "Warnings", "version": "1.0.0"},
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "parameters": [
+                            {"$ref": "#/components/parameters/Limit", "summary": "How many"}
+                        ],
+                        "responses": {
+                            "200": {
+                                "description": "ok",
+                                "content": {
+                                    "application/json": {
+                                        "examples": {"pets": {"externalValue": "pets.json"}}
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "/stores": {
+                    "get": {
+                        "responses": {"200": {"$ref": "#/paths/~1pets/get/responses/200"}}
+                    }
+                }
+            },
+            "components": {
+                "parameters": {
+                    "Limit": {"name": "limit", "in": "query", "schema": {"type": "integer"}}
+                }
+            }
+        }"##;
+        let dereferenced = OpenApiDereferencer::from_str(spec)?.dereference()?;
+        assert_eq!(
+            &[
+                Warning::SiblingsDropped {
+                    location: "/paths/~1pets/get/parameters/0".into(),
+                    reference: "#/components/parameters/Limit".into(),
+                    keywords: vec!["summary".into()],
+                },
+                Warning::ExternalExampleIgnored {
+                    location:
+                        "/paths/~1pets/get/responses/200/content/application~1json/examples/pets"
+                            .into(),
+                    url: "pets.json".into(),
+                },
+                Warning::RefOutsideComponents {
+                    location: "/paths/~1stores/get/responses/200".into(),
+                    reference: "#/paths/~1pets/get/responses/200".into(),
+                },
+            ],
+            dereferenced.warnings()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde_json::Value;
+
+use crate::loader::{bundle_external, DocumentLoader, FileDocumentLoader};
+use crate::{DereferencedOpenApi, OpenApiDereferencer, OpenApiDereferencerBuilder, OpenApiError};
+
+///How long the files have to be left alone before reloading, so a save that takes a few writes
+///is only loaded once it's done.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+///Watches a spec for [`watch`], until it's dropped.
+pub struct SpecWatcher {
+    _watcher: Arc<Mutex<RecommendedWatcher>>,
+}
+
+///Load and dereference the spec at `path`, then do it again whenever it or a file it refers to
+///changes. Each result goes to `callback`, the first load before this returns and the rest on a
+///thread of the watcher's own.
+pub fn watch(
+    path: impl AsRef<Path>,
+    callback: impl FnMut(Result<DereferencedOpenApi, OpenApiError>) + Send + 'static,
+) -> Result<SpecWatcher, OpenApiError> {
+    OpenApiDereferencer::builder().watch(path, callback)
+}
+
+impl OpenApiDereferencerBuilder {
+    ///[`watch`], dereferencing with these options every time. Files are loaded relative to the
+    ///spec rather than with the document loader, and every reload starts with a fresh cache.
+    pub fn watch(
+        self,
+        path: impl AsRef<Path>,
+        callback: impl FnMut(Result<DereferencedOpenApi, OpenApiError>) + Send + 'static,
+    ) -> Result<SpecWatcher, OpenApiError> {
+        let spec = path.as_ref().to_path_buf();
+        let (sender, events) = mpsc::channel();
+        let watcher = notify::recommended_watcher(sender).map_err(|e| watch_error(&spec, e))?;
+        let watcher = Arc::new(Mutex::new(watcher));
+        let mut reloader = Reloader {
+            spec,
+            builder: self,
+            callback,
+            watcher: Arc::downgrade(&watcher),
+            files: HashSet::new(),
+            directories: HashSet::new(),
+        };
+        let loaded = reloader.load()?;
+        (reloader.callback)(loaded);
+        std::thread::spawn(move || reloader.run(events));
+        Ok(SpecWatcher { _watcher: watcher })
+    }
+}
+
+struct Reloader<C> {
+    spec: PathBuf,
+    builder: OpenApiDereferencerBuilder,
+    callback: C,
+    ///Dropped along with the `SpecWatcher`, which ends `run`
+    watcher: Weak<Mutex<RecommendedWatcher>>,
+    ///The spec and every file it refers to
+    files: HashSet<PathBuf>,
+    ///The directories of `files`. Watching them rather than the files keeps working when an
+    ///editor saves by replacing the file.
+    directories: HashSet<PathBuf>,
+}
+
+impl<C> Reloader<C>
+where
+    C: FnMut(Result<DereferencedOpenApi, OpenApiError>),
+{
+    fn run(mut self, events: Receiver<notify::Result<Event>>) {
+        while let Ok(event) = events.recv() {
+            if !self.is_change(event) {
+                continue;
+            }
+            loop {
+                match events.recv_timeout(DEBOUNCE) {
+                    Ok(_) => {}
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            let loaded = self.load().and_then(|loaded| loaded);
+            (self.callback)(loaded);
+        }
+    }
+
+    fn is_change(&self, event: notify::Result<Event>) -> bool {
+        let Ok(event) = event else {
+            return false;
+        };
+        let changed = event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove();
+        changed
+            && event
+                .paths
+                .iter()
+                .any(|path| self.files.contains(&absolute(path)))
+    }
+
+    ///Dereference the spec and watch the files it's made of now. Fails if they can't be
+    ///watched, otherwise gives what dereferencing did.
+    fn load(&mut self) -> Result<Result<DereferencedOpenApi, OpenApiError>, OpenApiError> {
+        let loader = RecordingLoader {
+            files: FileDocumentLoader {
+                base: self.spec.parent().unwrap_or(Path::new("")).to_path_buf(),
+            },
+            loaded: Mutex::default(),
+        };
+        let dereferenced = dereference(&self.spec, &self.builder, &loader);
+        let mut files = loader
+            .loaded
+            .into_inner()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        files.insert(absolute(&self.spec));
+        if dereferenced.is_err() {
+            //A broken spec might not get as far as its refs, keep an eye on those too
+            files.extend(self.files.drain());
+        }
+        self.watch_files(files)?;
+        Ok(dereferenced)
+    }
+
+    fn watch_files(&mut self, files: HashSet<PathBuf>) -> Result<(), OpenApiError> {
+        let Some(watcher) = self.watcher.upgrade() else {
+            return Ok(());
+        };
+        let mut watcher = watcher
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let directories: HashSet<PathBuf> = files
+            .iter()
+            .filter_map(|file| file.parent())
+            .filter(|directory| directory.is_dir())
+            .map(Path::to_path_buf)
+            .collect();
+        for directory in self.directories.difference(&directories) {
+            let _ = watcher.unwatch(directory);
+        }
+        for directory in directories.difference(&self.directories) {
+            watcher
+                .watch(directory, RecursiveMode::NonRecursive)
+                .map_err(|e| watch_error(directory, e))?;
+        }
+        self.files = files;
+        self.directories = directories;
+        Ok(())
+    }
+}
+
+fn dereference(
+    spec: &Path,
+    builder: &OpenApiDereferencerBuilder,
+    loader: &RecordingLoader,
+) -> Result<DereferencedOpenApi, OpenApiError> {
+    let entry = spec
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| OpenApiError::FileError {
+            path: spec.into(),
+            msg: "Not a file".into(),
+        })?;
+    let document = bundle_external(loader.load(entry)?, entry, loader, None)?;
+    builder
+        .for_reload()
+        .build_from_value(document)?
+        .dereference()
+}
+
+///A `FileDocumentLoader` noting down the files it's asked for, so they can be watched.
+struct RecordingLoader {
+    files: FileDocumentLoader,
+    loaded: Mutex<HashSet<PathBuf>>,
+}
+
+impl DocumentLoader for RecordingLoader {
+    fn load(&self, uri: &str) -> Result<Value, OpenApiError> {
+        let relative = uri.strip_prefix("file://").unwrap_or(uri);
+        if !relative.contains("://") {
+            if let Ok(mut loaded) = self.loaded.lock() {
+                loaded.insert(absolute(&self.files.base.join(relative)));
+            }
+        }
+        self.files.load(uri)
+    }
+}
+
+///`path` with its directory made absolute and its symlinks resolved, the way the watcher
+///reports it.
+fn absolute(path: &Path) -> PathBuf {
+    let directory = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let directory = directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.to_path_buf());
+    match path.file_name() {
+        Some(name) => directory.join(name),
+        None => directory,
+    }
+}
+
+fn watch_error(path: &Path, e: notify::Error) -> OpenApiError {
+    OpenApiError::FileError {
+        path: path.into(),
+        msg: e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::time::Instant;
+
+    #[test]
+    pub fn test_watch() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("openapi-deref-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let spec = |title: &str, extra: &str| {
+            format!(
+                r##"{{
+                    "openapi": "3.1.0",
+                    "info": {{"title": "{title}", "version": "1.0.0"}},
+                    {extra}
+                    "paths": {{
+                        "/pets": {{"get": {{"responses": {{"200": {{"$ref": "responses.json#/Pets"}}}}}}}}
+                    }}
+                }}"##
+            )
+        };
+        let responses =
+            |description: &str| format!(r#"{{"Pets": {{"description": "{description}"}}}}"#);
+        std::fs::write(dir.join("openapi.json"), spec("Before", ""))?;
+        std::fs::write(dir.join("responses.json"), responses("Pets"))?;
+        let (sender, receiver) = mpsc::channel();
+        let _watcher = OpenApiDereferencer::builder().strict(true).watch(
+            dir.join("openapi.json"),
+            move |dereferenced| {
+                let loaded = dereferenced.map(|dereferenced| {
+                    let json = &dereferenced.json;
+                    format!(
+                        "{} {}",
+                        json["info"]["title"].as_str().unwrap_or_default(),
+                        json["paths"]["/pets"]["get"]["responses"]["200"]["description"]
+                            .as_str()
+                            .unwrap_or_default()
+                    )
+                });
+                let _ = sender.send(loaded.map_err(|e| e.to_string()));
+            },
+        )?;
+        //A save can show up as more than one change, so wait for the one that's expected
+        let wait_for = |expected: &dyn Fn(&Result<String, String>) -> bool| -> Result<()> {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            loop {
+                let loaded =
+                    receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))?;
+                if expected(&loaded) {
+                    return Ok(());
+                }
+            }
+        };
+        wait_for(&|loaded| loaded.as_deref() == Ok("Before Pets"))?;
+
+        std::fs::write(dir.join("openapi.json"), spec("After", ""))?;
+        wait_for(&|loaded| loaded.as_deref() == Ok("After Pets"))?;
+
+        std::fs::write(dir.join("responses.json"), responses("Dogs"))?;
+        wait_for(&|loaded| loaded.as_deref() == Ok("After Dogs"))?;
+
+        std::fs::write(
+            dir.join("openapi.json"),
+            spec("Strict", r#""petstore": true,"#),
+        )?;
+        wait_for(&|loaded| {
+            loaded
+                .as_ref()
+                .is_err_and(|e| e.starts_with("Fields not allowed"))
+        })?;
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
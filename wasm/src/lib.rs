@@ -0,0 +1,12 @@
+use std::str::FromStr;
+
+use openapi_deref::OpenApiDereferencer;
+use wasm_bindgen::prelude::*;
+
+///Dereference a 3.1 spec, returning the dereferenced spec as JSON. Errors are thrown as JS
+///errors.
+#[wasm_bindgen]
+pub fn dereference(json: &str) -> Result<String, JsError> {
+    let dereferenced = OpenApiDereferencer::from_str(json)?.dereference()?;
+    Ok(serde_json::to_string(&dereferenced.into_openapi())?)
+}